@@ -95,6 +95,7 @@ pub enum MetadataError {
 /// targets = [ "x86_64-apple-darwin", "x86_64-pc-windows-msvc" ]
 /// rustc-args = [ "--example-rustc-arg" ]
 /// rustdoc-args = [ "--example-rustdoc-arg" ]
+/// document-binaries = true
 /// ```
 ///
 /// You can define one or more fields in your `Cargo.toml`.
@@ -138,6 +139,12 @@ pub struct Metadata {
     /// These cannot be a subcommand, they may only be options.
     #[serde(default)]
     cargo_args: Vec<String>,
+
+    /// Whether to document the binaries of crates without a library.
+    ///
+    /// By default, docs.rs only documents libraries.
+    #[serde(default)]
+    document_binaries: bool,
 }
 
 /// The targets that should be built for a crate.
@@ -238,6 +245,11 @@ impl Metadata {
         }
     }
 
+    /// Whether the binaries of the crate should be documented when it doesn't have a library.
+    pub fn document_binaries(&self) -> bool {
+        self.document_binaries
+    }
+
     /// Return the arguments that should be passed to `cargo`.
     ///
     /// This will always include `rustdoc --lib`.
@@ -251,8 +263,30 @@ impl Metadata {
     /// For example, the links may point somewhere different than they would on docs.rs.
     /// However, rustdoc will see exactly the same code as it would on docs.rs, even counting `cfg`s.
     pub fn cargo_args(&self, additional_args: &[String], rustdoc_args: &[String]) -> Vec<String> {
+        self.cargo_args_for("--lib", additional_args, rustdoc_args)
+    }
+
+    /// Return the arguments that should be passed to `cargo` to document the binaries of a crate
+    /// without a library.
+    ///
+    /// This is the same as [`Metadata::cargo_args`], but with `rustdoc --bins` instead of
+    /// `rustdoc --lib`. Cargo documents the private items of binaries.
+    pub fn cargo_args_for_binaries(
+        &self,
+        additional_args: &[String],
+        rustdoc_args: &[String],
+    ) -> Vec<String> {
+        self.cargo_args_for("--bins", additional_args, rustdoc_args)
+    }
+
+    fn cargo_args_for(
+        &self,
+        targets: &str,
+        additional_args: &[String],
+        rustdoc_args: &[String],
+    ) -> Vec<String> {
         let mut cargo_args: Vec<String> =
-            vec!["rustdoc".into(), "--lib".into(), "-Zrustdoc-map".into()];
+            vec!["rustdoc".into(), targets.into(), "-Zrustdoc-map".into()];
 
         if let Some(features) = &self.features {
             cargo_args.push("--features".into());
@@ -383,6 +417,7 @@ mod test_parsing {
             rustc-args = [ "--example-rustc-arg" ]
             rustdoc-args = [ "--example-rustdoc-arg" ]
             cargo-args = [ "-Zbuild-std" ]
+            document-binaries = true
         "#;
 
         let metadata = Metadata::from_str(manifest).unwrap();
@@ -392,6 +427,7 @@ mod test_parsing {
         assert!(metadata.no_default_features);
         assert!(metadata.default_target.is_some());
         assert!(!metadata.proc_macro);
+        assert!(metadata.document_binaries());
 
         let features = metadata.features.unwrap();
         assert_eq!(features.len(), 2);
@@ -673,6 +709,18 @@ mod test_calculations {
         assert!(!env.contains_key("RUSTFLAGS"));
     }
 
+    #[test]
+    fn test_binaries() {
+        let metadata = Metadata {
+            all_features: true,
+            ..Metadata::default()
+        };
+        let mut expected_args = default_cargo_args(&["--all-features".into()]);
+        expected_args[1] = "--bins".into();
+        assert_eq!(metadata.cargo_args_for_binaries(&[], &[]), expected_args);
+        assert!(!metadata.document_binaries());
+    }
+
     #[test]
     fn test_features() {
         // all features
@@ -0,0 +1,2 @@
+DROP TABLE queue_scheduling_groups;
+DROP VIEW queue_owners;
@@ -0,0 +1,26 @@
+CREATE OR REPLACE VIEW queue_owners AS (
+  SELECT
+    queue.id AS queue_id,
+    crate_owner.login AS owner,
+    -- releases of crates we don't know the owners of yet (new crates)
+    -- are scheduled as if every crate had its own owner.
+    COALESCE(crate_owner.login, queue.name) AS scheduling_group
+  FROM
+    queue
+    LEFT OUTER JOIN LATERAL (
+      SELECT owners.login
+      FROM crates
+      INNER JOIN owner_rels ON owner_rels.cid = crates.id
+      INNER JOIN owners ON owners.id = owner_rels.oid
+      WHERE crates.name = queue.name
+      ORDER BY owners.id ASC
+      LIMIT 1
+    ) AS crate_owner ON TRUE
+);
+
+-- when did we last start a build for a scheduling group,
+-- so we can round-robin between groups with the same priority.
+CREATE TABLE queue_scheduling_groups (
+  name TEXT PRIMARY KEY,
+  last_dequeued TIMESTAMP WITH TIME ZONE NOT NULL
+);
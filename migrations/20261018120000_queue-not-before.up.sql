@@ -0,0 +1,7 @@
+ALTER TABLE queue ADD COLUMN not_before TIMESTAMP WITH TIME ZONE;
+
+-- entries that failed before waited `DOCSRS_DELAY_BETWEEN_BUILD_ATTEMPTS` (60 seconds by default)
+-- after their last attempt, keep them waiting.
+UPDATE queue
+SET not_before = last_attempt + INTERVAL '60 seconds'
+WHERE attempt > 0 AND last_attempt IS NOT NULL;
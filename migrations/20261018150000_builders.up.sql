@@ -0,0 +1,8 @@
+-- build servers that are currently running, with the build they are working on.
+CREATE TABLE builders (
+  name TEXT PRIMARY KEY,
+  toolchain TEXT,
+  build_id INT,
+  registered_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  last_heartbeat TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+ALTER TABLE crates DROP COLUMN registry_id;
+DROP TABLE registries;
@@ -0,0 +1,13 @@
+-- alternative registries we host documentation for.
+-- Their crates are stored as `{registry}/{name}`, so they never collide with crates.io crates.
+CREATE TABLE registries (
+  id SERIAL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  index_url TEXT NOT NULL UNIQUE,
+  crate_names_url TEXT,
+  last_seen_reference TEXT,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE crates ADD COLUMN registry_id INT REFERENCES registries(id);
+CREATE INDEX crates_registry_id_idx ON crates (registry_id);
@@ -0,0 +1,15 @@
+-- who may see the documentation of a crate, or of all crates of a hosted registry.
+-- Crates without any rule are public.
+-- Crate rules use the name, so a crate can be protected before its first release is built.
+CREATE TABLE access_rules (
+  id SERIAL PRIMARY KEY,
+  registry_id INT REFERENCES registries(id) ON DELETE CASCADE,
+  crate_name TEXT,
+  -- `*` allows every authenticated principal.
+  principal TEXT NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  CHECK ((registry_id IS NULL) <> (crate_name IS NULL))
+);
+
+CREATE INDEX access_rules_registry_id_idx ON access_rules (registry_id);
+CREATE INDEX access_rules_crate_name_idx ON access_rules (normalize_crate_name(crate_name));
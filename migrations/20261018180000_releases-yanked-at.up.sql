@@ -0,0 +1,3 @@
+-- when the release was yanked, for the release feeds.
+-- Releases yanked before we tracked this have no timestamp.
+ALTER TABLE releases ADD COLUMN yanked_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,38 @@
+-- where we send a notification when a build finishes.
+-- A webhook is for a single crate, all crates of an owner, or all crates of a hosted registry.
+CREATE TABLE webhooks (
+  id SERIAL PRIMARY KEY,
+  crate_name TEXT,
+  owner TEXT,
+  registry_id INT REFERENCES registries(id) ON DELETE CASCADE,
+  url TEXT NOT NULL,
+  -- used to sign the payloads, so receivers know they come from us.
+  secret TEXT NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  CHECK (num_nonnulls(crate_name, owner, registry_id) = 1)
+);
+
+CREATE INDEX webhooks_crate_name_idx ON webhooks (normalize_crate_name(crate_name));
+CREATE INDEX webhooks_owner_idx ON webhooks (owner);
+CREATE INDEX webhooks_registry_id_idx ON webhooks (registry_id);
+
+-- the payloads we send, and how sending them went.
+CREATE TABLE webhook_deliveries (
+  id SERIAL PRIMARY KEY,
+  webhook_id INT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+  release_id INT NOT NULL REFERENCES releases(id) ON DELETE CASCADE,
+  build_id INT NOT NULL,
+  payload JSONB NOT NULL,
+  attempts INT NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  delivered_at TIMESTAMP WITH TIME ZONE,
+  -- the HTTP status or the error of the last attempt.
+  last_status INT,
+  last_error TEXT,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhook_deliveries_pending_idx ON webhook_deliveries (next_attempt_at)
+  WHERE delivered_at IS NULL;
+CREATE INDEX webhook_deliveries_webhook_id_idx ON webhook_deliveries (webhook_id);
+CREATE INDEX webhook_deliveries_release_id_idx ON webhook_deliveries (release_id);
@@ -0,0 +1,16 @@
+-- the logs of failed builds, so admins can search them.
+-- `builds.id` isn't a primary key, so we can only reference the release.
+CREATE TABLE build_log_index (
+    build_id INT NOT NULL,
+    release_id INT NOT NULL REFERENCES releases(id) ON DELETE CASCADE,
+    target TEXT NOT NULL,
+    content TEXT NOT NULL,
+    -- tsvectors are limited to 1MB, so we only index the start of very long logs.
+    content_tsv TSVECTOR GENERATED ALWAYS AS (
+        strip(to_tsvector('simple', left(content, 500000)))
+    ) STORED,
+    PRIMARY KEY (build_id, target)
+);
+
+CREATE INDEX build_log_index_content_tsv_idx ON build_log_index USING GIN (content_tsv);
+CREATE INDEX build_log_index_release_id_idx ON build_log_index (release_id);
@@ -0,0 +1 @@
+DROP TABLE rustdoc_warnings;
@@ -0,0 +1,14 @@
+-- the warnings rustdoc emitted for a crate, from the latest build of the release.
+CREATE TABLE rustdoc_warnings (
+    id SERIAL PRIMARY KEY,
+    release_id INT NOT NULL REFERENCES releases(id) ON DELETE CASCADE,
+    target TEXT NOT NULL,
+    -- the lint, like `rustdoc::broken_intra_doc_links`.
+    code TEXT,
+    message TEXT NOT NULL,
+    file TEXT,
+    line INT,
+    rendered TEXT NOT NULL
+);
+
+CREATE INDEX rustdoc_warnings_release_id_idx ON rustdoc_warnings (release_id, target);
@@ -0,0 +1,2 @@
+DROP TABLE ice_occurrences;
+DROP TABLE ices;
@@ -0,0 +1,26 @@
+-- internal compiler errors found in build logs, deduplicated by their signature.
+CREATE TABLE ices (
+    id SERIAL PRIMARY KEY,
+    signature TEXT NOT NULL UNIQUE,
+    first_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- like `build_log_index`, this references the release instead of the build.
+-- the message, location and query stack are stored per occurrence, they can contain
+-- the names of items of private crates.
+CREATE TABLE ice_occurrences (
+    ice_id INT NOT NULL REFERENCES ices(id) ON DELETE CASCADE,
+    build_id INT NOT NULL,
+    release_id INT NOT NULL REFERENCES releases(id) ON DELETE CASCADE,
+    target TEXT NOT NULL,
+    rustc_version TEXT NOT NULL,
+    toolchain_date DATE,
+    message TEXT NOT NULL,
+    location TEXT,
+    query_stack TEXT[] NOT NULL,
+    seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (ice_id, build_id, target)
+);
+
+CREATE INDEX ice_occurrences_release_id_idx ON ice_occurrences (release_id);
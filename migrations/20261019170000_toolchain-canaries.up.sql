@@ -0,0 +1,24 @@
+-- canary runs, building a sample of crates with the current and a candidate toolchain
+-- before we switch to the candidate.
+CREATE TABLE toolchain_canaries (
+    id SERIAL PRIMARY KEY,
+    toolchain TEXT NOT NULL,
+    baseline_toolchain TEXT NOT NULL,
+    sample TEXT NOT NULL,
+    max_regressions INT NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ,
+    regressions INT,
+    passed BOOL
+);
+
+CREATE TABLE toolchain_canary_builds (
+    canary_id INT NOT NULL REFERENCES toolchain_canaries(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    baseline_successful BOOL NOT NULL,
+    baseline_doc_size BIGINT,
+    candidate_successful BOOL NOT NULL,
+    candidate_doc_size BIGINT,
+    PRIMARY KEY (canary_id, name, version)
+);
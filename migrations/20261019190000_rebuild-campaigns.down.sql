@@ -0,0 +1,3 @@
+ALTER TABLE queue DROP COLUMN campaign_id;
+DROP TABLE rebuild_campaign_releases;
+DROP TABLE rebuild_campaigns;
@@ -0,0 +1,21 @@
+-- named mass rebuilds, like after a rustdoc fix for a certain kind of failure.
+CREATE TABLE rebuild_campaigns (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    priority INT NOT NULL,
+    -- how the releases were selected.
+    selection TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- the releases of a campaign, with their newest build when the campaign was created.
+-- builds with a higher id are the rebuilds.
+CREATE TABLE rebuild_campaign_releases (
+    campaign_id INT NOT NULL REFERENCES rebuild_campaigns(id) ON DELETE CASCADE,
+    release_id INT NOT NULL REFERENCES releases(id) ON DELETE CASCADE,
+    previous_build_id INT,
+    previous_build_status build_status,
+    PRIMARY KEY (campaign_id, release_id)
+);
+
+ALTER TABLE queue ADD COLUMN campaign_id INT REFERENCES rebuild_campaigns(id) ON DELETE SET NULL;
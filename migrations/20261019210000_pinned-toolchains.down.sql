@@ -0,0 +1,2 @@
+ALTER TABLE builds DROP COLUMN pinned_toolchain;
+ALTER TABLE sandbox_overrides DROP COLUMN toolchain;
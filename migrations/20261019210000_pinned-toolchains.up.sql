@@ -0,0 +1,4 @@
+-- crates that only document with a certain toolchain, like after a rustdoc regression.
+ALTER TABLE sandbox_overrides ADD COLUMN toolchain TEXT;
+-- the toolchain of builds that didn't use the default one.
+ALTER TABLE builds ADD COLUMN pinned_toolchain TEXT;
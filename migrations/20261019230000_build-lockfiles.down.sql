@@ -0,0 +1,2 @@
+ALTER TABLE builds DROP COLUMN lockfile_source;
+DROP TYPE lockfile_source;
@@ -0,0 +1,4 @@
+CREATE TYPE lockfile_source AS ENUM ('published', 'generated', 'regenerated');
+
+-- where the `Cargo.lock` of the build came from, the file itself is in the storage.
+ALTER TABLE builds ADD COLUMN lockfile_source lockfile_source;
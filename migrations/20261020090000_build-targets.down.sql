@@ -0,0 +1,2 @@
+DROP TABLE rustdoc_json_formats;
+ALTER TABLE builds DROP COLUMN targets;
@@ -0,0 +1,10 @@
+-- the targets a build stored a build log for, so we don't have to list the logs in the storage.
+ALTER TABLE builds ADD COLUMN targets TEXT[];
+
+-- the rustdoc JSON format versions we stored for each target of a release.
+CREATE TABLE rustdoc_json_formats (
+    release_id INT NOT NULL REFERENCES releases(id) ON DELETE CASCADE,
+    target TEXT NOT NULL,
+    format_version TEXT NOT NULL,
+    PRIMARY KEY (release_id, target, format_version)
+);
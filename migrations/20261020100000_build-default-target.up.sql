@@ -0,0 +1,2 @@
+-- the default target of a build, known before its release is finished.
+ALTER TABLE builds ADD COLUMN default_target TEXT;
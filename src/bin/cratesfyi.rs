@@ -5,19 +5,21 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use anyhow::{Context as _, Error, Result, anyhow};
+use anyhow::{Context as _, Error, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
 use docs_rs::cdn::CdnBackend;
 use docs_rs::db::{self, CrateId, Overrides, Pool, add_path_into_database};
+use docs_rs::rebuild_campaigns::{self, CampaignOutcome, CampaignSelection, FailureCategory};
 use docs_rs::repositories::RepositoryStatsUpdater;
 use docs_rs::utils::{
     ConfigName, get_config, get_crate_pattern_and_priority, list_crate_priorities, queue_builder,
     remove_crate_priority, set_config, set_crate_priority,
 };
 use docs_rs::{
-    AsyncBuildQueue, AsyncStorage, BuildQueue, Config, Context, Index, InstanceMetrics,
-    PackageKind, RegistryApi, RustwideBuilder, ServiceMetrics, Storage,
-    start_background_metrics_webserver, start_web_server,
+    AsyncBuildQueue, AsyncStorage, BuildQueue, CanarySample, Config, Context, Index,
+    InstanceMetrics, PackageKind, RegistryApi, RustwideBuilder, ServiceMetrics, Storage,
+    build_logs, start_background_metrics_webserver, start_web_server, webhooks,
 };
 use futures_util::StreamExt;
 use once_cell::sync::OnceCell;
@@ -208,6 +210,7 @@ impl CommandLine {
                 }
 
                 start_background_metrics_webserver(Some(metric_server_socket_addr), &ctx)?;
+                docs_rs::utils::daemon::start_hosted_registry_watchers(&ctx)?;
 
                 ctx.runtime()?.block_on(async {
                     docs_rs::utils::watch_registry(
@@ -222,6 +225,7 @@ impl CommandLine {
                 metric_server_socket_addr,
             } => {
                 start_background_metrics_webserver(Some(metric_server_socket_addr), &ctx)?;
+                docs_rs::utils::daemon::start_background_builder_heartbeat(&ctx)?;
 
                 let build_queue = ctx.build_queue()?;
                 let config = ctx.config()?;
@@ -262,6 +266,12 @@ enum QueueSubcommand {
             allow_negative_numbers = true
         )]
         build_priority: i32,
+        /// Don't build the crate before this time (RFC 3339, e.g. `2025-01-01T02:00:00Z`)
+        #[arg(long, conflicts_with("delay"))]
+        at: Option<DateTime<Utc>>,
+        /// Don't build the crate before this many seconds have passed
+        #[arg(long, conflicts_with("at"))]
+        delay: Option<u64>,
     },
 
     /// Interactions with build queue priorities
@@ -270,6 +280,12 @@ enum QueueSubcommand {
         subcommand: PrioritySubcommand,
     },
 
+    /// Named mass rebuilds, comparing the outcomes before and after
+    Campaign {
+        #[command(subcommand)]
+        subcommand: CampaignSubcommand,
+    },
+
     /// Get the registry watcher's last seen reference
     GetLastSeenReference,
 
@@ -294,12 +310,28 @@ impl QueueSubcommand {
                 crate_name,
                 crate_version,
                 build_priority,
-            } => build_queue.add_crate(
-                &crate_name,
-                &crate_version,
-                build_priority,
-                ctx.config()?.registry_url.as_deref(),
-            )?,
+                at,
+                delay,
+            } => {
+                let not_before = match (at, delay) {
+                    (Some(at), None) => Some(at),
+                    (None, Some(delay)) => Some(
+                        Utc::now()
+                            + chrono::Duration::try_seconds(delay.try_into()?)
+                                .context("delay is too long")?,
+                    ),
+                    (None, None) => None,
+                    (Some(_), Some(_)) => unreachable!(),
+                };
+
+                build_queue.schedule_crate(
+                    &crate_name,
+                    &crate_version,
+                    build_priority,
+                    ctx.config()?.registry_url.as_deref(),
+                    not_before,
+                )?;
+            }
 
             Self::GetLastSeenReference => {
                 if let Some(reference) = build_queue.last_seen_reference()? {
@@ -325,11 +357,163 @@ impl QueueSubcommand {
             }
 
             Self::DefaultPriority { subcommand } => subcommand.handle_args(ctx)?,
+
+            Self::Campaign { subcommand } => subcommand.handle_args(ctx)?,
         }
         Ok(())
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+enum CampaignSubcommand {
+    /// Queue the releases matching all the given filters
+    ///
+    /// Without a list, the filters apply to the latest release of every crate.
+    Create {
+        /// A unique name for the campaign
+        #[arg(name = "NAME")]
+        name: String,
+
+        /// Priority of the builds
+        #[arg(
+            short = 'p',
+            long = "priority",
+            default_value = "20",
+            allow_negative_numbers = true
+        )]
+        priority: i32,
+
+        /// Only releases whose newest build used a nightly from this day (`YYYY-MM-DD`) or later
+        #[arg(long)]
+        toolchain_since: Option<chrono::NaiveDate>,
+
+        /// Only releases whose newest build used a nightly from this day (`YYYY-MM-DD`) or earlier
+        #[arg(long)]
+        toolchain_until: Option<chrono::NaiveDate>,
+
+        /// Only releases whose newest build failed
+        #[arg(long, conflicts_with_all(["ice", "log_contains"]))]
+        failed: bool,
+
+        /// Only releases whose newest build failed with an internal compiler error
+        #[arg(long, conflicts_with_all(["failed", "log_contains"]))]
+        ice: bool,
+
+        /// Only releases whose newest build failed with this text in the log
+        #[arg(long, conflicts_with_all(["failed", "ice"]))]
+        log_contains: Option<String>,
+
+        /// Only releases depending on this crate
+        #[arg(long)]
+        dependency: Option<String>,
+
+        /// Only the releases in this file, one `name` or `name version` per line
+        #[arg(long)]
+        list: Option<PathBuf>,
+    },
+
+    /// List the campaigns with their progress
+    List,
+
+    /// Compare the build outcomes of the releases of a campaign before and after the rebuild
+    Report {
+        #[arg(name = "NAME")]
+        name: String,
+
+        /// Only show the releases that were fixed or broken by the rebuild
+        #[arg(long)]
+        changes_only: bool,
+    },
+}
+
+impl CampaignSubcommand {
+    fn handle_args(self, ctx: BinContext) -> Result<()> {
+        ctx.runtime()?.block_on(async {
+            let conn = &mut *ctx.pool()?.get_async().await?;
+            match self {
+                Self::Create {
+                    name,
+                    priority,
+                    toolchain_since,
+                    toolchain_until,
+                    failed,
+                    ice,
+                    log_contains,
+                    dependency,
+                    list,
+                } => {
+                    let failure = match (failed, ice, log_contains) {
+                        (true, _, _) => Some(FailureCategory::Any),
+                        (_, true, _) => Some(FailureCategory::InternalCompilerError),
+                        (_, _, Some(text)) => Some(FailureCategory::LogContains(text)),
+                        _ => None,
+                    };
+                    let releases = match list {
+                        Some(path) => Some(rebuild_campaigns::parse_release_list(
+                            &std::fs::read_to_string(&path)
+                                .with_context(|| format!("failed to read {}", path.display()))?,
+                        )?),
+                        None => None,
+                    };
+                    let selection = CampaignSelection {
+                        toolchain_since,
+                        toolchain_until,
+                        failure,
+                        dependency,
+                        releases,
+                    };
+
+                    let campaign =
+                        rebuild_campaigns::create_campaign(conn, &name, &selection, priority)
+                            .await
+                            .context("failed to create the campaign")?;
+                    println!(
+                        "queued {} releases for campaign {} ({})",
+                        campaign.total, campaign.name, campaign.selection
+                    );
+                }
+
+                Self::List => {
+                    for campaign in rebuild_campaigns::list_campaigns(conn).await? {
+                        println!(
+                            "{}\t{}/{} rebuilt\tpriority {}\t{}\t{}",
+                            campaign.name,
+                            campaign.finished,
+                            campaign.total,
+                            campaign.priority,
+                            campaign.created_at.format("%Y-%m-%d %H:%M"),
+                            campaign.selection,
+                        );
+                    }
+                }
+
+                Self::Report { name, changes_only } => {
+                    let report = rebuild_campaigns::campaign_report(conn, &name)
+                        .await?
+                        .with_context(|| format!("there is no campaign {name}"))?;
+                    for release in &report.releases {
+                        let outcome = release.outcome();
+                        if !changes_only
+                            || matches!(outcome, CampaignOutcome::Fixed | CampaignOutcome::Broken)
+                        {
+                            println!("{outcome:?}\t{release}");
+                        }
+                    }
+                    println!(
+                        "{}/{} rebuilt: {} fixed, {} broken, {} unchanged",
+                        report.campaign.finished,
+                        report.campaign.total,
+                        report.count(CampaignOutcome::Fixed),
+                        report.count(CampaignOutcome::Broken),
+                        report.count(CampaignOutcome::Unchanged),
+                    );
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
 enum PrioritySubcommand {
     /// Get priority for a crate
@@ -433,6 +617,24 @@ enum BuildSubcommand {
         toolchain_name: String,
     },
 
+    /// Builds a sample of crates with the current toolchain and a candidate, and switches to the
+    /// candidate unless it breaks too many of them
+    CanaryToolchain {
+        toolchain_name: String,
+
+        /// Which crates to build: `recent` or `popular`
+        #[arg(long, default_value = "popular")]
+        sample: CanarySample,
+
+        /// How many crates to build, defaults to `DOCSRS_CANARY_SAMPLE_SIZE`
+        #[arg(long)]
+        sample_size: Option<u32>,
+
+        /// How many regressions we accept, defaults to `DOCSRS_CANARY_MAX_REGRESSIONS`
+        #[arg(long)]
+        max_regressions: Option<u32>,
+    },
+
     /// Locks the daemon, preventing it from building new crates
     Lock,
 
@@ -524,6 +726,50 @@ impl BuildSubcommand {
                 })?;
             }
 
+            Self::CanaryToolchain {
+                toolchain_name,
+                sample,
+                sample_size,
+                max_regressions,
+            } => {
+                let report = rustwide_builder()?
+                    .run_toolchain_canary(&toolchain_name, sample, sample_size, max_regressions)
+                    .context("failed to run the toolchain canary")?;
+
+                println!(
+                    "built {} crates with {} and {}",
+                    report.results.len(),
+                    report.baseline_toolchain,
+                    report.toolchain
+                );
+                for (result, regression) in report.regressions() {
+                    println!(
+                        "regression: {} {}: {regression}",
+                        result.name, result.version
+                    );
+                }
+                for result in report.fixes() {
+                    println!("fixed: {} {}", result.name, result.version);
+                }
+                if report.passed() {
+                    println!("switched to {}", report.toolchain);
+                } else if !report.has_enough_results() {
+                    bail!(
+                        "only compared {} of {} crates, too few to judge the toolchain; keeping {}",
+                        report.results.len(),
+                        report.sample_size,
+                        report.baseline_toolchain
+                    );
+                } else {
+                    bail!(
+                        "{} regressions, more than the {} we accept; keeping {}",
+                        report.regressions().count(),
+                        report.max_regressions,
+                        report.baseline_toolchain
+                    );
+                }
+            }
+
             Self::Lock => build_queue.lock().context("Failed to lock")?,
             Self::Unlock => build_queue.unlock().context("Failed to unlock")?,
         }
@@ -580,6 +826,30 @@ enum DatabaseSubcommand {
         command: LimitsSubcommand,
     },
 
+    /// Operations on the alternative registries we host documentation for
+    Registry {
+        #[command(subcommand)]
+        command: RegistrySubcommand,
+    },
+
+    /// Operations on the rules restricting who can see private crates
+    Access {
+        #[command(subcommand)]
+        command: AccessSubcommand,
+    },
+
+    /// Operations on the webhooks we send when builds finish
+    Webhooks {
+        #[command(subcommand)]
+        command: WebhooksSubcommand,
+    },
+
+    /// Search the logs of failed builds
+    BuildLogs {
+        #[command(subcommand)]
+        command: BuildLogsSubcommand,
+    },
+
     /// Compares the database with the index and resolves inconsistencies
     Synchronize {
         /// Don't actually resolve the inconsistencies, just log them
@@ -687,6 +957,14 @@ impl DatabaseSubcommand {
 
             Self::Limits { command } => command.handle_args(ctx)?,
 
+            Self::Registry { command } => command.handle_args(ctx)?,
+
+            Self::Access { command } => command.handle_args(ctx)?,
+
+            Self::Webhooks { command } => command.handle_args(ctx)?,
+
+            Self::BuildLogs { command } => command.handle_args(ctx)?,
+
             Self::Synchronize { dry_run } => {
                 ctx.runtime()?
                     .block_on(docs_rs::utils::consistency::run_check(&ctx, dry_run))?;
@@ -713,6 +991,9 @@ enum LimitsSubcommand {
         targets: Option<usize>,
         #[arg(long)]
         timeout: Option<usize>,
+        /// Build the crate with this toolchain instead of the default one
+        #[arg(long)]
+        toolchain: Option<String>,
     },
 
     /// Remove sandbox limits overrides for a crate
@@ -742,6 +1023,7 @@ impl LimitsSubcommand {
                     memory,
                     targets,
                     timeout,
+                    toolchain,
                 } => {
                     let overrides = Overrides::for_crate(&mut conn, &crate_name).await?;
                     println!("previous sandbox limit overrides for {crate_name} = {overrides:?}");
@@ -750,6 +1032,7 @@ impl LimitsSubcommand {
                         targets,
                         timeout: timeout
                             .map(|timeout| std::time::Duration::from_secs(timeout as _)),
+                        toolchain,
                     };
                     Overrides::save(&mut conn, &crate_name, overrides).await?;
                     let overrides = Overrides::for_crate(&mut conn, &crate_name).await?;
@@ -813,6 +1096,370 @@ impl BlacklistSubcommand {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+enum RegistrySubcommand {
+    /// List all hosted registries
+    List,
+
+    /// Add a registry, its crates are documented at `/{name}/{crate}/`
+    Add {
+        /// Registry name
+        #[arg(name = "NAME")]
+        name: String,
+
+        /// URL of the registry index, sparse indexes start with `sparse+`
+        #[arg(name = "INDEX_URL")]
+        index_url: String,
+
+        /// URL of a list of all crate names, for sparse indexes
+        #[arg(long)]
+        crate_names_url: Option<String>,
+    },
+
+    /// Remove a registry without crates
+    Remove {
+        /// Registry name
+        #[arg(name = "NAME")]
+        name: String,
+    },
+}
+
+impl RegistrySubcommand {
+    fn handle_args(self, ctx: BinContext) -> Result<()> {
+        ctx.runtime()?.block_on(async {
+            let conn = &mut *ctx.pool()?.get_async().await?;
+            match self {
+                Self::List => {
+                    for registry in db::registries::list_registries(conn)
+                        .await
+                        .context("failed to list registries")?
+                    {
+                        println!("{}\t{}", registry.name, registry.index_url);
+                    }
+                }
+
+                Self::Add {
+                    name,
+                    index_url,
+                    crate_names_url,
+                } => {
+                    db::registries::add_registry(
+                        conn,
+                        &name,
+                        &index_url,
+                        crate_names_url.as_deref(),
+                    )
+                    .await
+                    .context("failed to add registry")?;
+                }
+
+                Self::Remove { name } => db::registries::remove_registry(conn, &name)
+                    .await
+                    .context("failed to remove registry")?,
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+enum AccessSubcommand {
+    /// List all access rules
+    List,
+
+    /// Allow a principal to see a crate, or all crates of a hosted registry.
+    /// Crates are public until they have their first rule.
+    Allow {
+        /// The principal, `*` allows everyone who is authenticated
+        #[arg(name = "PRINCIPAL")]
+        principal: String,
+
+        /// Name of the crate, `{registry}/{name}` for crates of hosted registries
+        #[arg(
+            long = "crate",
+            conflicts_with = "registry",
+            required_unless_present = "registry"
+        )]
+        crate_name: Option<String>,
+
+        /// Name of the hosted registry
+        #[arg(long)]
+        registry: Option<String>,
+    },
+
+    /// Remove an access rule
+    Remove {
+        /// ID of the rule, see `list`
+        #[arg(name = "ID")]
+        id: i32,
+    },
+}
+
+impl AccessSubcommand {
+    fn handle_args(self, ctx: BinContext) -> Result<()> {
+        ctx.runtime()?.block_on(async {
+            let conn = &mut *ctx.pool()?.get_async().await?;
+            match self {
+                Self::List => {
+                    for rule in db::access_rules::list_access_rules(conn)
+                        .await
+                        .context("failed to list access rules")?
+                    {
+                        let target = match (rule.crate_name, rule.registry) {
+                            (Some(crate_name), _) => format!("crate {crate_name}"),
+                            (None, Some(registry)) => format!("registry {registry}"),
+                            (None, None) => unreachable!("rules cover a crate or a registry"),
+                        };
+                        println!("{}\t{}\t{}", rule.id, target, rule.principal);
+                    }
+                }
+
+                Self::Allow {
+                    principal,
+                    crate_name,
+                    registry,
+                } => {
+                    let target = match (&crate_name, &registry) {
+                        (Some(crate_name), _) => db::access_rules::AccessTarget::Crate(crate_name),
+                        (None, Some(registry)) => {
+                            db::access_rules::AccessTarget::Registry(registry)
+                        }
+                        (None, None) => unreachable!("clap requires one of them"),
+                    };
+                    let storage = ctx.async_storage().await?;
+                    let id = db::access_rules::add_access_rule(
+                        conn,
+                        &*ctx.config()?,
+                        &storage,
+                        target,
+                        &principal,
+                    )
+                    .await
+                    .context("failed to add access rule")?;
+                    println!("added access rule {id}");
+                }
+
+                Self::Remove { id } => {
+                    let storage = ctx.async_storage().await?;
+                    db::access_rules::remove_access_rule(conn, &storage, id)
+                        .await
+                        .context("failed to remove access rule")?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+enum WebhooksSubcommand {
+    /// List all webhooks
+    List,
+
+    /// Send the results of the builds of a crate, an owner, or a hosted registry to a URL.
+    #[command(group(clap::ArgGroup::new("target").required(true)))]
+    Add {
+        /// The URL we POST the build results to
+        #[arg(name = "URL")]
+        url: url::Url,
+
+        /// The secret we sign the payloads with, sent in the `X-Docsrs-Signature-256` header
+        #[arg(name = "SECRET")]
+        secret: String,
+
+        /// Name of the crate, `{registry}/{name}` for crates of hosted registries
+        #[arg(long = "crate", group = "target")]
+        crate_name: Option<String>,
+
+        /// Login of the crate owner
+        #[arg(long, group = "target")]
+        owner: Option<String>,
+
+        /// Name of the hosted registry
+        #[arg(long, group = "target")]
+        registry: Option<String>,
+    },
+
+    /// Remove a webhook, together with its delivery log
+    Remove {
+        /// ID of the webhook, see `list`
+        #[arg(name = "ID")]
+        id: i32,
+    },
+
+    /// Show the newest deliveries and their results
+    Deliveries {
+        /// Only show the deliveries of this webhook
+        #[arg(long)]
+        webhook: Option<i32>,
+
+        /// How many deliveries to show
+        #[arg(long, default_value = "20")]
+        limit: i64,
+    },
+}
+
+impl WebhooksSubcommand {
+    fn handle_args(self, ctx: BinContext) -> Result<()> {
+        ctx.runtime()?.block_on(async {
+            let conn = &mut *ctx.pool()?.get_async().await?;
+            match self {
+                Self::List => {
+                    for webhook in webhooks::list_webhooks(conn)
+                        .await
+                        .context("failed to list webhooks")?
+                    {
+                        let target = match (webhook.crate_name, webhook.owner, webhook.registry) {
+                            (Some(crate_name), _, _) => format!("crate {crate_name}"),
+                            (None, Some(owner), _) => format!("owner {owner}"),
+                            (None, None, Some(registry)) => format!("registry {registry}"),
+                            (None, None, None) => {
+                                unreachable!("webhooks cover a crate, an owner or a registry")
+                            }
+                        };
+                        println!("{}\t{}\t{}", webhook.id, target, webhook.url);
+                    }
+                }
+
+                Self::Add {
+                    url,
+                    secret,
+                    crate_name,
+                    owner,
+                    registry,
+                } => {
+                    let target = match (&crate_name, &owner, &registry) {
+                        (Some(crate_name), _, _) => webhooks::WebhookTarget::Crate(crate_name),
+                        (None, Some(owner), _) => webhooks::WebhookTarget::Owner(owner),
+                        (None, None, Some(registry)) => webhooks::WebhookTarget::Registry(registry),
+                        (None, None, None) => unreachable!("clap requires one of them"),
+                    };
+                    let id = webhooks::add_webhook(conn, target, &url, &secret)
+                        .await
+                        .context("failed to add webhook")?;
+                    println!("added webhook {id}");
+                }
+
+                Self::Remove { id } => webhooks::remove_webhook(conn, id)
+                    .await
+                    .context("failed to remove webhook")?,
+
+                Self::Deliveries { webhook, limit } => {
+                    for delivery in webhooks::list_deliveries(conn, webhook, limit)
+                        .await
+                        .context("failed to list webhook deliveries")?
+                    {
+                        let result = match (delivery.delivered_at, delivery.last_error) {
+                            (Some(delivered_at), _) => format!("delivered at {delivered_at}"),
+                            (None, Some(error)) => format!("failed: {error}"),
+                            (None, None) => "pending".to_owned(),
+                        };
+                        println!(
+                            "{}\twebhook {}\tbuild {}\t{} attempts\t{}",
+                            delivery.id,
+                            delivery.webhook_id,
+                            delivery.build_id,
+                            delivery.attempts,
+                            result
+                        );
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+enum BuildLogsSubcommand {
+    /// Index the stored logs of failed builds which aren't indexed yet
+    Index {
+        /// Only index the builds of the last N days
+        #[arg(long, default_value = "30")]
+        days: i64,
+    },
+
+    /// Find the failed builds whose logs contain some text
+    Search {
+        /// The exact text to search for, like `error[E0658]`
+        #[arg(name = "TEXT")]
+        text: String,
+
+        /// Only search the builds of this crate
+        #[arg(long = "crate")]
+        crate_name: Option<String>,
+
+        /// Only search builds with this in their rustc version, like `1.92.0-nightly`
+        #[arg(long)]
+        toolchain: Option<String>,
+
+        /// Only search builds finished on this day (`YYYY-MM-DD`) or later
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+
+        /// Only search builds finished on this day (`YYYY-MM-DD`) or earlier
+        #[arg(long)]
+        until: Option<chrono::NaiveDate>,
+
+        /// How many builds to show
+        #[arg(long, default_value = "100")]
+        limit: i64,
+    },
+}
+
+impl BuildLogsSubcommand {
+    fn handle_args(self, ctx: BinContext) -> Result<()> {
+        ctx.runtime()?.block_on(async {
+            let conn = &mut *ctx.pool()?.get_async().await?;
+            match self {
+                Self::Index { days } => {
+                    let since = Utc::now() - chrono::TimeDelta::days(days);
+                    let indexed = build_logs::index_failed_build_logs(
+                        conn,
+                        &*ctx.async_storage().await?,
+                        since,
+                    )
+                    .await
+                    .context("failed to index build logs")?;
+                    println!("indexed {indexed} build logs");
+                }
+
+                Self::Search {
+                    text,
+                    crate_name,
+                    toolchain,
+                    since,
+                    until,
+                    limit,
+                } => {
+                    let query = build_logs::BuildLogQuery {
+                        text,
+                        crate_name,
+                        toolchain,
+                        since,
+                        until,
+                        limit,
+                    };
+                    for log in build_logs::search_build_logs(conn, &query)
+                        .await
+                        .context("failed to search build logs")?
+                    {
+                        println!(
+                            "{} {}\t{}\t{}\t{}",
+                            log.name,
+                            log.version,
+                            log.rustc_version.as_deref().unwrap_or("unknown rustc"),
+                            log.build_details_path(),
+                            log.line.as_deref().unwrap_or_default().trim(),
+                        );
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
 enum DeleteSubcommand {
     /// Delete a whole crate
@@ -0,0 +1,402 @@
+//! Full-text search across the logs of failed builds.
+//!
+//! The builder indexes the logs of failed builds in Postgres, older ones can be indexed with
+//! [`index_failed_build_logs`]. Searches use the full-text index to find candidates, and only
+//! return logs containing the exact text searched for, so `error[E0658]` doesn't find every
+//! log with an `error`.
+
+use crate::{
+    AsyncStorage,
+    db::{BuildId, types::BuildStatus},
+};
+use anyhow::{Result, bail};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::stream::TryStreamExt;
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+/// The most results a search returns.
+pub const MAX_SEARCH_RESULTS: i64 = 1000;
+
+/// What to search for, and in which builds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildLogQuery {
+    /// The exact text to search for.
+    pub text: String,
+    pub crate_name: Option<String>,
+    /// Part of the rustc version of the build, like `1.92.0-nightly` or `2025-10-01`.
+    pub toolchain: Option<String>,
+    /// Only builds finished on this day or later.
+    pub since: Option<NaiveDate>,
+    /// Only builds finished on this day or earlier.
+    pub until: Option<NaiveDate>,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildLogMatch {
+    pub name: String,
+    pub version: String,
+    pub build_id: i32,
+    pub target: String,
+    pub rustc_version: Option<String>,
+    pub build_finished: Option<DateTime<Utc>>,
+    /// The first line of the log containing the text.
+    pub line: Option<String>,
+}
+
+impl BuildLogMatch {
+    /// The path of the build details page showing the log.
+    pub fn build_details_path(&self) -> String {
+        build_log_path(&self.name, &self.version, self.build_id, &self.target)
+    }
+}
+
+/// The path of the build details page showing the log of a build target.
+pub(crate) fn build_log_path(name: &str, version: &str, build_id: i32, target: &str) -> String {
+    format!("/crate/{name}/{version}/builds/{build_id}/{target}.txt")
+}
+
+/// Adds the log of a build target to the search index, replacing the one we already have.
+#[instrument(skip(conn, log))]
+pub(crate) async fn index_build_log(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+    target: &str,
+    log: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO build_log_index (build_id, release_id, target, content)
+         SELECT builds.id, builds.rid, $2, $3
+         FROM builds
+         WHERE builds.id = $1
+         ON CONFLICT (build_id, target) DO UPDATE
+            SET content = EXCLUDED.content",
+        build_id.0,
+        target,
+        // Postgres text can't contain NUL bytes.
+        log.replace('\0', ""),
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Indexes the stored logs of failed builds finished since the given time, which aren't
+/// indexed yet.
+///
+/// Returns how many logs were indexed.
+pub async fn index_failed_build_logs(
+    conn: &mut sqlx::PgConnection,
+    storage: &AsyncStorage,
+    since: DateTime<Utc>,
+) -> Result<usize> {
+    let build_ids: Vec<i32> = sqlx::query_scalar!(
+        r#"SELECT builds.id
+         FROM builds
+         WHERE
+            builds.build_status = $1 AND
+            builds.build_finished >= $2 AND
+            NOT EXISTS (
+                SELECT 1 FROM build_log_index WHERE build_log_index.build_id = builds.id
+            )
+         ORDER BY builds.id"#,
+        BuildStatus::Failure as BuildStatus,
+        since,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut indexed = 0;
+    for build_id in build_ids {
+        let prefix = format!("build-logs/{build_id}/");
+        let paths: Vec<String> = storage.list_prefix(&prefix).await.try_collect().await?;
+        for path in paths {
+            let Some(target) = path
+                .strip_prefix(&prefix)
+                .and_then(|filename| filename.strip_suffix(".txt"))
+                .filter(|target| !target.ends_with("_json"))
+            else {
+                continue;
+            };
+
+            let log = storage.get(&path, usize::MAX).await?;
+            index_build_log(
+                &mut *conn,
+                BuildId(build_id),
+                target,
+                &String::from_utf8_lossy(&log.content),
+            )
+            .await?;
+            indexed += 1;
+        }
+        debug!(build_id, "indexed build logs");
+    }
+
+    Ok(indexed)
+}
+
+/// Checks a query can be searched for.
+pub fn validate_query(query: &BuildLogQuery) -> Result<()> {
+    if !query.text.chars().any(char::is_alphanumeric) {
+        bail!("the search text has to contain letters or digits");
+    }
+    if !(1..=MAX_SEARCH_RESULTS).contains(&query.limit) {
+        bail!("the limit has to be between 1 and {MAX_SEARCH_RESULTS}");
+    }
+    Ok(())
+}
+
+/// Searches the indexed build logs, newest builds first.
+#[instrument(skip(conn))]
+pub async fn search_build_logs(
+    conn: &mut sqlx::PgConnection,
+    query: &BuildLogQuery,
+) -> Result<Vec<BuildLogMatch>> {
+    validate_query(query)?;
+
+    let since = query
+        .since
+        .map(|date| date.and_time(Default::default()).and_utc());
+    let until = query
+        .until
+        .and_then(|date| date.succ_opt())
+        .map(|date| date.and_time(Default::default()).and_utc());
+
+    Ok(sqlx::query_as!(
+        BuildLogMatch,
+        r#"SELECT
+            crates.name,
+            releases.version,
+            build_log_index.build_id,
+            build_log_index.target,
+            builds.rustc_version,
+            builds.build_finished,
+            (
+                SELECT line
+                FROM regexp_split_to_table(build_log_index.content, E'\n') AS line
+                WHERE strpos(line, $1) > 0
+                LIMIT 1
+            ) AS line
+         FROM build_log_index
+         INNER JOIN builds ON builds.id = build_log_index.build_id
+         INNER JOIN releases ON releases.id = build_log_index.release_id
+         INNER JOIN crates ON crates.id = releases.crate_id
+         WHERE
+            build_log_index.content_tsv @@ plainto_tsquery('simple', $1) AND
+            strpos(build_log_index.content, $1) > 0 AND
+            ($2::TEXT IS NULL OR normalize_crate_name(crates.name) = normalize_crate_name($2)) AND
+            ($3::TEXT IS NULL OR strpos(builds.rustc_version, $3) > 0) AND
+            ($4::TIMESTAMPTZ IS NULL OR builds.build_finished >= $4) AND
+            ($5::TIMESTAMPTZ IS NULL OR builds.build_finished < $5)
+         ORDER BY builds.build_finished DESC NULLS LAST, build_log_index.build_id DESC
+         LIMIT $6"#,
+        query.text,
+        query.crate_name,
+        query.toolchain,
+        since,
+        until,
+        query.limit,
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{FakeBuild, async_wrapper};
+
+    const LOG: &str = "[INFO] running `rustdoc`\n\
+                       [INFO] error[E0658]: use of unstable library feature `foo`\n\
+                       [INFO] error: aborting due to 1 previous error\n";
+
+    fn query(text: &str) -> BuildLogQuery {
+        BuildLogQuery {
+            text: text.into(),
+            limit: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn search() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let mut build_ids = Vec::new();
+            for (name, rustc_version) in [
+                ("foo", "rustc 1.92.0-nightly (abcdef012 2025-10-01)"),
+                ("bar", "rustc 1.93.0-nightly (012abcdef 2025-10-20)"),
+            ] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .builds(vec![
+                        FakeBuild::default()
+                            .build_status(BuildStatus::Failure)
+                            .rustc_version(rustc_version),
+                    ])
+                    .create()
+                    .await?;
+                let build_id = sqlx::query_scalar!(
+                    r#"SELECT builds.id as "id: BuildId"
+                     FROM builds
+                     INNER JOIN releases ON releases.id = builds.rid
+                     INNER JOIN crates ON crates.id = releases.crate_id
+                     WHERE crates.name = $1"#,
+                    name
+                )
+                .fetch_one(&mut *conn)
+                .await?;
+                index_build_log(&mut conn, build_id, "x86_64-unknown-linux-gnu", LOG).await?;
+                build_ids.push(build_id.0);
+            }
+
+            let results = search_build_logs(&mut conn, &query("error[E0658]")).await?;
+            assert_eq!(results.len(), 2);
+            assert_eq!(
+                results[0].line.as_deref(),
+                Some("[INFO] error[E0658]: use of unstable library feature `foo`")
+            );
+            assert_eq!(
+                results[0].build_details_path(),
+                format!(
+                    "/crate/{}/0.1.0/builds/{}/x86_64-unknown-linux-gnu.txt",
+                    results[0].name, results[0].build_id
+                )
+            );
+
+            // only the exact text matches, not every log containing its words.
+            assert!(
+                search_build_logs(&mut conn, &query("error[E0277]"))
+                    .await?
+                    .is_empty()
+            );
+            assert!(
+                search_build_logs(&mut conn, &query("feature foo"))
+                    .await?
+                    .is_empty()
+            );
+
+            let results = search_build_logs(
+                &mut conn,
+                &BuildLogQuery {
+                    crate_name: Some("Foo".into()),
+                    ..query("E0658")
+                },
+            )
+            .await?;
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].build_id, build_ids[0]);
+
+            let results = search_build_logs(
+                &mut conn,
+                &BuildLogQuery {
+                    toolchain: Some("1.93.0-nightly".into()),
+                    ..query("E0658")
+                },
+            )
+            .await?;
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].build_id, build_ids[1]);
+
+            let today = Utc::now().date_naive();
+            for (since, until, expected) in [
+                (Some(today), None, 2),
+                (None, Some(today), 2),
+                (today.succ_opt(), None, 0),
+                (None, today.pred_opt(), 0),
+            ] {
+                let results = search_build_logs(
+                    &mut conn,
+                    &BuildLogQuery {
+                        since,
+                        until,
+                        ..query("E0658")
+                    },
+                )
+                .await?;
+                assert_eq!(results.len(), expected, "since {since:?} until {until:?}");
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn invalid_queries() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            assert!(search_build_logs(&mut conn, &query("[]")).await.is_err());
+            assert!(
+                search_build_logs(
+                    &mut conn,
+                    &BuildLogQuery {
+                        limit: MAX_SEARCH_RESULTS + 1,
+                        ..query("error")
+                    }
+                )
+                .await
+                .is_err()
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn index_failed_builds() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .builds(vec![
+                    FakeBuild::default()
+                        .successful(false)
+                        .s3_build_log(LOG)
+                        .build_log_for_other_target("i686-pc-windows-msvc", LOG)
+                        .build_log_for_other_target("x86_64-unknown-linux-gnu_json", LOG),
+                ])
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("bar")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let mut conn = env.async_db().await.async_conn().await;
+            let storage = env.async_storage().await;
+            let since = Utc::now() - chrono::TimeDelta::days(1);
+
+            // only the logs of the failed build are indexed, without its rustdoc JSON log.
+            assert_eq!(
+                index_failed_build_logs(&mut conn, &storage, since).await?,
+                2
+            );
+            // and only once.
+            assert_eq!(
+                index_failed_build_logs(&mut conn, &storage, since).await?,
+                0
+            );
+
+            let mut targets: Vec<_> = search_build_logs(&mut conn, &query("E0658"))
+                .await?
+                .into_iter()
+                .map(|result| (result.name, result.target))
+                .collect();
+            targets.sort();
+            assert_eq!(
+                targets,
+                [
+                    ("foo".to_owned(), "i686-pc-windows-msvc".to_owned()),
+                    ("foo".to_owned(), "x86_64-unknown-linux-gnu".to_owned()),
+                ]
+            );
+
+            Ok(())
+        })
+    }
+}
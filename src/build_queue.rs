@@ -1,19 +1,26 @@
 use crate::Context;
+use crate::db::registries::{self, Registry, crate_name, split_crate_name};
 use crate::db::{CrateId, Pool, delete_crate, delete_version, update_latest_version_id};
 use crate::docbuilder::PackageKind;
 use crate::error::Result;
+use crate::events::{self, Event};
+use crate::index::{SPARSE_INDEX_CONCURRENCY, SparseIndexEntry, SparseIndexResponse};
 use crate::storage::AsyncStorage;
 use crate::utils::{ConfigName, get_config, get_crate_priority, report_error, retry, set_config};
 use crate::{BuildPackageSummary, cdn};
 use crate::{Config, Index, InstanceMetrics, RustwideBuilder};
 use anyhow::Context as _;
+use chrono::{DateTime, TimeDelta, Utc};
 use fn_error_context::context;
 use futures_util::{StreamExt, stream::TryStreamExt};
 use sqlx::Connection as _;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 use tracing::{debug, error, info, instrument};
+use url::Url;
 
 /// The static priority for background rebuilds.
 /// Used when queueing rebuilds, and when rendering them
@@ -21,6 +28,22 @@ use tracing::{debug, error, info, instrument};
 /// For normal build priorities we use smaller values.
 pub(crate) const REBUILD_PRIORITY: i32 = 20;
 
+/// Upper limit for the exponential backoff between build attempts.
+const MAX_DELAY_BETWEEN_BUILD_ATTEMPTS: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How many finished builds of a crate we use to estimate its build time.
+const BUILD_DURATION_HISTORY: i64 = 5;
+/// How many recent builds of all crates we use to estimate build times
+/// for crates without build history.
+const GLOBAL_BUILD_DURATION_HISTORY: i64 = 1000;
+/// Estimated build time when there is no build history at all.
+const DEFAULT_BUILD_DURATION: TimeDelta = TimeDelta::minutes(2);
+
+/// The channel we use to ask the registry watcher to check the index for new crates.
+pub(crate) const INDEX_SYNC_CHANNEL: &str = "docsrs_index_sync";
+/// Key of the advisory lock we hold while checking the index for new crates.
+const INDEX_SYNC_LOCK: i64 = 4242;
+
 #[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
 pub(crate) struct QueuedCrate {
     #[serde(skip)]
@@ -30,6 +53,15 @@ pub(crate) struct QueuedCrate {
     pub(crate) priority: i32,
     pub(crate) registry: Option<String>,
     pub(crate) attempt: i32,
+    pub(crate) not_before: Option<DateTime<Utc>>,
+}
+
+/// When we expect the documentation of a queued or in-progress release to be available.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct BuildEstimate {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) finished_at: DateTime<Utc>,
 }
 
 #[derive(Debug)]
@@ -39,6 +71,8 @@ pub struct AsyncBuildQueue {
     pub(crate) db: Pool,
     metrics: Arc<InstanceMetrics>,
     max_attempts: i32,
+    /// When we last fetched all crates of a sparse index, by registry name.
+    last_full_sparse_refresh: Mutex<HashMap<Option<String>, Instant>>,
 }
 
 impl AsyncBuildQueue {
@@ -54,6 +88,7 @@ impl AsyncBuildQueue {
             db,
             metrics,
             storage,
+            last_full_sparse_refresh: Mutex::default(),
         }
     }
 
@@ -83,7 +118,6 @@ impl AsyncBuildQueue {
         Ok(())
     }
 
-    #[context("error trying to add {name}-{version} to build queue")]
     pub async fn add_crate(
         &self,
         name: &str,
@@ -91,26 +125,38 @@ impl AsyncBuildQueue {
         priority: i32,
         registry: Option<&str>,
     ) -> Result<()> {
-        let mut conn = self.db.get_async().await?;
+        self.schedule_crate(name, version, priority, registry, None)
+            .await
+    }
 
-        sqlx::query!(
-            "INSERT INTO queue (name, version, priority, registry)
-                 VALUES ($1, $2, $3, $4)
-                 ON CONFLICT (name, version) DO UPDATE
-                    SET priority = EXCLUDED.priority,
-                        registry = EXCLUDED.registry,
-                        attempt = 0,
-                        last_attempt = NULL
-                ;",
-            name,
-            version,
-            priority,
-            registry,
-        )
-        .execute(&mut *conn)
-        .await?;
+    /// Add a crate to the build queue that won't be built before `not_before`.
+    ///
+    /// Without `not_before` the crate can be built right away, like with [`Self::add_crate`].
+    pub async fn schedule_crate(
+        &self,
+        name: &str,
+        version: &str,
+        priority: i32,
+        registry: Option<&str>,
+        not_before: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        self.queue_crate(name, version, priority, registry, not_before)
+            .await
+    }
 
-        Ok(())
+    async fn queue_crate(
+        &self,
+        name: &str,
+        version: &str,
+        priority: i32,
+        registry: Option<&str>,
+        not_before: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let mut conn = self.db.get_async().await?;
+        queue_crate(
+            &mut conn, name, version, priority, registry, not_before, None,
+        )
+        .await
     }
 
     pub(crate) async fn pending_count(&self) -> Result<usize> {
@@ -166,16 +212,67 @@ impl AsyncBuildQueue {
 
         Ok(sqlx::query_as!(
             QueuedCrate,
-            "SELECT id, name, version, priority, registry, attempt
+            "SELECT queue.id, queue.name, queue.version, queue.priority, queue.registry, queue.attempt, queue.not_before
                  FROM queue
-                 WHERE attempt < $1
-                 ORDER BY priority ASC, attempt ASC, id ASC",
+                 INNER JOIN (
+                    SELECT
+                        queue.id,
+                        queue_owners.scheduling_group,
+                        -- delayed entries don't take the place of due ones.
+                        ROW_NUMBER() OVER (
+                            PARTITION BY queue.priority, queue_owners.scheduling_group
+                            ORDER BY
+                                COALESCE(queue.not_before > NOW(), FALSE) ASC,
+                                queue.attempt ASC,
+                                queue.id ASC
+                        ) AS owner_position
+                    FROM queue
+                    INNER JOIN queue_owners ON queue_owners.queue_id = queue.id
+                    WHERE queue.attempt < $1
+                 ) AS fair_queue ON fair_queue.id = queue.id
+                 LEFT OUTER JOIN queue_scheduling_groups
+                    ON queue_scheduling_groups.name = fair_queue.scheduling_group
+                 ORDER BY
+                    queue.priority ASC,
+                    fair_queue.owner_position ASC,
+                    queue_scheduling_groups.last_dequeued ASC NULLS FIRST,
+                    queue.attempt ASC,
+                    queue.id ASC",
             self.max_attempts
         )
         .fetch_all(&mut *conn)
         .await?)
     }
 
+    /// Count the pending builds per crate owner, excluding background rebuilds.
+    ///
+    /// Returns the owners with the biggest backlog first. Crates whose owners we don't
+    /// know yet are not included.
+    pub(crate) async fn pending_count_by_owner(&self) -> Result<Vec<(String, usize)>> {
+        let mut conn = self.db.get_async().await?;
+
+        Ok(sqlx::query!(
+            r#"
+                SELECT
+                    queue_owners.owner as "owner!",
+                    COUNT(*) as "count!"
+                FROM queue
+                INNER JOIN queue_owners ON queue_owners.queue_id = queue.id
+                WHERE
+                    queue.attempt < $1 AND
+                    queue.priority < $2 AND
+                    queue_owners.owner IS NOT NULL
+                GROUP BY queue_owners.owner
+                ORDER BY "count!" DESC, queue_owners.owner ASC"#,
+            self.max_attempts,
+            REBUILD_PRIORITY,
+        )
+        .fetch(&mut *conn)
+        .map_ok(|row| (row.owner, row.count as usize))
+        .try_collect()
+        .await?)
+    }
+
     pub(crate) async fn has_build_queued(&self, name: &str, version: &str) -> Result<bool> {
         let mut conn = self.db.get_async().await?;
         Ok(sqlx::query_scalar!(
@@ -196,6 +293,304 @@ impl AsyncBuildQueue {
     }
 }
 
+/// Add a crates.io release to the build queue for a rebuild campaign.
+pub(crate) async fn queue_campaign_crate(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    version: &str,
+    priority: i32,
+    campaign_id: i32,
+) -> Result<()> {
+    queue_crate(conn, name, version, priority, None, None, Some(campaign_id)).await
+}
+
+#[context("error trying to add {name}-{version} to build queue")]
+async fn queue_crate(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    version: &str,
+    priority: i32,
+    registry: Option<&str>,
+    not_before: Option<DateTime<Utc>>,
+    campaign_id: Option<i32>,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO queue (name, version, priority, registry, not_before, campaign_id)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (name, version) DO UPDATE
+                SET priority = EXCLUDED.priority,
+                    registry = EXCLUDED.registry,
+                    not_before = EXCLUDED.not_before,
+                    campaign_id = EXCLUDED.campaign_id,
+                    attempt = 0,
+                    last_attempt = NULL
+            ;",
+        name,
+        version,
+        priority,
+        registry,
+        not_before,
+        campaign_id,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    events::notify(
+        conn,
+        &Event::Queued {
+            name: name.into(),
+            version: version.into(),
+            priority,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Build time estimates.
+impl AsyncBuildQueue {
+    /// Estimate when the builds in progress and the queued builds will be finished,
+    /// in the order they will be built.
+    ///
+    /// With `until`, we stop after the given release, so estimating a single release
+    /// doesn't have to look at the whole queue.
+    pub(crate) async fn estimate_finish_times(
+        &self,
+        until: Option<(&str, &str)>,
+    ) -> Result<Vec<BuildEstimate>> {
+        let mut conn = self.db.get_async().await?;
+
+        let in_progress = sqlx::query!(
+            r#"SELECT
+                crates.name,
+                releases.version,
+                builds.build_started
+             FROM builds
+             INNER JOIN releases ON releases.id = builds.rid
+             INNER JOIN crates ON releases.crate_id = crates.id
+             WHERE builds.build_status = 'in_progress'
+             ORDER BY builds.id ASC"#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut queued = self.queued_crates().await?;
+        queued.retain(|krate| {
+            !in_progress
+                .iter()
+                .any(|build| build.name == krate.name && build.version == krate.version)
+        });
+        if let Some((name, version)) = until {
+            if in_progress
+                .iter()
+                .any(|build| build.name == name && build.version == version)
+            {
+                queued.clear();
+            } else if let Some(position) = queued
+                .iter()
+                .position(|krate| krate.name == name && krate.version == version)
+            {
+                queued.truncate(position + 1);
+            }
+        }
+
+        let mut names: Vec<String> = in_progress
+            .iter()
+            .map(|build| build.name.clone())
+            .chain(queued.iter().map(|krate| krate.name.clone()))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        let durations = expected_build_durations(&mut conn, &names).await?;
+        let duration = |name: &str| {
+            durations
+                .get(name)
+                .copied()
+                .unwrap_or(DEFAULT_BUILD_DURATION)
+        };
+
+        let now = Utc::now();
+        let mut estimates = Vec::with_capacity(in_progress.len() + queued.len());
+
+        // We don't know how many build servers we have, so we assume every
+        // build in progress is running on its own server.
+        // The heap contains the times when each server will be free again.
+        let mut servers = BinaryHeap::new();
+        for build in in_progress {
+            let finished_at = (build.build_started.unwrap_or(now) + duration(&build.name)).max(now);
+            servers.push(Reverse(finished_at));
+            estimates.push(BuildEstimate {
+                name: build.name,
+                version: build.version,
+                finished_at,
+            });
+        }
+        if servers.is_empty() {
+            servers.push(Reverse(now));
+        }
+
+        for krate in queued {
+            let Reverse(free_at) = servers.pop().expect("we always have a build server");
+            let finished_at = match krate.not_before {
+                // the server will build other crates until this one can be built.
+                Some(not_before) if not_before > free_at => {
+                    servers.push(Reverse(free_at));
+                    not_before + duration(&krate.name)
+                }
+                _ => {
+                    let finished_at = free_at + duration(&krate.name);
+                    servers.push(Reverse(finished_at));
+                    finished_at
+                }
+            };
+            estimates.push(BuildEstimate {
+                name: krate.name,
+                version: krate.version,
+                finished_at,
+            });
+        }
+
+        Ok(estimates)
+    }
+
+    /// Estimate when the build of a queued or in-progress release will be finished.
+    ///
+    /// Returns `None` when the release is neither queued nor being built.
+    pub(crate) async fn estimate_finish_time(
+        &self,
+        name: &str,
+        version: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        // most releases are neither queued nor being built, so we check that before
+        // looking at the whole queue.
+        let mut conn = self.db.get_async().await?;
+        let pending = sqlx::query_scalar!(
+            r#"SELECT
+                EXISTS(
+                    SELECT 1
+                    FROM queue
+                    WHERE
+                        attempt < $1 AND
+                        name = $2 AND
+                        version = $3
+                ) OR EXISTS(
+                    SELECT 1
+                    FROM builds
+                    INNER JOIN releases ON releases.id = builds.rid
+                    INNER JOIN crates ON releases.crate_id = crates.id
+                    WHERE
+                        builds.build_status = 'in_progress' AND
+                        crates.name = $2 AND
+                        releases.version = $3
+                ) AS "pending!""#,
+            self.max_attempts,
+            name,
+            version,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        drop(conn);
+        if !pending {
+            return Ok(None);
+        }
+
+        Ok(self
+            .estimate_finish_times(Some((name, version)))
+            .await?
+            .into_iter()
+            .find(|estimate| estimate.name == name && estimate.version == version)
+            .map(|estimate| estimate.finished_at))
+    }
+}
+
+/// Expected build durations for the given crates.
+///
+/// Uses the average of the last builds of the crate. For crates without build history
+/// we fall back to the source size of the latest release and the average build time per
+/// byte of recent builds, and then to the average duration of recent builds.
+async fn expected_build_durations(
+    conn: &mut sqlx::PgConnection,
+    names: &[String],
+) -> Result<HashMap<String, TimeDelta>> {
+    let recent = sqlx::query!(
+        r#"SELECT
+            AVG(recent.seconds) as "avg_seconds",
+            (
+                SUM(recent.seconds) FILTER (WHERE recent.source_size IS NOT NULL) /
+                NULLIF(SUM(recent.source_size), 0)
+            )::FLOAT8 as "seconds_per_byte"
+         FROM (
+            SELECT
+                EXTRACT(EPOCH FROM builds.build_finished - builds.build_started)::FLOAT8 AS seconds,
+                releases.source_size
+            FROM builds
+            INNER JOIN releases ON releases.id = builds.rid
+            WHERE
+                builds.build_started IS NOT NULL AND
+                builds.build_finished IS NOT NULL
+            ORDER BY builds.id DESC
+            LIMIT $1
+         ) AS recent"#,
+        GLOBAL_BUILD_DURATION_HISTORY,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(sqlx::query!(
+        r#"SELECT
+            names.name as "name!",
+            history.avg_seconds,
+            latest.source_size as "source_size?"
+         FROM UNNEST($1::TEXT[]) AS names(name)
+         LEFT OUTER JOIN LATERAL (
+            SELECT AVG(
+                EXTRACT(EPOCH FROM last_builds.build_finished - last_builds.build_started)
+            )::FLOAT8 AS avg_seconds
+            FROM (
+                SELECT builds.build_started, builds.build_finished
+                FROM crates
+                INNER JOIN releases ON releases.crate_id = crates.id
+                INNER JOIN builds ON builds.rid = releases.id
+                WHERE
+                    crates.name = names.name AND
+                    builds.build_started IS NOT NULL AND
+                    builds.build_finished IS NOT NULL
+                ORDER BY builds.id DESC
+                LIMIT $2
+            ) AS last_builds
+         ) AS history ON TRUE
+         LEFT OUTER JOIN LATERAL (
+            SELECT releases.source_size
+            FROM crates
+            INNER JOIN releases ON releases.crate_id = crates.id
+            WHERE
+                crates.name = names.name AND
+                releases.source_size IS NOT NULL
+            ORDER BY releases.id DESC
+            LIMIT 1
+         ) AS latest ON TRUE"#,
+        names,
+        BUILD_DURATION_HISTORY,
+    )
+    .fetch(&mut *conn)
+    .map_ok(|row| {
+        let seconds = row
+            .avg_seconds
+            .or_else(|| Some(recent.seconds_per_byte? * row.source_size? as f64))
+            .or(recent.avg_seconds);
+
+        (
+            row.name,
+            seconds
+                .map(|seconds| TimeDelta::milliseconds((seconds * 1000.0) as i64))
+                .unwrap_or(DEFAULT_BUILD_DURATION),
+        )
+    })
+    .try_collect()
+    .await?)
+}
+
 /// Locking functions.
 impl AsyncBuildQueue {
     /// Checks for the lock and returns whether it currently exists.
@@ -222,134 +617,397 @@ impl AsyncBuildQueue {
 
 /// Index methods.
 impl AsyncBuildQueue {
+    /// Asks the registry watcher to check the registry index for new crates.
+    ///
+    /// Requests sent while the registry watcher is busy are coalesced into a single check.
+    pub async fn request_index_sync(&self) -> Result<()> {
+        let mut conn = self.db.get_async().await?;
+        sqlx::query("SELECT pg_notify($1, '')")
+            .bind(INDEX_SYNC_CHANNEL)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Like [`Self::get_new_crates`], but waits until no other registry watcher
+    /// is checking an index, so the same changes are never processed twice.
+    ///
+    /// Without a registry, this checks the index of the default registry.
+    ///
+    /// Returns the number of crates added
+    pub async fn sync_index(&self, index: &Index, registry: Option<&Registry>) -> Result<usize> {
+        let mut conn = self.db.get_async().await?;
+        let mut transaction = conn.begin().await?;
+
+        // the lock is released when the transaction ends.
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(INDEX_SYNC_LOCK)
+            .execute(&mut *transaction)
+            .await?;
+
+        let crates_added = self.get_new_crates_from(index, registry).await?;
+
+        transaction.commit().await?;
+        Ok(crates_added)
+    }
+
     /// Updates registry index repository and adds new crates into build queue.
     ///
     /// Returns the number of crates added
     pub async fn get_new_crates(&self, index: &Index) -> Result<usize> {
+        self.get_new_crates_from(index, None).await
+    }
+
+    /// Like [`Self::get_new_crates`], for the index of the given registry.
+    ///
+    /// The crates are queued with their registry-qualified name.
+    async fn get_new_crates_from(
+        &self,
+        index: &Index,
+        registry: Option<&Registry>,
+    ) -> Result<usize> {
+        if index.is_sparse() {
+            return self.get_new_crates_from_sparse_index(index, registry).await;
+        }
+
         let diff = index.diff()?;
+        let mut conn = self.db.get_async().await?;
 
-        let last_seen_reference = self
-            .last_seen_reference()
-            .await?
-            .context("no last_seen_reference set in database")?;
-        diff.set_last_seen_reference(last_seen_reference)?;
+        let last_seen_reference = match registry {
+            // we start with all crates of a newly added registry.
+            Some(registry) => registries::last_seen_reference(&mut conn, registry)
+                .await?
+                .map(|value| crates_index_diff::gix::ObjectId::from_hex(value.as_bytes()))
+                .transpose()?,
+            None => Some(
+                self.last_seen_reference()
+                    .await?
+                    .context("no last_seen_reference set in database")?,
+            ),
+        };
+        if let Some(last_seen_reference) = last_seen_reference {
+            diff.set_last_seen_reference(last_seen_reference)?;
+        }
 
         let (changes, new_reference) = diff.peek_changes_ordered()?;
 
-        let mut conn = self.db.get_async().await?;
         let mut crates_added = 0;
 
-        debug!("queueing changes from {last_seen_reference} to {new_reference}");
+        debug!("queueing changes from {last_seen_reference:?} to {new_reference}");
 
         for change in &changes {
-            if let Some((ref krate, ..)) = change.crate_deleted() {
-                match delete_crate(&mut conn, &self.storage, &self.config, krate)
-                    .await
-                    .with_context(|| format!("failed to delete crate {krate}"))
-                {
-                    Ok(_) => info!(
-                        "crate {} was deleted from the index and the database",
-                        krate
-                    ),
-                    Err(err) => report_error(&err),
-                }
-                if let Err(err) =
-                    cdn::queue_crate_invalidation(&mut conn, &self.config, krate).await
-                {
-                    report_error(&err);
-                }
+            if let Some((krate, ..)) = change.crate_deleted() {
+                self.delete_crate_from_index(&mut conn, &crate_name(registry, krate))
+                    .await;
                 continue;
             }
 
             if let Some(release) = change.version_deleted() {
-                match delete_version(
+                self.delete_release_from_index(
                     &mut conn,
-                    &self.storage,
-                    &self.config,
-                    &release.name,
+                    &crate_name(registry, &release.name),
                     &release.version,
                 )
-                .await
-                .with_context(|| {
-                    format!(
-                        "failed to delete version {}-{}",
-                        release.name, release.version
-                    )
-                }) {
-                    Ok(_) => info!(
-                        "release {}-{} was deleted from the index and the database",
-                        release.name, release.version
-                    ),
-                    Err(err) => report_error(&err),
-                }
-                if let Err(err) =
-                    cdn::queue_crate_invalidation(&mut conn, &self.config, &release.name).await
-                {
-                    report_error(&err);
-                }
+                .await;
                 continue;
             }
 
-            if let Some(release) = change.added() {
-                let priority = get_crate_priority(&mut conn, &release.name).await?;
-
-                match self
-                    .add_crate(
-                        &release.name,
+            if let Some(release) = change.added()
+                && self
+                    .queue_release_from_index(
+                        &mut conn,
+                        index,
+                        &crate_name(registry, &release.name),
                         &release.version,
-                        priority,
-                        index.repository_url(),
                     )
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "failed adding {}-{} into build queue",
-                            release.name, release.version
-                        )
-                    }) {
-                    Ok(()) => {
-                        debug!(
-                            "{}-{} added into build queue",
-                            release.name, release.version
-                        );
-                        self.metrics.queued_builds.inc();
-                        crates_added += 1;
-                    }
-                    Err(err) => report_error(&err),
-                }
+                    .await?
+            {
+                crates_added += 1;
             }
 
             let yanked = change.yanked();
             let unyanked = change.unyanked();
             if let Some(release) = yanked.or(unyanked) {
-                // FIXME: delay yanks of crates that have not yet finished building
-                // https://github.com/rust-lang/docs.rs/issues/1934
-                if let Err(err) = self
-                    .set_yanked_inner(
-                        &mut conn,
-                        release.name.as_str(),
-                        release.version.as_str(),
-                        yanked.is_some(),
-                    )
-                    .await
-                {
+                self.set_yanked_from_index(
+                    &mut conn,
+                    &crate_name(registry, &release.name),
+                    &release.version,
+                    yanked.is_some(),
+                )
+                .await;
+            }
+        }
+
+        // set the reference in the database
+        // so this survives recreating the registry watcher
+        // server.
+        match registry {
+            Some(registry) => {
+                registries::set_last_seen_reference(&mut conn, registry, &new_reference.to_string())
+                    .await?
+            }
+            None => self.set_last_seen_reference(new_reference).await?,
+        }
+
+        Ok(crates_added)
+    }
+
+    /// Sparse indexes have no history we could diff. Every check fetches the index entries
+    /// of the crates that are new in the crate names list, and every
+    /// `sparse_index_full_refresh_interval` we fetch the entries of all crates we know about.
+    /// We compare the entries that changed with our database.
+    ///
+    /// Returns the number of crates added
+    async fn get_new_crates_from_sparse_index(
+        &self,
+        index: &Index,
+        registry: Option<&Registry>,
+    ) -> Result<usize> {
+        let mut conn = self.db.get_async().await?;
+
+        let registry_name = registry.map(|r| r.name.clone());
+        let full_refresh = self
+            .last_full_sparse_refresh
+            .lock()
+            .unwrap()
+            .get(&registry_name)
+            .is_none_or(|last| last.elapsed() >= self.config.sparse_index_full_refresh_interval);
+
+        let known_names: BTreeSet<String> = sqlx::query_scalar!(
+            r#"SELECT name as "name!" FROM crates
+             UNION
+             SELECT name FROM queue"#
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .iter()
+        .map(|name| split_crate_name(name))
+        .filter(|(crate_registry, _)| *crate_registry == registry.map(|r| r.name.as_str()))
+        // the names in the index don't have the registry prefix.
+        .map(|(_, package_name)| package_name.to_owned())
+        .collect();
+
+        let crate_names_url = match registry {
+            Some(registry) => registry
+                .crate_names_url
+                .as_deref()
+                .map(Url::parse)
+                .transpose()?,
+            None => self.config.registry_crate_names_url.clone(),
+        };
+        let mut names = BTreeSet::new();
+        if let Some(url) = &crate_names_url {
+            names.extend(
+                index
+                    .fetch_sparse_crate_names(url)
+                    .await?
+                    .into_iter()
+                    .filter(|name| !known_names.contains(name)),
+            );
+        }
+        if full_refresh {
+            names.extend(known_names);
+        }
+
+        let mut responses = futures_util::stream::iter(names)
+            .map(|name| async move { index.fetch_sparse_entry(&name).await })
+            .buffer_unordered(SPARSE_INDEX_CONCURRENCY);
+
+        let mut crates_added = 0;
+        while let Some(response) = responses.next().await {
+            let response = match response {
+                Ok(response) => response,
+                Err(err) => {
                     report_error(&err);
+                    continue;
                 }
+            };
+
+            match self
+                .apply_sparse_index_entry(&mut conn, index, registry, &response)
+                .await
+            {
+                Ok(added) => {
+                    crates_added += added;
+                    // only remember entries we processed, so failed ones are retried.
+                    if let Err(err) = index.mark_sparse_entry_seen(response) {
+                        report_error(&err);
+                    }
+                }
+                Err(err) => report_error(&err),
+            }
+        }
+
+        if full_refresh {
+            self.last_full_sparse_refresh
+                .lock()
+                .unwrap()
+                .insert(registry_name, Instant::now());
+        }
+
+        Ok(crates_added)
+    }
+
+    /// Brings our database in line with a crate's entry in the sparse index.
+    ///
+    /// Returns the number of crates added
+    #[context("failed to process index entry of {}", response.name())]
+    async fn apply_sparse_index_entry(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        index: &Index,
+        registry: Option<&Registry>,
+        response: &SparseIndexResponse,
+    ) -> Result<usize> {
+        let entry = response.entry()?;
+        if let SparseIndexEntry::Unchanged = entry {
+            return Ok(0);
+        }
+
+        let name = &*crate_name(registry, response.name());
+        let known_releases: HashMap<String, Option<bool>> = sqlx::query!(
+            "SELECT releases.version, releases.yanked
+             FROM crates
+             INNER JOIN releases ON releases.crate_id = crates.id
+             WHERE crates.name = $1",
+            name,
+        )
+        .fetch(&mut *conn)
+        .map_ok(|row| (row.version, row.yanked))
+        .try_collect()
+        .await?;
+
+        let krate = match entry {
+            SparseIndexEntry::Unchanged => unreachable!(),
+            SparseIndexEntry::Missing => {
+                if !known_releases.is_empty() {
+                    self.delete_crate_from_index(conn, name).await;
+                }
+                return Ok(0);
+            }
+            SparseIndexEntry::Changed(krate) => krate,
+        };
+
+        let queued_versions: Vec<String> = sqlx::query_scalar!(
+            "SELECT version FROM queue WHERE name = $1 AND attempt < $2",
+            name,
+            self.max_attempts,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut crates_added = 0;
+        for release in krate.versions() {
+            match known_releases.get(release.version()) {
+                None if !queued_versions.iter().any(|v| v == release.version()) => {
+                    if self
+                        .queue_release_from_index(conn, index, name, release.version())
+                        .await?
+                    {
+                        crates_added += 1;
+                    }
+                }
+                // releases without a yank state get it from crates.io after the build
+                Some(Some(yanked)) if *yanked != release.is_yanked() => {
+                    self.set_yanked_from_index(conn, name, release.version(), release.is_yanked())
+                        .await;
+                }
+                _ => {}
+            }
+        }
+
+        for version in known_releases.keys() {
+            if !krate.versions().iter().any(|r| r.version() == version) {
+                self.delete_release_from_index(conn, name, version).await;
+            }
+        }
+
+        Ok(crates_added)
+    }
+
+    async fn delete_crate_from_index(&self, conn: &mut sqlx::PgConnection, krate: &str) {
+        match delete_crate(&mut *conn, &self.storage, &self.config, krate)
+            .await
+            .with_context(|| format!("failed to delete crate {krate}"))
+        {
+            Ok(_) => info!(
+                "crate {} was deleted from the index and the database",
+                krate
+            ),
+            Err(err) => report_error(&err),
+        }
+        if let Err(err) = cdn::queue_crate_invalidation(&mut *conn, &self.config, krate).await {
+            report_error(&err);
+        }
+    }
+
+    async fn delete_release_from_index(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        name: &str,
+        version: &str,
+    ) {
+        match delete_version(&mut *conn, &self.storage, &self.config, name, version)
+            .await
+            .with_context(|| format!("failed to delete version {name}-{version}"))
+        {
+            Ok(_) => info!(
+                "release {}-{} was deleted from the index and the database",
+                name, version
+            ),
+            Err(err) => report_error(&err),
+        }
+        if let Err(err) = cdn::queue_crate_invalidation(&mut *conn, &self.config, name).await {
+            report_error(&err);
+        }
+    }
 
-                if let Err(err) =
-                    cdn::queue_crate_invalidation(&mut conn, &self.config, &release.name).await
-                {
-                    report_error(&err);
-                }
+    /// Returns if the release was added to the build queue.
+    async fn queue_release_from_index(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        index: &Index,
+        name: &str,
+        version: &str,
+    ) -> Result<bool> {
+        let priority = get_crate_priority(&mut *conn, name).await?;
+
+        match self
+            .add_crate(name, version, priority, index.repository_url())
+            .await
+            .with_context(|| format!("failed adding {name}-{version} into build queue"))
+        {
+            Ok(()) => {
+                debug!("{}-{} added into build queue", name, version);
+                self.metrics.queued_builds.inc();
+                Ok(true)
+            }
+            Err(err) => {
+                report_error(&err);
+                Ok(false)
             }
         }
+    }
 
-        // set the reference in the database
-        // so this survives recreating the registry watcher
-        // server.
-        self.set_last_seen_reference(new_reference).await?;
+    async fn set_yanked_from_index(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        name: &str,
+        version: &str,
+        yanked: bool,
+    ) {
+        // FIXME: delay yanks of crates that have not yet finished building
+        // https://github.com/rust-lang/docs.rs/issues/1934
+        if let Err(err) = self
+            .set_yanked_inner(&mut *conn, name, version, yanked)
+            .await
+        {
+            report_error(&err);
+        }
 
-        Ok(crates_added)
+        if let Err(err) = cdn::queue_crate_invalidation(&mut *conn, &self.config, name).await {
+            report_error(&err);
+        }
     }
 
     pub async fn set_yanked(&self, name: &str, version: &str, yanked: bool) -> Result<()> {
@@ -370,7 +1028,13 @@ impl AsyncBuildQueue {
 
         if let Some(crate_id) = sqlx::query_scalar!(
             r#"UPDATE releases
-             SET yanked = $3
+             SET
+                yanked = $3,
+                yanked_at = CASE
+                    WHEN NOT $3 THEN NULL
+                    WHEN releases.yanked THEN releases.yanked_at
+                    ELSE NOW()
+                END
              FROM crates
              WHERE crates.id = releases.crate_id
                  AND name = $1
@@ -432,6 +1096,20 @@ impl BuildQueue {
             .block_on(self.inner.add_crate(name, version, priority, registry))
     }
 
+    pub fn schedule_crate(
+        &self,
+        name: &str,
+        version: &str,
+        priority: i32,
+        registry: Option<&str>,
+        not_before: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        self.runtime.block_on(
+            self.inner
+                .schedule_crate(name, version, priority, registry, not_before),
+        )
+    }
+
     pub fn set_yanked(&self, name: &str, version: &str, yanked: bool) -> Result<()> {
         self.runtime
             .block_on(self.inner.set_yanked(name, version, yanked))
@@ -493,19 +1171,41 @@ impl BuildQueue {
         // `SKIP LOCKED` here will enable another build-server to just
         // skip over taken (=locked) rows and start building the first
         // available one.
+        //
+        // Inside a priority, we round-robin between the owners of the
+        // queued crates, so one owner publishing hundreds of crates at once
+        // doesn't starve everyone else. The owner that was served longest ago
+        // comes first.
         let to_process = match self.runtime.block_on(
             sqlx::query_as!(
                 QueuedCrate,
-                "SELECT id, name, version, priority, registry, attempt
+                "SELECT queue.id, queue.name, queue.version, queue.priority, queue.registry, queue.attempt, queue.not_before
                  FROM queue
-                 WHERE
-                    attempt < $1 AND
-                    (last_attempt IS NULL OR last_attempt < NOW() - make_interval(secs => $2))
-                 ORDER BY priority ASC, attempt ASC, id ASC
+                 INNER JOIN (
+                    SELECT
+                        queue.id,
+                        queue_owners.scheduling_group,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY queue.priority, queue_owners.scheduling_group
+                            ORDER BY queue.attempt ASC, queue.id ASC
+                        ) AS owner_position
+                    FROM queue
+                    INNER JOIN queue_owners ON queue_owners.queue_id = queue.id
+                    WHERE
+                        queue.attempt < $1 AND
+                        (queue.not_before IS NULL OR queue.not_before <= NOW())
+                 ) AS fair_queue ON fair_queue.id = queue.id
+                 LEFT OUTER JOIN queue_scheduling_groups
+                    ON queue_scheduling_groups.name = fair_queue.scheduling_group
+                 ORDER BY
+                    queue.priority ASC,
+                    fair_queue.owner_position ASC,
+                    queue_scheduling_groups.last_dequeued ASC NULLS FIRST,
+                    queue.attempt ASC,
+                    queue.id ASC
                  LIMIT 1
-                 FOR UPDATE SKIP LOCKED",
+                 FOR UPDATE OF queue SKIP LOCKED",
                 self.inner.max_attempts,
-                self.inner.config.delay_between_build_attempts.as_secs_f64(),
             )
             .fetch_optional(&mut *transaction),
         )? {
@@ -513,6 +1213,25 @@ impl BuildQueue {
             None => return Ok(()),
         };
 
+        // This is intentionally not part of the transaction, other build servers
+        // should see it immediately and not be blocked by the row lock until
+        // the build is finished.
+        self.runtime.block_on(async {
+            let mut conn = self.inner.db.get_async().await?;
+            sqlx::query!(
+                "INSERT INTO queue_scheduling_groups (name, last_dequeued)
+                 SELECT scheduling_group, NOW()
+                 FROM queue_owners
+                 WHERE queue_id = $1
+                 ON CONFLICT (name) DO UPDATE
+                    SET last_dequeued = EXCLUDED.last_dequeued",
+                to_process.id,
+            )
+            .execute(&mut *conn)
+            .await?;
+            Ok::<_, anyhow::Error>(())
+        })?;
+
         let res = self
             .inner
             .metrics
@@ -529,15 +1248,25 @@ impl BuildQueue {
         }
 
         let mut increase_attempt_count = || -> Result<()> {
+            // the delay until the next attempt doubles with every failed attempt.
+            let delay = self
+                .inner
+                .config
+                .delay_between_build_attempts
+                .saturating_mul(2u32.saturating_pow(to_process.attempt.max(0) as u32))
+                .min(MAX_DELAY_BETWEEN_BUILD_ATTEMPTS);
+
             let attempt: i32 = self.runtime.block_on(
                 sqlx::query_scalar!(
                     "UPDATE queue
                          SET
                             attempt = attempt + 1,
-                            last_attempt = NOW()
+                            last_attempt = NOW(),
+                            not_before = NOW() + make_interval(secs => $2)
                          WHERE id = $1
                          RETURNING attempt;",
                     to_process.id,
+                    delay.as_secs_f64(),
                 )
                 .fetch_one(&mut *transaction),
             )?;
@@ -730,11 +1459,11 @@ pub async fn queue_rebuilds(
 
 #[cfg(test)]
 mod tests {
-    use crate::test::FakeBuild;
+    use crate::db::types::BuildStatus;
+    use crate::registry_api::{CrateOwner, OwnerKind};
+    use crate::test::{FakeBuild, TestSparseIndex};
 
     use super::*;
-    use chrono::Utc;
-    use std::time::Duration;
 
     #[test]
     fn test_rebuild_when_old() {
@@ -928,6 +1657,135 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_get_new_crates_from_sparse_index() {
+        crate::test::async_wrapper(|env| async move {
+            let registry = TestSparseIndex::new().await?;
+            env.override_config(|config| {
+                config.registry_crate_names_url = Some(registry.crate_names_url());
+                config.sparse_index_full_refresh_interval = Duration::ZERO;
+            });
+            let queue = env.async_build_queue().await;
+            let index = registry.index()?;
+
+            registry.publish("foo", "1.0.0")?;
+            registry.publish("bar", "0.1.0")?;
+            assert_eq!(queue.get_new_crates(&index).await?, 2);
+
+            // unchanged and already queued releases are not queued again
+            registry.publish("foo", "1.1.0")?;
+            assert_eq!(queue.get_new_crates(&index).await?, 1);
+            assert_eq!(queue.get_new_crates(&index).await?, 0);
+
+            let mut queued: Vec<_> = queue
+                .queued_crates()
+                .await?
+                .into_iter()
+                .map(|c| (c.name, c.version, c.registry))
+                .collect();
+            queued.sort();
+            assert_eq!(
+                queued,
+                vec![
+                    ("bar".into(), "0.1.0".into(), Some(registry.url())),
+                    ("foo".into(), "1.0.0".into(), Some(registry.url())),
+                    ("foo".into(), "1.1.0".into(), Some(registry.url())),
+                ]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_sparse_index_only_fetches_new_crates_between_full_refreshes() {
+        crate::test::async_wrapper(|env| async move {
+            let registry = TestSparseIndex::new().await?;
+            env.override_config(|config| {
+                config.registry_crate_names_url = Some(registry.crate_names_url());
+                config.sparse_index_full_refresh_interval = Duration::from_secs(60 * 60);
+            });
+            let queue = env.async_build_queue().await;
+            let index = registry.index()?;
+
+            registry.publish("foo", "1.0.0")?;
+            assert_eq!(queue.get_new_crates(&index).await?, 1);
+
+            // new crates are picked up right away, new releases of known crates
+            // wait for the next full refresh.
+            registry.publish("foo", "1.1.0")?;
+            registry.publish("bar", "0.1.0")?;
+            assert_eq!(queue.get_new_crates(&index).await?, 1);
+            assert!(!queue.has_build_queued("foo", "1.1.0").await?);
+
+            // a new queue fetches everything again on the first check
+            let queue = AsyncBuildQueue::new(
+                env.async_db().await.pool(),
+                env.instance_metrics(),
+                env.config(),
+                env.async_storage().await,
+            );
+            assert_eq!(queue.get_new_crates(&index).await?, 1);
+            assert!(queue.has_build_queued("foo", "1.1.0").await?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_sparse_index_yanks_and_deletions() {
+        crate::test::async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.sparse_index_full_refresh_interval = Duration::ZERO;
+            });
+            let registry = TestSparseIndex::new().await?;
+            let queue = env.async_build_queue().await;
+            let index = registry.index()?;
+
+            for (name, version) in [("foo", "1.0.0"), ("foo", "1.1.0"), ("bar", "1.0.0")] {
+                registry.publish(name, version)?;
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version(version)
+                    .create()
+                    .await?;
+            }
+            assert_eq!(queue.get_new_crates(&index).await?, 0);
+
+            registry.delete_crate("bar")?;
+            registry.delete_version("foo", "1.1.0")?;
+            registry.set_yanked("foo", "1.0.0", true)?;
+
+            assert_eq!(queue.get_new_crates(&index).await?, 0);
+
+            let mut conn = env.async_db().await.async_conn().await;
+            let releases: Vec<(String, String, Option<bool>)> = sqlx::query!(
+                "SELECT crates.name, releases.version, releases.yanked
+                 FROM crates
+                 INNER JOIN releases ON releases.crate_id = crates.id
+                 ORDER BY crates.name, releases.version"
+            )
+            .fetch(&mut *conn)
+            .map_ok(|row| (row.name, row.version, row.yanked))
+            .try_collect()
+            .await?;
+            assert_eq!(releases, vec![("foo".into(), "1.0.0".into(), Some(true))]);
+
+            // the next change of the entry is picked up too
+            registry.set_yanked("foo", "1.0.0", false)?;
+            assert_eq!(queue.get_new_crates(&index).await?, 0);
+            assert_eq!(
+                sqlx::query_scalar!("SELECT yanked FROM releases")
+                    .fetch_one(&mut *conn)
+                    .await?,
+                Some(false)
+            );
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_wait_between_build_attempts() {
         crate::test::wrapper(|env| {
@@ -954,10 +1812,10 @@ mod tests {
             })?;
 
             runtime.block_on(async {
-                // fake the build-attempt timestamp so it's older
+                // fake the next possible build-attempt timestamp so it's in the past
                 let mut conn = env.async_db().await.async_conn().await;
                 sqlx::query!(
-                    "UPDATE queue SET last_attempt = $1",
+                    "UPDATE queue SET not_before = $1",
                     Utc::now() - chrono::Duration::try_seconds(60).unwrap()
                 )
                 .execute(&mut *conn)
@@ -978,6 +1836,94 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_exponential_backoff_between_build_attempts() {
+        crate::test::wrapper(|env| {
+            env.override_config(|config| {
+                config.build_attempts = 99;
+                config.delay_between_build_attempts = Duration::from_secs(10);
+            });
+
+            let queue = env.build_queue();
+            queue.add_crate("krate", "1.0.0", 0, None)?;
+
+            let mut delays = Vec::new();
+            for _ in 0..3 {
+                queue.process_next_crate(|_| anyhow::bail!("simulate a failure"))?;
+
+                delays.push(env.runtime().block_on(async {
+                    let mut conn = env.async_db().await.async_conn().await;
+                    let row = sqlx::query!(
+                        r#"SELECT
+                            not_before as "not_before!",
+                            last_attempt as "last_attempt!"
+                         FROM queue"#
+                    )
+                    .fetch_one(&mut *conn)
+                    .await?;
+
+                    // make the crate available for the next attempt
+                    sqlx::query!("UPDATE queue SET not_before = NULL")
+                        .execute(&mut *conn)
+                        .await?;
+
+                    Ok::<_, anyhow::Error>((row.not_before - row.last_attempt).num_seconds())
+                })?);
+            }
+
+            assert_eq!(delays, vec![10, 20, 40]);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_scheduled_build_waits_until_not_before() {
+        crate::test::wrapper(|env| {
+            let queue = env.build_queue();
+
+            queue.schedule_crate(
+                "later",
+                "1.0.0",
+                0,
+                None,
+                Some(Utc::now() + chrono::Duration::try_hours(1).unwrap()),
+            )?;
+            queue.schedule_crate(
+                "earlier",
+                "1.0.0",
+                0,
+                None,
+                Some(Utc::now() - chrono::Duration::try_hours(1).unwrap()),
+            )?;
+
+            let mut built = Vec::new();
+            for _ in 0..2 {
+                queue.process_next_crate(|krate| {
+                    built.push(krate.name.clone());
+                    Ok(BuildPackageSummary::default())
+                })?;
+            }
+            assert_eq!(built, vec!["earlier"]);
+
+            // scheduled builds are still shown in the queue
+            let queued = queue.queued_crates()?;
+            assert_eq!(queued.len(), 1);
+            assert_eq!(queued[0].name, "later");
+            assert!(queued[0].not_before.is_some());
+
+            // adding it again without a time resets the schedule
+            queue.add_crate("later", "1.0.0", 0, None)?;
+            queue.process_next_crate(|krate| {
+                built.push(krate.name.clone());
+                Ok(BuildPackageSummary::default())
+            })?;
+            assert_eq!(built, vec!["earlier", "later"]);
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_add_and_process_crates() {
         const MAX_ATTEMPTS: u16 = 3;
@@ -1101,7 +2047,7 @@ mod tests {
             })?;
 
             let queued_invalidations = fetch_invalidations();
-            assert_eq!(queued_invalidations.len(), 3);
+            assert_eq!(queued_invalidations.len(), 4);
             assert!(
                 queued_invalidations
                     .iter()
@@ -1114,11 +2060,11 @@ mod tests {
             })?;
 
             let queued_invalidations = fetch_invalidations();
-            assert_eq!(queued_invalidations.len(), 6);
+            assert_eq!(queued_invalidations.len(), 8);
             assert!(
                 queued_invalidations
                     .iter()
-                    .skip(3)
+                    .skip(4)
                     .all(|i| i.krate == "will_fail")
             );
 
@@ -1126,6 +2072,274 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_fair_scheduling_between_owners() {
+        crate::test::wrapper(|env| {
+            let owner = |login: &str| CrateOwner {
+                login: login.into(),
+                avatar: "https://example.org/avatar".into(),
+                kind: OwnerKind::User,
+            };
+
+            env.runtime().block_on(async {
+                for (name, login) in [
+                    ("aws-a", "aws"),
+                    ("aws-b", "aws"),
+                    ("aws-c", "aws"),
+                    ("other", "someone"),
+                ] {
+                    env.fake_release()
+                        .await
+                        .name(name)
+                        .version("0.1.0")
+                        .add_owner(owner(login))
+                        .create()
+                        .await?;
+                }
+                Ok::<_, anyhow::Error>(())
+            })?;
+
+            let queue = env.build_queue();
+
+            queue.add_crate("aws-a", "0.2.0", 0, None)?;
+            queue.add_crate("aws-b", "0.2.0", 0, None)?;
+            queue.add_crate("aws-c", "0.2.0", 0, None)?;
+            queue.add_crate("other", "0.2.0", 0, None)?;
+            queue.add_crate("new-crate", "0.1.0", 0, None)?;
+            queue.add_crate("important", "0.1.0", -10, None)?;
+
+            let expected_order = ["important", "aws-a", "other", "new-crate", "aws-b", "aws-c"];
+
+            assert_eq!(
+                queue
+                    .queued_crates()?
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<_>>(),
+                expected_order,
+            );
+
+            for expected in expected_order {
+                queue.process_next_crate(|krate| {
+                    assert_eq!(expected, krate.name);
+                    Ok(BuildPackageSummary::default())
+                })?;
+            }
+            assert_eq!(queue.pending_count()?, 0);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_delayed_builds_dont_hold_back_due_builds_of_the_same_owner() {
+        crate::test::wrapper(|env| {
+            let owner = |login: &str| CrateOwner {
+                login: login.into(),
+                avatar: "https://example.org/avatar".into(),
+                kind: OwnerKind::User,
+            };
+
+            env.runtime().block_on(async {
+                for (name, login) in [("aws-a", "aws"), ("aws-b", "aws"), ("other", "someone")] {
+                    env.fake_release()
+                        .await
+                        .name(name)
+                        .version("0.1.0")
+                        .add_owner(owner(login))
+                        .create()
+                        .await?;
+                }
+                Ok::<_, anyhow::Error>(())
+            })?;
+
+            let queue = env.build_queue();
+            queue.schedule_crate(
+                "aws-a",
+                "0.2.0",
+                0,
+                None,
+                Some(Utc::now() + chrono::Duration::try_hours(1).unwrap()),
+            )?;
+            queue.add_crate("aws-b", "0.2.0", 0, None)?;
+            queue.add_crate("other", "0.2.0", 0, None)?;
+
+            let expected_order = ["aws-b", "other"];
+            assert_eq!(
+                queue
+                    .queued_crates()?
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<_>>(),
+                ["aws-b", "other", "aws-a"],
+            );
+            for expected in expected_order {
+                queue.process_next_crate(|krate| {
+                    assert_eq!(expected, krate.name);
+                    Ok(BuildPackageSummary::default())
+                })?;
+            }
+            assert_eq!(queue.pending_count()?, 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_estimate_finish_times() {
+        crate::test::async_wrapper(|env| async move {
+            let queue = env.async_build_queue().await;
+
+            // without any build history we use the default build duration
+            queue.add_crate("first", "1.0.0", 0, None).await?;
+            let estimate = queue
+                .estimate_finish_time("first", "1.0.0")
+                .await?
+                .expect("missing estimate");
+            let expected = Utc::now() + DEFAULT_BUILD_DURATION;
+            assert!((estimate - expected).abs() < TimeDelta::seconds(5));
+
+            env.fake_release()
+                .await
+                .name("slow")
+                .version("1.0.0")
+                .create()
+                .await?;
+            let mut conn = env.async_db().await.async_conn().await;
+            sqlx::query!(
+                "UPDATE builds SET build_started = build_finished - INTERVAL '10 minutes'"
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            queue.add_crate("slow", "2.0.0", 0, None).await?;
+            queue.add_crate("other", "1.0.0", 1, None).await?;
+
+            let now = Utc::now();
+            let estimates = queue.estimate_finish_times(None).await?;
+            assert_eq!(
+                estimates
+                    .iter()
+                    .map(|e| (e.name.as_str(), e.version.as_str()))
+                    .collect::<Vec<_>>(),
+                vec![("first", "1.0.0"), ("slow", "2.0.0"), ("other", "1.0.0")]
+            );
+
+            // "slow" took 10 minutes the last time, the others have no history
+            // and use the average of all recent builds.
+            let offsets: Vec<_> = estimates
+                .iter()
+                .map(|e| ((e.finished_at - now).num_seconds() as f64 / 60.0).round())
+                .collect();
+            assert_eq!(offsets, vec![10.0, 20.0, 30.0]);
+
+            // we stop early when estimating a single release
+            assert_eq!(
+                queue
+                    .estimate_finish_times(Some(("first", "1.0.0")))
+                    .await?
+                    .len(),
+                1
+            );
+            assert!(
+                queue
+                    .estimate_finish_time("unknown", "1.0.0")
+                    .await?
+                    .is_none()
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_estimate_finish_times_with_builds_in_progress() {
+        crate::test::async_wrapper(|env| async move {
+            let queue = env.async_build_queue().await;
+
+            for name in ["a", "b"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("1.0.0")
+                    .builds(vec![
+                        FakeBuild::default().build_status(BuildStatus::InProgress),
+                    ])
+                    .create()
+                    .await?;
+            }
+            queue.add_crate("c", "1.0.0", 0, None).await?;
+            queue.add_crate("d", "1.0.0", 0, None).await?;
+
+            let mut conn = env.async_db().await.async_conn().await;
+            sqlx::query!(
+                "UPDATE builds SET build_started = NOW() - INTERVAL '1 minute', build_finished = NULL"
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            let now = Utc::now();
+            let offsets: Vec<_> = queue
+                .estimate_finish_times(None)
+                .await?
+                .iter()
+                .map(|e| (e.name.clone(), (e.finished_at - now).num_seconds() / 30))
+                .collect();
+
+            // two builds in progress mean two build servers, the queued crates are
+            // built in parallel after the current builds are done.
+            assert_eq!(
+                offsets,
+                vec![
+                    ("a".into(), 1),
+                    ("b".into(), 1),
+                    ("c".into(), 5),
+                    ("d".into(), 5),
+                ]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_pending_count_by_owner() {
+        crate::test::async_wrapper(|env| async move {
+            for (name, login) in [("aws-a", "aws"), ("aws-b", "aws"), ("other", "someone")] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .add_owner(CrateOwner {
+                        login: login.into(),
+                        avatar: "https://example.org/avatar".into(),
+                        kind: OwnerKind::User,
+                    })
+                    .create()
+                    .await?;
+            }
+
+            let queue = env.async_build_queue().await;
+            assert!(queue.pending_count_by_owner().await?.is_empty());
+
+            queue.add_crate("aws-a", "0.2.0", 0, None).await?;
+            queue.add_crate("aws-b", "0.2.0", 0, None).await?;
+            queue.add_crate("other", "0.2.0", 0, None).await?;
+            queue.add_crate("new-crate", "0.1.0", 0, None).await?;
+            // rebuilds are not part of the owner backlog
+            queue
+                .add_crate("other", "0.1.0", REBUILD_PRIORITY, None)
+                .await?;
+
+            assert_eq!(
+                queue.pending_count_by_owner().await?,
+                vec![("aws".into(), 2), ("someone".into(), 1)]
+            );
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_pending_count() {
         crate::test::wrapper(|env| {
@@ -1,4 +1,9 @@
-use crate::{Config, InstanceMetrics, metrics::duration_to_seconds, utils::report_error};
+use crate::{
+    Config, InstanceMetrics,
+    events::{self, Event},
+    metrics::duration_to_seconds,
+    utils::report_error,
+};
 use anyhow::{Context, Error, Result, anyhow, bail};
 use aws_config::BehaviorVersion;
 use aws_sdk_cloudfront::{
@@ -560,7 +565,11 @@ pub(crate) async fn queue_crate_invalidation(
             conn,
             name,
             distribution_id,
-            &[&format!("/{name}*"), &format!("/crate/{name}*")],
+            &[
+                &format!("/{name}*"),
+                &format!("/crate/{name}*"),
+                &format!("/api/v1/crates/{name}*"),
+            ],
         )
         .await
         .context("error enqueueing web CDN invalidation")?;
@@ -571,6 +580,12 @@ pub(crate) async fn queue_crate_invalidation(
             .context("error enqueueing static CDN invalidation")?;
     }
 
+    if config.cloudfront_distribution_id_web.is_some()
+        || config.cloudfront_distribution_id_static.is_some()
+    {
+        events::notify(conn, &Event::CdnInvalidationQueued { name: name.into() }).await?;
+    }
+
     Ok(())
 }
 
@@ -793,6 +808,12 @@ mod tests {
                         "/crate/krate*".into(),
                         None
                     ),
+                    (
+                        "distribution_id_web".into(),
+                        "krate".into(),
+                        "/api/v1/crates/krate*".into(),
+                        None
+                    ),
                     (
                         "distribution_id_static".into(),
                         "krate".into(),
@@ -806,7 +827,7 @@ mod tests {
                 queued_or_active_crate_invalidation_count_by_distribution(&mut conn, &config)
                     .await?;
             assert_eq!(counts.len(), 2);
-            assert_eq!(*counts.get("distribution_id_web").unwrap(), 2);
+            assert_eq!(*counts.get("distribution_id_web").unwrap(), 3);
             assert_eq!(*counts.get("distribution_id_static").unwrap(), 1);
 
             // queueing the invalidation doesn't create it in the CDN
@@ -901,6 +922,12 @@ mod tests {
                         "/crate/krate*".into(),
                         None
                     ),
+                    (
+                        "distribution_id_web".into(),
+                        "krate".into(),
+                        "/api/v1/crates/krate*".into(),
+                        None
+                    ),
                     (
                         "distribution_id_static".into(),
                         "krate".into(),
@@ -914,7 +941,7 @@ mod tests {
                 queued_or_active_crate_invalidation_count_by_distribution(&mut conn, &config)
                     .await?;
             assert_eq!(counts.len(), 2);
-            assert_eq!(*counts.get("distribution_id_web").unwrap(), 2);
+            assert_eq!(*counts.get("distribution_id_web").unwrap(), 3);
             assert_eq!(*counts.get("distribution_id_static").unwrap(), 1);
 
             // queueing the invalidation doesn't create it in the CDN
@@ -946,7 +973,10 @@ mod tests {
             {
                 let ir_web = active_invalidations(&cdn, "distribution_id_web");
                 assert_eq!(ir_web.len(), 1);
-                assert_eq!(ir_web[0].path_patterns, vec!["/krate*", "/crate/krate*"]);
+                assert_eq!(
+                    ir_web[0].path_patterns,
+                    vec!["/krate*", "/crate/krate*", "/api/v1/crates/krate*"]
+                );
 
                 let ir_static = active_invalidations(&cdn, "distribution_id_static");
                 assert_eq!(ir_web.len(), 1);
@@ -1161,7 +1191,10 @@ mod tests {
             // and creates them in the CDN too
             let ir_web = active_invalidations(&cdn, "distribution_id_web");
             assert_eq!(ir_web.len(), 1);
-            assert_eq!(ir_web[0].path_patterns, vec!["/krate*", "/crate/krate*"]);
+            assert_eq!(
+                ir_web[0].path_patterns,
+                vec!["/krate*", "/crate/krate*", "/api/v1/crates/krate*"]
+            );
 
             Ok(())
         });
@@ -1,4 +1,4 @@
-use crate::{cdn::CdnKind, storage::StorageKind};
+use crate::{cdn::CdnKind, storage::StorageKind, web::auth::StaticTokens};
 use anyhow::{Context, Result, anyhow, bail};
 use std::{env::VarError, error::Error, path::PathBuf, str::FromStr, time::Duration};
 use tracing::trace;
@@ -11,9 +11,18 @@ pub struct Config {
     pub registry_url: Option<String>,
     pub registry_api_host: Url,
 
+    /// URL of a list of all crate names in a sparse registry index, one per line.
+    /// Sparse indexes can't list their crates, so without it we only notice changes
+    /// to crates we already know about.
+    pub(crate) registry_crate_names_url: Option<Url>,
+
     /// How long to wait between registry checks
     pub(crate) delay_between_registry_fetches: Duration,
 
+    /// How often we fetch the index entries of all known crates from a sparse registry index.
+    /// The other checks only fetch the crates that are new in the crate names list.
+    pub(crate) sparse_index_full_refresh_interval: Duration,
+
     // Database connection params
     pub(crate) database_url: String,
     pub(crate) max_pool_size: u32,
@@ -47,6 +56,32 @@ pub struct Config {
     // constant_time_eq for comparisons!)
     pub(crate) cratesio_token: Option<String>,
 
+    /// Static tokens to authenticate with, comma-separated `principal:token` pairs.
+    pub(crate) auth_tokens: StaticTokens,
+
+    /// OpenID Connect provider whose access tokens we accept.
+    pub(crate) oidc_issuer: Option<Url>,
+
+    /// The claim from the OIDC userinfo we use as principal.
+    pub(crate) oidc_principal_claim: String,
+
+    /// Require authentication for every page, not only for crates with access rules.
+    pub(crate) require_auth: bool,
+
+    /// Principals allowed to use the admin endpoints, like the build log search.
+    pub(crate) admin_principals: Vec<String>,
+
+    /// Secret used to check the signature of the registry index webhook.
+    /// Without it, we accept unsigned webhooks.
+    pub(crate) index_webhook_secret: Option<String>,
+
+    /// How often we try to deliver a build webhook before giving up.
+    pub(crate) webhook_max_attempts: i32,
+    /// How long we wait before retrying a failed webhook delivery, doubled for each further try.
+    pub(crate) webhook_retry_delay: Duration,
+    /// How long a webhook receiver may take to respond.
+    pub(crate) webhook_timeout: Duration,
+
     // amount of retries for external API calls, mostly crates.io
     pub crates_io_api_call_retries: u32,
 
@@ -112,6 +147,13 @@ pub struct Config {
     // Build params
     pub(crate) build_attempts: u16,
     pub(crate) delay_between_build_attempts: Duration,
+    /// How often a build server tells the others that it's still alive
+    pub(crate) builder_heartbeat_interval: Duration,
+    /// After how long without heartbeat we consider a build server dead,
+    /// and recover the build it was working on.
+    pub(crate) stale_build_timeout: Duration,
+    /// How often we upload the build log of a running build, so it can be followed live.
+    pub(crate) build_log_upload_interval: Duration,
     pub(crate) rustwide_workspace: PathBuf,
     pub(crate) temp_dir: PathBuf,
     pub(crate) inside_docker: bool,
@@ -123,6 +165,12 @@ pub struct Config {
 
     // automatic rebuild configuration
     pub(crate) max_queued_rebuilds: Option<u16>,
+
+    // toolchain canaries
+    /// How many crates we build with both toolchains
+    pub(crate) canary_sample_size: u32,
+    /// How many crates a candidate toolchain may break before we refuse to switch to it
+    pub(crate) canary_max_regressions: u32,
 }
 
 impl Config {
@@ -155,15 +203,32 @@ impl Config {
                 "DOCSRS_DELAY_BETWEEN_BUILD_ATTEMPTS",
                 60,
             )?),
+            builder_heartbeat_interval: Duration::from_secs(env::<u64>(
+                "DOCSRS_BUILDER_HEARTBEAT_INTERVAL",
+                30,
+            )?),
+            stale_build_timeout: Duration::from_secs(env::<u64>(
+                "DOCSRS_STALE_BUILD_TIMEOUT",
+                10 * 60,
+            )?),
+            build_log_upload_interval: Duration::from_secs(env::<u64>(
+                "DOCSRS_BUILD_LOG_UPLOAD_INTERVAL",
+                10,
+            )?),
             delay_between_registry_fetches: Duration::from_secs(env::<u64>(
                 "DOCSRS_DELAY_BETWEEN_REGISTRY_FETCHES",
                 60,
             )?),
+            sparse_index_full_refresh_interval: Duration::from_secs(env::<u64>(
+                "DOCSRS_SPARSE_INDEX_FULL_REFRESH_INTERVAL",
+                60 * 60,
+            )?),
 
             crates_io_api_call_retries: env("DOCSRS_CRATESIO_API_CALL_RETRIES", 3)?,
 
             registry_index_path: env("REGISTRY_INDEX_PATH", prefix.join("crates.io-index"))?,
             registry_url: maybe_env("REGISTRY_URL")?,
+            registry_crate_names_url: maybe_env("DOCSRS_REGISTRY_CRATE_NAMES_URL")?,
             registry_api_host: env(
                 "DOCSRS_REGISTRY_API_HOST",
                 "https://crates.io".parse().unwrap(),
@@ -198,6 +263,21 @@ impl Config {
             gitlab_accesstoken: maybe_env("DOCSRS_GITLAB_ACCESSTOKEN")?,
 
             cratesio_token: maybe_env("DOCSRS_CRATESIO_TOKEN")?,
+            index_webhook_secret: maybe_env("DOCSRS_INDEX_WEBHOOK_SECRET")?,
+            webhook_max_attempts: env("DOCSRS_WEBHOOK_MAX_ATTEMPTS", 6)?,
+            webhook_retry_delay: Duration::from_secs(env::<u64>("DOCSRS_WEBHOOK_RETRY_DELAY", 60)?),
+            webhook_timeout: Duration::from_secs(env::<u64>("DOCSRS_WEBHOOK_TIMEOUT", 10)?),
+
+            auth_tokens: env("DOCSRS_AUTH_TOKENS", StaticTokens::default())?,
+            oidc_issuer: maybe_env("DOCSRS_OIDC_ISSUER")?,
+            oidc_principal_claim: env("DOCSRS_OIDC_PRINCIPAL_CLAIM", "sub".to_string())?,
+            require_auth: env("DOCSRS_REQUIRE_AUTH", false)?,
+            admin_principals: env("DOCSRS_ADMIN_PRINCIPALS", String::new())?
+                .split(',')
+                .map(str::trim)
+                .filter(|principal| !principal.is_empty())
+                .map(Into::into)
+                .collect(),
 
             max_file_size: env("DOCSRS_MAX_FILE_SIZE", 50 * 1024 * 1024)?,
             max_file_size_html: env("DOCSRS_MAX_FILE_SIZE_HTML", 50 * 1024 * 1024)?,
@@ -247,6 +327,8 @@ impl Config {
                 86400,
             )?),
             max_queued_rebuilds: maybe_env("DOCSRS_MAX_QUEUED_REBUILDS")?,
+            canary_sample_size: env("DOCSRS_CANARY_SAMPLE_SIZE", 100)?,
+            canary_max_regressions: env("DOCSRS_CANARY_MAX_REGRESSIONS", 2)?,
         })
     }
 }
@@ -0,0 +1,364 @@
+//! Rules restricting who can see the documentation of a crate.
+//!
+//! Crates without rules are public. A rule either covers a single crate, or all crates of
+//! a hosted registry, and allows a principal to see them. Principals are the names the
+//! web server authenticates requests as.
+
+use crate::db::registries::{self, split_crate_name};
+use crate::error::Result;
+use crate::storage::{AsyncStorage, PathNotFoundError, rustdoc_archive_path};
+use crate::{Config, cdn};
+use anyhow::Context as _;
+use futures_util::stream::TryStreamExt;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// The principal that allows every authenticated principal.
+pub const ANY_PRINCIPAL: &str = "*";
+
+/// What an access rule covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessTarget<'a> {
+    Crate(&'a str),
+    Registry(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessRule {
+    pub id: i32,
+    pub crate_name: Option<String>,
+    pub registry: Option<String>,
+    pub principal: String,
+}
+
+/// Returns all access rules, sorted by what they cover.
+pub async fn list_access_rules(conn: &mut sqlx::PgConnection) -> Result<Vec<AccessRule>> {
+    Ok(sqlx::query_as!(
+        AccessRule,
+        r#"SELECT
+            access_rules.id,
+            access_rules.crate_name,
+            registries.name as "registry?",
+            access_rules.principal
+         FROM access_rules
+         LEFT JOIN registries ON registries.id = access_rules.registry_id
+         ORDER BY registries.name, access_rules.crate_name, access_rules.principal"#
+    )
+    .fetch(conn)
+    .try_collect()
+    .await?)
+}
+
+/// Allows a principal to see a crate, or the crates of a registry.
+///
+/// The first rule for a crate or registry makes it private, so we also revoke the public
+/// access to the files of its existing releases, and purge its pages from the CDN.
+pub async fn add_access_rule(
+    conn: &mut sqlx::PgConnection,
+    config: &Config,
+    storage: &AsyncStorage,
+    target: AccessTarget<'_>,
+    principal: &str,
+) -> Result<i32> {
+    let (crate_name, registry_id) = match target {
+        AccessTarget::Crate(name) => (Some(name), None),
+        AccessTarget::Registry(name) => {
+            let registry = registries::registry_by_name(&mut *conn, name)
+                .await?
+                .with_context(|| format!("registry {name} does not exist"))?;
+            (None, Some(registry.id))
+        }
+    };
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO access_rules (crate_name, registry_id, principal)
+         VALUES ($1, $2, $3)
+         RETURNING id",
+        crate_name,
+        registry_id,
+        principal,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let releases = covered_releases(&mut *conn, crate_name, registry_id).await?;
+    for (name, version) in &releases {
+        set_public_access(storage, name, version, false).await?;
+    }
+
+    let crate_names: BTreeSet<_> = releases.iter().map(|(name, _)| name).collect();
+    for name in crate_names {
+        cdn::queue_crate_invalidation(&mut *conn, config, name).await?;
+    }
+
+    Ok(id)
+}
+
+/// Returns the name and version of the releases a rule covers.
+async fn covered_releases(
+    conn: &mut sqlx::PgConnection,
+    crate_name: Option<&str>,
+    registry_id: Option<i32>,
+) -> Result<Vec<(String, String)>> {
+    Ok(sqlx::query!(
+        "SELECT crates.name, releases.version
+         FROM crates
+         INNER JOIN releases ON releases.crate_id = crates.id
+         WHERE
+            normalize_crate_name(crates.name) = normalize_crate_name($1) OR
+            crates.registry_id = $2",
+        crate_name,
+        registry_id,
+    )
+    .fetch(conn)
+    .map_ok(|row| (row.name, row.version))
+    .try_collect()
+    .await?)
+}
+
+/// Sets the public access to the rustdoc archive and the rustdoc JSON files of a release,
+/// the only files we make public.
+async fn set_public_access(
+    storage: &AsyncStorage,
+    name: &str,
+    version: &str,
+    public: bool,
+) -> Result<()> {
+    let mut paths = vec![rustdoc_archive_path(name, version)];
+    paths.extend(
+        storage
+            .list_prefix(&format!("rustdoc-json/{name}/{version}/"))
+            .await
+            .try_collect::<Vec<_>>()
+            .await?,
+    );
+
+    for path in paths {
+        match storage.set_public_access(&path, public).await {
+            Ok(()) => {}
+            // releases without documentation or with documentation outside an archive
+            Err(err) if err.is::<PathNotFoundError>() => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Removes an access rule, when it was the last one the crate or registry is public again.
+///
+/// For releases that are public again, we restore the public access to their files.
+pub async fn remove_access_rule(
+    conn: &mut sqlx::PgConnection,
+    storage: &AsyncStorage,
+    id: i32,
+) -> Result<()> {
+    let Some(rule) = sqlx::query!(
+        "DELETE FROM access_rules WHERE id = $1 RETURNING crate_name, registry_id",
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        anyhow::bail!("access rule {id} does not exist");
+    };
+
+    let releases =
+        covered_releases(&mut *conn, rule.crate_name.as_deref(), rule.registry_id).await?;
+
+    let names: Vec<&str> = releases.iter().map(|(name, _)| name.as_str()).collect();
+    let still_private = allowed_principals_for_crates(&mut *conn, &names).await?;
+    for (name, version) in &releases {
+        if !still_private.contains_key(name) {
+            set_public_access(storage, name, version, true).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the principals allowed to see a crate, an empty list means the crate is public.
+pub(crate) async fn allowed_principals(
+    conn: &mut sqlx::PgConnection,
+    crate_name: &str,
+) -> Result<Vec<String>> {
+    Ok(allowed_principals_for_crates(conn, &[crate_name])
+        .await?
+        .remove(crate_name)
+        .unwrap_or_default())
+}
+
+/// Returns the principals allowed to see each of the crates, public crates are missing in the
+/// returned map.
+pub(crate) async fn allowed_principals_for_crates(
+    conn: &mut sqlx::PgConnection,
+    crate_names: &[&str],
+) -> Result<HashMap<String, Vec<String>>> {
+    let registries: Vec<_> = crate_names
+        .iter()
+        .map(|name| split_crate_name(name).0)
+        .collect();
+
+    let mut principals: HashMap<String, Vec<String>> = HashMap::new();
+    let mut rows = sqlx::query!(
+        r#"SELECT
+            names.name as "name!",
+            access_rules.principal
+         FROM UNNEST($1::TEXT[], $2::TEXT[]) AS names(name, registry)
+         INNER JOIN access_rules ON
+            normalize_crate_name(access_rules.crate_name) = normalize_crate_name(names.name) OR
+            access_rules.registry_id = (
+                SELECT registries.id FROM registries WHERE registries.name = names.registry
+            )"#,
+        crate_names as _,
+        registries as _,
+    )
+    .fetch(conn);
+    while let Some(row) = rows.try_next().await? {
+        principals.entry(row.name).or_default().push(row.principal);
+    }
+
+    Ok(principals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::registries::add_registry;
+    use crate::test::async_wrapper;
+
+    #[test]
+    fn test_access_rules() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let storage = env.async_storage().await;
+            add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+
+            assert!(allowed_principals(&mut conn, "foo").await?.is_empty());
+            assert!(allowed_principals(&mut conn, "acme/foo").await?.is_empty());
+
+            let id = add_access_rule(
+                &mut conn,
+                &env.config(),
+                &storage,
+                AccessTarget::Crate("foo-bar"),
+                "alice",
+            )
+            .await?;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &storage,
+                AccessTarget::Registry("acme"),
+                "bob",
+            )
+            .await?;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &storage,
+                AccessTarget::Crate("acme/foo"),
+                "carol",
+            )
+            .await?;
+            assert!(
+                add_access_rule(
+                    &mut conn,
+                    &env.config(),
+                    &storage,
+                    AccessTarget::Registry("unknown"),
+                    "bob"
+                )
+                .await
+                .is_err()
+            );
+
+            assert_eq!(allowed_principals(&mut conn, "Foo_Bar").await?, ["alice"]);
+            assert!(allowed_principals(&mut conn, "foo").await?.is_empty());
+            let mut principals = allowed_principals(&mut conn, "acme/foo").await?;
+            principals.sort();
+            assert_eq!(principals, ["bob", "carol"]);
+            assert_eq!(allowed_principals(&mut conn, "acme/bar").await?, ["bob"]);
+
+            let mut principals =
+                allowed_principals_for_crates(&mut conn, &["foo", "Foo_Bar", "acme/bar"]).await?;
+            assert_eq!(principals.len(), 2);
+            assert_eq!(principals.remove("Foo_Bar").unwrap(), ["alice"]);
+            assert_eq!(principals.remove("acme/bar").unwrap(), ["bob"]);
+
+            assert_eq!(list_access_rules(&mut conn).await?.len(), 3);
+
+            remove_access_rule(&mut conn, &storage, id).await?;
+            assert!(remove_access_rule(&mut conn, &storage, id).await.is_err());
+            assert!(allowed_principals(&mut conn, "foo-bar").await?.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_access_rule_revokes_public_access() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.cache_invalidatable_responses = true;
+                config.cloudfront_distribution_id_web = Some("distribution_id_web".into());
+            });
+            let mut conn = env.async_db().await.async_conn().await;
+            let storage = env.async_storage().await;
+
+            for name in ["private", "public"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("1.0.0")
+                    .archive_storage(true)
+                    .create()
+                    .await?;
+            }
+            let json_path = "rustdoc-json/private/1.0.0/x86_64-unknown-linux-gnu/latest.json.zst";
+            storage.store_one(json_path, Vec::new()).await?;
+            storage.set_public_access(json_path, true).await?;
+            assert!(
+                storage
+                    .get_public_access(&rustdoc_archive_path("private", "1.0.0"))
+                    .await?
+            );
+
+            let id = add_access_rule(
+                &mut conn,
+                &env.config(),
+                &storage,
+                AccessTarget::Crate("private"),
+                "alice",
+            )
+            .await?;
+
+            assert!(
+                !storage
+                    .get_public_access(&rustdoc_archive_path("private", "1.0.0"))
+                    .await?
+            );
+            assert!(!storage.get_public_access(json_path).await?);
+            assert!(
+                storage
+                    .get_public_access(&rustdoc_archive_path("public", "1.0.0"))
+                    .await?
+            );
+            let invalidated: BTreeSet<_> = cdn::queued_or_active_crate_invalidations(&mut conn)
+                .await?
+                .into_iter()
+                .map(|invalidation| invalidation.krate)
+                .collect();
+            assert_eq!(invalidated, BTreeSet::from(["private".to_owned()]));
+
+            remove_access_rule(&mut conn, &storage, id).await?;
+
+            assert!(
+                storage
+                    .get_public_access(&rustdoc_archive_path("private", "1.0.0"))
+                    .await?
+            );
+            assert!(storage.get_public_access(json_path).await?);
+
+            Ok(())
+        })
+    }
+}
@@ -1,18 +1,25 @@
 use crate::{
-    db::types::{BuildStatus, Feature},
-    docbuilder::DocCoverage,
+    db::{
+        builders::builder_name,
+        registries::{registry_by_name, split_crate_name},
+        types::{BuildStatus, Feature, LockfileSource},
+    },
+    docbuilder::{DocCoverage, RustdocWarning},
     error::Result,
+    events,
     registry_api::{CrateData, CrateOwner, ReleaseData},
-    storage::CompressionAlgorithm,
-    utils::{MetadataPackage, rustc_version::parse_rustc_date},
+    storage::{CompressionAlgorithm, RustdocJsonFormatVersion},
+    utils::{MetadataPackage, report_error, rustc_version::parse_rustc_date},
     web::crate_details::{latest_release, releases_for_crate},
+    webhooks::queue_build_webhooks,
 };
 use anyhow::{Context, anyhow};
 use derive_more::Display;
 use futures_util::stream::TryStreamExt;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use slug::slugify;
+use sqlx::Connection as _;
 use std::{
     collections::{HashMap, HashSet},
     fs,
@@ -29,7 +36,19 @@ pub struct CrateId(pub i32);
 #[sqlx(transparent)]
 pub struct ReleaseId(pub i32);
 
-#[derive(Debug, Clone, Copy, Display, PartialEq, Eq, Hash, Serialize, sqlx::Type)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Display,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    utoipa::ToSchema,
+)]
 #[sqlx(transparent)]
 pub struct BuildId(pub i32);
 
@@ -247,6 +266,53 @@ pub(crate) async fn add_doc_coverage(
     .await?)
 }
 
+/// Replaces the rustdoc warnings of a release with the ones of the current build, for
+/// every target we built.
+#[instrument(skip(conn, warnings))]
+pub(crate) async fn replace_rustdoc_warnings(
+    conn: &mut sqlx::PgConnection,
+    release_id: ReleaseId,
+    warnings: &[(String, Vec<RustdocWarning>)],
+) -> Result<()> {
+    debug!("Adding rustdoc warnings into database");
+    let mut transaction = conn.begin().await?;
+
+    sqlx::query!(
+        "DELETE FROM rustdoc_warnings WHERE release_id = $1",
+        release_id.0
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    for (target, warnings) in warnings {
+        let codes: Vec<_> = warnings.iter().map(|w| w.code.clone()).collect();
+        let messages: Vec<_> = warnings.iter().map(|w| w.message.clone()).collect();
+        let files: Vec<_> = warnings.iter().map(|w| w.file.clone()).collect();
+        let lines: Vec<_> = warnings.iter().map(|w| w.line).collect();
+        let rendered: Vec<_> = warnings.iter().map(|w| w.rendered.clone()).collect();
+
+        sqlx::query!(
+            "INSERT INTO rustdoc_warnings (release_id, target, code, message, file, line, rendered)
+             SELECT $1, $2, code, message, file, line, rendered
+             FROM UNNEST($3::TEXT[], $4::TEXT[], $5::TEXT[], $6::INT[], $7::TEXT[])
+                WITH ORDINALITY AS warnings(code, message, file, line, rendered, position)
+             ORDER BY position",
+            release_id.0,
+            target,
+            &codes as &[Option<String>],
+            &messages,
+            &files as &[Option<String>],
+            &lines as &[Option<i32>],
+            &rendered,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    transaction.commit().await?;
+    Ok(())
+}
+
 /// Adds a build into database
 #[instrument(skip(conn))]
 pub(crate) async fn finish_build(
@@ -259,7 +325,7 @@ pub(crate) async fn finish_build(
     errors: Option<&str>,
 ) -> Result<()> {
     debug!("updating build after finishing");
-    let hostname = hostname::get()?;
+    let build_server = builder_name()?;
 
     let rustc_date = match parse_rustc_date(rustc_version) {
         Ok(date) => Some(date),
@@ -292,7 +358,7 @@ pub(crate) async fn finish_build(
         rustc_version,
         docsrs_version,
         build_status as BuildStatus,
-        hostname.to_str().unwrap_or(""),
+        build_server,
         errors,
         documentation_size.map(|v| v as i64),
         rustc_date,
@@ -301,7 +367,13 @@ pub(crate) async fn finish_build(
     .fetch_one(&mut *conn)
     .await?;
 
-    update_build_status(conn, release_id).await?;
+    update_build_status(&mut *conn, release_id).await?;
+    events::notify_build(&mut *conn, build_id).await?;
+
+    // a failing webhook shouldn't fail the build.
+    if let Err(err) = queue_build_webhooks(conn, build_id).await {
+        report_error(&err.context("error queueing webhooks"));
+    }
 
     Ok(())
 }
@@ -313,6 +385,7 @@ pub(crate) async fn update_build_with_error(
     errors: Option<&str>,
 ) -> Result<BuildId> {
     debug!("updating build with error");
+
     let release_id = sqlx::query_scalar!(
         r#"UPDATE builds
          SET
@@ -327,20 +400,36 @@ pub(crate) async fn update_build_with_error(
     .fetch_one(&mut *conn)
     .await?;
 
-    update_build_status(conn, release_id).await?;
+    update_build_status(&mut *conn, release_id).await?;
+    events::notify_build(&mut *conn, build_id).await?;
+
+    if let Err(err) = queue_build_webhooks(conn, build_id).await {
+        report_error(&err.context("error queueing webhooks"));
+    }
 
     Ok(build_id)
 }
 
 pub(crate) async fn initialize_crate(conn: &mut sqlx::PgConnection, name: &str) -> Result<CrateId> {
+    let registry_id = match split_crate_name(name) {
+        (Some(registry), _) => Some(
+            registry_by_name(&mut *conn, registry)
+                .await?
+                .with_context(|| format!("unknown registry {registry} for crate {name}"))?
+                .id,
+        ),
+        (None, _) => None,
+    };
+
     sqlx::query_scalar!(
-        "INSERT INTO crates (name)
-         VALUES ($1)
+        "INSERT INTO crates (name, registry_id)
+         VALUES ($1, $2)
          ON CONFLICT (name) DO UPDATE
          SET -- this `SET` is needed so the id is always returned.
             name = EXCLUDED.name
          RETURNING id",
-        name
+        name,
+        registry_id,
     )
     .fetch_one(&mut *conn)
     .await
@@ -375,24 +464,108 @@ pub(crate) async fn initialize_build(
     conn: &mut sqlx::PgConnection,
     release_id: ReleaseId,
 ) -> Result<BuildId> {
-    let hostname = hostname::get()?;
-
     let build_id = sqlx::query_scalar!(
         r#"INSERT INTO builds(rid, build_status, build_server, build_started)
          VALUES ($1, $2, $3, NOW())
          RETURNING id as "id: BuildId" "#,
         release_id.0,
         BuildStatus::InProgress as BuildStatus,
-        hostname.to_str().unwrap_or(""),
+        builder_name()?,
     )
     .fetch_one(&mut *conn)
     .await?;
 
-    update_build_status(conn, release_id).await?;
+    update_build_status(&mut *conn, release_id).await?;
+    events::notify_build(&mut *conn, build_id).await?;
 
     Ok(build_id)
 }
 
+/// Records that a build uses the toolchain the crate is pinned to instead of the default one.
+pub(crate) async fn set_build_pinned_toolchain(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+    toolchain: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE builds SET pinned_toolchain = $1 WHERE id = $2",
+        toolchain,
+        build_id.0,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Records the default target of a build, so we can show its build log while it is running.
+pub(crate) async fn set_build_default_target(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+    default_target: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE builds SET default_target = $1 WHERE id = $2",
+        default_target,
+        build_id.0,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn set_build_lockfile_source(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+    lockfile_source: LockfileSource,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE builds SET lockfile_source = $1 WHERE id = $2",
+        lockfile_source as LockfileSource,
+        build_id.0,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Records the targets a build stored a build log for.
+pub(crate) async fn set_build_targets(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+    targets: &[&str],
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE builds SET targets = $1 WHERE id = $2",
+        targets as _,
+        build_id.0,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Records that a build stored the rustdoc JSON of its release for a target in this format version.
+pub(crate) async fn add_rustdoc_json_format(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+    target: &str,
+    format_version: RustdocJsonFormatVersion,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO rustdoc_json_formats (release_id, target, format_version)
+         SELECT builds.rid, $2, $3
+         FROM builds
+         WHERE builds.id = $1
+         ON CONFLICT DO NOTHING",
+        build_id.0,
+        target,
+        format_version.to_string(),
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
 /// Convert dependencies into Vec<(String, String, String, bool)>
 fn convert_dependencies(pkg: &MetadataPackage) -> Vec<(String, String, String, bool)> {
     pkg.dependencies
@@ -0,0 +1,406 @@
+//! Registry of the build servers.
+//!
+//! Every build server registers itself with its hostname and regularly sends heartbeats.
+//! When a build server crashes in the middle of a build, its build would stay
+//! `in_progress` forever. We use the heartbeats to find and recover these builds.
+
+use crate::{
+    db::{BuildId, ReleaseId, types::BuildStatus, update_build_status},
+    error::Result,
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::TryStreamExt;
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{instrument, warn};
+
+/// The error message we set on builds that were recovered after their build server stopped
+/// sending heartbeats.
+const STALE_BUILD_ERROR: &str = "the build server stopped responding during the build";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Builder {
+    pub(crate) name: String,
+    pub(crate) toolchain: Option<String>,
+    /// name and version of the release that is currently being built
+    pub(crate) current_build: Option<(String, String)>,
+    pub(crate) registered_at: DateTime<Utc>,
+    pub(crate) last_heartbeat: DateTime<Utc>,
+}
+
+/// The name we register this build server with, the same we store in `builds.build_server`.
+pub(crate) fn builder_name() -> Result<String> {
+    Ok(hostname::get()?.to_str().unwrap_or("").to_owned())
+}
+
+/// Registers a build server.
+///
+/// A build server that is just starting isn't working on any build, so this also forgets
+/// the build it was working on before a restart.
+pub(crate) async fn register_builder(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    toolchain: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO builders (name, toolchain, build_id, registered_at, last_heartbeat)
+         VALUES ($1, $2, NULL, NOW(), NOW())
+         ON CONFLICT (name) DO UPDATE
+            SET
+                toolchain = EXCLUDED.toolchain,
+                build_id = NULL,
+                registered_at = EXCLUDED.registered_at,
+                last_heartbeat = EXCLUDED.last_heartbeat",
+        name,
+        toolchain,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Tells the other build servers that this build server is still alive.
+pub(crate) async fn send_heartbeat(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    toolchain: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO builders (name, toolchain, last_heartbeat)
+         VALUES ($1, $2, NOW())
+         ON CONFLICT (name) DO UPDATE
+            SET
+                toolchain = EXCLUDED.toolchain,
+                last_heartbeat = EXCLUDED.last_heartbeat",
+        name,
+        toolchain,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Sets the build the given build server is working on.
+///
+/// Only the build loop of the registered build server calls this, builds started from the
+/// command line on the same host would otherwise replace its current build.
+pub(crate) async fn set_current_build(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    build_id: Option<BuildId>,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE builders SET build_id = $2 WHERE name = $1",
+        name,
+        build_id.map(|id| id.0),
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Returns the build servers that sent a heartbeat recently, sorted by name.
+pub(crate) async fn active_builders(
+    conn: &mut sqlx::PgConnection,
+    stale_after: Duration,
+) -> Result<Vec<Builder>> {
+    Ok(sqlx::query!(
+        r#"SELECT
+            builders.name,
+            builders.toolchain,
+            crates.name as "crate_name?",
+            releases.version as "crate_version?",
+            builders.registered_at,
+            builders.last_heartbeat
+         FROM builders
+         LEFT OUTER JOIN builds ON builds.id = builders.build_id
+         LEFT OUTER JOIN releases ON releases.id = builds.rid
+         LEFT OUTER JOIN crates ON crates.id = releases.crate_id
+         WHERE builders.last_heartbeat > NOW() - make_interval(secs => $1)
+         ORDER BY builders.name ASC"#,
+        stale_after.as_secs_f64(),
+    )
+    .fetch(conn)
+    .map_ok(|row| Builder {
+        name: row.name,
+        toolchain: row.toolchain,
+        current_build: row.crate_name.zip(row.crate_version),
+        registered_at: row.registered_at,
+        last_heartbeat: row.last_heartbeat,
+    })
+    .try_collect()
+    .await?)
+}
+
+/// Marks in-progress builds as failed when their build server stopped working on them.
+///
+/// That is the case when the build server stopped sending heartbeats while working on the
+/// build, or registered again after the build started, which it does when it restarts.
+/// To not race with build servers that just started a build, only builds that were
+/// started longer than `stale_after` ago are recovered.
+///
+/// Builds started from the command line are never the current build of a build server, so
+/// they are only recovered when a build server on the same host restarts during the build.
+///
+/// The queue entry of a build whose build server crashed is still in the queue,
+/// so the release will be built again.
+#[instrument(skip(conn))]
+pub(crate) async fn recover_stale_builds(
+    conn: &mut sqlx::PgConnection,
+    stale_after: Duration,
+) -> Result<Vec<BuildId>> {
+    let recovered = sqlx::query!(
+        r#"UPDATE builds
+         SET
+            build_status = $1,
+            errors = $2,
+            build_finished = NOW()
+         WHERE
+            builds.build_status = 'in_progress' AND
+            builds.build_started < NOW() - make_interval(secs => $3) AND
+            EXISTS (
+                SELECT 1
+                FROM builders
+                WHERE
+                    builders.name = builds.build_server AND
+                    CASE
+                        WHEN builders.build_id = builds.id
+                        THEN builders.last_heartbeat <= NOW() - make_interval(secs => $3)
+                        ELSE builders.registered_at > builds.build_started
+                    END
+            )
+         RETURNING
+            id as "id: BuildId",
+            rid as "rid: ReleaseId",
+            build_server"#,
+        BuildStatus::Failure as BuildStatus,
+        STALE_BUILD_ERROR,
+        stale_after.as_secs_f64(),
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for build in &recovered {
+        warn!(
+            build_id = %build.id,
+            build_server = ?build.build_server,
+            "recovered stale build",
+        );
+        update_build_status(conn, build.rid).await?;
+    }
+
+    Ok(recovered.into_iter().map(|build| build.id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{FakeBuild, async_wrapper};
+
+    #[test]
+    fn test_register_and_list_builders() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let stale_after = Duration::from_secs(60);
+
+            register_builder(&mut conn, "builder-b", Some("nightly")).await?;
+            send_heartbeat(&mut conn, "builder-a", None).await?;
+
+            // builders that didn't send a heartbeat for a while are not active.
+            send_heartbeat(&mut conn, "gone", None).await?;
+            sqlx::query!(
+                "UPDATE builders SET last_heartbeat = NOW() - INTERVAL '1 hour' WHERE name = 'gone'"
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            let builders = active_builders(&mut conn, stale_after).await?;
+            assert_eq!(
+                builders
+                    .iter()
+                    .map(|b| (b.name.as_str(), b.toolchain.as_deref()))
+                    .collect::<Vec<_>>(),
+                vec![("builder-a", None), ("builder-b", Some("nightly"))]
+            );
+            assert!(builders.iter().all(|b| b.current_build.is_none()));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_current_build() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let stale_after = Duration::from_secs(60);
+
+            let release_id = env
+                .fake_release()
+                .await
+                .name("foo")
+                .version("1.0.0")
+                .builds(vec![
+                    FakeBuild::default().build_status(BuildStatus::InProgress),
+                ])
+                .create()
+                .await?;
+            let build_id = sqlx::query_scalar!(
+                r#"SELECT id as "id: BuildId" FROM builds WHERE rid = $1"#,
+                release_id.0
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+
+            register_builder(&mut conn, "builder", None).await?;
+            set_current_build(&mut conn, "builder", Some(build_id)).await?;
+
+            let builders = active_builders(&mut conn, stale_after).await?;
+            assert_eq!(
+                builders[0].current_build,
+                Some(("foo".into(), "1.0.0".into()))
+            );
+
+            // a restart forgets the current build
+            register_builder(&mut conn, "builder", None).await?;
+            let builders = active_builders(&mut conn, stale_after).await?;
+            assert!(builders[0].current_build.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_recover_stale_builds() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let stale_after = Duration::from_secs(60);
+
+            for name in [
+                "alive",
+                "cli",
+                "cli-on-daemon-host",
+                "crashed",
+                "hung",
+                "new",
+            ] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("1.0.0")
+                    .builds(vec![
+                        FakeBuild::default().build_status(BuildStatus::InProgress),
+                    ])
+                    .create()
+                    .await?;
+            }
+            // builds started from the command line run on servers that never registered.
+            sqlx::query!(
+                "UPDATE builds
+                 SET
+                    build_started = NOW() - INTERVAL '1 hour',
+                    build_finished = NULL,
+                    build_server = CASE crates.name
+                        WHEN 'cli' THEN 'cli-host'
+                        WHEN 'cli-on-daemon-host' THEN 'daemon-host'
+                        WHEN 'hung' THEN 'hung-host'
+                        ELSE 'builder'
+                    END
+                 FROM releases, crates
+                 WHERE
+                    releases.id = builds.rid AND
+                    crates.id = releases.crate_id AND
+                    crates.name != 'new'"
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            let alive_build = sqlx::query_scalar!(
+                r#"SELECT builds.id as "id: BuildId"
+                 FROM builds
+                 INNER JOIN releases ON releases.id = builds.rid
+                 INNER JOIN crates ON crates.id = releases.crate_id
+                 WHERE crates.name = 'alive'"#
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            let hung_build = sqlx::query_scalar!(
+                r#"SELECT builds.id as "id: BuildId"
+                 FROM builds
+                 INNER JOIN releases ON releases.id = builds.rid
+                 INNER JOIN crates ON crates.id = releases.crate_id
+                 WHERE crates.name = 'hung'"#
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            // `builder` restarted after `crashed` started.
+            register_builder(&mut conn, "builder", None).await?;
+            set_current_build(&mut conn, "builder", Some(alive_build)).await?;
+            // the other build servers are running since before the builds started.
+            register_builder(&mut conn, "daemon-host", None).await?;
+            register_builder(&mut conn, "hung-host", None).await?;
+            set_current_build(&mut conn, "hung-host", Some(hung_build)).await?;
+            sqlx::query!(
+                "UPDATE builders
+                 SET
+                    registered_at = NOW() - INTERVAL '2 hours',
+                    last_heartbeat = CASE name
+                        WHEN 'hung-host' THEN NOW() - INTERVAL '1 hour'
+                        ELSE NOW()
+                    END
+                 WHERE name IN ('daemon-host', 'hung-host')"
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            assert_eq!(recover_stale_builds(&mut conn, stale_after).await?.len(), 2);
+
+            let statuses: Vec<(String, BuildStatus, Option<String>)> = sqlx::query!(
+                r#"SELECT
+                    crates.name,
+                    builds.build_status as "build_status: BuildStatus",
+                    builds.errors
+                 FROM builds
+                 INNER JOIN releases ON releases.id = builds.rid
+                 INNER JOIN crates ON crates.id = releases.crate_id
+                 ORDER BY crates.name"#
+            )
+            .fetch(&mut *conn)
+            .map_ok(|row| (row.name, row.build_status, row.errors))
+            .try_collect()
+            .await?;
+            assert_eq!(
+                statuses,
+                vec![
+                    ("alive".into(), BuildStatus::InProgress, None),
+                    ("cli".into(), BuildStatus::InProgress, None),
+                    ("cli-on-daemon-host".into(), BuildStatus::InProgress, None),
+                    (
+                        "crashed".into(),
+                        BuildStatus::Failure,
+                        Some(STALE_BUILD_ERROR.into())
+                    ),
+                    (
+                        "hung".into(),
+                        BuildStatus::Failure,
+                        Some(STALE_BUILD_ERROR.into())
+                    ),
+                    ("new".into(), BuildStatus::InProgress, None),
+                ]
+            );
+
+            // the release status is updated too
+            let release_status = sqlx::query_scalar!(
+                r#"SELECT release_build_status.build_status as "build_status: BuildStatus"
+                 FROM release_build_status
+                 INNER JOIN releases ON releases.id = release_build_status.rid
+                 INNER JOIN crates ON crates.id = releases.crate_id
+                 WHERE crates.name = 'crashed'"#
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            assert_eq!(release_status, BuildStatus::Failure);
+
+            Ok(())
+        })
+    }
+}
@@ -28,32 +28,12 @@ pub async fn delete_crate(
     name: &str,
 ) -> Result<()> {
     let crate_id = get_id(conn, name).await?;
-    let is_library = delete_crate_from_database(conn, name, crate_id).await?;
-    // #899
-    let paths = if is_library {
-        LIBRARY_STORAGE_PATHS_TO_DELETE
-    } else {
-        OTHER_STORAGE_PATHS_TO_DELETE
-    };
+    let (is_library, versions) = delete_crate_from_database(conn, name, crate_id).await?;
 
-    for prefix in paths {
-        // delete the whole rustdoc/source folder for this crate.
-        // it will include existing archives.
-        let remote_folder = format!("{prefix}/{name}/");
-        storage.delete_prefix(&remote_folder).await?;
-
-        // remove existing local archive index files.
-        let local_index_folder = config.local_archive_cache_path.join(&remote_folder);
-        if local_index_folder.exists() {
-            tokio::fs::remove_dir_all(&local_index_folder)
-                .await
-                .with_context(|| {
-                    format!(
-                        "error when trying to remove local index: {:?}",
-                        &local_index_folder
-                    )
-                })?;
-        }
+    // we don't delete the whole folder of the crate, the folder of the crate `acme`
+    // also contains the crates of the hosted registry `acme`.
+    for version in versions {
+        delete_release_files(storage, config, name, &version, is_library).await?;
     }
 
     Ok(())
@@ -68,6 +48,18 @@ pub async fn delete_version(
     version: &str,
 ) -> Result<()> {
     let is_library = delete_version_from_database(conn, name, version).await?;
+    delete_release_files(storage, config, name, version, is_library).await
+}
+
+/// Deletes the files of a release from the storage, and the local indexes of its archives.
+async fn delete_release_files(
+    storage: &AsyncStorage,
+    config: &Config,
+    name: &str,
+    version: &str,
+    is_library: bool,
+) -> Result<()> {
+    // #899
     let paths = if is_library {
         LIBRARY_STORAGE_PATHS_TO_DELETE
     } else {
@@ -169,12 +161,12 @@ async fn delete_version_from_database(
     Ok(is_library)
 }
 
-/// Returns whether any release in this crate was a library
+/// Returns whether any release in this crate was a library, and the versions of its releases
 async fn delete_crate_from_database(
     conn: &mut sqlx::PgConnection,
     name: &str,
     crate_id: CrateId,
-) -> Result<bool> {
+) -> Result<(bool, Vec<String>)> {
     let mut transaction = conn.begin().await?;
 
     sqlx::query!("DELETE FROM sandbox_overrides WHERE crate_name = $1", name,)
@@ -204,9 +196,12 @@ async fn delete_crate_from_database(
     .await?
     .unwrap_or(false);
 
-    sqlx::query!("DELETE FROM releases WHERE crate_id = $1;", crate_id.0)
-        .execute(&mut *transaction)
-        .await?;
+    let versions = sqlx::query_scalar!(
+        "DELETE FROM releases WHERE crate_id = $1 RETURNING version;",
+        crate_id.0
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
     sqlx::query!("DELETE FROM crates WHERE id = $1;", crate_id.0)
         .execute(&mut *transaction)
         .await?;
@@ -214,13 +209,14 @@ async fn delete_crate_from_database(
     // Transactions automatically rollback when not committing, so if any of the previous queries
     // fail the whole transaction will be aborted.
     transaction.commit().await?;
-    Ok(has_library)
+    Ok((has_library, versions))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::db::ReleaseId;
+    use crate::db::registries::add_registry;
     use crate::registry_api::{CrateOwner, OwnerKind};
     use crate::storage::{CompressionAlgorithm, rustdoc_json_path};
     use crate::test::{async_wrapper, fake_release_that_failed_before_build};
@@ -385,6 +381,51 @@ mod tests {
         });
     }
 
+    #[test_case(true)]
+    #[test_case(false)]
+    fn test_delete_crate_keeps_hosted_registry_crates(archive_storage: bool) {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let storage = env.async_storage().await;
+
+            // crates.io can publish a crate with the name of a registry we host.
+            // The files of `acme/foo` are stored in the folder of the crate `acme`.
+            add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+            for name in ["acme", "acme/foo"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("1.0.0")
+                    .archive_storage(archive_storage)
+                    .create()
+                    .await?;
+            }
+
+            delete_crate(&mut conn, &storage, &env.config(), "acme").await?;
+
+            assert!(!crate_exists(&mut conn, "acme").await?);
+            assert!(
+                !storage
+                    .rustdoc_file_exists("acme", "1.0.0", None, "acme/index.html", archive_storage)
+                    .await?
+            );
+            assert!(crate_exists(&mut conn, "acme/foo").await?);
+            assert!(
+                storage
+                    .rustdoc_file_exists(
+                        "acme/foo",
+                        "1.0.0",
+                        None,
+                        "foo/index.html",
+                        archive_storage
+                    )
+                    .await?
+            );
+
+            Ok(())
+        });
+    }
+
     #[test_case(true)]
     #[test_case(false)]
     fn test_delete_version(archive_storage: bool) {
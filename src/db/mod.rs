@@ -4,8 +4,10 @@ use sqlx::migrate::{Migrate, Migrator};
 
 pub use self::add_package::update_latest_version_id;
 pub(crate) use self::add_package::{
-    add_doc_coverage, finish_build, finish_release, initialize_build, initialize_crate,
-    initialize_release, update_build_with_error,
+    add_doc_coverage, add_rustdoc_json_format, finish_build, finish_release, initialize_build,
+    initialize_crate, initialize_release, replace_rustdoc_warnings, set_build_default_target,
+    set_build_lockfile_source, set_build_pinned_toolchain, set_build_targets,
+    update_build_with_error,
 };
 pub use self::{
     add_package::{
@@ -17,13 +19,16 @@ pub use self::{
     pool::{AsyncPoolClient, Pool, PoolError},
 };
 
+pub mod access_rules;
 mod add_package;
 pub mod blacklist;
+pub(crate) mod builders;
 pub mod delete;
 pub(crate) mod file;
 pub(crate) mod mimes;
 mod overrides;
 mod pool;
+pub mod registries;
 pub(crate) mod types;
 
 static MIGRATOR: Migrator = sqlx::migrate!();
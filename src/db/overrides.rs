@@ -2,11 +2,13 @@ use crate::error::Result;
 use futures_util::stream::TryStreamExt;
 use std::time::Duration;
 
-#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
 pub struct Overrides {
     pub memory: Option<usize>,
     pub targets: Option<usize>,
     pub timeout: Option<Duration>,
+    /// The toolchain to build the crate with instead of the default one.
+    pub toolchain: Option<String>,
 }
 
 macro_rules! row_to_overrides {
@@ -15,6 +17,7 @@ macro_rules! row_to_overrides {
             memory: $row.max_memory_bytes.map(|i| i as usize),
             targets: $row.max_targets.map(|i| i as usize),
             timeout: $row.timeout_seconds.map(|i| Duration::from_secs(i as u64)),
+            toolchain: $row.toolchain,
         }
     }};
 }
@@ -56,19 +59,21 @@ impl Overrides {
         sqlx::query!(
             "
             INSERT INTO sandbox_overrides (
-                crate_name, max_memory_bytes, max_targets, timeout_seconds
+                crate_name, max_memory_bytes, max_targets, timeout_seconds, toolchain
             )
-            VALUES ($1, $2, $3, $4)
+            VALUES ($1, $2, $3, $4, $5)
             ON CONFLICT (crate_name) DO UPDATE
                 SET
                     max_memory_bytes = $2,
                     max_targets = $3,
-                    timeout_seconds = $4
+                    timeout_seconds = $4,
+                    toolchain = $5
             ",
             krate,
             overrides.memory.map(|i| i as i64),
             overrides.targets.map(|i| i as i32),
             overrides.timeout.map(|d| d.as_secs() as i32),
+            overrides.toolchain,
         )
         .execute(&mut *conn)
         .await?;
@@ -105,7 +110,7 @@ mod test {
                 targets: Some(1),
                 ..Overrides::default()
             };
-            Overrides::save(&mut conn, krate, expected).await?;
+            Overrides::save(&mut conn, krate, expected.clone()).await?;
             let actual = Overrides::for_crate(&mut conn, krate).await?;
             assert_eq!(actual, Some(expected));
 
@@ -114,8 +119,9 @@ mod test {
                 memory: Some(100_000),
                 targets: Some(1),
                 timeout: Some(Duration::from_secs(300)),
+                toolchain: Some("nightly-2025-10-01".into()),
             };
-            Overrides::save(&mut conn, krate, expected).await?;
+            Overrides::save(&mut conn, krate, expected.clone()).await?;
             let actual = Overrides::for_crate(&mut conn, krate).await?;
             assert_eq!(actual, Some(expected));
 
@@ -124,7 +130,7 @@ mod test {
                 memory: Some(1),
                 ..Overrides::default()
             };
-            Overrides::save(&mut conn, krate, expected).await?;
+            Overrides::save(&mut conn, krate, expected.clone()).await?;
             let actual = Overrides::for_crate(&mut conn, krate).await?;
             assert_eq!(actual, Some(expected));
 
@@ -0,0 +1,265 @@
+//! Alternative registries we host documentation for.
+//!
+//! Crates from these registries live in their own namespace: the crate `foo` from the
+//! registry `acme` is stored as `acme/foo` everywhere in docs.rs, and its documentation
+//! is served at `/acme/foo/`. Only rustwide and the registry index see the plain name.
+
+use crate::{error::Result, web::RESERVED_PREFIXES};
+use futures_util::stream::TryStreamExt;
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+enum RegistryError {
+    #[error("invalid registry name {0}, only lowercase letters, digits, `-` and `_` are allowed")]
+    InvalidName(String),
+
+    #[error("registry name {0} is reserved")]
+    ReservedName(String),
+
+    #[error("registry {0} already exists")]
+    AlreadyExists(String),
+
+    #[error("registry name {0} is already used by a crate")]
+    CrateExists(String),
+
+    #[error("registry {0} does not exist")]
+    NotFound(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Registry {
+    pub id: i32,
+    pub name: String,
+    pub index_url: String,
+    /// list of all crate names, for sparse indexes that can't list their crates.
+    pub crate_names_url: Option<String>,
+}
+
+impl Registry {
+    /// The name we store a crate of this registry under.
+    pub fn crate_name(&self, package_name: &str) -> String {
+        format!("{}/{}", self.name, package_name)
+    }
+}
+
+/// The name we store a crate under, for crates from the default registry it's just the package name.
+pub(crate) fn crate_name(registry: Option<&Registry>, package_name: &str) -> String {
+    match registry {
+        Some(registry) => registry.crate_name(package_name),
+        None => package_name.to_owned(),
+    }
+}
+
+/// Splits a crate name into the name of its registry and the name of the package in that registry.
+pub(crate) fn split_crate_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once('/') {
+        Some((registry, package_name)) => (Some(registry), package_name),
+        None => (None, name),
+    }
+}
+
+/// Returns all registries, sorted by name.
+pub async fn list_registries(conn: &mut sqlx::PgConnection) -> Result<Vec<Registry>> {
+    Ok(sqlx::query_as!(
+        Registry,
+        "SELECT id, name, index_url, crate_names_url FROM registries ORDER BY name ASC"
+    )
+    .fetch(conn)
+    .try_collect()
+    .await?)
+}
+
+pub async fn registry_by_name(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+) -> Result<Option<Registry>> {
+    Ok(sqlx::query_as!(
+        Registry,
+        "SELECT id, name, index_url, crate_names_url FROM registries WHERE name = $1",
+        name
+    )
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// Adds a registry, its index is picked up by the registry watcher after the next restart.
+pub async fn add_registry(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    index_url: &str,
+    crate_names_url: Option<&str>,
+) -> Result<Registry> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return Err(RegistryError::InvalidName(name.into()).into());
+    }
+    // the registry name is the first segment of the URLs of its crates.
+    if RESERVED_PREFIXES.contains(&name) {
+        return Err(RegistryError::ReservedName(name.into()).into());
+    }
+    if registry_by_name(&mut *conn, name).await?.is_some() {
+        return Err(RegistryError::AlreadyExists(name.into()).into());
+    }
+    if sqlx::query_scalar!(
+        r#"SELECT EXISTS(
+            SELECT 1 FROM crates WHERE normalize_crate_name(name) = normalize_crate_name($1)
+        ) AS "exists!""#,
+        name
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    {
+        return Err(RegistryError::CrateExists(name.into()).into());
+    }
+
+    Ok(sqlx::query_as!(
+        Registry,
+        "INSERT INTO registries (name, index_url, crate_names_url)
+         VALUES ($1, $2, $3)
+         RETURNING id, name, index_url, crate_names_url",
+        name,
+        index_url,
+        crate_names_url,
+    )
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Removes a registry, this fails while we still have crates from it.
+pub async fn remove_registry(conn: &mut sqlx::PgConnection, name: &str) -> Result<()> {
+    if sqlx::query!("DELETE FROM registries WHERE name = $1", name)
+        .execute(conn)
+        .await?
+        .rows_affected()
+        == 0
+    {
+        return Err(RegistryError::NotFound(name.into()).into());
+    }
+    Ok(())
+}
+
+pub(crate) async fn last_seen_reference(
+    conn: &mut sqlx::PgConnection,
+    registry: &Registry,
+) -> Result<Option<String>> {
+    Ok(sqlx::query_scalar!(
+        "SELECT last_seen_reference FROM registries WHERE id = $1",
+        registry.id
+    )
+    .fetch_one(conn)
+    .await?)
+}
+
+pub(crate) async fn set_last_seen_reference(
+    conn: &mut sqlx::PgConnection,
+    registry: &Registry,
+    reference: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE registries SET last_seen_reference = $2 WHERE id = $1",
+        registry.id,
+        reference
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::async_wrapper;
+
+    #[test]
+    fn test_add_and_list_registries() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+
+            let acme = add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+            add_registry(
+                &mut conn,
+                "a-registry",
+                "sparse+https://a.example/index/",
+                Some("https://a.example/names.txt"),
+            )
+            .await?;
+
+            assert_eq!(
+                list_registries(&mut conn)
+                    .await?
+                    .into_iter()
+                    .map(|r| r.name)
+                    .collect::<Vec<_>>(),
+                vec!["a-registry", "acme"]
+            );
+            assert_eq!(registry_by_name(&mut conn, "acme").await?, Some(acme));
+            assert_eq!(registry_by_name(&mut conn, "other").await?, None);
+
+            for name in ["acme", "crate", "Acme", "a/b", ""] {
+                assert!(
+                    add_registry(&mut conn, name, "https://other.example/index", None)
+                        .await
+                        .is_err(),
+                    "{name}"
+                );
+            }
+
+            // registry names can't be crate names, or their URLs would overlap.
+            env.fake_release()
+                .await
+                .name("some_crate")
+                .version("1.0.0")
+                .create()
+                .await?;
+            assert!(
+                add_registry(&mut conn, "some-crate", "https://other.example/index", None)
+                    .await
+                    .is_err()
+            );
+
+            remove_registry(&mut conn, "a-registry").await?;
+            assert!(remove_registry(&mut conn, "a-registry").await.is_err());
+            assert_eq!(list_registries(&mut conn).await?.len(), 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_reserved_names() {
+        for name in [
+            "-",
+            "_",
+            "about",
+            "api",
+            "crate",
+            "favicon.ico",
+            "openapi.json",
+            "opensearch.xml",
+            "releases",
+            "robots.txt",
+            "sitemap.xml",
+        ] {
+            assert!(RESERVED_PREFIXES.contains(&name), "{name}");
+        }
+        assert!(!RESERVED_PREFIXES.contains(&"acme"));
+    }
+
+    #[test]
+    fn test_crate_names() {
+        let registry = Registry {
+            id: 1,
+            name: "acme".into(),
+            index_url: "https://acme.example/index".into(),
+            crate_names_url: None,
+        };
+
+        assert_eq!(crate_name(Some(&registry), "foo"), "acme/foo");
+        assert_eq!(crate_name(None, "foo"), "foo");
+        assert_eq!(split_crate_name("acme/foo"), (Some("acme"), "foo"));
+        assert_eq!(split_crate_name("foo"), (None, "foo"));
+    }
+}
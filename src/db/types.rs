@@ -17,7 +17,9 @@ impl Feature {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema,
+)]
 #[sqlx(type_name = "build_status", rename_all = "snake_case")]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum BuildStatus {
@@ -42,6 +44,33 @@ impl PartialEq<&str> for BuildStatus {
     }
 }
 
+/// Where the `Cargo.lock` a build used came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "lockfile_source", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum LockfileSource {
+    /// The crate was published with a lockfile.
+    Published,
+    /// The crate was published without a lockfile, so one was generated.
+    Generated,
+    /// The build failed with the published lockfile, so it was retried with a new one.
+    Regenerated,
+}
+
+impl LockfileSource {
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            Self::Published => "the lockfile published with the crate was used",
+            Self::Generated => {
+                "the crate was published without a lockfile, a new one was generated"
+            }
+            Self::Regenerated => {
+                "the build failed with the published lockfile, it was regenerated for a second attempt"
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,332 @@
+use anyhow::Result;
+use futures_util::TryStreamExt as _;
+use std::fmt;
+
+/// Docs that shrink below this share of their size with the current toolchain count as a
+/// regression, they usually lost most of their items.
+const MIN_DOC_SIZE_RATIO: f64 = 0.5;
+
+/// A canary run fails when we couldn't compare the builds of at least this share of its
+/// sample, too few results don't tell us enough about the candidate.
+const MIN_RESULTS_RATIO: f64 = 0.8;
+
+/// Which crates a canary run builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum CanarySample {
+    /// The newest releases.
+    Recent,
+    /// The newest releases of the crates with the most GitHub stars.
+    Popular,
+}
+
+/// The outcome of building a crate with one toolchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CanaryBuild {
+    pub(crate) successful: bool,
+    /// The size of the generated documentation of the default target, in bytes.
+    pub(crate) doc_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanaryRegression {
+    /// The crate built with the current toolchain, but not with the candidate.
+    BuildFailed,
+    /// The docs are much smaller with the candidate.
+    DocsShrunk { from: u64, to: u64 },
+}
+
+impl fmt::Display for CanaryRegression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BuildFailed => write!(f, "the build failed"),
+            Self::DocsShrunk { from, to } => {
+                write!(f, "the docs shrunk from {from} to {to} bytes")
+            }
+        }
+    }
+}
+
+/// A crate built with both toolchains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanaryResult {
+    pub name: String,
+    pub version: String,
+    pub(crate) baseline: CanaryBuild,
+    pub(crate) candidate: CanaryBuild,
+}
+
+impl CanaryResult {
+    pub fn regression(&self) -> Option<CanaryRegression> {
+        if !self.baseline.successful {
+            return None;
+        }
+        if !self.candidate.successful {
+            return Some(CanaryRegression::BuildFailed);
+        }
+        match (self.baseline.doc_size, self.candidate.doc_size) {
+            (Some(from), to) if (to.unwrap_or(0) as f64) < from as f64 * MIN_DOC_SIZE_RATIO => {
+                Some(CanaryRegression::DocsShrunk {
+                    from,
+                    to: to.unwrap_or(0),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the crate only builds with the candidate.
+    pub fn is_fixed(&self) -> bool {
+        !self.baseline.successful && self.candidate.successful
+    }
+}
+
+/// The results of a canary run.
+#[derive(Debug, Clone)]
+pub struct CanaryReport {
+    pub toolchain: String,
+    pub baseline_toolchain: String,
+    /// How many crates we wanted to compare.
+    pub sample_size: u32,
+    pub max_regressions: u32,
+    pub results: Vec<CanaryResult>,
+}
+
+impl CanaryReport {
+    pub fn regressions(&self) -> impl Iterator<Item = (&CanaryResult, CanaryRegression)> {
+        self.results
+            .iter()
+            .filter_map(|result| Some((result, result.regression()?)))
+    }
+
+    pub fn fixes(&self) -> impl Iterator<Item = &CanaryResult> {
+        self.results.iter().filter(|result| result.is_fixed())
+    }
+
+    /// Whether we compared enough crates of the sample to judge the candidate.
+    pub fn has_enough_results(&self) -> bool {
+        self.results.len() as f64 >= f64::from(self.sample_size) * MIN_RESULTS_RATIO
+    }
+
+    /// Whether we can switch to the candidate toolchain.
+    pub fn passed(&self) -> bool {
+        self.has_enough_results() && self.regressions().count() <= self.max_regressions as usize
+    }
+}
+
+/// The latest releases of the crates a canary run builds, at most `limit`.
+///
+/// Only crates.io crates are in the sample, and never yanked releases or blacklisted crates.
+pub(crate) async fn select_sample(
+    conn: &mut sqlx::PgConnection,
+    sample: CanarySample,
+    limit: i64,
+) -> Result<Vec<(String, String)>> {
+    Ok(sqlx::query!(
+        r#"SELECT crates.name, releases.version
+         FROM crates
+         INNER JOIN releases ON releases.id = crates.latest_version_id
+         LEFT JOIN repositories ON repositories.id = releases.repository_id
+         WHERE
+            crates.registry_id IS NULL AND
+            releases.yanked IS NOT TRUE AND
+            NOT EXISTS (
+                SELECT 1 FROM blacklisted_crates WHERE blacklisted_crates.crate_name = crates.name
+            )
+         ORDER BY
+            CASE WHEN $1 THEN repositories.stars END DESC NULLS LAST,
+            releases.release_time DESC NULLS LAST,
+            crates.name
+         LIMIT $2"#,
+        sample == CanarySample::Popular,
+        limit,
+    )
+    .fetch(conn)
+    .map_ok(|row| (row.name, row.version))
+    .try_collect()
+    .await?)
+}
+
+/// Starts recording a canary run, returning its id.
+pub(crate) async fn start_canary(
+    conn: &mut sqlx::PgConnection,
+    toolchain: &str,
+    baseline_toolchain: &str,
+    sample: CanarySample,
+    max_regressions: u32,
+) -> Result<i32> {
+    Ok(sqlx::query_scalar!(
+        "INSERT INTO toolchain_canaries (toolchain, baseline_toolchain, sample, max_regressions)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id",
+        toolchain,
+        baseline_toolchain,
+        sample.to_string(),
+        max_regressions as i32,
+    )
+    .fetch_one(conn)
+    .await?)
+}
+
+pub(crate) async fn record_canary_result(
+    conn: &mut sqlx::PgConnection,
+    canary_id: i32,
+    result: &CanaryResult,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO toolchain_canary_builds (
+            canary_id, name, version,
+            baseline_successful, baseline_doc_size,
+            candidate_successful, candidate_doc_size
+         )
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        canary_id,
+        result.name,
+        result.version,
+        result.baseline.successful,
+        result.baseline.doc_size.map(|size| size as i64),
+        result.candidate.successful,
+        result.candidate.doc_size.map(|size| size as i64),
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn finish_canary(
+    conn: &mut sqlx::PgConnection,
+    canary_id: i32,
+    report: &CanaryReport,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE toolchain_canaries
+         SET finished_at = NOW(), regressions = $2, passed = $3
+         WHERE id = $1",
+        canary_id,
+        report.regressions().count() as i32,
+        report.passed(),
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{FakeBuild, async_wrapper};
+    use chrono::{Duration, Utc};
+
+    fn build(successful: bool, doc_size: Option<u64>) -> CanaryBuild {
+        CanaryBuild {
+            successful,
+            doc_size,
+        }
+    }
+
+    fn result(baseline: CanaryBuild, candidate: CanaryBuild) -> CanaryResult {
+        CanaryResult {
+            name: "foo".into(),
+            version: "0.1.0".into(),
+            baseline,
+            candidate,
+        }
+    }
+
+    #[test]
+    fn regressions() {
+        assert_eq!(
+            result(build(true, Some(100)), build(true, Some(90))).regression(),
+            None
+        );
+        assert_eq!(
+            result(build(true, Some(100)), build(false, None)).regression(),
+            Some(CanaryRegression::BuildFailed)
+        );
+        assert_eq!(
+            result(build(true, Some(100)), build(true, Some(40))).regression(),
+            Some(CanaryRegression::DocsShrunk { from: 100, to: 40 })
+        );
+        // binaries don't have docs.
+        assert_eq!(
+            result(build(true, None), build(true, None)).regression(),
+            None
+        );
+
+        let fixed = result(build(false, None), build(true, Some(100)));
+        assert_eq!(fixed.regression(), None);
+        assert!(fixed.is_fixed());
+        assert_eq!(
+            result(build(false, None), build(false, None)).regression(),
+            None
+        );
+    }
+
+    #[test]
+    fn threshold() {
+        let mut report = CanaryReport {
+            toolchain: "nightly-2025-10-02".into(),
+            baseline_toolchain: "nightly-2025-10-01".into(),
+            sample_size: 3,
+            max_regressions: 1,
+            results: vec![
+                result(build(true, Some(100)), build(true, Some(100))),
+                result(build(true, Some(100)), build(false, None)),
+                result(build(false, None), build(true, Some(100))),
+            ],
+        };
+        assert!(report.passed());
+        assert_eq!(report.fixes().count(), 1);
+
+        report
+            .results
+            .push(result(build(true, Some(100)), build(true, Some(10))));
+        assert_eq!(report.regressions().count(), 2);
+        assert!(!report.passed());
+
+        // too few of the crates could be compared.
+        report.results.truncate(2);
+        report.max_regressions = 2;
+        assert!(!report.has_enough_results());
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn sample() {
+        async_wrapper(|env| async move {
+            for (name, days_ago, stars, yanked) in [
+                ("old", 10, 1000, false),
+                ("new", 1, 10, false),
+                ("yanked", 1, 5000, true),
+                ("blacklisted", 1, 5000, false),
+            ] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .release_time(Utc::now() - Duration::days(days_ago))
+                    .github_stats(format!("foo/{name}"), stars, 0, 0)
+                    .yanked(yanked)
+                    .builds(vec![FakeBuild::default()])
+                    .create()
+                    .await?;
+            }
+            let mut conn = env.async_db().await.async_conn().await;
+            crate::db::blacklist::add_crate(&mut conn, "blacklisted").await?;
+
+            let names = |sample: Vec<(String, String)>| -> Vec<String> {
+                sample.into_iter().map(|(name, _)| name).collect()
+            };
+            assert_eq!(
+                names(select_sample(&mut conn, CanarySample::Recent, 10).await?),
+                ["new", "old"]
+            );
+            assert_eq!(
+                names(select_sample(&mut conn, CanarySample::Popular, 1).await?),
+                ["old"]
+            );
+
+            Ok(())
+        })
+    }
+}
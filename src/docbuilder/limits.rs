@@ -11,6 +11,8 @@ pub(crate) struct Limits {
     pub timeout: Duration,
     pub networking: bool,
     pub max_log_size: usize,
+    /// The toolchain the crate is pinned to, if it doesn't build with the default one.
+    pub toolchain: Option<String>,
 }
 
 impl Limits {
@@ -22,6 +24,7 @@ impl Limits {
             targets: crate::DEFAULT_MAX_TARGETS,
             networking: false,
             max_log_size: 100 * 1024, // 100 KB
+            toolchain: None,
         }
     }
 
@@ -44,6 +47,7 @@ impl Limits {
             timeout: overrides.timeout.unwrap_or(default.timeout),
             networking: default.networking,
             max_log_size: default.max_log_size,
+            toolchain: overrides.toolchain,
         })
     }
 
@@ -101,7 +105,7 @@ mod test {
                 hexponent,
                 Limits {
                     targets: 15,
-                    ..defaults
+                    ..defaults.clone()
                 }
             );
 
@@ -111,6 +115,7 @@ mod test {
                 memory: defaults.memory * 2,
                 timeout: defaults.timeout * 2,
                 targets: 1,
+                toolchain: Some("nightly-2025-10-01".into()),
                 ..defaults
             };
             Overrides::save(
@@ -120,6 +125,7 @@ mod test {
                     memory: Some(limits.memory),
                     targets: Some(limits.targets),
                     timeout: Some(limits.timeout),
+                    toolchain: limits.toolchain.clone(),
                 },
             )
             .await?;
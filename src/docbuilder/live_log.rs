@@ -0,0 +1,115 @@
+use crate::{Storage, utils::report_error};
+use rustwide::logging::LogStorage;
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::Duration,
+};
+use tracing::debug;
+
+/// Regularly uploads the log of a running build to its final path, so it can be followed
+/// on the build page while the build is in progress.
+///
+/// We upload the whole log each time, so the last upload wins. The log is limited by
+/// `max_log_size` like any other build log. Uploading stops when this is dropped, the
+/// complete log is stored after the build, like before.
+pub(super) struct LiveBuildLog {
+    stop: Option<mpsc::Sender<()>>,
+    uploader: Option<thread::JoinHandle<()>>,
+}
+
+impl LiveBuildLog {
+    pub(super) fn start(
+        storage: Arc<Storage>,
+        path: String,
+        log: LogStorage,
+        interval: Duration,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel();
+
+        let uploader = thread::spawn(move || {
+            let mut uploaded_len = 0;
+            // the sender is dropped when we should stop.
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let content = log.to_string();
+                // log lines are only ever appended.
+                if content.len() == uploaded_len {
+                    continue;
+                }
+
+                debug!(path, len = content.len(), "uploading live build log");
+                uploaded_len = content.len();
+                if let Err(err) = storage.store_one(&path, content) {
+                    report_error(&err.context("error uploading live build log"));
+                }
+            }
+        });
+
+        Self {
+            stop: Some(stop),
+            uploader: Some(uploader),
+        }
+    }
+}
+
+impl Drop for LiveBuildLog {
+    fn drop(&mut self) {
+        // wait for a running upload, so it can't overwrite the complete log stored after it.
+        drop(self.stop.take());
+        if let Some(uploader) = self.uploader.take() {
+            let _ = uploader.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::wrapper;
+    use rustwide::logging;
+
+    #[test]
+    fn uploads_log_while_running() {
+        wrapper(|env| {
+            let storage = env.storage();
+            let log = LogStorage::new(log::LevelFilter::Info);
+            let path = "build-logs/1/x86_64-unknown-linux-gnu.txt";
+
+            let live_log = LiveBuildLog::start(
+                storage.clone(),
+                path.into(),
+                log.clone(),
+                Duration::from_millis(10),
+            );
+            logging::capture(&log, || log::info!("first line"));
+
+            let wait_for = |expected: &str| -> anyhow::Result<()> {
+                for _ in 0..500 {
+                    if storage.exists(path)?
+                        && String::from_utf8(storage.get(path, usize::MAX)?.content)? == expected
+                    {
+                        return Ok(());
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+                anyhow::bail!("the live log was not uploaded")
+            };
+            wait_for("[INFO] first line\n")?;
+
+            logging::capture(&log, || log::info!("second line"));
+            wait_for("[INFO] first line\n[INFO] second line\n")?;
+
+            // nothing is uploaded after it's dropped.
+            drop(live_log);
+            storage.store_one(path, "complete log")?;
+            logging::capture(&log, || log::info!("third line"));
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(storage.get(path, usize::MAX)?.content, b"complete log");
+
+            Ok(())
+        })
+    }
+}
@@ -1,7 +1,12 @@
+mod canary;
 mod limits;
+mod live_log;
+mod rustdoc_warnings;
 mod rustwide_builder;
 
+pub use self::canary::CanarySample;
 pub(crate) use self::limits::Limits;
+pub(crate) use self::rustdoc_warnings::{MAX_WARNINGS_PER_TARGET, RustdocWarning};
 pub(crate) use self::rustwide_builder::DocCoverage;
 pub use self::rustwide_builder::{BuildPackageSummary, PackageKind, RustwideBuilder};
 
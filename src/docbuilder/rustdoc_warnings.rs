@@ -0,0 +1,231 @@
+use rustwide::cmd::ProcessLinesActions;
+use serde::Deserialize;
+
+/// The most warnings we keep per target. Crates with more usually have the same warning
+/// over and over again.
+pub(crate) const MAX_WARNINGS_PER_TARGET: usize = 1000;
+
+/// A warning rustdoc emitted while documenting the crate, like a broken intra-doc link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RustdocWarning {
+    /// The lint, like `rustdoc::broken_intra_doc_links`.
+    pub(crate) code: Option<String>,
+    pub(crate) message: String,
+    /// The source file, relative to the root of the crate.
+    pub(crate) file: Option<String>,
+    pub(crate) line: Option<i32>,
+    /// The warning like rustdoc prints it, with the code it's about.
+    pub(crate) rendered: String,
+}
+
+/// A line of `cargo --message-format=json` output.
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum CargoMessage {
+    CompilerMessage {
+        package_id: String,
+        message: Diagnostic,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    message: String,
+    code: Option<DiagnosticCode>,
+    level: String,
+    spans: Vec<DiagnosticSpan>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticSpan {
+    file_name: String,
+    line_start: i32,
+    is_primary: bool,
+}
+
+/// What we log for a line of build output.
+#[derive(Debug, PartialEq, Eq)]
+enum LogLine {
+    Original,
+    Removed,
+    Replaced(String),
+}
+
+/// Collects the warnings for the crate we document from the JSON messages of a build.
+///
+/// The JSON messages are turned back into the text cargo would have printed, so the build
+/// log looks like it always did.
+pub(super) struct RustdocWarnings {
+    package_id: String,
+    warnings: Vec<RustdocWarning>,
+}
+
+impl RustdocWarnings {
+    /// Collects the warnings for the package with this id, from `cargo metadata`.
+    pub(super) fn new(package_id: impl Into<String>) -> Self {
+        Self {
+            package_id: package_id.into(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Handles a line of build output, for [`rustwide::cmd::Command::process_lines`].
+    pub(super) fn process_line(&mut self, line: &str, actions: &mut ProcessLinesActions) {
+        match self.handle_line(line) {
+            LogLine::Original => {}
+            LogLine::Removed => actions.remove_line(),
+            LogLine::Replaced(rendered) => actions.replace_with_lines(rendered.lines()),
+        }
+    }
+
+    fn handle_line(&mut self, line: &str) -> LogLine {
+        if !line.starts_with('{') {
+            return LogLine::Original;
+        }
+        let Ok(message) = serde_json::from_str::<CargoMessage>(line) else {
+            return LogLine::Original;
+        };
+
+        let (package_id, diagnostic) = match message {
+            CargoMessage::CompilerMessage {
+                package_id,
+                message,
+            } => (package_id, message),
+            // artifacts and the like, cargo doesn't print them without JSON messages.
+            CargoMessage::Other => return LogLine::Removed,
+        };
+
+        let rendered = diagnostic
+            .rendered
+            .unwrap_or_else(|| format!("{}: {}", diagnostic.level, diagnostic.message));
+
+        // the summaries like "1 warning emitted" don't point to any code.
+        if package_id == self.package_id
+            && diagnostic.level == "warning"
+            && !diagnostic.spans.is_empty()
+            && self.warnings.len() < MAX_WARNINGS_PER_TARGET
+        {
+            let primary_span = diagnostic.spans.iter().find(|span| span.is_primary);
+            self.warnings.push(RustdocWarning {
+                code: diagnostic.code.map(|code| code.code),
+                message: diagnostic.message,
+                file: primary_span.map(|span| span.file_name.clone()),
+                line: primary_span.map(|span| span.line_start),
+                rendered: rendered.trim_end().to_owned(),
+            });
+        }
+
+        LogLine::Replaced(rendered)
+    }
+
+    pub(super) fn into_warnings(self) -> Vec<RustdocWarning> {
+        self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE_ID: &str = "path+file:///opt/rustwide/workdir#foo@0.1.0";
+
+    fn compiler_message(package_id: &str, level: &str, code: Option<&str>, spans: bool) -> String {
+        serde_json::json!({
+            "reason": "compiler-message",
+            "package_id": package_id,
+            "target": {"name": "foo"},
+            "message": {
+                "message": "unresolved link to `Bar`",
+                "code": code.map(|code| serde_json::json!({"code": code, "explanation": null})),
+                "level": level,
+                "spans": if spans {
+                    serde_json::json!([
+                        {"file_name": "src/other.rs", "line_start": 1, "is_primary": false},
+                        {"file_name": "src/lib.rs", "line_start": 3, "is_primary": true},
+                    ])
+                } else {
+                    serde_json::json!([])
+                },
+                "children": [],
+                "rendered": format!("{level}: unresolved link to `Bar`\n --> src/lib.rs:3:10\n"),
+            },
+        })
+        .to_string()
+    }
+
+    fn process(lines: &[String]) -> (Vec<LogLine>, Vec<RustdocWarning>) {
+        let mut warnings = RustdocWarnings::new(PACKAGE_ID);
+        let log = lines
+            .iter()
+            .map(|line| warnings.handle_line(line))
+            .collect();
+        (log, warnings.into_warnings())
+    }
+
+    #[test]
+    fn collects_warnings_of_the_crate() {
+        let (log, warnings) = process(&[
+            "   Documenting foo v0.1.0".into(),
+            compiler_message(
+                PACKAGE_ID,
+                "warning",
+                Some("rustdoc::broken_intra_doc_links"),
+                true,
+            ),
+            // a dependency.
+            compiler_message(
+                "registry+https://github.com/rust-lang/crates.io-index#bar@1.0.0",
+                "warning",
+                None,
+                true,
+            ),
+            // the summary.
+            compiler_message(PACKAGE_ID, "warning", None, false),
+            compiler_message(PACKAGE_ID, "error", None, true),
+            r#"{"reason":"build-finished","success":true}"#.into(),
+        ]);
+
+        assert_eq!(
+            warnings,
+            [RustdocWarning {
+                code: Some("rustdoc::broken_intra_doc_links".into()),
+                message: "unresolved link to `Bar`".into(),
+                file: Some("src/lib.rs".into()),
+                line: Some(3),
+                rendered: "warning: unresolved link to `Bar`\n --> src/lib.rs:3:10".into(),
+            }]
+        );
+        let rendered = |level: &str| {
+            LogLine::Replaced(format!(
+                "{level}: unresolved link to `Bar`\n --> src/lib.rs:3:10\n"
+            ))
+        };
+        assert_eq!(
+            log,
+            [
+                LogLine::Original,
+                rendered("warning"),
+                rendered("warning"),
+                rendered("warning"),
+                rendered("error"),
+                LogLine::Removed,
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_warnings() {
+        let message = compiler_message(PACKAGE_ID, "warning", None, true);
+        let lines = vec![message; MAX_WARNINGS_PER_TARGET + 1];
+        let (_, warnings) = process(&lines);
+        assert_eq!(warnings.len(), MAX_WARNINGS_PER_TARGET);
+    }
+}
@@ -1,20 +1,29 @@
 use crate::RUSTDOC_STATIC_STORAGE_PREFIX;
+use crate::build_logs;
 use crate::db::{
     BuildId,
     file::{add_path_into_database, file_list_to_json},
 };
-use crate::db::{CrateId, ReleaseId};
+use crate::db::{CrateId, ReleaseId, access_rules, builders, registries::split_crate_name};
 use crate::db::{
-    Pool, add_doc_coverage, add_path_into_remote_archive, finish_build, finish_release,
-    initialize_build, initialize_crate, initialize_release, types::BuildStatus,
+    Pool, add_doc_coverage, add_path_into_remote_archive, add_rustdoc_json_format, finish_build,
+    finish_release, initialize_build, initialize_crate, initialize_release,
+    replace_rustdoc_warnings, set_build_default_target, set_build_lockfile_source,
+    set_build_pinned_toolchain, set_build_targets,
+    types::{BuildStatus, LockfileSource},
     update_build_with_error, update_crate_data_in_database,
 };
-use crate::docbuilder::Limits;
+use crate::docbuilder::canary::{self, CanaryBuild, CanaryReport, CanaryResult, CanarySample};
+use crate::docbuilder::live_log::LiveBuildLog;
+use crate::docbuilder::rustdoc_warnings::RustdocWarnings;
+use crate::docbuilder::{Limits, RustdocWarning};
 use crate::error::Result;
+use crate::ices;
 use crate::repositories::RepositoryStatsUpdater;
 use crate::storage::{
-    CompressionAlgorithm, RustdocJsonFormatVersion, compress, get_file_list, rustdoc_archive_path,
-    rustdoc_json_path, source_archive_path,
+    CARGO_LOCK_ARTIFACT, CARGO_METADATA_ARTIFACT, CompressionAlgorithm, RustdocJsonFormatVersion,
+    build_artifact_path, compress, get_file_list, rustdoc_archive_path, rustdoc_json_path,
+    source_archive_path,
 };
 use crate::utils::{
     CargoMetadata, ConfigName, copy_dir_all, get_config, parse_rustc_version, report_error,
@@ -24,9 +33,11 @@ use crate::{AsyncStorage, Config, Context, InstanceMetrics, RegistryApi, Storage
 use crate::{db::blacklist::is_blacklisted, utils::MetadataPackage};
 use anyhow::{Context as _, Error, anyhow, bail};
 use docsrs_metadata::{BuildTargets, DEFAULT_TARGETS, HOST_TARGET, Metadata};
+use flate2::read::GzDecoder;
 use itertools::Itertools as _;
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
 use regex::Regex;
-use rustwide::cmd::{Command, CommandError, SandboxBuilder, SandboxImage};
+use rustwide::cmd::{Command, CommandError, ProcessLinesActions, SandboxBuilder, SandboxImage};
 use rustwide::logging::{self, LogStorage};
 use rustwide::toolchain::ToolchainError;
 use rustwide::{AlternativeRegistry, Build, Crate, Toolchain, Workspace, WorkspaceBuilder};
@@ -44,10 +55,36 @@ const USER_AGENT: &str = "docs.rs builder (https://github.com/rust-lang/docs.rs)
 const COMPONENTS: &[&str] = &["llvm-tools-preview", "rustc-dev", "rustfmt"];
 const DUMMY_CRATE_NAME: &str = "empty-library";
 const DUMMY_CRATE_VERSION: &str = "1.0.0";
+/// The characters rustwide escapes in the names of its cache directories.
+const RUSTWIDE_ESCAPED: &AsciiSet = &CONTROLS
+    .add(b'/')
+    .add(b'\\')
+    .add(b'<')
+    .add(b'>')
+    .add(b':')
+    .add(b'"')
+    .add(b'|')
+    .add(b'?')
+    .add(b'*')
+    .add(b' ');
 
 pub const RUSTDOC_JSON_COMPRESSION_ALGORITHMS: &[CompressionAlgorithm] =
     &[CompressionAlgorithm::Zstd, CompressionAlgorithm::Gzip];
 
+/// Whether a `.crate` file contains a lockfile next to the manifest.
+fn crate_file_has_lockfile(crate_file: impl std::io::Read) -> Result<bool> {
+    let mut archive = tar::Archive::new(GzDecoder::new(crate_file));
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?;
+        // the files are in a `{name}-{version}` directory.
+        if path.components().count() == 2 && path.ends_with("Cargo.lock") {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 /// read the format version from a rustdoc JSON file.
 fn read_format_version_from_rustdoc_json(
     reader: impl std::io::Read,
@@ -66,23 +103,59 @@ fn read_format_version_from_rustdoc_json(
     ))
 }
 
-async fn get_configured_toolchain(conn: &mut sqlx::PgConnection) -> Result<Toolchain> {
-    let name: String = get_config(conn, ConfigName::Toolchain)
+async fn get_configured_toolchain_name(conn: &mut sqlx::PgConnection) -> Result<String> {
+    Ok(get_config(conn, ConfigName::Toolchain)
         .await?
-        .unwrap_or_else(|| "nightly".into());
+        .unwrap_or_else(|| "nightly".into()))
+}
+
+async fn get_configured_toolchain(conn: &mut sqlx::PgConnection) -> Result<Toolchain> {
+    Ok(toolchain_from_name(
+        &get_configured_toolchain_name(conn).await?,
+    ))
+}
 
+fn toolchain_from_name(name: &str) -> Toolchain {
     // If the toolchain is all hex, assume it references an artifact from
     // CI, for instance an `@bors try` build.
     let re = Regex::new(r"^[a-fA-F0-9]+$").unwrap();
-    if re.is_match(&name) {
-        debug!("using CI build {}", &name);
-        Ok(Toolchain::ci(&name, false))
+    if re.is_match(name) {
+        debug!("using CI build {}", name);
+        Toolchain::ci(name, false)
+    } else {
+        debug!("using toolchain {}", name);
+        Toolchain::dist(name)
+    }
+}
+
+/// Whether we document the binaries of a crate. Crates have to opt into it, and we only do it
+/// for crates without a library.
+fn documents_binaries(metadata: &Metadata, cargo_metadata: &CargoMetadata) -> bool {
+    metadata.document_binaries() && !cargo_metadata.root().is_library()
+}
+
+/// The name of the directory rustdoc puts the docs of the crate into, if we document it.
+///
+/// For binaries, that's the first one, which is also the release's `target_name`.
+fn documented_name(metadata: &Metadata, cargo_metadata: &CargoMetadata) -> Option<String> {
+    if documents_binaries(metadata, cargo_metadata) {
+        Some(cargo_metadata.root().package_name())
     } else {
-        debug!("using toolchain {}", &name);
-        Ok(Toolchain::dist(&name))
+        cargo_metadata.root().library_name()
     }
 }
 
+/// How [`RustwideBuilder::prepare_command`] runs cargo.
+#[derive(Debug, Clone, Copy, Default)]
+struct CommandOptions {
+    /// Let rustc write its metrics, when we collect them.
+    collect_metrics: bool,
+    /// Let cargo print its messages as JSON.
+    json_messages: bool,
+    /// Document the binaries of the crate instead of its library.
+    document_binaries: bool,
+}
+
 fn build_workspace<C: Context>(context: &C) -> Result<Workspace> {
     let config = context.config()?;
 
@@ -124,6 +197,10 @@ pub struct RustwideBuilder {
     registry_api: Arc<RegistryApi>,
     repository_stats_updater: Arc<RepositoryStatsUpdater>,
     workspace_initialize_time: Instant,
+    /// Toolchains crates are pinned to that we already installed next to the configured one.
+    pinned_toolchains: HashSet<Toolchain>,
+    /// The name of the registered build server whose current build we keep up to date.
+    registered_builder: Option<String>,
 }
 
 impl RustwideBuilder {
@@ -148,6 +225,26 @@ impl RustwideBuilder {
             registry_api: context.registry_api()?,
             repository_stats_updater: context.repository_stats_updater()?,
             workspace_initialize_time: Instant::now(),
+            pinned_toolchains: HashSet::new(),
+            registered_builder: None,
+        })
+    }
+
+    /// Keeps the current build of the registered build server with this name up to date.
+    ///
+    /// Only the build loop of the daemon does this, builds started from the command line
+    /// don't belong to the build server running on the same host.
+    pub fn track_current_build(&mut self, builder_name: String) {
+        self.registered_builder = Some(builder_name);
+    }
+
+    fn set_current_build(&self, build_id: Option<BuildId>) -> Result<()> {
+        let Some(name) = &self.registered_builder else {
+            return Ok(());
+        };
+        self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            builders::set_current_build(&mut conn, name, build_id).await
         })
     }
 
@@ -160,6 +257,7 @@ impl RustwideBuilder {
             info!("start reinitialize workspace again");
             self.workspace = build_workspace(context)?;
             self.workspace_initialize_time = Instant::now();
+            self.pinned_toolchains.clear();
         }
 
         Ok(())
@@ -255,6 +353,222 @@ impl RustwideBuilder {
         Ok(has_changed)
     }
 
+    /// Builds the latest releases of a sample of crates with the configured toolchain and a
+    /// candidate, and switches to the candidate when it doesn't break more than
+    /// `max_regressions` of them, and we could compare enough of them.
+    ///
+    /// The candidate is installed next to the configured toolchain, nothing of the builds is
+    /// stored apart from the results of the comparison.
+    pub fn run_toolchain_canary(
+        &mut self,
+        candidate: &str,
+        sample: CanarySample,
+        sample_size: Option<u32>,
+        max_regressions: Option<u32>,
+    ) -> Result<CanaryReport> {
+        let sample_size = sample_size.unwrap_or(self.config.canary_sample_size);
+        let max_regressions = max_regressions.unwrap_or(self.config.canary_max_regressions);
+
+        self.update_toolchain()?;
+        let (baseline, crates) = self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            Ok::<_, Error>((
+                get_configured_toolchain_name(&mut conn).await?,
+                canary::select_sample(&mut conn, sample, sample_size.into()).await?,
+            ))
+        })?;
+        let baseline_toolchain = self.toolchain.clone();
+        let candidate_toolchain = toolchain_from_name(candidate);
+        self.install_toolchain(&candidate_toolchain)?;
+
+        let canary_id = self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            canary::start_canary(&mut conn, candidate, &baseline, sample, max_regressions).await
+        })?;
+
+        let mut report = CanaryReport {
+            toolchain: candidate.to_owned(),
+            baseline_toolchain: baseline,
+            sample_size,
+            max_regressions,
+            results: Vec::new(),
+        };
+        for (name, version) in crates {
+            let baseline = match self.canary_build(&baseline_toolchain, &name, &version) {
+                Ok(baseline) => baseline,
+                Err(err) => {
+                    // like crates we can't fetch, these don't tell us anything about the toolchain.
+                    report_error(&err.context(format!(
+                        "canary build of {name} {version} failed, skipping it"
+                    )));
+                    continue;
+                }
+            };
+            let candidate = match self.canary_build(&candidate_toolchain, &name, &version) {
+                Ok(candidate) => candidate,
+                Err(err) => {
+                    // the same build worked with the current toolchain, so the candidate broke it.
+                    report_error(&err.context(format!(
+                        "canary build of {name} {version} failed with {candidate}"
+                    )));
+                    CanaryBuild {
+                        successful: false,
+                        doc_size: None,
+                    }
+                }
+            };
+            let result = CanaryResult {
+                name,
+                version,
+                baseline,
+                candidate,
+            };
+            self.runtime.block_on(async {
+                let mut conn = self.db.get_async().await?;
+                canary::record_canary_result(&mut conn, canary_id, &result).await
+            })?;
+            report.results.push(result);
+        }
+
+        self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            canary::finish_canary(&mut conn, canary_id, &report).await?;
+            if report.passed() {
+                info!("canary of {candidate} passed, switching to it");
+                set_config(&mut conn, ConfigName::Toolchain, candidate).await?;
+            } else {
+                warn!(
+                    "canary of {candidate} failed, keeping {}",
+                    report.baseline_toolchain
+                );
+            }
+            Ok::<_, Error>(())
+        })?;
+
+        Ok(report)
+    }
+
+    /// Installs another toolchain next to the configured one, with the targets and components
+    /// builds need.
+    fn install_toolchain(&self, toolchain: &Toolchain) -> Result<()> {
+        toolchain.install(&self.workspace)?;
+        if toolchain.as_ci().is_some() {
+            return Ok(());
+        }
+        for target in DEFAULT_TARGETS {
+            toolchain.add_target(&self.workspace, target)?;
+        }
+        for component in COMPONENTS {
+            if let Err(err) = toolchain.add_component(&self.workspace, component) {
+                warn!("failed to install {component}: {err}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Documents the default target of a crates.io release with the given toolchain, without
+    /// storing anything.
+    fn canary_build(
+        &mut self,
+        toolchain: &Toolchain,
+        name: &str,
+        version: &str,
+    ) -> Result<CanaryBuild> {
+        self.with_toolchain(toolchain, |builder| {
+            builder.canary_build_inner(name, version)
+        })
+    }
+
+    /// Runs `f` with the given toolchain in place of the configured one.
+    fn with_toolchain<T>(&mut self, toolchain: &Toolchain, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.toolchain, toolchain.clone());
+        let result = f(self);
+        self.toolchain = previous;
+        result
+    }
+
+    /// The toolchain the crate is pinned to in its sandbox overrides, if it differs from the
+    /// configured one.
+    ///
+    /// The first time a toolchain is used it's installed, and its essential files are uploaded.
+    fn pinned_toolchain(&mut self, name: &str) -> Result<Option<(String, Toolchain)>> {
+        let Some(pinned) = self.get_limits(name)?.toolchain else {
+            return Ok(None);
+        };
+        let toolchain = toolchain_from_name(&pinned);
+        if toolchain == self.toolchain {
+            return Ok(None);
+        }
+
+        if !self.pinned_toolchains.contains(&toolchain) {
+            info!("installing pinned toolchain {}", pinned);
+            self.install_toolchain(&toolchain)?;
+            self.with_toolchain(&toolchain, |builder| builder.upload_essential_files())?;
+            self.pinned_toolchains.insert(toolchain.clone());
+        }
+        Ok(Some((pinned, toolchain)))
+    }
+
+    fn canary_build_inner(&self, name: &str, version: &str) -> Result<CanaryBuild> {
+        info!("canary build of {} {}", name, version);
+        let limits = self.get_limits(name)?;
+
+        self.workspace.purge_all_build_dirs()?;
+        let mut build_dir = self
+            .workspace
+            .build_dir(&format!("canary-{name}-{version}"));
+        let krate = Crate::crates_io(name, version);
+        krate.fetch(&self.workspace)?;
+
+        let result = build_dir
+            .build(&self.toolchain, &krate, self.prepare_sandbox(&limits))
+            .run(|build| {
+                let metadata = Metadata::from_crate_root(build.host_source_dir())?;
+                let default_target = metadata
+                    .targets(self.config.include_default_targets)
+                    .default_target;
+                let rustdoc_flags = vec![
+                    "--emit=invocation-specific".to_string(),
+                    "--resource-suffix".to_string(),
+                    format!("-{}", parse_rustc_version(self.rustc_version()?)?),
+                ];
+
+                let successful = self
+                    .prepare_command(
+                        build,
+                        default_target,
+                        &metadata,
+                        &limits,
+                        rustdoc_flags,
+                        CommandOptions::default(),
+                    )
+                    .and_then(|command| command.run().map_err(Error::from))
+                    .is_ok();
+
+                let doc_dir = if metadata.proc_macro {
+                    build.host_target_dir().join("doc")
+                } else {
+                    build.host_target_dir().join(default_target).join("doc")
+                };
+                let doc_size = (successful && doc_dir.is_dir()).then(|| {
+                    walkdir::WalkDir::new(&doc_dir)
+                        .into_iter()
+                        .filter_map(|entry| entry.ok()?.metadata().ok())
+                        .filter(|metadata| metadata.is_file())
+                        .map(|metadata| metadata.len())
+                        .sum()
+                });
+
+                Ok(CanaryBuild {
+                    successful,
+                    doc_size,
+                })
+            });
+
+        krate.purge_from_cache(&self.workspace)?;
+        result
+    }
+
     fn rustc_version(&self) -> Result<String> {
         let version = self
             .toolchain
@@ -298,7 +612,60 @@ impl RustwideBuilder {
         })
     }
 
+    /// Whether the files of a crate can be public in the bucket, which they can't when
+    /// access rules restrict who can see the crate.
+    #[instrument(skip(self))]
+    fn is_public(&self, krate: &str) -> Result<bool> {
+        self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            Ok(access_rules::allowed_principals(&mut conn, krate)
+                .await?
+                .is_empty())
+        })
+    }
+
+    /// Whether a crate was published with a lockfile.
+    ///
+    /// rustwide keeps the `.crate` files it downloads in its cache, we look into them at the
+    /// paths rustwide uses.
+    fn has_published_lockfile(
+        &self,
+        kind: &PackageKind<'_>,
+        package_name: &str,
+        version: &str,
+    ) -> Result<bool> {
+        let sources = match kind {
+            PackageKind::Local(path) => return Ok(path.join("Cargo.lock").exists()),
+            PackageKind::CratesIo => "cratesio-sources".to_owned(),
+            PackageKind::Registry(index) => {
+                format!("{}-sources", utf8_percent_encode(index, RUSTWIDE_ESCAPED))
+            }
+        };
+        let path = self
+            .config
+            .rustwide_workspace
+            .join("cache")
+            .join(sources)
+            .join(package_name)
+            .join(format!("{package_name}-{version}.crate"));
+
+        let crate_file = File::open(&path)
+            .with_context(|| format!("could not open the fetched crate {}", path.display()))?;
+        crate_file_has_lockfile(BufReader::new(crate_file))
+    }
+
     pub fn add_essential_files(&mut self) -> Result<()> {
+        let rustc_version = self.upload_essential_files()?;
+        self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            set_config(&mut conn, ConfigName::RustcVersion, rustc_version).await
+        })?;
+        Ok(())
+    }
+
+    /// Builds a dummy crate with the current toolchain and uploads the static files rustdoc
+    /// generated, returning the rustc version.
+    fn upload_essential_files(&mut self) -> Result<String> {
         let rustc_version = self.rustc_version()?;
         let parsed_rustc_version = parse_rustc_version(&rustc_version)?;
 
@@ -371,16 +738,11 @@ impl RustwideBuilder {
                         &dest,
                     ))?;
                 }
-
-                self.runtime.block_on(async {
-                    let mut conn = self.db.get_async().await?;
-                    set_config(&mut conn, ConfigName::RustcVersion, rustc_version).await
-                })?;
                 Ok(())
             })?;
 
         krate.purge_from_cache(&self.workspace)?;
-        Ok(())
+        Ok(rustc_version)
     }
 
     pub fn build_local_package(&mut self, path: &Path) -> Result<BuildPackageSummary> {
@@ -412,16 +774,38 @@ impl RustwideBuilder {
             let build_id = initialize_build(&mut conn, release_id).await?;
             Ok::<_, Error>((crate_id, release_id, build_id))
         })?;
+        self.set_current_build(Some(build_id))?;
 
-        match self.build_package_inner(
-            name,
-            version,
-            kind,
-            crate_id,
-            release_id,
-            build_id,
-            collect_metrics,
-        ) {
+        let result = self.pinned_toolchain(name).and_then(|pinned| match pinned {
+            Some((pinned, toolchain)) => {
+                self.runtime.block_on(async {
+                    let mut conn = self.db.get_async().await?;
+                    set_build_pinned_toolchain(&mut conn, build_id, &pinned).await
+                })?;
+                self.with_toolchain(&toolchain, |builder| {
+                    builder.build_package_inner(
+                        name,
+                        version,
+                        kind,
+                        crate_id,
+                        release_id,
+                        build_id,
+                        collect_metrics,
+                    )
+                })
+            }
+            None => self.build_package_inner(
+                name,
+                version,
+                kind,
+                crate_id,
+                release_id,
+                build_id,
+                collect_metrics,
+            ),
+        });
+
+        let summary = match result {
             Ok(successful) => Ok(BuildPackageSummary {
                 successful,
                 should_reattempt: false,
@@ -440,7 +824,9 @@ impl RustwideBuilder {
                     should_reattempt: true,
                 })
             }),
-        }
+        };
+        self.set_current_build(None)?;
+        summary
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -468,6 +854,7 @@ impl RustwideBuilder {
         }
 
         let limits = self.get_limits(name)?;
+        let public = self.is_public(name)?;
         #[cfg(target_os = "linux")]
         if !self.config.disable_memory_limit {
             use anyhow::Context;
@@ -503,22 +890,31 @@ impl RustwideBuilder {
         // which we don't do. Currently our separate builders use a separate rustwide workspace.
         info_span!("purge_all_build_dirs").in_scope(|| self.workspace.purge_all_build_dirs())?;
 
-        let mut build_dir = self.workspace.build_dir(&format!("{name}-{version}"));
+        // crates of hosted registries are stored with their registry as prefix,
+        // the registry itself only knows the package name.
+        let (hosted_registry, package_name) = split_crate_name(name);
+        let mut build_dir = self
+            .workspace
+            .build_dir(&format!("{package_name}-{version}"));
 
-        let is_local = matches!(kind, PackageKind::Local(_));
+        // the crates.io API doesn't know local crates or crates of hosted registries.
+        let fetch_registry_data =
+            !matches!(kind, PackageKind::Local(_)) && hosted_registry.is_none();
         let krate = {
             let _span = info_span!("krate.fetch").entered();
 
             let krate = match kind {
                 PackageKind::Local(path) => Crate::local(path),
-                PackageKind::CratesIo => Crate::crates_io(name, version),
+                PackageKind::CratesIo => Crate::crates_io(package_name, version),
                 PackageKind::Registry(registry) => {
-                    Crate::registry(AlternativeRegistry::new(registry), name, version)
+                    Crate::registry(AlternativeRegistry::new(registry), package_name, version)
                 }
             };
             krate.fetch(&self.workspace)?;
             krate
         };
+        // rustwide generates a lockfile while preparing the build, so we have to look before.
+        let published_lockfile = self.has_published_lockfile(&kind, package_name, version)?;
 
         fs::create_dir_all(&self.config.temp_dir)?;
         let local_storage = tempfile::tempdir_in(&self.config.temp_dir)?;
@@ -548,6 +944,10 @@ impl RustwideBuilder {
                 } = metadata.targets(self.config.include_default_targets);
                 let mut targets = vec![default_target];
                 targets.extend(&other_targets);
+                self.runtime.block_on(async {
+                    let mut conn = self.db.get_async().await?;
+                    set_build_default_target(&mut conn, build_id, default_target).await
+                })?;
 
                 {
                     let _span = info_span!("fetch_build_std_dependencies").entered();
@@ -555,18 +955,34 @@ impl RustwideBuilder {
                     build.fetch_build_std_dependencies(&targets)?;
                 }
 
-
                 let mut has_docs = false;
                 let mut successful_targets = Vec::new();
 
                 // Perform an initial build
-                let mut res =
-                    self.execute_build(build_id, name, version, default_target, true, build, &limits, &metadata, false, collect_metrics)?;
+                let mut res = self.execute_build(
+                    build_id,
+                    name,
+                    version,
+                    default_target,
+                    true,
+                    build,
+                    &limits,
+                    &metadata,
+                    false,
+                    collect_metrics,
+                )?;
 
-                // If the build fails with the lockfile given, try using only the dependencies listed in Cargo.toml.
+                // If the build fails with the lockfile given, try using only the dependencies
+                // listed in Cargo.toml.
                 let cargo_lock = build.host_source_dir().join("Cargo.lock");
+                let mut lockfile_source = if published_lockfile {
+                    LockfileSource::Published
+                } else {
+                    LockfileSource::Generated
+                };
                 if !res.result.successful && cargo_lock.exists() {
                     info!("removing lockfile and reattempting build");
+                    lockfile_source = LockfileSource::Regenerated;
                     std::fs::remove_file(cargo_lock)?;
                     {
                         let _span = info_span!("cargo_generate_lockfile").entered();
@@ -582,21 +998,36 @@ impl RustwideBuilder {
                             .args(&["fetch", "--locked"])
                             .run_capture()?;
                     }
-                    res =
-                        self.execute_build(build_id, name, version, default_target, true, build, &limits, &metadata, false, collect_metrics)?;
+                    res = self.execute_build(
+                        build_id,
+                        name,
+                        version,
+                        default_target,
+                        true,
+                        build,
+                        &limits,
+                        &metadata,
+                        false,
+                        collect_metrics,
+                    )?;
                 }
 
                 if res.result.successful
-                    && let Some(name) = res.cargo_metadata.root().library_name() {
-                        let host_target = build.host_target_dir();
-                        has_docs = host_target
-                            .join(default_target)
-                            .join("doc")
-                            .join(name)
-                            .is_dir();
-                    }
+                    && let Some(name) = documented_name(&metadata, &res.cargo_metadata)
+                {
+                    let host_target = build.host_target_dir();
+                    has_docs = host_target
+                        .join(default_target)
+                        .join("doc")
+                        .join(name)
+                        .is_dir();
+                }
 
                 let mut target_build_logs = HashMap::new();
+                let mut rustdoc_warnings = vec![(
+                    res.target.clone(),
+                    std::mem::take(&mut res.rustdoc_warnings),
+                )];
                 let documentation_size = if has_docs {
                     debug!("adding documentation for the default target to the database");
                     self.copy_docs(
@@ -609,7 +1040,8 @@ impl RustwideBuilder {
                     successful_targets.push(res.target.clone());
 
                     // Then build the documentation for all the targets
-                    // Limit the number of targets so that no one can try to build all 200000 possible targets
+                    // Limit the number of targets so that no one can try to build all 200000
+                    // possible targets
                     for target in other_targets.into_iter().take(limits.targets()) {
                         debug!("building package {} {} for {}", name, version, target);
                         let target_res = self.build_target(
@@ -625,13 +1057,14 @@ impl RustwideBuilder {
                             collect_metrics,
                         )?;
                         target_build_logs.insert(target, target_res.build_log);
+                        rustdoc_warnings.push((target_res.target, target_res.rustdoc_warnings));
                     }
                     let (file_list, new_alg) =
                         self.runtime.block_on(add_path_into_remote_archive(
                             &self.async_storage,
                             &rustdoc_archive_path(name, version),
                             local_storage.path(),
-                            true,
+                            public,
                         ))?;
                     let documentation_size = file_list.iter().map(|info| info.size).sum::<u64>();
                     self.metrics
@@ -659,8 +1092,42 @@ impl RustwideBuilder {
                     None,
                 ))?;
 
+                if !res.result.successful
+                    && let Err(err) = self.runtime.block_on(build_logs::index_build_log(
+                        &mut async_conn,
+                        build_id,
+                        default_target,
+                        &res.build_log,
+                    ))
+                {
+                    report_error(&err.context("error indexing the build log"));
+                }
+
+                for (target, log) in std::iter::once((default_target, &res.build_log))
+                    .chain(target_build_logs.iter().map(|(target, log)| (*target, log)))
+                {
+                    if let Err(err) = self.runtime.block_on(ices::record_ices(
+                        &mut async_conn,
+                        build_id,
+                        target,
+                        &res.result.rustc_version,
+                        log,
+                    )) {
+                        report_error(&err.context("error recording internal compiler errors"));
+                    }
+                }
+
                 {
                     let _span = info_span!("store_build_logs").entered();
+                    let targets: Vec<&str> = std::iter::once(default_target)
+                        .chain(target_build_logs.keys().copied())
+                        .collect();
+                    self.runtime.block_on(set_build_targets(
+                        &mut async_conn,
+                        build_id,
+                        &targets,
+                    ))?;
+
                     let build_log_path = format!("build-logs/{build_id}/{default_target}.txt");
                     self.storage.store_one(build_log_path, res.build_log)?;
                     for (target, log) in target_build_logs {
@@ -669,6 +1136,16 @@ impl RustwideBuilder {
                     }
                 }
 
+                {
+                    let _span = info_span!("store_build_artifacts").entered();
+                    self.store_build_artifacts(
+                        build_id,
+                        build,
+                        &res.cargo_metadata,
+                        lockfile_source,
+                    )?;
+                }
+
                 if res.result.successful {
                     self.metrics.successful_builds.inc();
                 } else if res.cargo_metadata.root().is_library() {
@@ -677,7 +1154,7 @@ impl RustwideBuilder {
                     self.metrics.non_library_builds.inc();
                 }
 
-                let release_data = if !is_local {
+                let release_data = if fetch_registry_data {
                     match self
                         .runtime
                         .block_on(self.registry_api.get_release_data(name, version))
@@ -703,17 +1180,25 @@ impl RustwideBuilder {
                 // This mainly happens with manually triggered or automated rebuilds.
                 // The `release_build_status` table is already updated with the information from
                 // the current build via `finish_build`.
-                let current_release_build_status = self.runtime.block_on(sqlx::query_scalar!(
-                    r#"
+                let current_release_build_status = self.runtime.block_on(
+                    sqlx::query_scalar!(
+                        r#"
                     SELECT build_status AS "build_status: BuildStatus"
                     FROM release_build_status
                     WHERE rid = $1
                     "#,
-                    release_id.0,
-                ).fetch_optional(&mut *async_conn))?;
+                        release_id.0,
+                    )
+                    .fetch_optional(&mut *async_conn),
+                )?;
 
-                if !res.result.successful && current_release_build_status == Some(BuildStatus::Success) {
-                    info!("build was unsuccessful, but the release was already successfully built in the past. Skipping release record update.");
+                if !res.result.successful
+                    && current_release_build_status == Some(BuildStatus::Success)
+                {
+                    info!(
+                        "build was unsuccessful, but the release was already successfully built \
+                         in the past. Skipping release record update."
+                    );
                     return Ok(false);
                 }
 
@@ -744,8 +1229,15 @@ impl RustwideBuilder {
                     ))?;
                 }
 
-                // Some crates.io crate data is mutable, so we proactively update it during a release
-                if !is_local {
+                self.runtime.block_on(replace_rustdoc_warnings(
+                    &mut async_conn,
+                    release_id,
+                    &rustdoc_warnings,
+                ))?;
+
+                // Some crates.io crate data is mutable, so we proactively update it during a
+                // release
+                if fetch_registry_data {
                     match self
                         .runtime
                         .block_on(self.registry_api.get_crate_data(name))
@@ -787,6 +1279,31 @@ impl RustwideBuilder {
         Ok(successful)
     }
 
+    /// Stores the final `Cargo.lock` and the `cargo metadata` output of a build, so we can tell
+    /// which dependency versions it resolved.
+    fn store_build_artifacts(
+        &self,
+        build_id: BuildId,
+        build: &Build,
+        cargo_metadata: &CargoMetadata,
+        lockfile_source: LockfileSource,
+    ) -> Result<()> {
+        let cargo_lock = fs::read(build.host_source_dir().join("Cargo.lock"))?;
+        self.storage.store_one(
+            build_artifact_path(build_id, CARGO_LOCK_ARTIFACT),
+            cargo_lock,
+        )?;
+        self.storage.store_one(
+            build_artifact_path(build_id, CARGO_METADATA_ARTIFACT),
+            cargo_metadata.raw(),
+        )?;
+
+        self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            set_build_lockfile_source(&mut conn, build_id, lockfile_source).await
+        })
+    }
+
     #[instrument(skip(self, build))]
     #[allow(clippy::too_many_arguments)]
     fn build_target(
@@ -852,9 +1369,16 @@ impl RustwideBuilder {
 
         let successful = logging::capture(&storage, || {
             let _span = info_span!("cargo_build_json", target = %target).entered();
-            self.prepare_command(build, target, metadata, limits, rustdoc_flags, false)
-                .and_then(|command| command.run().map_err(Error::from))
-                .is_ok()
+            self.prepare_command(
+                build,
+                target,
+                metadata,
+                limits,
+                rustdoc_flags,
+                CommandOptions::default(),
+            )
+            .and_then(|command| command.run().map_err(Error::from))
+            .is_ok()
         });
 
         {
@@ -905,6 +1429,8 @@ impl RustwideBuilder {
                 )
             })?;
 
+        let public = self.is_public(name)?;
+
         let format_version = {
             let _span = info_span!("read_format_version").entered();
             read_format_version_from_rustdoc_json(&File::open(&json_filename)?)
@@ -928,10 +1454,20 @@ impl RustwideBuilder {
 
                 self.storage
                     .store_one_uncompressed(&path, compressed_json.clone())?;
-                self.storage.set_public_access(&path, true)?;
+                if public {
+                    self.storage.set_public_access(&path, true)?;
+                }
             }
         }
 
+        self.runtime.block_on(async {
+            let mut conn = self.db.get_async().await?;
+            for format_version in [format_version, RustdocJsonFormatVersion::Latest] {
+                add_rustdoc_json_format(&mut conn, build_id, target, format_version).await?;
+            }
+            Ok::<_, Error>(())
+        })?;
+
         Ok(())
     }
 
@@ -964,23 +1500,30 @@ impl RustwideBuilder {
             items_with_examples: 0,
         };
 
-        self.prepare_command(build, target, metadata, limits, rustdoc_flags, false)?
-            .process_lines(&mut |line, _| {
-                if line.starts_with('{') && line.ends_with('}') {
-                    let parsed = match serde_json::from_str::<HashMap<String, FileCoverage>>(line) {
-                        Ok(parsed) => parsed,
-                        Err(_) => return,
-                    };
-                    for file in parsed.values() {
-                        coverage.total_items += file.total;
-                        coverage.documented_items += file.with_docs;
-                        coverage.total_items_needing_examples += file.total_examples;
-                        coverage.items_with_examples += file.with_examples;
-                    }
+        self.prepare_command(
+            build,
+            target,
+            metadata,
+            limits,
+            rustdoc_flags,
+            CommandOptions::default(),
+        )?
+        .process_lines(&mut |line, _| {
+            if line.starts_with('{') && line.ends_with('}') {
+                let parsed = match serde_json::from_str::<HashMap<String, FileCoverage>>(line) {
+                    Ok(parsed) => parsed,
+                    Err(_) => return,
+                };
+                for file in parsed.values() {
+                    coverage.total_items += file.total;
+                    coverage.documented_items += file.with_docs;
+                    coverage.total_items_needing_examples += file.total_examples;
+                    coverage.items_with_examples += file.with_examples;
                 }
-            })
-            .log_output(true)
-            .run()?;
+            }
+        })
+        .log_output(true)
+        .run()?;
 
         Ok(
             if coverage.total_items == 0 && coverage.documented_items == 0 {
@@ -1060,21 +1603,41 @@ impl RustwideBuilder {
             );
         }
 
+        let live_log = LiveBuildLog::start(
+            self.storage.clone(),
+            format!("build-logs/{build_id}/{target}.txt"),
+            storage.clone(),
+            self.config.build_log_upload_interval,
+        );
+        let mut rustdoc_warnings = RustdocWarnings::new(&cargo_metadata.root().id);
         let successful = {
             let _span = info_span!("cargo_build", target = %target, is_default_target).entered();
             logging::capture(&storage, || {
+                let mut process_line = |line: &str, actions: &mut ProcessLinesActions| {
+                    rustdoc_warnings.process_line(line, actions)
+                };
                 self.prepare_command(
                     build,
                     target,
                     metadata,
                     limits,
                     rustdoc_flags,
-                    collect_metrics,
+                    CommandOptions {
+                        collect_metrics,
+                        json_messages: true,
+                        document_binaries: documents_binaries(metadata, &cargo_metadata),
+                    },
                 )
-                .and_then(|command| command.run().map_err(Error::from))
+                .and_then(|command| {
+                    command
+                        .process_lines(&mut process_line)
+                        .run()
+                        .map_err(Error::from)
+                })
                 .is_ok()
             })
         };
+        drop(live_log);
 
         if collect_metrics
             && let Some(compiler_metric_target_dir) = &self.config.compiler_metrics_collection_path
@@ -1116,6 +1679,7 @@ impl RustwideBuilder {
             doc_coverage,
             cargo_metadata,
             build_log: storage.to_string(),
+            rustdoc_warnings: rustdoc_warnings.into_warnings(),
             target: target.to_string(),
         })
     }
@@ -1127,8 +1691,14 @@ impl RustwideBuilder {
         metadata: &Metadata,
         limits: &Limits,
         mut rustdoc_flags_extras: Vec<String>,
-        collect_metrics: bool,
+        options: CommandOptions,
     ) -> Result<Command<'ws, 'pl>> {
+        let CommandOptions {
+            collect_metrics,
+            json_messages,
+            document_binaries,
+        } = options;
+
         // Add docs.rs specific arguments
         let mut cargo_args = vec![
             "--offline".into(),
@@ -1152,6 +1722,9 @@ impl RustwideBuilder {
         if let Some(cpu_limit) = self.config.build_cpu_limit {
             cargo_args.push(format!("-j{cpu_limit}"));
         }
+        if json_messages {
+            cargo_args.push("--message-format=json".into());
+        }
         // Cargo has a series of frightening bugs around cross-compiling proc-macros:
         // - Passing `--target` causes RUSTDOCFLAGS to fail to be passed 🤦
         // - Passing `--target` will *create* `target/{target-name}/doc` but will put the docs in `target/doc` anyway
@@ -1174,7 +1747,11 @@ impl RustwideBuilder {
         ];
 
         rustdoc_flags_extras.extend(UNCONDITIONAL_ARGS.iter().map(|&s| s.to_owned()));
-        let mut cargo_args = metadata.cargo_args(&cargo_args, &rustdoc_flags_extras);
+        let mut cargo_args = if document_binaries {
+            metadata.cargo_args_for_binaries(&cargo_args, &rustdoc_flags_extras)
+        } else {
+            metadata.cargo_args(&cargo_args, &rustdoc_flags_extras)
+        };
 
         // If the explicit target is not a tier one target, we need to install it.
         let has_build_std = cargo_args.windows(2).any(|args| {
@@ -1249,6 +1826,7 @@ struct FullBuildResult {
     cargo_metadata: CargoMetadata,
     doc_coverage: Option<DocCoverage>,
     build_log: String,
+    rustdoc_warnings: Vec<RustdocWarning>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -1387,7 +1965,8 @@ mod tests {
                         b.build_status::TEXT as build_status,
                         b.docsrs_version,
                         b.rustc_version,
-                        b.documentation_size
+                        b.documentation_size,
+                        b.lockfile_source::TEXT as lockfile_source
                     FROM
                         crates as c
                         INNER JOIN releases AS r ON c.id = r.crate_id
@@ -1413,6 +1992,11 @@ mod tests {
             assert!(row.source_size > 0);
             assert!(row.documentation_size.unwrap() > 0);
 
+            // the lockfile and metadata of the build are stored
+            assert!(row.lockfile_source.is_some());
+            assert!(storage.exists(&build_artifact_path(row.build_id, CARGO_LOCK_ARTIFACT))?);
+            assert!(storage.exists(&build_artifact_path(row.build_id, CARGO_METADATA_ARTIFACT))?);
+
             let mut targets: Vec<String> = row
                 .doc_targets
                 .unwrap()
@@ -1542,6 +2126,50 @@ mod tests {
         })
     }
 
+    #[test]
+    #[ignore]
+    fn test_build_protected_crate() {
+        wrapper(|env| {
+            let crate_ = DUMMY_CRATE_NAME;
+            let version = DUMMY_CRATE_VERSION;
+
+            env.runtime().block_on(async {
+                let mut conn = env.async_db().await.async_conn().await;
+                access_rules::add_access_rule(
+                    &mut conn,
+                    &env.config(),
+                    &*env.async_storage().await,
+                    access_rules::AccessTarget::Crate(crate_),
+                    "alice",
+                )
+                .await
+            })?;
+
+            let mut builder = RustwideBuilder::init(env).unwrap();
+            builder.update_toolchain()?;
+            assert!(
+                builder
+                    .build_package(crate_, version, PackageKind::CratesIo, false)?
+                    .successful
+            );
+
+            // the files of protected crates are not public in the bucket
+            let storage = env.storage();
+            assert!(!storage.get_public_access(&rustdoc_archive_path(crate_, version))?);
+            let json_path = rustdoc_json_path(
+                crate_,
+                version,
+                "x86_64-unknown-linux-gnu",
+                RustdocJsonFormatVersion::Latest,
+                Some(CompressionAlgorithm::Zstd),
+            );
+            assert!(storage.exists(&json_path)?);
+            assert!(!storage.get_public_access(&json_path)?);
+
+            Ok(())
+        })
+    }
+
     #[test]
     #[ignore]
     fn test_collect_metrics() {
@@ -1752,6 +2380,40 @@ mod tests {
         });
     }
 
+    #[test]
+    #[ignore]
+    fn test_toolchain_canary() {
+        wrapper(|env| {
+            env.runtime().block_on(async {
+                env.fake_release()
+                    .await
+                    .name(DUMMY_CRATE_NAME)
+                    .version(DUMMY_CRATE_VERSION)
+                    .create()
+                    .await
+            })?;
+
+            let mut builder = RustwideBuilder::init(env).unwrap();
+            builder.update_toolchain()?;
+            let report =
+                builder.run_toolchain_canary("nightly", CanarySample::Recent, Some(1), Some(0))?;
+            assert_eq!(report.results.len(), 1);
+            let result = &report.results[0];
+            assert!(result.baseline.successful);
+            assert!(result.baseline.doc_size.unwrap() > 0);
+            assert_eq!(result.baseline, result.candidate);
+            assert!(report.passed());
+
+            let toolchain: Option<String> = env.runtime().block_on(async {
+                let mut conn = env.async_db().await.async_conn().await;
+                get_config(&mut conn, ConfigName::Toolchain).await
+            })?;
+            assert_eq!(toolchain.as_deref(), Some("nightly"));
+
+            Ok(())
+        });
+    }
+
     #[test]
     #[ignore]
     fn test_cross_compile_non_host_default() {
@@ -2000,6 +2662,42 @@ mod tests {
         });
     }
 
+    #[test]
+    #[ignore]
+    fn test_build_documented_binary() {
+        wrapper(|env| {
+            let mut builder = RustwideBuilder::init(env)?;
+            builder.update_toolchain()?;
+            assert!(
+                builder
+                    .build_local_package(Path::new("tests/crates/documented-binary"))?
+                    .successful
+            );
+
+            let row = env.runtime().block_on(async {
+                let mut conn = env.async_db().await.async_conn().await;
+                sqlx::query!(
+                    "SELECT releases.rustdoc_status, releases.is_library, releases.target_name
+                     FROM crates
+                     INNER JOIN releases ON crates.id = releases.crate_id
+                     WHERE crates.name = 'documented-binary'"
+                )
+                .fetch_one(&mut *conn)
+                .await
+            })?;
+            assert_eq!(row.rustdoc_status, Some(true));
+            assert_eq!(row.is_library, Some(false));
+            assert_eq!(row.target_name.as_deref(), Some("documented_binary"));
+
+            // private items of binaries are documented
+            let web = env.runtime().block_on(env.web_app());
+            env.runtime().block_on(
+                web.assert_success("/documented-binary/0.1.0/documented_binary/fn.parse_args.html"),
+            )?;
+            Ok(())
+        })
+    }
+
     #[test]
     #[ignore]
     fn test_build_std() {
@@ -2100,4 +2798,29 @@ mod tests {
 
         Ok(())
     }
+
+    #[test_case(&["foo-0.1.0/Cargo.toml", "foo-0.1.0/Cargo.lock"], true)]
+    #[test_case(&["foo-0.1.0/Cargo.toml", "foo-0.1.0/sub/Cargo.lock"], false)]
+    #[test_case(&["foo-0.1.0/Cargo.toml"], false)]
+    fn test_crate_file_has_lockfile(paths: &[&str], expected: bool) -> Result<()> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for path in paths {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(0);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, io::empty())?;
+        }
+        let crate_file = builder.into_inner()?.finish()?;
+
+        assert_eq!(
+            crate_file_has_lockfile(io::Cursor::new(crate_file))?,
+            expected
+        );
+
+        Ok(())
+    }
 }
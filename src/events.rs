@@ -0,0 +1,224 @@
+//! Build activity, sent through Postgres `NOTIFY` so every web server sees the events of
+//! every build server.
+//!
+//! Notifications are only sent when the transaction sending them commits, and are lost for
+//! listeners that aren't connected at that moment, so they are only good for live updates.
+//! The database stays the source of truth.
+
+use crate::{
+    db::{BuildId, types::BuildStatus},
+    utils::report_error,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use std::time::Duration;
+use tokio::sync::{OnceCell, broadcast};
+use tracing::warn;
+
+/// The channel we send the events on.
+const EVENTS_CHANNEL: &str = "docsrs_events";
+
+/// How many events a slow listener can fall behind before it misses events.
+const EVENTS_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event {
+    /// A release was added to the build queue, or its queue entry was updated.
+    Queued {
+        name: String,
+        version: String,
+        priority: i32,
+    },
+    BuildStarted {
+        name: String,
+        version: String,
+        build_id: BuildId,
+    },
+    BuildFinished {
+        name: String,
+        version: String,
+        build_id: BuildId,
+        build_status: BuildStatus,
+    },
+    /// The pages of a crate will be purged from the CDN.
+    CdnInvalidationQueued { name: String },
+}
+
+impl Event {
+    /// The name of the event, used as event type in the event stream.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Queued { .. } => "queued",
+            Self::BuildStarted { .. } => "build_started",
+            Self::BuildFinished { .. } => "build_finished",
+            Self::CdnInvalidationQueued { .. } => "cdn_invalidation_queued",
+        }
+    }
+
+    /// The crate the event is about.
+    pub(crate) fn crate_name(&self) -> &str {
+        match self {
+            Self::Queued { name, .. }
+            | Self::BuildStarted { name, .. }
+            | Self::BuildFinished { name, .. }
+            | Self::CdnInvalidationQueued { name } => name,
+        }
+    }
+
+    /// The event for a started or finished build.
+    async fn for_build(conn: &mut sqlx::PgConnection, build_id: BuildId) -> Result<Self> {
+        let build = sqlx::query!(
+            r#"SELECT
+                crates.name,
+                releases.version,
+                builds.build_status as "build_status: BuildStatus"
+             FROM builds
+             INNER JOIN releases ON releases.id = builds.rid
+             INNER JOIN crates ON crates.id = releases.crate_id
+             WHERE builds.id = $1"#,
+            build_id.0,
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(match build.build_status {
+            BuildStatus::InProgress => Self::BuildStarted {
+                name: build.name,
+                version: build.version,
+                build_id,
+            },
+            build_status => Self::BuildFinished {
+                name: build.name,
+                version: build.version,
+                build_id,
+                build_status,
+            },
+        })
+    }
+}
+
+/// Sends an event to all listeners, once the current transaction commits.
+pub(crate) async fn notify(conn: &mut sqlx::PgConnection, event: &Event) -> Result<()> {
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(EVENTS_CHANNEL)
+        .bind(serde_json::to_string(event)?)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Sends the [`Event::BuildStarted`] or [`Event::BuildFinished`] event for a build.
+pub(crate) async fn notify_build(conn: &mut sqlx::PgConnection, build_id: BuildId) -> Result<()> {
+    let event = Event::for_build(&mut *conn, build_id).await?;
+    notify(conn, &event).await
+}
+
+/// Listens to the events and fans them out to any number of subscribers.
+///
+/// We only start listening with the first subscriber, using a single database connection
+/// for all of them.
+#[derive(Debug)]
+pub(crate) struct EventListener {
+    database_url: String,
+    sender: OnceCell<broadcast::Sender<Event>>,
+}
+
+impl EventListener {
+    pub(crate) fn new(database_url: impl Into<String>) -> Self {
+        Self {
+            database_url: database_url.into(),
+            sender: OnceCell::new(),
+        }
+    }
+
+    pub(crate) async fn subscribe(&self) -> Result<broadcast::Receiver<Event>> {
+        let sender = self
+            .sender
+            .get_or_try_init(|| async {
+                let mut listener = PgListener::connect(&self.database_url).await?;
+                listener.listen(EVENTS_CHANNEL).await?;
+
+                let (sender, _) = broadcast::channel(EVENTS_CAPACITY);
+                tokio::spawn(forward_events(listener, sender.clone()));
+                Ok::<_, anyhow::Error>(sender)
+            })
+            .await?;
+
+        Ok(sender.subscribe())
+    }
+}
+
+async fn forward_events(mut listener: PgListener, sender: broadcast::Sender<Event>) {
+    loop {
+        // the listener reconnects by itself, events sent in the meantime are lost.
+        match listener.recv().await {
+            Ok(notification) => match serde_json::from_str(notification.payload()) {
+                // sending only fails when there are no subscribers right now.
+                Ok(event) => {
+                    let _ = sender.send(event);
+                }
+                Err(err) => warn!(?err, payload = notification.payload(), "invalid event"),
+            },
+            Err(err) => {
+                report_error(&anyhow::Error::from(err).context("error receiving events"));
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::async_wrapper;
+
+    #[test]
+    fn event_round_trip() {
+        let event = Event::BuildFinished {
+            name: "foo".into(),
+            version: "0.1.0".into(),
+            build_id: BuildId(42),
+            build_status: BuildStatus::Failure,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "event": "build_finished",
+                "name": "foo",
+                "version": "0.1.0",
+                "build_id": 42,
+                "build_status": "failure",
+            })
+        );
+        assert_eq!(serde_json::from_value::<Event>(json).unwrap(), event);
+    }
+
+    #[test]
+    fn notify_subscribers() {
+        async_wrapper(|env| async move {
+            let listener = EventListener::new(&env.config().database_url);
+            let mut first = listener.subscribe().await?;
+            let mut second = listener.subscribe().await?;
+
+            let event = Event::CdnInvalidationQueued {
+                name: "events-notify-subscribers".into(),
+            };
+            let mut conn = env.async_db().await.async_conn().await;
+            notify(&mut conn, &event).await?;
+
+            // other tests send events on the same channel.
+            for receiver in [&mut first, &mut second] {
+                tokio::time::timeout(Duration::from_secs(10), async {
+                    while receiver.recv().await? != event {}
+                    Ok::<_, anyhow::Error>(())
+                })
+                .await??;
+            }
+
+            Ok(())
+        })
+    }
+}
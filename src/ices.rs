@@ -0,0 +1,580 @@
+//! Internal compiler errors (ICEs) found in the logs of our builds.
+//!
+//! The builder looks for ICEs in every build log. We store them deduplicated by a signature
+//! built from where the compiler failed, so the compiler team can see which ICEs are new,
+//! which toolchains they appeared with, and how many crates they affect.
+
+use crate::{build_logs::build_log_path, db::BuildId, utils::rustc_version::parse_rustc_date};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::stream::TryStreamExt;
+use regex::Regex;
+use serde::Serialize;
+use std::{collections::HashMap, sync::LazyLock};
+use tracing::{info, instrument};
+
+/// How many of the crates affected by an ICE we list.
+pub(crate) const MAX_LISTED_CRATES: i64 = 50;
+
+/// We keep this much of the messages, some contain whole types or MIR bodies.
+const MAX_MESSAGE_LEN: usize = 1000;
+
+/// We keep this many queries of the query stack, the first ones are the interesting ones.
+const MAX_QUERY_STACK_LEN: usize = 20;
+
+/// An ICE as the compiler printed it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Ice {
+    pub(crate) message: String,
+    /// Where in the compiler it happened, like `compiler/rustc_middle/src/ty/mod.rs:123:45`.
+    pub(crate) location: Option<String>,
+    /// The names of the queries in the query stack, innermost first.
+    pub(crate) query_stack: Vec<String>,
+}
+
+impl Ice {
+    /// What makes two ICEs the same.
+    ///
+    /// Line numbers change between toolchains, and messages contain the names of the items
+    /// of the crate, so we leave them out.
+    pub(crate) fn signature(&self) -> String {
+        static QUOTED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`[^`]*`").unwrap());
+        // paths with the crate hash, like `foo[1a2b]::bar` in `DefId`s.
+        static PATHS: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"\w+\[[0-9a-f]+\](?:::\w+)*").unwrap());
+        static NUMBERS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+
+        let file = self
+            .location
+            .as_deref()
+            .and_then(|location| location.split(':').next())
+            .unwrap_or("-");
+        let message = QUOTED.replace_all(&self.message, "`_`");
+        let message = PATHS.replace_all(&message, "_");
+        let message = NUMBERS.replace_all(&message, "N");
+        let message: String = message.chars().take(200).collect();
+        let query = self.query_stack.first().map(String::as_str).unwrap_or("-");
+
+        format!("{file} | {message} | {query}")
+    }
+}
+
+/// Removes the `[INFO] [stderr] ` prefixes of our build log lines.
+fn strip_log_prefix(line: &str) -> &str {
+    static PREFIX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:\[[A-Za-z]+\] )*").unwrap());
+    PREFIX
+        .find(line)
+        .map_or(line, |prefix| &line[prefix.end()..])
+}
+
+/// Splits the location from messages like `compiler/rustc_x/src/lib.rs:1:2: the message`.
+fn split_location(message: &str) -> (Option<String>, String) {
+    static LOCATION: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^([^\s:]+\.rs:\d+:\d+): (.*)$").unwrap());
+    match LOCATION.captures(message) {
+        Some(captures) => (Some(captures[1].to_owned()), captures[2].to_owned()),
+        None => (None, message.to_owned()),
+    }
+}
+
+fn truncate(mut message: String) -> String {
+    if message.len() > MAX_MESSAGE_LEN {
+        let mut end = MAX_MESSAGE_LEN;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+        message.push('…');
+    }
+    message
+}
+
+/// Finds the ICEs in a build log.
+pub(crate) fn detect_ices(log: &str) -> Vec<Ice> {
+    static QUERY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#\d+ \[(\w+)\]").unwrap());
+
+    let mut ices: Vec<Ice> = Vec::new();
+    // whether the last ICE is still being printed, and can get a query stack.
+    let mut open = false;
+    let mut panic_message_next = false;
+    let mut in_query_stack = false;
+
+    for line in log.lines().map(strip_log_prefix) {
+        if in_query_stack {
+            if line.starts_with("end of query stack") {
+                in_query_stack = false;
+                open = false;
+            } else if let Some(captures) = QUERY.captures(line)
+                && let Some(ice) = ices.last_mut()
+                && ice.query_stack.len() < MAX_QUERY_STACK_LEN
+            {
+                ice.query_stack.push(captures[1].to_owned());
+            }
+            continue;
+        }
+
+        if panic_message_next {
+            panic_message_next = false;
+            if let Some(ice) = ices.last_mut() {
+                ice.message = truncate(line.trim().to_owned());
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("error: internal compiler error: ") {
+            let (location, message) = split_location(rest.trim());
+            ices.push(Ice {
+                message: truncate(message),
+                location,
+                query_stack: Vec::new(),
+            });
+            open = true;
+        } else if let Some(rest) = line.strip_prefix("thread 'rustc' panicked at ") {
+            // the panic of an `internal compiler error` only points into the error handling.
+            if open {
+                continue;
+            }
+            if let Some(location) = rest.trim_end().strip_suffix(':') {
+                // since Rust 1.73 the message follows on the next line.
+                ices.push(Ice {
+                    message: String::new(),
+                    location: Some(location.to_owned()),
+                    query_stack: Vec::new(),
+                });
+                panic_message_next = true;
+            } else {
+                // before, it was `'the message', compiler/rustc_x/src/lib.rs:1:2`.
+                let (message, location) = match rest.trim().rsplit_once("', ") {
+                    Some((message, location)) => (message.trim_start_matches('\''), Some(location)),
+                    None => (rest.trim(), None),
+                };
+                ices.push(Ice {
+                    message: truncate(message.to_owned()),
+                    location: location.map(str::to_owned),
+                    query_stack: Vec::new(),
+                });
+            }
+            open = true;
+        } else if open && line.starts_with("query stack during panic:") {
+            in_query_stack = true;
+        } else if line.starts_with("error: could not compile")
+            || line.starts_with("error: could not document")
+        {
+            open = false;
+        }
+    }
+
+    ices
+}
+
+/// Stores the ICEs in the log of a build target.
+///
+/// Returns how many ICEs we found.
+#[instrument(skip(conn, log))]
+pub(crate) async fn record_ices(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+    target: &str,
+    rustc_version: &str,
+    log: &str,
+) -> Result<usize> {
+    let ices = detect_ices(log);
+    let toolchain_date = parse_rustc_date(rustc_version).ok();
+
+    for ice in &ices {
+        let ice_id = sqlx::query_scalar!(
+            "INSERT INTO ices (signature)
+             VALUES ($1)
+             ON CONFLICT (signature) DO UPDATE
+                SET last_seen = NOW()
+             RETURNING id",
+            ice.signature(),
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO ice_occurrences (
+                ice_id, build_id, release_id, target, rustc_version, toolchain_date,
+                message, location, query_stack
+             )
+             SELECT $1, builds.id, builds.rid, $3, $4, $5, $6, $7, $8
+             FROM builds
+             WHERE builds.id = $2
+             ON CONFLICT DO NOTHING",
+            ice_id,
+            build_id.0,
+            target,
+            rustc_version,
+            toolchain_date,
+            ice.message,
+            ice.location,
+            &ice.query_stack,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    if !ices.is_empty() {
+        info!(count = ices.len(), "found internal compiler errors");
+    }
+    Ok(ices.len())
+}
+
+/// A crate affected by an ICE.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct IceCrate {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) build_id: i32,
+    pub(crate) target: String,
+    pub(crate) rustc_version: String,
+    pub(crate) toolchain_date: Option<NaiveDate>,
+}
+
+impl IceCrate {
+    /// The path of the build details page showing the log.
+    pub(crate) fn build_details_path(&self) -> String {
+        build_log_path(&self.name, &self.version, self.build_id, &self.target)
+    }
+}
+
+/// An ICE with the crates it affects.
+///
+/// Everything in it comes from the occurrences in public crates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct IceReport {
+    pub(crate) signature: String,
+    /// The message of the first occurrence.
+    pub(crate) message: String,
+    pub(crate) location: Option<String>,
+    pub(crate) query_stack: Vec<String>,
+    pub(crate) first_seen: DateTime<Utc>,
+    pub(crate) last_seen: DateTime<Utc>,
+    /// The date of the oldest nightly toolchain we saw it with.
+    pub(crate) first_toolchain: Option<NaiveDate>,
+    /// The date of the newest nightly toolchain we saw it with.
+    pub(crate) last_toolchain: Option<NaiveDate>,
+    pub(crate) crate_count: i64,
+    /// The crates we saw it with last, at most [`MAX_LISTED_CRATES`].
+    pub(crate) crates: Vec<IceCrate>,
+}
+
+/// Returns the ICEs we saw last, leaving out private crates.
+pub(crate) async fn list_ices(conn: &mut sqlx::PgConnection, limit: i64) -> Result<Vec<IceReport>> {
+    let mut ices: Vec<(i32, IceReport)> = sqlx::query!(
+        r#"WITH public_occurrences AS (
+            SELECT ice_occurrences.*, releases.crate_id
+            FROM ice_occurrences
+            INNER JOIN releases ON releases.id = ice_occurrences.release_id
+            INNER JOIN crates ON crates.id = releases.crate_id
+            WHERE NOT EXISTS (
+                SELECT 1
+                FROM access_rules
+                WHERE
+                    normalize_crate_name(access_rules.crate_name) = normalize_crate_name(crates.name) OR
+                    access_rules.registry_id = crates.registry_id
+            )
+         ),
+         public_ices AS (
+            SELECT
+                ice_id,
+                MIN(seen_at) AS first_seen,
+                MAX(seen_at) AS last_seen,
+                MIN(toolchain_date) AS first_toolchain,
+                MAX(toolchain_date) AS last_toolchain,
+                COUNT(DISTINCT crate_id) AS crate_count
+            FROM public_occurrences
+            GROUP BY ice_id
+            ORDER BY last_seen DESC, ice_id DESC
+            LIMIT $1
+         )
+         SELECT
+            ices.id,
+            ices.signature,
+            first_occurrence.message AS "message!",
+            first_occurrence.location,
+            first_occurrence.query_stack AS "query_stack!",
+            public_ices.first_seen AS "first_seen!",
+            public_ices.last_seen AS "last_seen!",
+            public_ices.first_toolchain,
+            public_ices.last_toolchain,
+            public_ices.crate_count AS "crate_count!"
+         FROM public_ices
+         INNER JOIN ices ON ices.id = public_ices.ice_id
+         CROSS JOIN LATERAL (
+            SELECT message, location, query_stack
+            FROM public_occurrences
+            WHERE public_occurrences.ice_id = ices.id
+            ORDER BY seen_at, build_id
+            LIMIT 1
+         ) AS first_occurrence
+         ORDER BY public_ices.last_seen DESC, ices.id DESC"#,
+        limit,
+    )
+    .fetch(&mut *conn)
+    .map_ok(|row| {
+        (
+            row.id,
+            IceReport {
+                signature: row.signature,
+                message: row.message,
+                location: row.location,
+                query_stack: row.query_stack,
+                first_seen: row.first_seen,
+                last_seen: row.last_seen,
+                first_toolchain: row.first_toolchain,
+                last_toolchain: row.last_toolchain,
+                crate_count: row.crate_count,
+                crates: Vec::new(),
+            },
+        )
+    })
+    .try_collect()
+    .await?;
+
+    let ice_ids: Vec<i32> = ices.iter().map(|(id, _)| *id).collect();
+    let mut crates: HashMap<i32, Vec<IceCrate>> = HashMap::new();
+    let mut rows = sqlx::query!(
+        r#"SELECT
+            ice_id AS "ice_id!",
+            name AS "name!",
+            version AS "version!",
+            build_id AS "build_id!",
+            target AS "target!",
+            rustc_version AS "rustc_version!",
+            toolchain_date
+         FROM (
+            SELECT
+                ice_occurrences.ice_id,
+                crates.name,
+                releases.version,
+                ice_occurrences.build_id,
+                ice_occurrences.target,
+                ice_occurrences.rustc_version,
+                ice_occurrences.toolchain_date,
+                ROW_NUMBER() OVER (
+                    PARTITION BY ice_occurrences.ice_id
+                    ORDER BY ice_occurrences.seen_at DESC, ice_occurrences.build_id DESC
+                ) AS row_number
+            FROM ice_occurrences
+            INNER JOIN releases ON releases.id = ice_occurrences.release_id
+            INNER JOIN crates ON crates.id = releases.crate_id
+            WHERE
+                ice_occurrences.ice_id = ANY($1) AND
+                NOT EXISTS (
+                    SELECT 1
+                    FROM access_rules
+                    WHERE
+                        normalize_crate_name(access_rules.crate_name) = normalize_crate_name(crates.name) OR
+                        access_rules.registry_id = crates.registry_id
+                )
+         ) AS occurrences
+         WHERE row_number <= $2
+         ORDER BY ice_id, row_number"#,
+        &ice_ids,
+        MAX_LISTED_CRATES,
+    )
+    .fetch(&mut *conn);
+    while let Some(row) = rows.try_next().await? {
+        crates.entry(row.ice_id).or_default().push(IceCrate {
+            name: row.name,
+            version: row.version,
+            build_id: row.build_id,
+            target: row.target,
+            rustc_version: row.rustc_version,
+            toolchain_date: row.toolchain_date,
+        });
+    }
+    drop(rows);
+
+    for (id, ice) in &mut ices {
+        ice.crates = crates.remove(id).unwrap_or_default();
+    }
+    Ok(ices.into_iter().map(|(_, ice)| ice).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::access_rules::{AccessTarget, add_access_rule};
+    use crate::db::types::BuildStatus;
+    use crate::test::{FakeBuild, async_wrapper};
+
+    const PANIC_LOG: &str = "\
+[INFO] [stderr]  Documenting foo v0.1.0 (/opt/rustwide/workdir)
+[INFO] [stderr] thread 'rustc' panicked at compiler/rustc_middle/src/ty/mod.rs:123:45:
+[INFO] [stderr] called `Option::unwrap()` on a `None` value
+[INFO] [stderr] stack backtrace:
+[INFO] [stderr]    0: rust_begin_unwind
+[INFO] [stderr] error: the compiler unexpectedly panicked. this is a bug.
+[INFO] [stderr] query stack during panic:
+[INFO] [stderr] #0 [typeck] type-checking `foo::bar`
+[INFO] [stderr] #1 [analysis] running analysis passes on this crate
+[INFO] [stderr] end of query stack
+[INFO] [stderr] error: could not document `foo`
+";
+
+    const BUG_LOG: &str = "\
+[INFO] [stdout] error: internal compiler error: compiler/rustc_hir_typeck/src/lib.rs:300:9: no type for node HirId(DefId(0:7 ~ foo[1234]::bar).12)
+[INFO] [stderr] thread 'rustc' panicked at compiler/rustc_errors/src/lib.rs:1000:17:
+[INFO] [stderr] Box<dyn Any>
+[INFO] [stderr] query stack during panic:
+[INFO] [stderr] #0 [typeck] type-checking `foo::bar`
+[INFO] [stderr] end of query stack
+";
+
+    #[test]
+    fn detect_panic() {
+        assert_eq!(
+            detect_ices(PANIC_LOG),
+            [Ice {
+                message: "called `Option::unwrap()` on a `None` value".into(),
+                location: Some("compiler/rustc_middle/src/ty/mod.rs:123:45".into()),
+                query_stack: vec!["typeck".into(), "analysis".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn detect_old_panic() {
+        let log =
+            "thread 'rustc' panicked at 'index out of bounds', compiler/rustc_x/src/lib.rs:1:2\n";
+        assert_eq!(
+            detect_ices(log),
+            [Ice {
+                message: "index out of bounds".into(),
+                location: Some("compiler/rustc_x/src/lib.rs:1:2".into()),
+                query_stack: Vec::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn detect_bug() {
+        assert_eq!(
+            detect_ices(BUG_LOG),
+            [Ice {
+                message: "no type for node HirId(DefId(0:7 ~ foo[1234]::bar).12)".into(),
+                location: Some("compiler/rustc_hir_typeck/src/lib.rs:300:9".into()),
+                query_stack: vec!["typeck".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn ignores_other_panics() {
+        let log = "\
+[INFO] [stderr] thread 'main' panicked at build.rs:3:5:
+[INFO] [stderr] the build script failed
+[INFO] [stderr] error: proc macro panicked
+";
+        assert!(detect_ices(log).is_empty());
+    }
+
+    #[test]
+    fn signature_ignores_crate_details() {
+        let ice = detect_ices(BUG_LOG).remove(0);
+        let other_crate = Ice {
+            message: "no type for node HirId(DefId(0:42 ~ baz[abcd]::qux).3)".into(),
+            location: Some("compiler/rustc_hir_typeck/src/lib.rs:310:9".into()),
+            query_stack: vec!["typeck".into()],
+        };
+        assert_eq!(ice.signature(), other_crate.signature());
+        assert_ne!(ice.signature(), detect_ices(PANIC_LOG)[0].signature());
+    }
+
+    #[test]
+    fn record_and_list() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            // the first occurrence is the one of the private crate, with its item names.
+            let private_log = BUG_LOG.replace("foo[1234]::bar", "private[5678]::secret");
+            for (name, rustc_version, log) in [
+                (
+                    "private",
+                    "rustc 1.92.0-nightly (012abcdef 2025-10-09)",
+                    private_log.as_str(),
+                ),
+                (
+                    "foo",
+                    "rustc 1.92.0-nightly (abcdef012 2025-10-01)",
+                    BUG_LOG,
+                ),
+                (
+                    "bar",
+                    "rustc 1.92.0-nightly (012abcdef 2025-10-05)",
+                    BUG_LOG,
+                ),
+            ] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .builds(vec![
+                        FakeBuild::default()
+                            .build_status(BuildStatus::Failure)
+                            .rustc_version(rustc_version),
+                    ])
+                    .create()
+                    .await?;
+                let build_id = sqlx::query_scalar!(
+                    r#"SELECT builds.id as "id: BuildId"
+                     FROM builds
+                     INNER JOIN releases ON releases.id = builds.rid
+                     INNER JOIN crates ON crates.id = releases.crate_id
+                     WHERE crates.name = $1"#,
+                    name
+                )
+                .fetch_one(&mut *conn)
+                .await?;
+                for _ in 0..2 {
+                    // recording the same log twice doesn't add it twice.
+                    assert_eq!(
+                        record_ices(
+                            &mut conn,
+                            build_id,
+                            "x86_64-unknown-linux-gnu",
+                            rustc_version,
+                            log
+                        )
+                        .await?,
+                        1
+                    );
+                }
+            }
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private"),
+                "alice",
+            )
+            .await?;
+
+            let ices = list_ices(&mut conn, 10).await?;
+            assert_eq!(ices.len(), 1);
+            let ice = &ices[0];
+            assert_eq!(ice.crate_count, 2);
+            assert_eq!(ice.first_toolchain, NaiveDate::from_ymd_opt(2025, 10, 1));
+            assert_eq!(ice.last_toolchain, NaiveDate::from_ymd_opt(2025, 10, 5));
+            assert_eq!(ice.query_stack, ["typeck"]);
+            assert_eq!(
+                ice.message,
+                "no type for node HirId(DefId(0:7 ~ foo[1234]::bar).12)"
+            );
+            let mut names: Vec<_> = ice.crates.iter().map(|c| c.name.as_str()).collect();
+            names.sort();
+            assert_eq!(names, ["bar", "foo"]);
+            assert_eq!(
+                ice.crates[0].build_details_path(),
+                format!(
+                    "/crate/{}/0.1.0/builds/{}/x86_64-unknown-linux-gnu.txt",
+                    ice.crates[0].name, ice.crates[0].build_id
+                )
+            );
+
+            Ok(())
+        })
+    }
+}
@@ -1,18 +1,95 @@
 use crate::error::Result;
 use crate::utils::report_error;
-use anyhow::Context;
+use anyhow::{Context, bail};
 use crates_index_diff::gix;
+use reqwest::header::{HeaderValue, USER_AGENT};
 use std::path::PathBuf;
 use std::process::Command;
 use std::sync::atomic::AtomicBool;
+use url::Url;
+
+const APP_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    " ",
+    include_str!(concat!(env!("OUT_DIR"), "/git_version"))
+);
+
+/// Registry URLs with this prefix are only available through the sparse HTTP protocol.
+const SPARSE_PREFIX: &str = "sparse+";
+/// How many index entries we fetch at the same time from a sparse registry index.
+pub(crate) const SPARSE_INDEX_CONCURRENCY: usize = 16;
 
 pub struct Index {
     path: PathBuf,
     repository_url: Option<String>,
+    sparse: Option<SparseIndex>,
+}
+
+/// A sparse registry index.
+///
+/// There is no git history we could diff, so we fetch the index entries of the crates
+/// we are interested in. The entries are cached in the index path together with their
+/// `ETag` / `Last-Modified` header, so we only download entries that changed.
+struct SparseIndex {
+    index: crates_index::SparseIndex,
+    client: reqwest::Client,
+}
+
+/// The result of fetching a crate from a sparse registry index.
+pub(crate) enum SparseIndexEntry {
+    /// The index entry didn't change since it was last marked as seen.
+    Unchanged,
+    /// The crate doesn't exist (anymore).
+    Missing,
+    Changed(crates_index::Crate),
+}
+
+/// A response from a sparse registry index.
+///
+/// It's only written to the cache by [`Index::mark_sparse_entry_seen`], so when processing a
+/// changed entry fails, we see the change again the next time we fetch the crate.
+pub(crate) struct SparseIndexResponse {
+    name: String,
+    status: http::StatusCode,
+    headers: http::HeaderMap,
+    body: Vec<u8>,
+}
+
+impl SparseIndexResponse {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn entry(&self) -> Result<SparseIndexEntry> {
+        Ok(match self.status {
+            http::StatusCode::NOT_MODIFIED => SparseIndexEntry::Unchanged,
+            http::StatusCode::NOT_FOUND
+            | http::StatusCode::GONE
+            | http::StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => SparseIndexEntry::Missing,
+            http::StatusCode::OK => SparseIndexEntry::Changed(
+                crates_index::Crate::from_slice(&self.body)
+                    .with_context(|| format!("invalid index entry for crate {}", self.name))?,
+            ),
+            status => bail!(
+                "unexpected status {status} fetching crate {} from the sparse index",
+                self.name
+            ),
+        })
+    }
+
+    fn into_http(self) -> Result<http::Response<Vec<u8>>> {
+        let mut response = http::Response::builder().status(self.status);
+        *response.headers_mut().expect("response builder is valid") = self.headers;
+        Ok(response.body(self.body)?)
+    }
 }
 
 impl Index {
     pub fn from_url(path: PathBuf, url: String) -> Result<Self> {
+        if url.starts_with(SPARSE_PREFIX) {
+            return Self::sparse(path, url);
+        }
+
         crates_index_diff::Index::from_path_or_cloned_with_options(
             &path,
             gix::progress::Discard,
@@ -25,6 +102,7 @@ impl Index {
         Ok(Self {
             path,
             repository_url: Some(url),
+            sparse: None,
         })
     }
 
@@ -37,10 +115,41 @@ impl Index {
         Ok(Self {
             path,
             repository_url: None,
+            sparse: None,
         })
     }
 
+    /// Opens a sparse registry index, the url has to start with `sparse+`.
+    ///
+    /// Nothing is fetched here, the path is only used as cache for the index entries.
+    fn sparse(path: PathBuf, url: String) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .default_headers(
+                [(USER_AGENT, HeaderValue::from_static(APP_USER_AGENT))]
+                    .into_iter()
+                    .collect(),
+            )
+            .build()?;
+
+        Ok(Self {
+            sparse: Some(SparseIndex {
+                index: crates_index::SparseIndex::at_path(path.clone(), url.clone()),
+                client,
+            }),
+            path,
+            repository_url: Some(url),
+        })
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        self.sparse.is_some()
+    }
+
     pub fn diff(&self) -> Result<crates_index_diff::Index> {
+        if self.is_sparse() {
+            bail!("sparse registry indexes have no git history to diff");
+        }
+
         let options = self
             .repository_url
             .clone()
@@ -57,6 +166,10 @@ impl Index {
     }
 
     pub(crate) fn crates(&self) -> Result<crates_index::GitIndex> {
+        if self.is_sparse() {
+            bail!("sparse registry indexes can't list their crates");
+        }
+
         tracing::debug!("Opening with `crates_index`");
         // crates_index requires the repo url to match the existing origin or it tries to reinitialize the repo
         let repo_url = self
@@ -68,7 +181,97 @@ impl Index {
         Ok(index)
     }
 
+    fn sparse_index(&self) -> Result<&SparseIndex> {
+        self.sparse
+            .as_ref()
+            .context("registry index is not a sparse index")
+    }
+
+    /// Fetches the index entry of a crate from the sparse index.
+    ///
+    /// Entries that didn't change since they were last marked as seen are not downloaded again.
+    pub(crate) async fn fetch_sparse_entry(&self, name: &str) -> Result<SparseIndexResponse> {
+        let sparse = self.sparse_index()?;
+
+        let request = sparse
+            .index
+            .make_cache_request(name)
+            .with_context(|| format!("invalid crate name {name}"))?
+            .body(())?;
+
+        let mut headers = request.headers().clone();
+        // reqwest adds this header itself, and only decompresses the response when it did.
+        headers.remove(http::header::ACCEPT_ENCODING);
+
+        let response = sparse
+            .client
+            .get(request.uri().to_string())
+            .headers(headers)
+            .send()
+            .await
+            .with_context(|| format!("failed to fetch crate {name} from the sparse index"))?;
+
+        Ok(SparseIndexResponse {
+            name: name.into(),
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.bytes().await?.into(),
+        })
+    }
+
+    /// Writes a changed index entry to the cache, so it's only returned again after the next change.
+    pub(crate) fn mark_sparse_entry_seen(&self, response: SparseIndexResponse) -> Result<()> {
+        if response.status != http::StatusCode::OK {
+            return Ok(());
+        }
+
+        let name = response.name.clone();
+        self.sparse_index()?
+            .index
+            .parse_cache_response(&name, response.into_http()?, true)?;
+        Ok(())
+    }
+
+    /// Loads a crate from the sparse index, using the cache when the entry didn't change.
+    pub(crate) async fn load_sparse_crate(
+        &self,
+        name: &str,
+    ) -> Result<Option<crates_index::Crate>> {
+        let response = self.fetch_sparse_entry(name).await?;
+        Ok(self
+            .sparse_index()?
+            .index
+            .parse_cache_response(name, response.into_http()?, true)?)
+    }
+
+    /// Fetches a list of crate names, one per line.
+    ///
+    /// The sparse protocol has no way to list all crates of a registry, so registries
+    /// can publish this list to make us pick up new crates.
+    pub(crate) async fn fetch_sparse_crate_names(&self, url: &Url) -> Result<Vec<String>> {
+        let names = self
+            .sparse_index()?
+            .client
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(names
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(Into::into)
+            .collect())
+    }
+
     pub fn run_git_gc(&self) {
+        if self.is_sparse() {
+            return;
+        }
+
         let gc = Command::new("git")
             .arg("-C")
             .arg(&self.path)
@@ -5,8 +5,8 @@
 pub use self::build_queue::{AsyncBuildQueue, BuildQueue, queue_rebuilds};
 pub use self::config::Config;
 pub use self::context::Context;
-pub use self::docbuilder::PackageKind;
 pub use self::docbuilder::{BuildPackageSummary, RustwideBuilder};
+pub use self::docbuilder::{CanarySample, PackageKind};
 pub use self::index::Index;
 pub use self::metrics::{InstanceMetrics, ServiceMetrics};
 pub use self::registry_api::RegistryApi;
@@ -15,6 +15,7 @@ pub use self::web::{start_background_metrics_webserver, start_web_server};
 
 pub use font_awesome_as_a_crate::icons;
 
+pub mod build_logs;
 mod build_queue;
 pub mod cdn;
 mod config;
@@ -22,8 +23,11 @@ mod context;
 pub mod db;
 mod docbuilder;
 mod error;
+mod events;
+mod ices;
 pub mod index;
 pub mod metrics;
+pub mod rebuild_campaigns;
 mod registry_api;
 pub mod repositories;
 pub mod storage;
@@ -31,6 +35,7 @@ pub mod storage;
 mod test;
 pub mod utils;
 mod web;
+pub mod webhooks;
 
 #[allow(dead_code)]
 mod target {
@@ -4,7 +4,7 @@ mod macros;
 use self::macros::MetricFromOpts;
 use crate::{
     AsyncBuildQueue, Config, cdn,
-    db::{CrateId, Pool, ReleaseId},
+    db::{CrateId, Pool, ReleaseId, builders},
     target::TargetAtom,
 };
 use anyhow::Error;
@@ -122,6 +122,8 @@ metrics! {
         pub(crate) failed_builds: IntCounter,
         /// Number of builds that did not complete due to not being a library
         pub(crate) non_library_builds: IntCounter,
+        /// Number of builds recovered after their build server stopped responding
+        pub(crate) recovered_stale_builds: IntCounter,
 
         /// Number of files uploaded to the storage backend
         pub(crate) uploaded_files_total: IntCounter,
@@ -260,6 +262,7 @@ pub struct ServiceMetrics {
     pub prioritized_crates_count: IntGauge,
     pub failed_crates_count: IntGauge,
     pub queue_is_locked: IntGauge,
+    pub active_builders_count: IntGauge,
     pub queued_crates_count_by_priority: IntGaugeVec,
     pub queued_cdn_invalidations_by_distribution: IntGaugeVec,
 
@@ -295,6 +298,12 @@ impl ServiceMetrics {
                 "Whether the build queue is locked",
                 None,
             )?,
+            active_builders_count: metric_from_opts(
+                &registry,
+                "active_builders_count",
+                "Number of build servers that recently sent a heartbeat",
+                None,
+            )?,
             queued_crates_count_by_priority: metric_from_opts(
                 &registry,
                 "queued_crates_count_by_priority",
@@ -359,6 +368,12 @@ impl ServiceMetrics {
                 .set(count);
         }
 
+        self.active_builders_count.set(
+            builders::active_builders(&mut conn, config.stale_build_timeout)
+                .await?
+                .len() as i64,
+        );
+
         self.failed_crates_count
             .set(queue.failed_count().await? as i64);
         Ok(self.registry.gather())
@@ -0,0 +1,686 @@
+//! Named mass rebuilds.
+//!
+//! A campaign selects releases, remembers the outcome of their newest build and queues them.
+//! Builds started after that are the rebuilds, so the report can compare both outcomes.
+
+use crate::{build_queue::queue_campaign_crate, db::types::BuildStatus};
+use anyhow::{Result, bail};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::TryStreamExt as _;
+use sqlx::Connection as _;
+use std::fmt;
+use tracing::{info, instrument};
+
+/// How the builds of the selected releases failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureCategory {
+    /// Any failed build.
+    Any,
+    /// Builds we found an internal compiler error in.
+    InternalCompilerError,
+    /// Builds whose indexed log contains this text.
+    LogContains(String),
+}
+
+/// Which releases a campaign rebuilds.
+///
+/// Without a list, the filters apply to the latest release of every crate. Campaigns only
+/// rebuild crates.io crates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CampaignSelection {
+    /// Only releases whose newest build used a nightly from this day or later.
+    pub toolchain_since: Option<NaiveDate>,
+    /// Only releases whose newest build used a nightly from this day or earlier.
+    pub toolchain_until: Option<NaiveDate>,
+    /// Only releases whose newest build failed like this.
+    pub failure: Option<FailureCategory>,
+    /// Only releases depending on this crate.
+    pub dependency: Option<String>,
+    /// Only these releases, a missing version means the latest release.
+    pub releases: Option<Vec<(String, Option<String>)>>,
+}
+
+impl fmt::Display for CampaignSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(releases) = &self.releases {
+            parts.push(format!("{} listed releases", releases.len()));
+        }
+        if let Some(since) = self.toolchain_since {
+            parts.push(format!("toolchain since {since}"));
+        }
+        if let Some(until) = self.toolchain_until {
+            parts.push(format!("toolchain until {until}"));
+        }
+        match &self.failure {
+            Some(FailureCategory::Any) => parts.push("failed".into()),
+            Some(FailureCategory::InternalCompilerError) => {
+                parts.push("failed with an internal compiler error".into())
+            }
+            Some(FailureCategory::LogContains(text)) => {
+                parts.push(format!("failed with {text:?} in the log"))
+            }
+            None => {}
+        }
+        if let Some(dependency) = &self.dependency {
+            parts.push(format!("depending on {dependency}"));
+        }
+
+        if parts.is_empty() {
+            write!(f, "all latest releases")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// Parses a list of releases, one `name` or `name version` per line.
+///
+/// Empty lines and lines starting with `#` are ignored.
+pub fn parse_release_list(list: &str) -> Result<Vec<(String, Option<String>)>> {
+    let mut releases = Vec::new();
+    for (number, line) in list.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), version, None) => {
+                releases.push((name.to_owned(), version.map(str::to_owned)))
+            }
+            _ => bail!("invalid release on line {}: {line:?}", number + 1),
+        }
+    }
+    Ok(releases)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Campaign {
+    pub id: i32,
+    pub name: String,
+    pub priority: i32,
+    pub selection: String,
+    pub created_at: DateTime<Utc>,
+    /// How many releases the campaign queued.
+    pub total: i64,
+    /// How many of them were rebuilt since.
+    pub finished: i64,
+}
+
+/// What a rebuild changed for a release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CampaignOutcome {
+    /// Not rebuilt yet.
+    Pending,
+    /// The release didn't build before, and does now.
+    Fixed,
+    /// The release built before, and doesn't anymore.
+    Broken,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CampaignRelease {
+    pub name: String,
+    pub version: String,
+    pub(crate) previous_status: Option<BuildStatus>,
+    pub previous_rustc_version: Option<String>,
+    pub(crate) current_status: Option<BuildStatus>,
+    pub current_rustc_version: Option<String>,
+}
+
+impl fmt::Display for CampaignRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn status(status: Option<BuildStatus>, missing: &'static str) -> &'static str {
+            match status {
+                Some(BuildStatus::Success) => "success",
+                Some(BuildStatus::Failure) => "failure",
+                Some(BuildStatus::InProgress) => "in progress",
+                None => missing,
+            }
+        }
+
+        write!(
+            f,
+            "{} {}: {} -> {}",
+            self.name,
+            self.version,
+            status(self.previous_status, "never built"),
+            status(self.current_status, "queued"),
+        )?;
+        if let Some(rustc_version) = &self.current_rustc_version {
+            write!(f, " ({rustc_version})")?;
+        }
+        Ok(())
+    }
+}
+
+impl CampaignRelease {
+    pub fn outcome(&self) -> CampaignOutcome {
+        match (self.previous_status, self.current_status) {
+            (_, None | Some(BuildStatus::InProgress)) => CampaignOutcome::Pending,
+            (Some(BuildStatus::Success), Some(BuildStatus::Failure)) => CampaignOutcome::Broken,
+            (Some(previous), Some(BuildStatus::Success)) if previous != BuildStatus::Success => {
+                CampaignOutcome::Fixed
+            }
+            (None, Some(BuildStatus::Success)) => CampaignOutcome::Fixed,
+            _ => CampaignOutcome::Unchanged,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CampaignReport {
+    pub campaign: Campaign,
+    pub releases: Vec<CampaignRelease>,
+}
+
+impl CampaignReport {
+    pub fn count(&self, outcome: CampaignOutcome) -> usize {
+        self.releases
+            .iter()
+            .filter(|release| release.outcome() == outcome)
+            .count()
+    }
+}
+
+/// Creates a campaign and queues the releases it selects.
+#[instrument(skip(conn, selection))]
+pub async fn create_campaign(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    selection: &CampaignSelection,
+    priority: i32,
+) -> Result<Campaign> {
+    let (list_names, list_versions): (Vec<String>, Vec<String>) = selection
+        .releases
+        .iter()
+        .flatten()
+        .map(|(name, version)| (name.clone(), version.clone().unwrap_or_default()))
+        .unzip();
+    let (failure, log_text) = match &selection.failure {
+        None => (None, None),
+        Some(FailureCategory::Any) => (Some("any"), None),
+        Some(FailureCategory::InternalCompilerError) => (Some("ice"), None),
+        Some(FailureCategory::LogContains(text)) => (Some("log"), Some(text.as_str())),
+    };
+
+    let releases = sqlx::query!(
+        r#"SELECT
+            crates.name,
+            releases.id AS release_id,
+            releases.version,
+            newest_build.id AS "build_id?",
+            newest_build.build_status AS "build_status?: BuildStatus"
+         FROM crates
+         INNER JOIN releases ON releases.crate_id = crates.id
+         LEFT JOIN LATERAL (
+            SELECT builds.id, builds.build_status, builds.rustc_nightly_date
+            FROM builds
+            WHERE builds.rid = releases.id
+            ORDER BY builds.id DESC
+            LIMIT 1
+         ) AS newest_build ON TRUE
+         WHERE
+            crates.registry_id IS NULL AND
+            (
+                (NOT $1 AND releases.id = crates.latest_version_id) OR
+                EXISTS (
+                    SELECT 1
+                    FROM UNNEST($2::TEXT[], $3::TEXT[]) AS list(name, version)
+                    WHERE
+                        normalize_crate_name(list.name) = normalize_crate_name(crates.name) AND
+                        (
+                            list.version = releases.version OR
+                            (list.version = '' AND releases.id = crates.latest_version_id)
+                        )
+                )
+            ) AND
+            ($4::DATE IS NULL OR newest_build.rustc_nightly_date >= $4) AND
+            ($5::DATE IS NULL OR newest_build.rustc_nightly_date <= $5) AND
+            (
+                $6::TEXT IS NULL OR
+                (
+                    newest_build.build_status = 'failure' AND
+                    CASE $6
+                        WHEN 'ice' THEN EXISTS (
+                            SELECT 1 FROM ice_occurrences
+                            WHERE ice_occurrences.build_id = newest_build.id
+                        )
+                        WHEN 'log' THEN EXISTS (
+                            SELECT 1 FROM build_log_index
+                            WHERE
+                                build_log_index.build_id = newest_build.id AND
+                                strpos(build_log_index.content, $7) > 0
+                        )
+                        ELSE TRUE
+                    END
+                )
+            ) AND
+            (
+                $8::TEXT IS NULL OR
+                CASE WHEN json_typeof(releases.dependencies) = 'array' THEN EXISTS (
+                    SELECT 1
+                    FROM json_array_elements(releases.dependencies) AS dependency
+                    WHERE normalize_crate_name(dependency->>0) = normalize_crate_name($8)
+                ) ELSE FALSE END
+            )
+         ORDER BY crates.name, releases.id"#,
+        selection.releases.is_some(),
+        &list_names,
+        &list_versions,
+        selection.toolchain_since,
+        selection.toolchain_until,
+        failure,
+        log_text,
+        selection.dependency,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut transaction = conn.begin().await?;
+    let campaign_id = sqlx::query_scalar!(
+        "INSERT INTO rebuild_campaigns (name, priority, selection)
+         VALUES ($1, $2, $3)
+         RETURNING id",
+        name,
+        priority,
+        selection.to_string(),
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    for release in &releases {
+        sqlx::query!(
+            "INSERT INTO rebuild_campaign_releases
+                (campaign_id, release_id, previous_build_id, previous_build_status)
+             VALUES ($1, $2, $3, $4)",
+            campaign_id,
+            release.release_id,
+            release.build_id,
+            release.build_status as Option<BuildStatus>,
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        queue_campaign_crate(
+            &mut transaction,
+            &release.name,
+            &release.version,
+            priority,
+            campaign_id,
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+    info!(
+        campaign_id,
+        releases = releases.len(),
+        "created rebuild campaign"
+    );
+
+    get_campaign(conn, name)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("campaign {name} vanished"))
+}
+
+/// All campaigns, newest first.
+pub async fn list_campaigns(conn: &mut sqlx::PgConnection) -> Result<Vec<Campaign>> {
+    campaigns(conn, None).await
+}
+
+pub async fn get_campaign(conn: &mut sqlx::PgConnection, name: &str) -> Result<Option<Campaign>> {
+    Ok(campaigns(conn, Some(name)).await?.pop())
+}
+
+async fn campaigns(conn: &mut sqlx::PgConnection, name: Option<&str>) -> Result<Vec<Campaign>> {
+    Ok(sqlx::query_as!(
+        Campaign,
+        r#"SELECT
+            rebuild_campaigns.id,
+            rebuild_campaigns.name,
+            rebuild_campaigns.priority,
+            rebuild_campaigns.selection,
+            rebuild_campaigns.created_at,
+            COUNT(rebuild_campaign_releases.release_id) AS "total!",
+            COUNT(rebuild.id) FILTER (WHERE rebuild.build_status != 'in_progress') AS "finished!"
+         FROM rebuild_campaigns
+         LEFT JOIN rebuild_campaign_releases
+            ON rebuild_campaign_releases.campaign_id = rebuild_campaigns.id
+         LEFT JOIN LATERAL (
+            SELECT builds.id, builds.build_status
+            FROM builds
+            WHERE
+                builds.rid = rebuild_campaign_releases.release_id AND
+                builds.id > COALESCE(rebuild_campaign_releases.previous_build_id, 0)
+            ORDER BY builds.id DESC
+            LIMIT 1
+         ) AS rebuild ON TRUE
+         WHERE $1::TEXT IS NULL OR rebuild_campaigns.name = $1
+         GROUP BY rebuild_campaigns.id
+         ORDER BY rebuild_campaigns.created_at DESC, rebuild_campaigns.id DESC"#,
+        name,
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+/// The releases of a campaign, with their build outcome before and after the rebuild.
+pub async fn campaign_report(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+) -> Result<Option<CampaignReport>> {
+    let Some(campaign) = get_campaign(&mut *conn, name).await? else {
+        return Ok(None);
+    };
+
+    let releases = sqlx::query_as!(
+        CampaignRelease,
+        r#"SELECT
+            crates.name,
+            releases.version,
+            rebuild_campaign_releases.previous_build_status AS "previous_status: BuildStatus",
+            previous_build.rustc_version::TEXT AS previous_rustc_version,
+            rebuild.build_status AS "current_status?: BuildStatus",
+            rebuild.rustc_version AS "current_rustc_version?"
+         FROM rebuild_campaign_releases
+         INNER JOIN releases ON releases.id = rebuild_campaign_releases.release_id
+         INNER JOIN crates ON crates.id = releases.crate_id
+         LEFT JOIN builds AS previous_build
+            ON previous_build.id = rebuild_campaign_releases.previous_build_id
+         LEFT JOIN LATERAL (
+            SELECT builds.build_status, builds.rustc_version::TEXT
+            FROM builds
+            WHERE
+                builds.rid = releases.id AND
+                builds.id > COALESCE(rebuild_campaign_releases.previous_build_id, 0)
+            ORDER BY builds.id DESC
+            LIMIT 1
+         ) AS rebuild ON TRUE
+         WHERE rebuild_campaign_releases.campaign_id = $1
+         ORDER BY crates.name, releases.id"#,
+        campaign.id,
+    )
+    .fetch(&mut *conn)
+    .try_collect()
+    .await?;
+
+    Ok(Some(CampaignReport { campaign, releases }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{BuildId, registries::add_registry},
+        ices,
+        test::{FakeBuild, async_wrapper},
+    };
+
+    #[test]
+    fn release_list() {
+        assert_eq!(
+            parse_release_list("# comment\nfoo\n\n  bar 1.0.0  \n").unwrap(),
+            [
+                ("foo".to_owned(), None),
+                ("bar".to_owned(), Some("1.0.0".to_owned()))
+            ]
+        );
+        assert!(parse_release_list("foo 1.0.0 extra").is_err());
+    }
+
+    #[test]
+    fn outcome() {
+        let release = |previous_status, current_status| CampaignRelease {
+            name: "foo".into(),
+            version: "0.1.0".into(),
+            previous_status,
+            previous_rustc_version: None,
+            current_status,
+            current_rustc_version: None,
+        };
+        use BuildStatus::*;
+        use CampaignOutcome::*;
+        assert_eq!(release(Some(Failure), None).outcome(), Pending);
+        assert_eq!(release(Some(Failure), Some(InProgress)).outcome(), Pending);
+        assert_eq!(release(Some(Failure), Some(Success)).outcome(), Fixed);
+        assert_eq!(release(None, Some(Success)).outcome(), Fixed);
+        assert_eq!(release(Some(Success), Some(Failure)).outcome(), Broken);
+        assert_eq!(release(Some(Success), Some(Success)).outcome(), Unchanged);
+        assert_eq!(release(Some(Failure), Some(Failure)).outcome(), Unchanged);
+    }
+
+    #[test]
+    fn selection() {
+        async_wrapper(|env| async move {
+            let failed = |rustc_version: &str| {
+                vec![
+                    FakeBuild::default()
+                        .build_status(BuildStatus::Failure)
+                        .rustc_version(rustc_version),
+                ]
+            };
+            env.fake_release()
+                .await
+                .name("old-nightly")
+                .version("0.1.0")
+                .builds(failed("rustc 1.90.0-nightly (abcdef012 2025-07-01)"))
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("ice")
+                .version("0.1.0")
+                .builds(failed("rustc 1.92.0-nightly (abcdef012 2025-10-01)"))
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("uses-serde")
+                .version("0.1.0")
+                .add_dependency(crate::utils::Dependency::new(
+                    "serde".into(),
+                    "1.0.0".into(),
+                ))
+                .create()
+                .await?;
+            for version in ["0.1.0", "0.2.0"] {
+                env.fake_release()
+                    .await
+                    .name("two-versions")
+                    .version(version)
+                    .create()
+                    .await?;
+            }
+
+            // campaigns don't rebuild crates of hosted registries.
+            let mut conn = env.async_db().await.async_conn().await;
+            add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+            env.fake_release()
+                .await
+                .name("acme/uses-serde")
+                .version("0.1.0")
+                .add_dependency(crate::utils::Dependency::new(
+                    "serde".into(),
+                    "1.0.0".into(),
+                ))
+                .create()
+                .await?;
+
+            let ice_build: BuildId = sqlx::query_scalar!(
+                r#"SELECT builds.id AS "id: BuildId"
+                 FROM builds
+                 INNER JOIN releases ON releases.id = builds.rid
+                 INNER JOIN crates ON crates.id = releases.crate_id
+                 WHERE crates.name = 'ice'"#
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            ices::record_ices(
+                &mut conn,
+                ice_build,
+                "x86_64-unknown-linux-gnu",
+                "rustc 1.92.0-nightly (abcdef012 2025-10-01)",
+                "error: internal compiler error: compiler/rustc_x/src/lib.rs:1:2: no type",
+            )
+            .await?;
+
+            let mut select = async |name: &str, selection: CampaignSelection| {
+                create_campaign(&mut conn, name, &selection, 15).await?;
+                let report = campaign_report(&mut conn, name).await?.unwrap();
+                Ok::<_, anyhow::Error>(
+                    report
+                        .releases
+                        .into_iter()
+                        .map(|release| format!("{} {}", release.name, release.version))
+                        .collect::<Vec<_>>(),
+                )
+            };
+
+            assert_eq!(
+                select(
+                    "failed",
+                    CampaignSelection {
+                        failure: Some(FailureCategory::Any),
+                        ..Default::default()
+                    }
+                )
+                .await?,
+                ["ice 0.1.0", "old-nightly 0.1.0"]
+            );
+            assert_eq!(
+                select(
+                    "ices",
+                    CampaignSelection {
+                        failure: Some(FailureCategory::InternalCompilerError),
+                        ..Default::default()
+                    }
+                )
+                .await?,
+                ["ice 0.1.0"]
+            );
+            assert_eq!(
+                select(
+                    "old",
+                    CampaignSelection {
+                        toolchain_since: NaiveDate::from_ymd_opt(2025, 1, 1),
+                        toolchain_until: NaiveDate::from_ymd_opt(2025, 8, 1),
+                        ..Default::default()
+                    }
+                )
+                .await?,
+                ["old-nightly 0.1.0"]
+            );
+            assert_eq!(
+                select(
+                    "serde",
+                    CampaignSelection {
+                        dependency: Some("serde".into()),
+                        ..Default::default()
+                    }
+                )
+                .await?,
+                ["uses-serde 0.1.0"]
+            );
+            assert_eq!(
+                select(
+                    "list",
+                    CampaignSelection {
+                        releases: Some(parse_release_list("two-versions\ntwo-versions 0.1.0")?),
+                        ..Default::default()
+                    }
+                )
+                .await?,
+                ["two-versions 0.1.0", "two-versions 0.2.0"]
+            );
+
+            let queued = sqlx::query!(
+                r#"SELECT queue.priority, rebuild_campaigns.name AS "campaign?"
+                 FROM queue
+                 LEFT JOIN rebuild_campaigns ON rebuild_campaigns.id = queue.campaign_id
+                 WHERE queue.name = 'uses-serde'"#
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            assert_eq!(queued.priority, 15);
+            assert_eq!(queued.campaign.as_deref(), Some("serde"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn report() {
+        async_wrapper(|env| async move {
+            for name in ["broken", "fixed", "pending"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .builds(vec![FakeBuild::default().build_status(
+                        if name == "broken" {
+                            BuildStatus::Success
+                        } else {
+                            BuildStatus::Failure
+                        },
+                    )])
+                    .create()
+                    .await?;
+            }
+
+            let mut conn = env.async_db().await.async_conn().await;
+            create_campaign(&mut conn, "everything", &CampaignSelection::default(), 20).await?;
+
+            // the rebuilds.
+            for (name, status) in [
+                ("broken", BuildStatus::Failure),
+                ("fixed", BuildStatus::Success),
+            ] {
+                sqlx::query!(
+                    "INSERT INTO builds (rid, build_status, rustc_version, build_finished)
+                     SELECT releases.id, $2, 'rustc 1.92.0-nightly (abcdef012 2025-10-19)', NOW()
+                     FROM releases
+                     INNER JOIN crates ON crates.id = releases.crate_id
+                     WHERE crates.name = $1",
+                    name,
+                    status as BuildStatus,
+                )
+                .execute(&mut *conn)
+                .await?;
+            }
+
+            let report = campaign_report(&mut conn, "everything").await?.unwrap();
+            assert_eq!(report.campaign.total, 3);
+            assert_eq!(report.campaign.finished, 2);
+            assert_eq!(report.campaign.priority, 20);
+            assert_eq!(report.campaign.selection, "all latest releases");
+            let outcomes: Vec<_> = report
+                .releases
+                .iter()
+                .map(|release| (release.name.as_str(), release.outcome()))
+                .collect();
+            assert_eq!(
+                outcomes,
+                [
+                    ("broken", CampaignOutcome::Broken),
+                    ("fixed", CampaignOutcome::Fixed),
+                    ("pending", CampaignOutcome::Pending),
+                ]
+            );
+            assert_eq!(
+                report.releases[1].to_string(),
+                "fixed 0.1.0: failure -> success (rustc 1.92.0-nightly (abcdef012 2025-10-19))"
+            );
+            assert_eq!(
+                report.releases[2].to_string(),
+                "pending 0.1.0: failure -> queued"
+            );
+
+            assert_eq!(list_campaigns(&mut conn).await?, [report.campaign]);
+            assert!(campaign_report(&mut conn, "unknown").await?.is_none());
+
+            Ok(())
+        })
+    }
+}
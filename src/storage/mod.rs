@@ -1033,6 +1033,17 @@ pub(crate) fn source_archive_path(name: &str, version: &str) -> String {
     format!("sources/{name}/{version}.zip")
 }
 
+/// The `Cargo.lock` a build used, after any regeneration.
+pub(crate) const CARGO_LOCK_ARTIFACT: &str = "Cargo.lock";
+/// The `cargo metadata` output of a build, with the resolved dependency graph.
+pub(crate) const CARGO_METADATA_ARTIFACT: &str = "cargo-metadata.json";
+/// The files we store for each build next to its logs.
+pub(crate) const BUILD_ARTIFACTS: &[&str] = &[CARGO_LOCK_ARTIFACT, CARGO_METADATA_ARTIFACT];
+
+pub(crate) fn build_artifact_path(build_id: impl fmt::Display, filename: &str) -> String {
+    format!("build-artifacts/{build_id}/{filename}")
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
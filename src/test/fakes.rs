@@ -1,6 +1,7 @@
 use super::TestDatabase;
 
 use crate::db::file::{FileEntry, file_list_to_json};
+use crate::db::registries::split_crate_name;
 use crate::db::types::BuildStatus;
 use crate::db::{
     BuildId, ReleaseId, initialize_build, initialize_crate, initialize_release, update_build_status,
@@ -160,7 +161,8 @@ impl<'a> FakeRelease<'a> {
     pub(crate) fn name(mut self, new: &str) -> Self {
         self.package.name = new.into();
         self.package.id = format!("{new}-id");
-        self.package.targets[0].name = new.into();
+        // crates from hosted registries have a registry-qualified name, but not their targets.
+        self.package.targets[0].name = split_crate_name(new).1.into();
         self
     }
 
@@ -265,6 +267,14 @@ impl<'a> FakeRelease<'a> {
         self
     }
 
+    /// A binary crate that opted into having its binaries documented.
+    pub(crate) fn documented_binary(self) -> Self {
+        Self {
+            has_docs: true,
+            ..self.binary(true)
+        }
+    }
+
     pub(crate) fn keywords(mut self, keywords: Vec<String>) -> Self {
         self.package.keywords = keywords;
         self
@@ -467,8 +477,10 @@ impl<'a> FakeRelease<'a> {
         let builds = self.builds.unwrap_or_else(|| vec![FakeBuild::default()]);
 
         if builds.last().map(|b| b.build_status) == Some(BuildStatus::Success) {
-            let index = [&package.name, "index.html"].join("/");
-            if package.is_library() && !rustdoc_files.iter().any(|(path, _)| path == &index) {
+            let index = [split_crate_name(&package.name).1, "index.html"].join("/");
+            if (package.is_library() || self.has_docs)
+                && !rustdoc_files.iter().any(|(path, _)| path == &index)
+            {
                 rustdoc_files.push((&index, DEFAULT_CONTENT));
             }
 
@@ -515,36 +527,34 @@ impl<'a> FakeRelease<'a> {
 
         let default_target = self.default_target.unwrap_or("x86_64-unknown-linux-gnu");
 
-        {
-            let mut targets = self.doc_targets.clone();
-            if !targets.contains(&default_target.to_owned()) {
-                targets.push(default_target.to_owned());
-            }
-            for target in &targets {
-                let dummy_rustdoc_json_content = serde_json::to_vec(&serde_json::json!({
-                    "format_version": 42
-                }))?;
-
-                for alg in RUSTDOC_JSON_COMPRESSION_ALGORITHMS {
-                    let compressed_json: Vec<u8> = compress(&*dummy_rustdoc_json_content, *alg)?;
-
-                    for format_version in [
-                        RustdocJsonFormatVersion::Version(42),
-                        RustdocJsonFormatVersion::Latest,
-                    ] {
-                        storage
-                            .store_one_uncompressed(
-                                &rustdoc_json_path(
-                                    &package.name,
-                                    &package.version,
-                                    target,
-                                    format_version,
-                                    Some(*alg),
-                                ),
-                                compressed_json.clone(),
-                            )
-                            .await?;
-                    }
+        let mut json_targets = self.doc_targets.clone();
+        if !json_targets.contains(&default_target.to_owned()) {
+            json_targets.push(default_target.to_owned());
+        }
+        for target in &json_targets {
+            let dummy_rustdoc_json_content = serde_json::to_vec(&serde_json::json!({
+                "format_version": 42
+            }))?;
+
+            for alg in RUSTDOC_JSON_COMPRESSION_ALGORITHMS {
+                let compressed_json: Vec<u8> = compress(&*dummy_rustdoc_json_content, *alg)?;
+
+                for format_version in [
+                    RustdocJsonFormatVersion::Version(42),
+                    RustdocJsonFormatVersion::Latest,
+                ] {
+                    storage
+                        .store_one_uncompressed(
+                            &rustdoc_json_path(
+                                &package.name,
+                                &package.version,
+                                target,
+                                format_version,
+                                Some(*alg),
+                            ),
+                            compressed_json.clone(),
+                        )
+                        .await?;
                 }
             }
         }
@@ -584,6 +594,20 @@ impl<'a> FakeRelease<'a> {
                 .create(&mut async_conn, &storage, release_id, default_target)
                 .await?;
         }
+        for target in &json_targets {
+            for format_version in ["42", "latest"] {
+                sqlx::query!(
+                    "INSERT INTO rustdoc_json_formats (release_id, target, format_version)
+                     VALUES ($1, $2, $3)
+                     ON CONFLICT DO NOTHING",
+                    release_id.0,
+                    target,
+                    format_version,
+                )
+                .execute(&mut *async_conn)
+                .await?;
+            }
+        }
         if let Some(coverage) = self.doc_coverage {
             crate::db::add_doc_coverage(&mut async_conn, release_id, coverage).await?;
         }
@@ -709,6 +733,12 @@ impl FakeBuild {
             .await?;
         }
 
+        let mut targets: Vec<&str> = self.other_build_logs.keys().map(String::as_str).collect();
+        if self.s3_build_log.is_some() {
+            targets.push(default_target);
+        }
+        crate::db::set_build_targets(&mut *conn, build_id, &targets).await?;
+
         let prefix = format!("build-logs/{build_id}/");
 
         if let Some(s3_build_log) = self.s3_build_log.as_deref() {
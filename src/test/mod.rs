@@ -1,6 +1,12 @@
 mod fakes;
+mod oidc;
+mod sparse_index;
+mod webhook_receiver;
 
 pub(crate) use self::fakes::{FakeBuild, fake_release_that_failed_before_build};
+pub(crate) use self::oidc::TestOidcProvider;
+pub(crate) use self::sparse_index::TestSparseIndex;
+pub(crate) use self::webhook_receiver::TestWebhookReceiver;
 use crate::cdn::CdnBackend;
 use crate::db::{self, AsyncPoolClient, Pool};
 use crate::error::Result;
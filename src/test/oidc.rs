@@ -0,0 +1,98 @@
+//! A local stand-in for an OpenID Connect provider.
+
+use crate::error::Result;
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+use url::Url;
+
+struct Provider {
+    addr: SocketAddr,
+    /// access token => subject
+    tokens: HashMap<String, String>,
+    userinfo_requests: AtomicUsize,
+}
+
+/// Serves the discovery document and the userinfo endpoint, for a fixed set of access tokens.
+pub(crate) struct TestOidcProvider {
+    provider: Arc<Provider>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl TestOidcProvider {
+    pub(crate) async fn new(tokens: &[(&str, &str)]) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let provider = Arc::new(Provider {
+            addr: listener.local_addr()?,
+            tokens: tokens
+                .iter()
+                .map(|(token, subject)| (token.to_string(), subject.to_string()))
+                .collect(),
+            userinfo_requests: AtomicUsize::new(0),
+        });
+
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/userinfo", get(userinfo))
+            .with_state(provider.clone());
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Ok(Self { provider, server })
+    }
+
+    pub(crate) fn issuer(&self) -> Url {
+        format!("http://{}/", self.provider.addr).parse().unwrap()
+    }
+
+    /// How often tokens were validated with the userinfo endpoint.
+    pub(crate) fn userinfo_requests(&self) -> usize {
+        self.provider.userinfo_requests.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for TestOidcProvider {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn discovery(State(provider): State<Arc<Provider>>) -> impl IntoResponse {
+    let issuer = format!("http://{}", provider.addr);
+    Json(serde_json::json!({
+        "issuer": issuer,
+        "userinfo_endpoint": format!("{issuer}/userinfo"),
+    }))
+}
+
+async fn userinfo(State(provider): State<Arc<Provider>>, headers: HeaderMap) -> Response {
+    provider.userinfo_requests.fetch_add(1, Ordering::SeqCst);
+
+    let subject = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| provider.tokens.get(token));
+
+    match subject {
+        Some(subject) => Json(serde_json::json!({
+            "sub": subject,
+            "email": format!("{subject}@example.com"),
+        }))
+        .into_response(),
+        None => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
@@ -0,0 +1,174 @@
+//! A static-file stand-in for a sparse registry index.
+
+use crate::{Index, error::Result};
+use axum::{
+    Router,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+/// The path we serve the list of all crate names at.
+const CRATE_NAMES_PATH: &str = "names.txt";
+
+/// Serves a sparse registry index from a temporary directory.
+///
+/// Like the real ones, it sends an `ETag` and answers conditional requests with `304`.
+pub(crate) struct TestSparseIndex {
+    root: tempfile::TempDir,
+    cache: tempfile::TempDir,
+    addr: SocketAddr,
+    /// crate name => versions and their yank state
+    crates: Mutex<BTreeMap<String, Vec<(String, bool)>>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl TestSparseIndex {
+    pub(crate) async fn new() -> Result<Self> {
+        let root = tempfile::tempdir()?;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let app = Router::new()
+            .fallback(serve_file)
+            .with_state(Arc::new(root.path().to_owned()));
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let index = Self {
+            root,
+            cache: tempfile::tempdir()?,
+            addr,
+            crates: Mutex::new(BTreeMap::new()),
+            server,
+        };
+        index.write_crate_names()?;
+        Ok(index)
+    }
+
+    pub(crate) fn url(&self) -> String {
+        format!("sparse+http://{}/", self.addr)
+    }
+
+    pub(crate) fn crate_names_url(&self) -> Url {
+        format!("http://{}/{CRATE_NAMES_PATH}", self.addr)
+            .parse()
+            .unwrap()
+    }
+
+    /// A new [`Index`] pointing to this registry, with its own cache.
+    pub(crate) fn index(&self) -> Result<Index> {
+        Index::from_url(self.cache.path().to_owned(), self.url())
+    }
+
+    pub(crate) fn publish(&self, name: &str, version: &str) -> Result<()> {
+        self.update(name, |versions| versions.push((version.into(), false)))
+    }
+
+    pub(crate) fn set_yanked(&self, name: &str, version: &str, yanked: bool) -> Result<()> {
+        self.update(name, |versions| {
+            for (v, y) in versions.iter_mut() {
+                if v == version {
+                    *y = yanked;
+                }
+            }
+        })
+    }
+
+    pub(crate) fn delete_version(&self, name: &str, version: &str) -> Result<()> {
+        self.update(name, |versions| versions.retain(|(v, _)| v != version))
+    }
+
+    pub(crate) fn delete_crate(&self, name: &str) -> Result<()> {
+        self.crates.lock().unwrap().remove(name);
+        fs::remove_file(self.root.path().join(crate_path(name)))?;
+        self.write_crate_names()
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut Vec<(String, bool)>)) -> Result<()> {
+        let content = {
+            let mut crates = self.crates.lock().unwrap();
+            let versions = crates.entry(name.into()).or_default();
+            f(versions);
+
+            versions
+                .iter()
+                .map(|(version, yanked)| {
+                    serde_json::json!({
+                        "name": name,
+                        "vers": version,
+                        "deps": [],
+                        "cksum": "0".repeat(64),
+                        "features": {},
+                        "yanked": yanked,
+                    })
+                    .to_string()
+                        + "\n"
+                })
+                .collect::<String>()
+        };
+
+        let path = self.root.path().join(crate_path(name));
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)?;
+        self.write_crate_names()
+    }
+
+    fn write_crate_names(&self) -> Result<()> {
+        let names: String = self
+            .crates
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|name| format!("{name}\n"))
+            .collect();
+        fs::write(self.root.path().join(CRATE_NAMES_PATH), names)?;
+        Ok(())
+    }
+}
+
+impl Drop for TestSparseIndex {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// The path of a crate in a registry index, see
+/// <https://doc.rust-lang.org/cargo/reference/registry-index.html#index-files>
+fn crate_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    }
+}
+
+async fn serve_file(State(root): State<Arc<PathBuf>>, uri: Uri, headers: HeaderMap) -> Response {
+    let relative = Path::new(uri.path().trim_start_matches('/'));
+    let Ok(content) = fs::read(root.join(relative)) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let etag = format!("\"{}\"", hex::encode(Sha256::digest(&content)));
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes())
+    {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    ([(header::ETAG, etag)], Body::from(content)).into_response()
+}
@@ -0,0 +1,90 @@
+//! A local HTTP server receiving our webhooks.
+
+use crate::error::Result;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ReceivedWebhook {
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Bytes,
+}
+
+#[derive(Default)]
+struct Receiver {
+    requests: Mutex<Vec<ReceivedWebhook>>,
+    /// the statuses we respond with, in order. When they are used up we respond with `200`.
+    statuses: Mutex<VecDeque<StatusCode>>,
+}
+
+/// Records every request it gets.
+pub(crate) struct TestWebhookReceiver {
+    receiver: Arc<Receiver>,
+    addr: SocketAddr,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl TestWebhookReceiver {
+    /// A receiver first responding with the given statuses, then with `200`.
+    pub(crate) async fn new(statuses: &[StatusCode]) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let receiver = Arc::new(Receiver {
+            statuses: Mutex::new(statuses.iter().copied().collect()),
+            ..Default::default()
+        });
+        let app = Router::new().fallback(receive).with_state(receiver.clone());
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Ok(Self {
+            receiver,
+            addr,
+            server,
+        })
+    }
+
+    pub(crate) fn url(&self) -> Url {
+        format!("http://{}/hook", self.addr).parse().unwrap()
+    }
+
+    pub(crate) fn requests(&self) -> Vec<ReceivedWebhook> {
+        self.receiver.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for TestWebhookReceiver {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn receive(
+    State(receiver): State<Arc<Receiver>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    receiver
+        .requests
+        .lock()
+        .unwrap()
+        .push(ReceivedWebhook { headers, body });
+    receiver
+        .statuses
+        .lock()
+        .unwrap()
+        .pop_front()
+        .unwrap_or(StatusCode::OK)
+}
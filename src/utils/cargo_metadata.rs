@@ -7,6 +7,8 @@ use std::path::Path;
 
 pub(crate) struct CargoMetadata {
     root: Package,
+    /// The `cargo metadata` output this was loaded from.
+    raw: String,
 }
 
 impl CargoMetadata {
@@ -40,8 +42,8 @@ impl CargoMetadata {
         Self::load_from_metadata(std::str::from_utf8(&res.stdout)?)
     }
 
-    pub(crate) fn load_from_metadata(metadata: &str) -> Result<Self> {
-        let metadata = serde_json::from_str::<DeserializedMetadata>(metadata)?;
+    pub(crate) fn load_from_metadata(metadata_json: &str) -> Result<Self> {
+        let metadata = serde_json::from_str::<DeserializedMetadata>(metadata_json)?;
         let root = metadata.resolve.root;
         Ok(CargoMetadata {
             root: metadata
@@ -49,12 +51,17 @@ impl CargoMetadata {
                 .into_iter()
                 .find(|pkg| pkg.id == root)
                 .context("metadata.packages missing root package")?,
+            raw: metadata_json.to_owned(),
         })
     }
 
     pub(crate) fn root(&self) -> &Package {
         &self.root
     }
+
+    pub(crate) fn raw(&self) -> &str {
+        &self.raw
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Default)]
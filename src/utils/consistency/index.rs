@@ -1,33 +1,96 @@
 use super::data::{Crate, Crates, Release, Releases};
-use crate::Index;
+use crate::{Index, index::SPARSE_INDEX_CONCURRENCY};
 use anyhow::Result;
+use futures_util::{StreamExt as _, TryStreamExt as _};
 use rayon::iter::ParallelIterator;
 
 pub(super) fn load(index: &Index) -> Result<Crates> {
     let mut result: Crates = index
         .crates()?
         .crates_parallel()
-        .map(|krate| {
-            krate.map(|krate| {
-                let mut releases: Releases = krate
-                    .versions()
-                    .iter()
-                    .map(|version| Release {
-                        version: version.version().into(),
-                        yanked: Some(version.is_yanked()),
-                    })
-                    .collect();
-                releases.sort_by(|lhs, rhs| lhs.version.cmp(&rhs.version));
-
-                Crate {
-                    name: krate.name().into(),
-                    releases,
-                }
-            })
-        })
+        .map(|krate| krate.map(crate_from_index))
         .collect::<Result<_, _>>()?;
 
     result.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
 
     Ok(result)
 }
+
+/// Loads the given crates from a sparse registry index.
+///
+/// Sparse indexes can't list their crates, so the caller has to pass all
+/// crate names it is interested in.
+pub(super) async fn load_sparse(
+    index: &Index,
+    names: impl IntoIterator<Item = String>,
+) -> Result<Crates> {
+    let mut result: Crates = futures_util::stream::iter(names)
+        .map(|name| async move { index.load_sparse_crate(&name).await })
+        .buffer_unordered(SPARSE_INDEX_CONCURRENCY)
+        .try_filter_map(|krate| async move { Ok(krate.map(crate_from_index)) })
+        .try_collect()
+        .await?;
+
+    result.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+    Ok(result)
+}
+
+fn crate_from_index(krate: crates_index::Crate) -> Crate {
+    let mut releases: Releases = krate
+        .versions()
+        .iter()
+        .map(|version| Release {
+            version: version.version().into(),
+            yanked: Some(version.is_yanked()),
+        })
+        .collect();
+    releases.sort_by(|lhs, rhs| lhs.version.cmp(&rhs.version));
+
+    Crate {
+        name: krate.name().into(),
+        releases,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{TestSparseIndex, async_wrapper};
+
+    #[test]
+    fn test_load_sparse() {
+        async_wrapper(|_env| async move {
+            let registry = TestSparseIndex::new().await?;
+            registry.publish("krate", "0.2.0")?;
+            registry.publish("krate", "0.1.0")?;
+            registry.set_yanked("krate", "0.1.0", true)?;
+            registry.publish("other", "1.0.0")?;
+
+            let index = registry.index()?;
+            let crates = load_sparse(&index, ["missing".into(), "krate".into()]).await?;
+
+            assert_eq!(
+                crates,
+                vec![Crate {
+                    name: "krate".into(),
+                    releases: vec![
+                        Release {
+                            version: "0.1.0".into(),
+                            yanked: Some(true),
+                        },
+                        Release {
+                            version: "0.2.0".into(),
+                            yanked: Some(false),
+                        },
+                    ],
+                }]
+            );
+
+            // loading again uses the cached entry.
+            assert_eq!(load_sparse(&index, ["krate".into()]).await?, crates);
+
+            Ok(())
+        })
+    }
+}
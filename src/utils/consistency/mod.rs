@@ -1,6 +1,7 @@
 use crate::{Context, db::delete, utils::spawn_blocking};
 use anyhow::{Context as _, Result};
 use itertools::Itertools;
+use std::collections::BTreeSet;
 use tracing::{info, warn};
 
 mod data;
@@ -34,11 +35,21 @@ pub async fn run_check<C: Context>(ctx: &C, dry_run: bool) -> Result<()> {
         .context("Loading crate data from database for consistency check")?;
 
     tracing::info!("Loading data from index...");
-    let index_data = spawn_blocking({
-        let index = index.clone();
-        move || index::load(&index)
-    })
-    .await
+    let index_data = if index.is_sparse() {
+        // we can't list the crates in a sparse index, so we look at the crates we know,
+        // and the ones the registry publishes a list of.
+        let mut names: BTreeSet<String> = db_data.iter().map(|krate| krate.name.clone()).collect();
+        if let Some(url) = &ctx.config()?.registry_crate_names_url {
+            names.extend(index.fetch_sparse_crate_names(url).await?);
+        }
+        index::load_sparse(&index, names).await
+    } else {
+        spawn_blocking({
+            let index = index.clone();
+            move || index::load(&index)
+        })
+        .await
+    }
     .context("Loading crate data from index for consistency check")?;
 
     let diff = diff::calculate_diff(db_data.iter(), index_data.iter());
@@ -3,11 +3,20 @@
 //! This daemon will start web server, track new packages and build them
 
 use crate::{
-    AsyncBuildQueue, Config, Context, Index, RustwideBuilder, cdn, queue_rebuilds,
-    utils::{queue_builder, report_error},
+    AsyncBuildQueue, Config, Context, Index, RustwideBuilder,
+    build_queue::INDEX_SYNC_CHANNEL,
+    cdn,
+    db::{
+        builders,
+        registries::{self, Registry},
+    },
+    queue_rebuilds,
+    utils::{ConfigName, get_config, queue_builder, report_error},
     web::start_web_server,
+    webhooks,
 };
 use anyhow::{Context as _, Error, anyhow};
+use sqlx::postgres::PgListener;
 use std::future::Future;
 use std::sync::Arc;
 use std::thread;
@@ -18,23 +27,41 @@ use tracing::{debug, info};
 /// Run the registry watcher
 /// NOTE: this should only be run once, otherwise crates would be added
 /// to the queue multiple times.
+///
+/// We check the index when the index webhook asks us to, and regularly as a fallback
+/// in case we missed a webhook.
 pub async fn watch_registry(
     build_queue: Arc<AsyncBuildQueue>,
     config: Arc<Config>,
     index: Arc<Index>,
+) -> Result<(), Error> {
+    watch_registry_index(build_queue, config, index, None).await
+}
+
+/// Like [`watch_registry`], for the index of the given registry.
+async fn watch_registry_index(
+    build_queue: Arc<AsyncBuildQueue>,
+    config: Arc<Config>,
+    index: Arc<Index>,
+    registry: Option<Registry>,
 ) -> Result<(), Error> {
     let mut last_gc = Instant::now();
 
+    let mut listener = PgListener::connect(&config.database_url).await?;
+    listener.listen(INDEX_SYNC_CHANNEL).await?;
+
     loop {
         if build_queue.is_locked().await? {
             debug!("Queue is locked, skipping checking new crates");
         } else {
             debug!("Checking new crates");
             match build_queue
-                .get_new_crates(&index)
+                .sync_index(&index, registry.as_ref())
                 .await
-                .context("Failed to get new crates")
-            {
+                .with_context(|| match &registry {
+                    Some(registry) => format!("Failed to get new crates from {}", registry.name),
+                    None => "Failed to get new crates".into(),
+                }) {
                 Ok(n) => debug!("{} crates added to queue", n),
                 Err(e) => report_error(&e),
             }
@@ -48,7 +75,21 @@ pub async fn watch_registry(
             .await?;
             last_gc = Instant::now();
         }
-        tokio::time::sleep(config.delay_between_registry_fetches).await;
+
+        tokio::select! {
+            _ = tokio::time::sleep(config.delay_between_registry_fetches) => {}
+            notification = listener.recv() => match notification {
+                Ok(_) => {
+                    debug!("index sync requested");
+                    // a burst of webhooks only needs one check of the index.
+                    while listener.next_buffered().is_some() {}
+                }
+                Err(err) => {
+                    report_error(&anyhow!(err).context("failed to receive index sync requests"));
+                    tokio::time::sleep(config.delay_between_registry_fetches).await;
+                }
+            },
+        }
     }
 }
 
@@ -65,6 +106,48 @@ fn start_registry_watcher<C: Context>(context: &C) -> Result<(), Error> {
         watch_registry(build_queue, config, index).await
     });
 
+    start_hosted_registry_watchers(context)
+}
+
+/// Starts a registry watcher for each of the alternative registries we host.
+///
+/// Registries added later are picked up after a restart.
+pub fn start_hosted_registry_watchers<C: Context>(context: &C) -> Result<(), Error> {
+    let runtime = context.runtime()?;
+    let build_queue = runtime.block_on(context.async_build_queue())?;
+    let config = context.config()?;
+
+    let hosted_registries = runtime.block_on(async {
+        let mut conn = context.pool()?.get_async().await?;
+        registries::list_registries(&mut conn).await
+    })?;
+
+    for registry in hosted_registries {
+        info!("starting registry watcher for {}", registry.name);
+        let build_queue = build_queue.clone();
+        let config = config.clone();
+
+        runtime.spawn(async move {
+            let index = spawn_blocking({
+                let path = config.prefix.join("registries").join(&registry.name);
+                let url = registry.index_url.clone();
+                move || Index::from_url(path, url)
+            })
+            .await?
+            .with_context(|| format!("failed to open the index of registry {}", registry.name));
+
+            match index {
+                Ok(index) => {
+                    watch_registry_index(build_queue, config, Arc::new(index), Some(registry)).await
+                }
+                Err(err) => {
+                    report_error(&err);
+                    Err(err)
+                }
+            }
+        });
+    }
+
     Ok(())
 }
 
@@ -178,6 +261,76 @@ pub fn start_background_cdn_invalidator<C: Context>(context: &C) -> Result<(), E
     Ok(())
 }
 
+/// Sends the queued webhooks of finished builds.
+pub fn start_background_webhook_delivery<C: Context>(context: &C) -> Result<(), Error> {
+    let runtime = context.runtime()?;
+    let pool = context.pool()?;
+    let config = context.config()?;
+    let client = webhooks::webhook_client(&config)?;
+
+    async_cron(
+        &runtime,
+        "webhook delivery",
+        Duration::from_secs(10),
+        move || {
+            let pool = pool.clone();
+            let config = config.clone();
+            let client = client.clone();
+            async move {
+                let mut conn = pool.get_async().await?;
+                webhooks::deliver_queued_webhooks(&mut conn, &config, &client)
+                    .await
+                    .context("error delivering webhooks")?;
+                Ok(())
+            }
+        },
+    );
+    Ok(())
+}
+
+/// Registers this build server and regularly sends heartbeats.
+///
+/// The heartbeats are also used to recover builds of build servers that stopped responding.
+pub fn start_background_builder_heartbeat<C: Context>(context: &C) -> Result<(), Error> {
+    let runtime = context.runtime()?;
+    let pool = context.pool()?;
+    let config = context.config()?;
+    let metrics = context.instance_metrics()?;
+    let name = builders::builder_name()?;
+
+    runtime.block_on(async {
+        let mut conn = pool.get_async().await?;
+        let toolchain: Option<String> = get_config(&mut conn, ConfigName::Toolchain).await?;
+        builders::register_builder(&mut conn, &name, toolchain.as_deref()).await
+    })?;
+
+    async_cron(
+        &runtime,
+        "builder heartbeat",
+        config.builder_heartbeat_interval,
+        move || {
+            let pool = pool.clone();
+            let config = config.clone();
+            let metrics = metrics.clone();
+            let name = name.clone();
+            async move {
+                let mut conn = pool.get_async().await?;
+                let toolchain: Option<String> =
+                    get_config(&mut conn, ConfigName::Toolchain).await?;
+                builders::send_heartbeat(&mut conn, &name, toolchain.as_deref()).await?;
+
+                let recovered =
+                    builders::recover_stale_builds(&mut conn, config.stale_build_timeout).await?;
+                metrics
+                    .recovered_stale_builds
+                    .inc_by(recovered.len() as u64);
+                Ok(())
+            }
+        },
+    );
+    Ok(())
+}
+
 pub fn start_daemon<C: Context + Send + Sync + 'static>(
     context: C,
     enable_registry_watcher: bool,
@@ -200,7 +353,8 @@ pub fn start_daemon<C: Context + Send + Sync + 'static>(
     // build new crates every minute
     let build_queue = context.build_queue()?;
     let config = context.config()?;
-    let rustwide_builder = RustwideBuilder::init(&*context)?;
+    let mut rustwide_builder = RustwideBuilder::init(&*context)?;
+    rustwide_builder.track_current_build(builders::builder_name()?);
     thread::Builder::new()
         .name("build queue reader".to_string())
         .spawn({
@@ -208,10 +362,12 @@ pub fn start_daemon<C: Context + Send + Sync + 'static>(
             move || queue_builder(&*context, rustwide_builder, build_queue, config).unwrap()
         })
         .unwrap();
+    start_background_builder_heartbeat(&*context)?;
 
     start_background_repository_stats_updater(&*context)?;
     start_background_cdn_invalidator(&*context)?;
     start_background_queue_rebuild(&*context)?;
+    start_background_webhook_delivery(&*context)?;
 
     // NOTE: if a error occurred earlier in `start_daemon`, the server will _not_ be joined -
     // instead it will get killed when the process exits.
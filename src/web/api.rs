@@ -0,0 +1,1708 @@
+//! The versioned JSON API, documented at `/about/api`.
+//!
+//! The response types here are the stable schema of the API, so they are separate from the
+//! structs we use for the HTML pages. Fields can be added within a version, but never
+//! removed or changed.
+
+use crate::{
+    AsyncStorage, Config,
+    db::{
+        BuildId, ReleaseId, access_rules,
+        types::{BuildStatus, Feature as DbFeature},
+    },
+    ices,
+    registry_api::OwnerKind,
+    utils::report_error,
+    web::{
+        MatchedRelease, ReqVersion,
+        auth::{Principal, retain_visible},
+        builds::get_builds,
+        cache::CachePolicy,
+        crate_details::{CrateDetails, Release},
+        error::{AxumNope, AxumResult, EscapedURI, JsonAxumNope, JsonAxumResult, JsonError},
+        extractors::{DbConnection, Path},
+        file::File,
+        ices::{MAX_ICES, issue_url},
+        match_version,
+        releases::{self, Order},
+        select_release,
+    },
+};
+use anyhow::{Context as _, anyhow};
+use axum::{
+    Json,
+    extract::{Extension, Query, Request, rejection::JsonRejection},
+    http::{
+        HeaderValue, Method, StatusCode,
+        header::{
+            ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+            ACCESS_CONTROL_ALLOW_ORIGIN, CONTENT_TYPE,
+        },
+    },
+    middleware::Next,
+    response::{IntoResponse, Response as AxumResponse},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::TryStreamExt as _;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+use utoipa::{IntoParams, ToSchema};
+
+/// The prefix of all routes of this API version.
+pub(super) const PREFIX: &str = "/api/v1";
+
+/// How many releases we return per page of the release lists.
+const RELEASES_PER_PAGE: i64 = 30;
+
+/// How many crates can be requested at once from the bulk status endpoint.
+const MAX_BULK_STATUS_CRATES: usize = 1000;
+
+/// Allows browsers to use the API from other sites, for all responses including errors.
+///
+/// Also answers the preflight requests browsers send before `POST`ing JSON.
+pub(super) async fn cors_middleware(request: Request, next: Next) -> AxumResponse {
+    let mut response = if request.method() == Method::OPTIONS {
+        (
+            StatusCode::NO_CONTENT,
+            [
+                (ACCESS_CONTROL_ALLOW_METHODS, "GET, POST"),
+                (ACCESS_CONTROL_ALLOW_HEADERS, "authorization, content-type"),
+            ],
+        )
+            .into_response()
+    } else {
+        next.run(request).await
+    };
+    response
+        .headers_mut()
+        .insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+    response
+}
+
+/// The same caching as the HTML pages of a release.
+fn release_cache_policy(req_version: &ReqVersion) -> CachePolicy {
+    if req_version.is_latest() {
+        CachePolicy::ForeverInCdn
+    } else {
+        CachePolicy::ForeverInCdnAndStaleInBrowser
+    }
+}
+
+fn json_response(cache_policy: CachePolicy, value: impl Serialize) -> AxumResponse {
+    (Extension(cache_policy), Json(value)).into_response()
+}
+
+/// Finds the release, and redirects semver requirements to the exact version like the HTML pages.
+async fn match_release(
+    conn: &mut sqlx::PgConnection,
+    name: &str,
+    req_version: &ReqVersion,
+    subpath: &str,
+) -> AxumResult<MatchedRelease> {
+    match_version(conn, name, req_version)
+        .await?
+        .assume_exact_name()?
+        .into_canonical_req_version_or_else(|version| {
+            AxumNope::Redirect(
+                EscapedURI::new(&format!("{PREFIX}/crates/{name}/{version}{subpath}"), None),
+                CachePolicy::ForeverInCdn,
+            )
+        })
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub(crate) struct ReleaseParams {
+    /// The name of the crate.
+    name: String,
+    /// An exact version, `latest`, or a semver requirement which redirects to the matching
+    /// exact version.
+    #[param(value_type = String)]
+    version: ReqVersion,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiOwner {
+    login: String,
+    avatar: String,
+    kind: OwnerKind,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiDependency {
+    name: String,
+    req: String,
+    /// `normal`, `dev` or `build`
+    kind: String,
+    optional: bool,
+}
+
+impl ApiDependency {
+    /// Parses the dependencies we store as `[name, req, kind, optional]` arrays.
+    fn parse_all(dependencies: Option<&Value>) -> Vec<Self> {
+        dependencies
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| {
+                Some(Self {
+                    name: dependency.get(0)?.as_str()?.into(),
+                    req: dependency.get(1)?.as_str()?.into(),
+                    kind: dependency
+                        .get(2)
+                        .and_then(Value::as_str)
+                        .unwrap_or("normal")
+                        .into(),
+                    optional: dependency.get(3).and_then(Value::as_bool).unwrap_or(false),
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiDocCoverage {
+    total_items: i32,
+    documented_items: i32,
+    total_items_needing_examples: Option<i32>,
+    items_with_examples: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiCrateDetails {
+    name: String,
+    version: String,
+    description: Option<String>,
+    license: Option<String>,
+    keywords: Vec<String>,
+    owners: Vec<ApiOwner>,
+    dependencies: Vec<ApiDependency>,
+    release_time: Option<DateTime<Utc>>,
+    yanked: bool,
+    build_status: BuildStatus,
+    rustdoc_status: bool,
+    is_library: bool,
+    target_name: Option<String>,
+    default_target: Option<String>,
+    doc_targets: Vec<String>,
+    /// the documentation on docs.rs, when the release has some
+    docs_url: Option<String>,
+    homepage_url: Option<String>,
+    repository_url: Option<String>,
+    documentation_url: Option<String>,
+    doc_coverage: Option<ApiDocCoverage>,
+}
+
+impl From<CrateDetails> for ApiCrateDetails {
+    fn from(details: CrateDetails) -> Self {
+        let rustdoc_status = details.rustdoc_status.unwrap_or(false);
+        let docs_url = match (&details.target_name, rustdoc_status) {
+            (Some(target_name), true) => Some(format!(
+                "/{}/{}/{}/",
+                details.name, details.metadata.req_version, target_name
+            )),
+            _ => None,
+        };
+        let doc_coverage = match (details.total_items, details.documented_items) {
+            (Some(total_items), Some(documented_items)) => Some(ApiDocCoverage {
+                total_items,
+                documented_items,
+                total_items_needing_examples: details.total_items_needing_examples,
+                items_with_examples: details.items_with_examples,
+            }),
+            _ => None,
+        };
+
+        Self {
+            version: details.version.to_string(),
+            description: details.description,
+            license: details.license,
+            keywords: details
+                .keywords
+                .as_ref()
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|keyword| keyword.as_str().map(Into::into))
+                .collect(),
+            owners: details
+                .owners
+                .into_iter()
+                .map(|(login, avatar, kind)| ApiOwner {
+                    login,
+                    avatar,
+                    kind,
+                })
+                .collect(),
+            dependencies: ApiDependency::parse_all(details.dependencies.as_ref()),
+            release_time: details.release_time,
+            yanked: details.metadata.yanked.unwrap_or(false),
+            build_status: details.build_status,
+            rustdoc_status,
+            is_library: details.is_library.unwrap_or(false),
+            target_name: details.target_name,
+            default_target: details.metadata.default_target,
+            doc_targets: details.metadata.doc_targets.unwrap_or_default(),
+            docs_url,
+            homepage_url: details.homepage_url,
+            repository_url: details.repository_url,
+            documentation_url: details.documentation_url,
+            doc_coverage,
+            name: details.name,
+        }
+    }
+}
+
+/// The details of a release.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{name}/{version}",
+    tag = "crates",
+    params(ReleaseParams),
+    responses(
+        (status = OK, body = ApiCrateDetails),
+        (status = FOUND, description = "redirect to the matching exact version"),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
+pub(crate) async fn crate_details_handler(
+    Path(params): Path<ReleaseParams>,
+    mut conn: DbConnection,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let release = match_release(&mut conn, &params.name, &params.version, "").await?;
+        let details = CrateDetails::from_matched_release(&mut conn, release).await?;
+
+        Ok(json_response(
+            release_cache_policy(&params.version),
+            ApiCrateDetails::from(details),
+        ))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiCrateRelease {
+    version: String,
+    build_status: BuildStatus,
+    yanked: bool,
+    rustdoc_status: bool,
+    is_library: bool,
+    release_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiCrateReleases {
+    /// newest first
+    releases: Vec<ApiCrateRelease>,
+}
+
+/// All releases of a crate.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{name}/releases",
+    tag = "crates",
+    params(("name" = String, Path, description = "The name of the crate.")),
+    responses(
+        (status = OK, body = ApiCrateReleases),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
+pub(crate) async fn crate_releases_handler(
+    Path(name): Path<String>,
+    mut conn: DbConnection,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let release = match_version(&mut conn, &name, &ReqVersion::Latest)
+            .await?
+            .assume_exact_name()?;
+
+        let releases = release
+            .all_releases
+            .into_iter()
+            .map(|release| ApiCrateRelease {
+                version: release.version.to_string(),
+                build_status: release.build_status,
+                yanked: release.yanked.unwrap_or(false),
+                rustdoc_status: release.rustdoc_status.unwrap_or(false),
+                is_library: release.is_library.unwrap_or(false),
+                release_time: release.release_time,
+            })
+            .collect();
+
+        Ok(json_response(
+            CachePolicy::ForeverInCdn,
+            ApiCrateReleases { releases },
+        ))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiBuildLog {
+    /// the file name, for most builds `{target}.txt`
+    filename: String,
+    url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiBuild {
+    id: BuildId,
+    rustc_version: Option<String>,
+    docsrs_version: Option<String>,
+    build_status: BuildStatus,
+    build_time: Option<DateTime<Utc>>,
+    errors: Option<String>,
+    details_url: String,
+    /// empty for builds from before we stored the logs for each target
+    logs: Vec<ApiBuildLog>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiBuilds {
+    /// newest first
+    builds: Vec<ApiBuild>,
+}
+
+/// The builds of a release, including builds in progress.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{name}/{version}/builds",
+    tag = "builds",
+    params(ReleaseParams),
+    responses(
+        (status = OK, body = ApiBuilds),
+        (status = FOUND, description = "redirect to the matching exact version"),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
+pub(crate) async fn builds_handler(
+    Path(params): Path<ReleaseParams>,
+    mut conn: DbConnection,
+    Extension(storage): Extension<Arc<AsyncStorage>>,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let name = &params.name;
+        let version = match_release(&mut conn, name, &params.version, "/builds")
+            .await?
+            .into_version();
+
+        let mut builds = Vec::new();
+        for build in get_builds(&mut conn, name, &version).await? {
+            let prefix = format!("build-logs/{}/", build.id);
+            let logs = storage
+                .list_prefix(&prefix)
+                .await
+                .map_ok(|path| {
+                    let filename = path
+                        .strip_prefix(&prefix)
+                        .expect("since we query for the prefix, it has to be always there")
+                        .to_owned();
+                    ApiBuildLog {
+                        url: format!(
+                            "{PREFIX}/crates/{name}/{version}/builds/{}/logs/{filename}",
+                            build.id
+                        ),
+                        filename,
+                    }
+                })
+                .try_collect()
+                .await?;
+
+            builds.push(ApiBuild {
+                details_url: format!("/crate/{name}/{version}/builds/{}", build.id),
+                id: build.id,
+                rustc_version: build.rustc_version,
+                docsrs_version: build.docsrs_version,
+                build_status: build.build_status,
+                build_time: build.build_time,
+                errors: build.errors,
+                logs,
+            });
+        }
+
+        // like the HTML build list, which also shows builds in progress.
+        Ok(json_response(CachePolicy::NoCaching, ApiBuilds { builds }))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub(crate) struct BuildLogParams {
+    /// The name of the crate.
+    name: String,
+    /// The exact version of the release.
+    #[param(value_type = String)]
+    version: semver::Version,
+    /// The id of the build.
+    id: i32,
+    /// The file name of the log, from the build list.
+    filename: String,
+}
+
+/// A build log, as plain text.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{name}/{version}/builds/{id}/logs/{filename}",
+    tag = "builds",
+    params(BuildLogParams),
+    responses(
+        (status = OK, body = String, content_type = "text/plain"),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
+pub(crate) async fn build_log_handler(
+    Path(params): Path<BuildLogParams>,
+    mut conn: DbConnection,
+    Extension(config): Extension<Arc<Config>>,
+    Extension(storage): Extension<Arc<AsyncStorage>>,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        if params.filename.contains('/') {
+            return Err(AxumNope::ResourceNotFound);
+        }
+
+        sqlx::query_scalar!(
+            "SELECT builds.id
+             FROM builds
+             INNER JOIN releases ON releases.id = builds.rid
+             INNER JOIN crates ON releases.crate_id = crates.id
+             WHERE builds.id = $1 AND crates.name = $2 AND releases.version = $3",
+            params.id,
+            params.name,
+            params.version.to_string(),
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(AxumNope::BuildNotFound)?;
+
+        let file = File::from_path(
+            &storage,
+            &format!("build-logs/{}/{}", params.id, params.filename),
+            &config,
+        )
+        .await?;
+
+        Ok((
+            Extension(CachePolicy::NoCaching),
+            [(CONTENT_TYPE, "text/plain; charset=utf-8")],
+            file.0.content,
+        )
+            .into_response())
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiFeatures {
+    /// feature name => the features and dependencies it enables, `null` for old releases
+    /// where we don't know the features.
+    features: Option<BTreeMap<String, Vec<String>>>,
+}
+
+/// The public features of a release.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{name}/{version}/features",
+    tag = "crates",
+    params(ReleaseParams),
+    responses(
+        (status = OK, body = ApiFeatures),
+        (status = FOUND, description = "redirect to the matching exact version"),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
+pub(crate) async fn features_handler(
+    Path(params): Path<ReleaseParams>,
+    mut conn: DbConnection,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let version = match_release(&mut conn, &params.name, &params.version, "/features")
+            .await?
+            .into_version();
+
+        let features = sqlx::query_scalar!(
+            r#"
+            SELECT releases.features as "features?: Vec<DbFeature>"
+            FROM releases
+            INNER JOIN crates ON crates.id = releases.crate_id
+            WHERE crates.name = $1 AND releases.version = $2"#,
+            params.name,
+            version.to_string(),
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow!("missing release"))?;
+
+        let features = features.map(|features| {
+            features
+                .into_iter()
+                .filter(|feature| !feature.is_private())
+                .map(|feature| (feature.name, feature.subfeatures))
+                .collect()
+        });
+
+        Ok(json_response(
+            release_cache_policy(&params.version),
+            ApiFeatures { features },
+        ))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiPlatforms {
+    default_target: Option<String>,
+    doc_targets: Vec<String>,
+}
+
+/// The targets we built documentation for.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{name}/{version}/platforms",
+    tag = "crates",
+    params(ReleaseParams),
+    responses(
+        (status = OK, body = ApiPlatforms),
+        (status = FOUND, description = "redirect to the matching exact version"),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
+pub(crate) async fn platforms_handler(
+    Path(params): Path<ReleaseParams>,
+    mut conn: DbConnection,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let release = match_release(&mut conn, &params.name, &params.version, "/platforms").await?;
+        let details = CrateDetails::from_matched_release(&mut conn, release).await?;
+
+        Ok(json_response(
+            release_cache_policy(&params.version),
+            ApiPlatforms {
+                default_target: details.metadata.default_target,
+                doc_targets: details.metadata.doc_targets.unwrap_or_default(),
+            },
+        ))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiReleaseListEntry {
+    name: String,
+    version: String,
+    description: Option<String>,
+    target_name: Option<String>,
+    rustdoc_status: bool,
+    build_time: Option<DateTime<Utc>>,
+    stars: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiReleaseList {
+    releases: Vec<ApiReleaseListEntry>,
+    /// the URL of the next page, if there is one
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct ReleaseListParams {
+    /// The page, starting at 1.
+    page: Option<i64>,
+}
+
+/// The release lists from the start page.
+#[utoipa::path(
+    get,
+    path = "/api/v1/releases/{kind}",
+    tag = "releases",
+    params(
+        (
+            "kind" = String,
+            Path,
+            description = "`recent`, `stars`, `recent-failures` or `failures`",
+        ),
+        ReleaseListParams,
+    ),
+    responses(
+        (status = OK, body = ApiReleaseList),
+        (status = BAD_REQUEST, body = JsonError),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
+pub(crate) async fn release_list_handler(
+    Path(kind): Path<String>,
+    Query(params): Query<ReleaseListParams>,
+    principal: Option<Extension<Principal>>,
+    mut conn: DbConnection,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let (order, latest_only) = match kind.as_str() {
+            "recent" => (Order::ReleaseTime, false),
+            "stars" => (Order::GithubStars, true),
+            "recent-failures" => (Order::RecentFailures, false),
+            "failures" => (Order::FailuresByGithubStars, true),
+            _ => return Err(AxumNope::ResourceNotFound),
+        };
+        let page = params.page.unwrap_or(1);
+        if page < 1 {
+            return Err(AxumNope::BadRequest(anyhow!("pages start at 1")));
+        }
+
+        let mut releases =
+            releases::get_releases(&mut conn, page, RELEASES_PER_PAGE, order, latest_only).await?;
+        let next_page = (releases.len() == RELEASES_PER_PAGE as usize)
+            .then(|| format!("{PREFIX}/releases/{kind}?page={}", page + 1));
+
+        let private = retain_visible(&mut conn, principal.as_deref(), &mut releases, |release| {
+            &release.name
+        })
+        .await?;
+        let releases = releases
+            .into_iter()
+            .map(|release| ApiReleaseListEntry {
+                name: release.name,
+                version: release.version,
+                description: release.description,
+                target_name: release.target_name,
+                rustdoc_status: release.rustdoc_status,
+                build_time: release.build_time,
+                stars: release.stars,
+            })
+            .collect();
+
+        // like the HTML release lists
+        Ok(json_response(
+            if private {
+                CachePolicy::NoStoreMustRevalidate
+            } else {
+                CachePolicy::NoCaching
+            },
+            ApiReleaseList {
+                releases,
+                next_page,
+            },
+        ))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiIceCrate {
+    name: String,
+    version: String,
+    target: String,
+    rustc_version: String,
+    /// the date of the nightly toolchain, if it was one
+    toolchain_date: Option<NaiveDate>,
+    /// the build log with the internal compiler error
+    build_url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiIce {
+    /// what we group occurrences by, stable across crates and toolchains
+    signature: String,
+    message: String,
+    location: Option<String>,
+    query_stack: Vec<String>,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    first_toolchain: Option<NaiveDate>,
+    last_toolchain: Option<NaiveDate>,
+    crate_count: i64,
+    /// a prefilled rust-lang/rust issue
+    issue_url: String,
+    /// the crates we saw it with last
+    crates: Vec<ApiIceCrate>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiIceList {
+    ices: Vec<ApiIce>,
+}
+
+/// The internal compiler errors we found in builds, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/ices",
+    tag = "builds",
+    responses(
+        (status = OK, body = ApiIceList),
+    )
+)]
+pub(crate) async fn ices_handler(mut conn: DbConnection) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let ices = ices::list_ices(&mut conn, MAX_ICES)
+            .await?
+            .into_iter()
+            .map(|ice| ApiIce {
+                issue_url: issue_url(&ice),
+                crates: ice
+                    .crates
+                    .into_iter()
+                    .map(|krate| ApiIceCrate {
+                        build_url: format!("https://docs.rs{}", krate.build_details_path()),
+                        name: krate.name,
+                        version: krate.version,
+                        target: krate.target,
+                        rustc_version: krate.rustc_version,
+                        toolchain_date: krate.toolchain_date,
+                    })
+                    .collect(),
+                signature: ice.signature,
+                message: ice.message,
+                location: ice.location,
+                query_stack: ice.query_stack,
+                first_seen: ice.first_seen,
+                last_seen: ice.last_seen,
+                first_toolchain: ice.first_toolchain,
+                last_toolchain: ice.last_toolchain,
+                crate_count: ice.crate_count,
+            })
+            .collect();
+
+        // like the HTML page
+        Ok(json_response(CachePolicy::NoCaching, ApiIceList { ices }))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct BulkStatusRequest {
+    crates: Vec<BulkStatusRequestCrate>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct BulkStatusRequestCrate {
+    name: String,
+    /// An exact version, `latest`, or a semver requirement. Defaults to `latest`.
+    #[serde(default)]
+    #[schema(value_type = String)]
+    version: ReqVersion,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiCrateStatus {
+    /// the name as requested
+    name: String,
+    /// the version as requested
+    req_version: String,
+    /// the exact version the request matched
+    version: Option<String>,
+    /// whether the release has documentation
+    doc_status: bool,
+    default_target: Option<String>,
+    /// the status of the newest build of the release
+    latest_build_status: Option<BuildStatus>,
+    /// why we couldn't match a release, like for crates or versions that don't exist
+    error: Option<JsonError>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiBulkStatus {
+    /// in the order of the request
+    crates: Vec<ApiCrateStatus>,
+}
+
+/// Loads all releases of the crates, keyed by the requested names.
+///
+/// Like in `match_version`, the names match crates with `-` and `_` swapped. The releases are
+/// sorted newest version first.
+async fn releases_for_crates(
+    conn: &mut sqlx::PgConnection,
+    names: &[&str],
+) -> anyhow::Result<HashMap<String, (String, Vec<Release>)>> {
+    let mut crates: HashMap<String, (String, Vec<Release>)> = HashMap::new();
+
+    let mut rows = sqlx::query!(
+        r#"SELECT
+             requested.name as "requested_name!",
+             crates.name,
+             releases.id as "id: ReleaseId",
+             releases.version,
+             release_build_status.build_status as "build_status!: BuildStatus",
+             releases.yanked,
+             releases.is_library,
+             releases.rustdoc_status,
+             releases.release_time,
+             releases.target_name
+         FROM UNNEST($1::TEXT[]) AS requested(name)
+         INNER JOIN crates ON normalize_crate_name(crates.name) = normalize_crate_name(requested.name)
+         INNER JOIN releases ON releases.crate_id = crates.id
+         INNER JOIN release_build_status ON releases.id = release_build_status.rid"#,
+        names as _,
+    )
+    .fetch(&mut *conn);
+
+    while let Some(row) = rows.try_next().await? {
+        let version = match semver::Version::parse(&row.version).with_context(|| {
+            format!(
+                "invalid semver in database for crate {}: {}",
+                row.name, row.version
+            )
+        }) {
+            Ok(version) => version,
+            Err(err) => {
+                report_error(&err);
+                continue;
+            }
+        };
+
+        crates
+            .entry(row.requested_name)
+            .or_insert_with(|| (row.name, Vec::new()))
+            .1
+            .push(Release {
+                id: row.id,
+                version,
+                build_status: row.build_status,
+                yanked: row.yanked,
+                is_library: row.is_library,
+                rustdoc_status: row.rustdoc_status,
+                target_name: row.target_name,
+                release_time: row.release_time,
+            });
+    }
+
+    for (_, releases) in crates.values_mut() {
+        releases.sort_by(|a, b| b.version.cmp(&a.version));
+    }
+
+    Ok(crates)
+}
+
+/// The status of many releases at once, for tools that would otherwise request `status.json`
+/// for each of them.
+#[utoipa::path(
+    post,
+    path = "/api/v1/crates/status",
+    tag = "crates",
+    request_body = BulkStatusRequest,
+    responses(
+        (status = OK, body = ApiBulkStatus),
+        (status = BAD_REQUEST, body = JsonError),
+    )
+)]
+pub(crate) async fn bulk_status_handler(
+    mut conn: DbConnection,
+    principal: Option<Extension<Principal>>,
+    request: Result<Json<BulkStatusRequest>, JsonRejection>,
+) -> JsonAxumResult<AxumResponse> {
+    async move {
+        let Json(request) = request.map_err(|err| AxumNope::BadRequest(err.into()))?;
+        if request.crates.len() > MAX_BULK_STATUS_CRATES {
+            return Err(AxumNope::BadRequest(anyhow!(
+                "at most {MAX_BULK_STATUS_CRATES} crates can be requested at once"
+            )));
+        }
+
+        let names: Vec<_> = request
+            .crates
+            .iter()
+            .map(|krate| krate.name.as_str())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let crates = releases_for_crates(&mut conn, &names).await?;
+
+        let crate_names: Vec<_> = crates.values().map(|(name, _)| name.as_str()).collect();
+        let allowed_principals =
+            access_rules::allowed_principals_for_crates(&mut conn, &crate_names).await?;
+        let can_see = |name: &str| {
+            allowed_principals.get(name).is_none_or(|allowed| {
+                principal
+                    .as_ref()
+                    .is_some_and(|Extension(principal)| principal.is_allowed(allowed))
+            })
+        };
+
+        let matched: Vec<_> = request
+            .crates
+            .iter()
+            .map(|krate| match crates.get(&krate.name) {
+                // like the access checks on the other endpoints, we don't tell if private
+                // crates exist.
+                Some((name, releases)) if !releases.is_empty() && can_see(name) => {
+                    select_release(releases, &krate.version)
+                }
+                _ => Err(AxumNope::CrateNotFound),
+            })
+            .collect();
+
+        let release_ids: Vec<_> = matched
+            .iter()
+            .filter_map(|release| release.as_ref().ok())
+            .map(|release| release.id.0)
+            .collect();
+        let details: HashMap<_, _> = sqlx::query!(
+            r#"SELECT
+                 releases.id as "id: ReleaseId",
+                 releases.default_target,
+                 latest_build.build_status as "latest_build_status?: BuildStatus"
+             FROM releases
+             LEFT JOIN LATERAL (
+                 SELECT builds.build_status
+                 FROM builds
+                 WHERE builds.rid = releases.id
+                 ORDER BY builds.id DESC
+                 LIMIT 1
+             ) AS latest_build ON TRUE
+             WHERE releases.id = ANY($1)"#,
+            &release_ids,
+        )
+        .fetch(&mut *conn)
+        .map_ok(|row| (row.id, (row.default_target, row.latest_build_status)))
+        .try_collect()
+        .await?;
+
+        let statuses = request
+            .crates
+            .into_iter()
+            .zip(matched)
+            .map(|(krate, release)| {
+                let mut status = ApiCrateStatus {
+                    name: krate.name,
+                    req_version: krate.version.to_string(),
+                    version: None,
+                    doc_status: false,
+                    default_target: None,
+                    latest_build_status: None,
+                    error: None,
+                };
+                match release {
+                    Ok(release) => {
+                        status.version = Some(release.version.to_string());
+                        status.doc_status = release.rustdoc_status.unwrap_or(false);
+                        if let Some((default_target, latest_build_status)) =
+                            details.get(&release.id)
+                        {
+                            status.default_target = default_target.clone();
+                            status.latest_build_status = *latest_build_status;
+                        }
+                    }
+                    Err(err) => status.error = Some(err.into()),
+                }
+                status
+            })
+            .collect();
+
+        Ok(json_response(
+            CachePolicy::NoStoreMustRevalidate,
+            ApiBulkStatus { crates: statuses },
+        ))
+    }
+    .await
+    .map_err(JsonAxumNope)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{
+            BuildId,
+            access_rules::{AccessTarget, add_access_rule},
+            types::BuildStatus,
+        },
+        ices,
+        registry_api::{CrateOwner, OwnerKind},
+        test::{AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper},
+        web::cache::CachePolicy,
+    };
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, header},
+        response::Response,
+    };
+    use reqwest::StatusCode;
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use test_case::test_case;
+    use tower::ServiceExt as _;
+
+    async fn post_bulk_status(
+        web: &Router,
+        body: impl Into<Body>,
+        token: Option<&str>,
+    ) -> anyhow::Result<Response> {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/crates/status")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        Ok(web.clone().oneshot(request.body(body.into())?).await?)
+    }
+
+    #[test]
+    fn crate_details() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .description("a fake crate")
+                .keywords(vec!["fake".into()])
+                .add_owner(CrateOwner {
+                    login: "foobar".into(),
+                    avatar: "https://example.org/foobar".into(),
+                    kind: OwnerKind::User,
+                })
+                .add_platform("x86_64-pc-windows-msvc")
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/api/v1/crates/foo/0.1.0").await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+            response
+                .assert_cache_control(CachePolicy::ForeverInCdnAndStaleInBrowser, &env.config());
+
+            let details: Value = response.json().await?;
+            assert_eq!(details["name"], "foo");
+            assert_eq!(details["version"], "0.1.0");
+            assert_eq!(details["description"], "a fake crate");
+            assert_eq!(details["keywords"], json!(["fake"]));
+            assert_eq!(
+                details["owners"],
+                json!([{
+                    "login": "foobar",
+                    "avatar": "https://example.org/foobar",
+                    "kind": "user",
+                }])
+            );
+            assert_eq!(details["build_status"], "success");
+            assert_eq!(details["yanked"], false);
+            assert_eq!(details["rustdoc_status"], true);
+            assert_eq!(details["docs_url"], "/foo/0.1.0/foo/");
+            assert_eq!(details["default_target"], "x86_64-unknown-linux-gnu");
+            assert!(
+                details["doc_targets"]
+                    .as_array()
+                    .unwrap()
+                    .contains(&json!("x86_64-pc-windows-msvc"))
+            );
+
+            web.get("/api/v1/crates/foo/latest")
+                .await?
+                .assert_cache_control(CachePolicy::ForeverInCdn, &env.config());
+
+            Ok(())
+        })
+    }
+
+    #[test_case("~0.1", "/api/v1/crates/foo/0.1.0")]
+    #[test_case("0.1/builds", "/api/v1/crates/foo/0.1.0/builds")]
+    #[test_case("%5E0.1/features", "/api/v1/crates/foo/0.1.0/features")]
+    #[test_case("*/platforms", "/api/v1/crates/foo/latest/platforms")]
+    fn semver_redirects(path: &str, expected: &str) {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let response = web
+                .assert_redirect_cached(
+                    &format!("/api/v1/crates/foo/{path}"),
+                    expected,
+                    CachePolicy::ForeverInCdn,
+                    &env.config(),
+                )
+                .await?;
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+
+            Ok(())
+        })
+    }
+
+    #[test_case("/api/v1/crates/foo/0.2.0")]
+    #[test_case("/api/v1/crates/bar/latest")]
+    #[test_case("/api/v1/crates/bar/releases")]
+    #[test_case("/api/v1/crates/foo/0.1.0/builds/1/logs/x86_64-unknown-linux-gnu.txt")]
+    #[test_case("/api/v1/releases/unknown")]
+    fn not_found(path: &str) {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .no_builds()
+                .create()
+                .await?;
+
+            let response = env.web_app().await.get(path).await?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+            let error: Value = response.json().await?;
+            assert!(error["title"].is_string());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn crate_releases() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.2.0")
+                .yanked(true)
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.3.0")
+                .build_result_failed()
+                .create()
+                .await?;
+
+            let response = env
+                .web_app()
+                .await
+                .get("/api/v1/crates/foo/releases")
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.assert_cache_control(CachePolicy::ForeverInCdn, &env.config());
+
+            let releases: Value = response.json().await?;
+            let releases: Vec<_> = releases["releases"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|release| {
+                    (
+                        release["version"].as_str().unwrap(),
+                        release["build_status"].as_str().unwrap(),
+                        release["yanked"].as_bool().unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                releases,
+                [
+                    ("0.3.0", "failure", false),
+                    ("0.2.0", "success", true),
+                    ("0.1.0", "success", false),
+                ]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn builds_and_logs() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .builds(vec![
+                    FakeBuild::default()
+                        .s3_build_log("A build log")
+                        .build_log_for_other_target("other_target", "other target build log"),
+                ])
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/api/v1/crates/foo/0.1.0/builds").await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.assert_cache_control(CachePolicy::NoCaching, &env.config());
+
+            let builds: Value = response.json().await?;
+            let build = &builds["builds"][0];
+            let id = build["id"].as_i64().unwrap();
+            assert_eq!(build["build_status"], "success");
+            assert_eq!(
+                build["rustc_version"],
+                "rustc 2.0.0-nightly (000000000 1970-01-01)"
+            );
+            assert_eq!(
+                build["details_url"],
+                format!("/crate/foo/0.1.0/builds/{id}")
+            );
+            assert_eq!(
+                build["logs"],
+                json!([
+                    {
+                        "filename": "other_target.txt",
+                        "url": format!("/api/v1/crates/foo/0.1.0/builds/{id}/logs/other_target.txt"),
+                    },
+                    {
+                        "filename": "x86_64-unknown-linux-gnu.txt",
+                        "url": format!("/api/v1/crates/foo/0.1.0/builds/{id}/logs/x86_64-unknown-linux-gnu.txt"),
+                    },
+                ])
+            );
+
+            // the details page the build links to exists
+            web.assert_success(build["details_url"].as_str().unwrap())
+                .await?;
+
+            for (log, expected) in [
+                (&build["logs"][0], "other target build log"),
+                (&build["logs"][1], "A build log"),
+            ] {
+                let response = web.get(log["url"].as_str().unwrap()).await?;
+                assert_eq!(response.status(), StatusCode::OK);
+                assert_eq!(
+                    response.headers()["content-type"],
+                    "text/plain; charset=utf-8"
+                );
+                assert_eq!(response.headers()["access-control-allow-origin"], "*");
+                assert_eq!(response.text().await?, expected);
+            }
+
+            // logs are only available through the release they belong to
+            env.fake_release()
+                .await
+                .name("bar")
+                .version("0.1.0")
+                .create()
+                .await?;
+            web.assert_not_found(&format!(
+                "/api/v1/crates/bar/0.1.0/builds/{id}/logs/x86_64-unknown-linux-gnu.txt"
+            ))
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn features() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .features(HashMap::from([
+                    ("default".into(), vec!["feature1".into()]),
+                    ("feature1".into(), vec!["feature2".into()]),
+                    ("feature2".into(), vec![]),
+                    ("_private".into(), vec![]),
+                ]))
+                .create()
+                .await?;
+
+            let response = env
+                .web_app()
+                .await
+                .get("/api/v1/crates/foo/latest/features")
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.assert_cache_control(CachePolicy::ForeverInCdn, &env.config());
+            assert_eq!(
+                response.json::<Value>().await?,
+                json!({
+                    "features": {
+                        "default": ["feature1"],
+                        "feature1": ["feature2"],
+                        "feature2": [],
+                    }
+                })
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn platforms() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .default_target("x86_64-pc-windows-msvc")
+                .create()
+                .await?;
+
+            let response = env
+                .web_app()
+                .await
+                .get("/api/v1/crates/foo/0.1.0/platforms")
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let platforms: Value = response.json().await?;
+            assert_eq!(platforms["default_target"], "x86_64-pc-windows-msvc");
+            assert_eq!(platforms["doc_targets"], json!(["x86_64-pc-windows-msvc"]));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn release_lists() {
+        async_wrapper(|env| async move {
+            for version in ["0.1.0", "0.2.0"] {
+                env.fake_release()
+                    .await
+                    .name("foo")
+                    .version(version)
+                    .create()
+                    .await?;
+            }
+            env.fake_release()
+                .await
+                .name("bar")
+                .version("0.1.0")
+                .build_result_failed()
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/api/v1/releases/recent").await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+            response.assert_cache_control(CachePolicy::NoCaching, &env.config());
+
+            let list: Value = response.json().await?;
+            assert_eq!(list["next_page"], Value::Null);
+            let releases: Vec<_> = list["releases"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|release| {
+                    (
+                        release["name"].as_str().unwrap(),
+                        release["version"].as_str().unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                releases,
+                [("bar", "0.1.0"), ("foo", "0.2.0"), ("foo", "0.1.0")]
+            );
+
+            let list: Value = web
+                .get("/api/v1/releases/recent-failures")
+                .await?
+                .json()
+                .await?;
+            assert_eq!(list["releases"].as_array().unwrap().len(), 1);
+            assert_eq!(list["releases"][0]["name"], "bar");
+
+            let list: Value = web
+                .get("/api/v1/releases/recent?page=2")
+                .await?
+                .json()
+                .await?;
+            assert!(list["releases"].as_array().unwrap().is_empty());
+
+            assert_eq!(
+                web.get("/api/v1/releases/recent?page=0").await?.status(),
+                StatusCode::BAD_REQUEST
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn release_lists_hide_private_crates() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            for name in ["foo", "private"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .create()
+                    .await?;
+            }
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private"),
+                "alice",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/api/v1/releases/recent").await?;
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+            let list: Value = response.json().await?;
+            assert_eq!(list["releases"].as_array().unwrap().len(), 1);
+            assert_eq!(list["releases"][0]["name"], "foo");
+
+            let response = web
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/api/v1/releases/recent")
+                        .header(header::AUTHORIZATION, "Bearer alice-token")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            let list: Value = response.json().await?;
+            assert_eq!(list["releases"].as_array().unwrap().len(), 2);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn ices() {
+        async_wrapper(|env| async move {
+            let rustc = "rustc 1.92.0-nightly (abcdef012 2025-10-01)";
+            for name in ["foo", "private"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .builds(vec![
+                        FakeBuild::default()
+                            .build_status(BuildStatus::Failure)
+                            .rustc_version(rustc),
+                    ])
+                    .create()
+                    .await?;
+            }
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private"),
+                "alice",
+            )
+            .await?;
+            let builds: Vec<BuildId> =
+                sqlx::query_scalar!(r#"SELECT id as "id: BuildId" FROM builds ORDER BY id"#)
+                    .fetch_all(&mut *conn)
+                    .await?;
+            for build_id in &builds {
+                ices::record_ices(
+                    &mut conn,
+                    *build_id,
+                    "x86_64-unknown-linux-gnu",
+                    rustc,
+                    "error: internal compiler error: compiler/rustc_x/src/lib.rs:1:2: no type for `bar`",
+                )
+                .await?;
+            }
+
+            let web = env.web_app().await;
+            let response = web.get("/api/v1/ices").await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.assert_cache_control(CachePolicy::NoCaching, &env.config());
+
+            let list: Value = response.json().await?;
+            let ices = list["ices"].as_array().unwrap();
+            assert_eq!(ices.len(), 1);
+            assert_eq!(ices[0]["message"], "no type for `bar`");
+            assert_eq!(ices[0]["first_toolchain"], "2025-10-01");
+            // the private crate isn't listed.
+            assert_eq!(ices[0]["crate_count"], 1);
+            assert_eq!(
+                ices[0]["crates"],
+                json!([{
+                    "name": "foo",
+                    "version": "0.1.0",
+                    "target": "x86_64-unknown-linux-gnu",
+                    "rustc_version": rustc,
+                    "toolchain_date": "2025-10-01",
+                    "build_url": format!(
+                        "https://docs.rs/crate/foo/0.1.0/builds/{}/x86_64-unknown-linux-gnu.txt",
+                        builds[0]
+                    ),
+                }])
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn crate_named_api() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("api")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let page = web.assert_success("/api/0.1.0/api/").await?.text().await?;
+            assert!(page.contains("default content for test/fakes"));
+            web.assert_success("/api/v1/crates/api/0.1.0").await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn bulk_status() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .default_target("x86_64-pc-windows-msvc")
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.2.0")
+                .yanked(true)
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("bar-baz")
+                .version("1.0.0")
+                .builds(vec![
+                    FakeBuild::default(),
+                    FakeBuild::default().build_status(BuildStatus::Failure),
+                ])
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("private")
+                .version("1.0.0")
+                .create()
+                .await?;
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private"),
+                "alice",
+            )
+            .await?;
+
+            let request = json!({
+                "crates": [
+                    { "name": "foo" },
+                    { "name": "foo", "version": "0.2.0" },
+                    { "name": "foo", "version": "^3" },
+                    { "name": "bar_baz", "version": "~1" },
+                    { "name": "unknown", "version": "1.0.0" },
+                    { "name": "private" },
+                ]
+            })
+            .to_string();
+
+            let web = env.web_app().await;
+            let response = post_bulk_status(&web, request.clone(), None).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+
+            let crates = response.json::<Value>().await?["crates"].take();
+            let crates = crates.as_array().unwrap();
+            assert_eq!(crates.len(), 6);
+
+            assert_eq!(
+                crates[0],
+                json!({
+                    "name": "foo",
+                    "req_version": "latest",
+                    "version": "0.1.0",
+                    "doc_status": true,
+                    "default_target": "x86_64-pc-windows-msvc",
+                    "latest_build_status": "success",
+                    "error": null,
+                })
+            );
+            // exact versions also match yanked releases
+            assert_eq!(crates[1]["version"], "0.2.0");
+            assert_eq!(crates[2]["version"], Value::Null);
+            assert!(crates[2]["error"]["title"].is_string());
+            assert_eq!(crates[3]["name"], "bar_baz");
+            assert_eq!(crates[3]["version"], "1.0.0");
+            assert_eq!(crates[3]["latest_build_status"], "failure");
+            assert_eq!(crates[4]["version"], Value::Null);
+            assert!(crates[4]["error"]["title"].is_string());
+            // without access, private crates are the same as unknown crates
+            assert_eq!(crates[5]["error"], crates[4]["error"]);
+
+            let response = post_bulk_status(&web, request, Some("alice-token")).await?;
+            let crates = response.json::<Value>().await?["crates"].take();
+            assert_eq!(crates[5]["version"], "1.0.0");
+            assert_eq!(crates[5]["error"], Value::Null);
+
+            Ok(())
+        })
+    }
+
+    #[test_case("not json"; "invalid json")]
+    #[test_case(r#"{"crates": [{"name": "foo", "version": "not a version"}]}"#; "invalid version")]
+    #[test_case(&json!({ "crates": vec![json!({"name": "foo"}); 1001] }).to_string(); "too many")]
+    fn bulk_status_bad_request(body: &str) {
+        async_wrapper(|env| async move {
+            let response = post_bulk_status(&env.web_app().await, body.to_owned(), None).await?;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let error: Value = response.json().await?;
+            assert!(error["title"].is_string());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn cors_preflight() {
+        async_wrapper(|env| async move {
+            let response = env
+                .web_app()
+                .await
+                .oneshot(
+                    Request::builder()
+                        .method("OPTIONS")
+                        .uri("/api/v1/crates/status")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+            assert_eq!(
+                response.headers()["access-control-allow-methods"],
+                "GET, POST"
+            );
+
+            Ok(())
+        })
+    }
+}
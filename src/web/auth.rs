@@ -0,0 +1,558 @@
+//! Authentication, and access checks for private crates.
+//!
+//! Requests authenticate with a token, either as bearer token, or as the password of HTTP
+//! basic authentication, so browsers can show a login prompt. Tokens are static tokens from
+//! the config, or access tokens of an OpenID Connect provider, which we validate with its
+//! userinfo endpoint.
+//!
+//! Crates with access rules (see [`crate::db::access_rules`]) are only served to the
+//! principals the rules allow, and never cached by the CDN.
+
+use crate::{
+    Config,
+    db::{
+        Pool,
+        access_rules::{self, ANY_PRINCIPAL},
+    },
+    web::{cache::CachePolicy, error::AxumNope},
+};
+use anyhow::{Context as _, bail};
+use axum::{
+    extract::{Extension, RawPathParams, Request},
+    http::{
+        HeaderMap, HeaderValue,
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response as AxumResponse},
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD as b64};
+use serde::Deserialize;
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+use url::Url;
+
+/// How long we trust the result of validating a token with the OIDC provider.
+const OIDC_CACHE_DURATION: Duration = Duration::from_secs(60);
+
+/// Above this size, we drop the expired tokens from the cache.
+const OIDC_CACHE_SIZE: usize = 10_000;
+
+/// URLs we serve without authentication, even when it's required for every page.
+/// The static files are needed for the error pages, the index webhook has its own signature.
+const PUBLIC_PATHS: &[&str] = &["/-/static/", "/_/index-webhook"];
+
+static CHALLENGE: HeaderValue = HeaderValue::from_static(r#"Basic realm="docs.rs", Bearer"#);
+
+/// Static tokens, configured as comma-separated `principal:token` pairs.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct StaticTokens(Vec<(String, String)>);
+
+impl StaticTokens {
+    fn principal(&self, token: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(_, t)| constant_time_eq::constant_time_eq(t.as_bytes(), token.as_bytes()))
+            .map(|(principal, _)| principal.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid static token, expected `principal:token`")]
+pub(crate) struct InvalidStaticToken;
+
+impl FromStr for StaticTokens {
+    type Err = InvalidStaticToken;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once(':') {
+                Some((principal, token)) if !principal.is_empty() && !token.is_empty() => {
+                    Ok((principal.to_owned(), token.to_owned()))
+                }
+                _ => Err(InvalidStaticToken),
+            })
+            .collect::<Result<_, _>>()
+            .map(StaticTokens)
+    }
+}
+
+/// The name a request is authenticated as.
+///
+/// Added to authenticated requests, for handlers that have to check access rules themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Principal(String);
+
+impl Principal {
+    /// Whether the principal is allowed to see a crate with these access rules.
+    pub(crate) fn is_allowed(&self, allowed_principals: &[String]) -> bool {
+        allowed_principals
+            .iter()
+            .any(|allowed| allowed == ANY_PRINCIPAL || allowed == &self.0)
+    }
+
+    /// Whether the principal may use the admin endpoints.
+    pub(crate) fn is_admin(&self, config: &Config) -> bool {
+        config.admin_principals.contains(&self.0)
+    }
+}
+
+/// Removes the crates the principal isn't allowed to see from a list.
+///
+/// Returns whether the list had crates with access rules, responses with the list then depend
+/// on who is asking.
+pub(crate) async fn retain_visible<T>(
+    conn: &mut sqlx::PgConnection,
+    principal: Option<&Principal>,
+    items: &mut Vec<T>,
+    crate_name: impl Fn(&T) -> &str,
+) -> anyhow::Result<bool> {
+    let names: Vec<&str> = items.iter().map(&crate_name).collect();
+    let allowed_principals = access_rules::allowed_principals_for_crates(conn, &names).await?;
+
+    items.retain(|item| {
+        allowed_principals
+            .get(crate_name(item))
+            .is_none_or(|allowed| principal.is_some_and(|principal| principal.is_allowed(allowed)))
+    });
+    Ok(!allowed_principals.is_empty())
+}
+
+/// Added to requests for crates with access rules.
+///
+/// Handlers can't hand out public URLs for the files of these crates.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Protected;
+
+#[derive(Debug, Deserialize)]
+struct OidcDiscovery {
+    userinfo_endpoint: Url,
+}
+
+/// hashed token => when we validated it, and its principal
+type ValidatedTokens = HashMap<[u8; 32], (Instant, Option<String>)>;
+
+struct Oidc {
+    issuer: Url,
+    principal_claim: String,
+    client: reqwest::Client,
+    userinfo_endpoint: OnceCell<Url>,
+    cache: RwLock<ValidatedTokens>,
+}
+
+impl Oidc {
+    async fn userinfo_endpoint(&self) -> anyhow::Result<&Url> {
+        self.userinfo_endpoint
+            .get_or_try_init(|| async {
+                let discovery_url = self
+                    .issuer
+                    .join(".well-known/openid-configuration")
+                    .context("invalid OIDC issuer")?;
+                let discovery: OidcDiscovery = self
+                    .client
+                    .get(discovery_url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+                    .context("invalid OIDC discovery document")?;
+                Ok(discovery.userinfo_endpoint)
+            })
+            .await
+    }
+
+    async fn principal(&self, token: &str) -> anyhow::Result<Option<String>> {
+        let key: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        if let Some((validated_at, principal)) = self.cache.read().unwrap().get(&key)
+            && validated_at.elapsed() < OIDC_CACHE_DURATION
+        {
+            return Ok(principal.clone());
+        }
+
+        let response = self
+            .client
+            .get(self.userinfo_endpoint().await?.clone())
+            .bearer_auth(token)
+            .send()
+            .await?;
+
+        let principal = if response.status().is_client_error() {
+            None
+        } else {
+            let userinfo: HashMap<String, serde_json::Value> =
+                response.error_for_status()?.json().await?;
+            match userinfo.get(&self.principal_claim) {
+                Some(serde_json::Value::String(principal)) => Some(principal.clone()),
+                _ => bail!("userinfo is missing the {} claim", self.principal_claim),
+            }
+        };
+
+        let mut cache = self.cache.write().unwrap();
+        if cache.len() >= OIDC_CACHE_SIZE {
+            cache.retain(|_, (validated_at, _)| validated_at.elapsed() < OIDC_CACHE_DURATION);
+        }
+        cache.insert(key, (Instant::now(), principal.clone()));
+
+        Ok(principal)
+    }
+}
+
+pub(crate) struct Authenticator {
+    static_tokens: StaticTokens,
+    oidc: Option<Oidc>,
+    require_auth: bool,
+}
+
+impl Authenticator {
+    pub(crate) fn new(config: &Config) -> anyhow::Result<Self> {
+        let oidc = config
+            .oidc_issuer
+            .as_ref()
+            .map(|issuer| -> anyhow::Result<_> {
+                Ok(Oidc {
+                    issuer: issuer.clone(),
+                    principal_claim: config.oidc_principal_claim.clone(),
+                    client: reqwest::Client::builder()
+                        .timeout(Duration::from_secs(10))
+                        .build()?,
+                    userinfo_endpoint: OnceCell::new(),
+                    cache: RwLock::default(),
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            static_tokens: config.auth_tokens.clone(),
+            oidc,
+            require_auth: config.require_auth,
+        })
+    }
+
+    /// Returns the principal of the token in the request.
+    ///
+    /// Unknown tokens are ignored, other endpoints like the rebuild API have their own tokens.
+    async fn authenticate(&self, headers: &HeaderMap) -> anyhow::Result<Option<Principal>> {
+        let Some(token) = token(headers) else {
+            return Ok(None);
+        };
+
+        if let Some(principal) = self.static_tokens.principal(&token) {
+            return Ok(Some(Principal(principal.to_owned())));
+        }
+
+        if let Some(oidc) = &self.oidc
+            && let Some(principal) = oidc
+                .principal(&token)
+                .await
+                .context("failed to validate token with the OIDC provider")?
+        {
+            return Ok(Some(Principal(principal)));
+        }
+
+        Ok(None)
+    }
+}
+
+/// The token from the `Authorization` header.
+fn token(headers: &HeaderMap) -> Option<String> {
+    let (scheme, credentials) = headers.get(AUTHORIZATION)?.to_str().ok()?.split_once(' ')?;
+
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Some(credentials.trim().to_owned())
+    } else if scheme.eq_ignore_ascii_case("basic") {
+        // the user name is ignored, the token is the password.
+        let credentials = String::from_utf8(b64.decode(credentials.trim()).ok()?).ok()?;
+        let (_, password) = credentials.split_once(':')?;
+        Some(password.to_owned())
+    } else {
+        None
+    }
+}
+
+pub(crate) fn unauthorized() -> AxumResponse {
+    let mut response = AxumNope::Unauthorized("authentication required").into_response();
+    response
+        .headers_mut()
+        .insert(WWW_AUTHENTICATE, CHALLENGE.clone());
+    response
+        .extensions_mut()
+        .insert(CachePolicy::NoStoreMustRevalidate);
+    response
+}
+
+pub(crate) async fn auth_middleware(
+    Extension(authenticator): Extension<Arc<Authenticator>>,
+    Extension(pool): Extension<Pool>,
+    params: Result<RawPathParams, axum::extract::rejection::RawPathParamsRejection>,
+    mut request: Request,
+    next: Next,
+) -> AxumResponse {
+    let principal = match authenticator.authenticate(request.headers()).await {
+        Ok(principal) => principal,
+        Err(err) => return AxumNope::InternalError(err).into_response(),
+    };
+
+    let path = request.uri().path();
+    let mut protected =
+        authenticator.require_auth && !PUBLIC_PATHS.iter().any(|public| path.starts_with(public));
+    if protected && principal.is_none() {
+        return unauthorized();
+    }
+
+    let crate_name = params.ok().and_then(|params| {
+        params
+            .iter()
+            .find(|(key, _)| *key == "name")
+            .map(|(_, name)| name.to_owned())
+    });
+    if let Some(crate_name) = crate_name {
+        let allowed_principals = match pool.get_async().await {
+            Ok(mut conn) => access_rules::allowed_principals(&mut conn, &crate_name).await,
+            Err(err) => Err(err.into()),
+        };
+        let allowed_principals = match allowed_principals {
+            Ok(allowed_principals) => allowed_principals,
+            Err(err) => return AxumNope::InternalError(err).into_response(),
+        };
+
+        if !allowed_principals.is_empty() {
+            let Some(principal) = &principal else {
+                return unauthorized();
+            };
+            if !principal.is_allowed(&allowed_principals) {
+                // we don't tell if the crate exists.
+                return AxumNope::CrateNotFound.into_response();
+            }
+
+            protected = true;
+            request.extensions_mut().insert(Protected);
+        }
+    }
+
+    if let Some(principal) = principal {
+        request.extensions_mut().insert(principal);
+    }
+
+    let mut response = next.run(request).await;
+    if protected {
+        // neither the CDN nor shared caches may keep responses only some principals can see.
+        response
+            .extensions_mut()
+            .insert(CachePolicy::NoStoreMustRevalidate);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::access_rules::{AccessTarget, add_access_rule};
+    use crate::test::{AxumResponseTestExt, TestOidcProvider, async_wrapper};
+    use axum::{Router, body::Body, http::StatusCode};
+    use test_case::test_case;
+    use tower::ServiceExt as _;
+
+    async fn get(
+        web: &Router,
+        path: &str,
+        authorization: Option<&str>,
+    ) -> anyhow::Result<AxumResponse> {
+        let mut request = Request::builder().uri(path);
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        Ok(web.clone().oneshot(request.body(Body::empty())?).await?)
+    }
+
+    #[test_case("Bearer secret", Some("secret"))]
+    #[test_case("bearer secret", Some("secret"); "lowercase scheme")]
+    // `alice:secret`
+    #[test_case("Basic YWxpY2U6c2VjcmV0", Some("secret"))]
+    #[test_case("Basic invalid", None)]
+    #[test_case("Digest secret", None)]
+    #[test_case("secret", None)]
+    fn test_token(authorization: &str, expected: Option<&str>) {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, authorization.parse().unwrap());
+        assert_eq!(token(&headers).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_parse_static_tokens() {
+        let tokens: StaticTokens = "alice:secret, bob:other:colon".parse().unwrap();
+        assert_eq!(tokens.principal("secret"), Some("alice"));
+        assert_eq!(tokens.principal("other:colon"), Some("bob"));
+        assert_eq!(tokens.principal("unknown"), None);
+
+        assert!("".parse::<StaticTokens>().unwrap().0.is_empty());
+        assert!("alice".parse::<StaticTokens>().is_err());
+        assert!("alice:".parse::<StaticTokens>().is_err());
+    }
+
+    #[test]
+    fn private_crate() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token,bob:bob-token".parse().unwrap();
+            });
+            env.fake_release()
+                .await
+                .name("private")
+                .version("1.0.0")
+                .archive_storage(true)
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("public")
+                .version("1.0.0")
+                .create()
+                .await?;
+
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private"),
+                "alice",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+
+            let response = get(&web, "/crate/private/1.0.0", None).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()[WWW_AUTHENTICATE], CHALLENGE);
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+
+            // unknown tokens are the same as no token
+            let response = get(&web, "/private/1.0.0/private/", Some("Bearer unknown")).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+            // other principals don't see that the crate exists
+            let response = get(&web, "/crate/private/1.0.0", Some("Bearer bob-token")).await?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            for path in ["/crate/private/1.0.0", "/private/1.0.0/private/"] {
+                let response = get(&web, path, Some("Bearer alice-token")).await?;
+                assert_eq!(response.status(), StatusCode::OK, "{path}");
+                response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+            }
+
+            // the archive is served by us, not from the public bucket
+            let response = get(
+                &web,
+                "/crate/private/1.0.0/download",
+                Some("Basic YWxpY2U6YWxpY2UtdG9rZW4="),
+            )
+            .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(!response.bytes().await?.is_empty());
+
+            let response = get(&web, "/crate/public/1.0.0", None).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response
+                .assert_cache_control(CachePolicy::ForeverInCdnAndStaleInBrowser, &env.config());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn private_registry() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            let mut conn = env.async_db().await.async_conn().await;
+            crate::db::registries::add_registry(
+                &mut conn,
+                "acme",
+                "https://acme.example/index",
+                None,
+            )
+            .await?;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Registry("acme"),
+                ANY_PRINCIPAL,
+            )
+            .await?;
+
+            env.fake_release()
+                .await
+                .name("acme/foo")
+                .version("1.0.0")
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let response = get(&web, "/acme/foo/1.0.0/foo/", None).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let response = get(&web, "/acme/foo/1.0.0/foo/", Some("Bearer alice-token")).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn require_auth() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+                config.require_auth = true;
+            });
+
+            let web = env.web_app().await;
+            let response = get(&web, "/", None).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()[WWW_AUTHENTICATE], CHALLENGE);
+
+            let response = get(&web, "/", Some("Bearer alice-token")).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+
+            let response = get(&web, "/-/static/style.css", None).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn oidc_tokens() {
+        async_wrapper(|env| async move {
+            let provider = TestOidcProvider::new(&[("valid-token", "alice")]).await?;
+            env.override_config(|config| {
+                config.oidc_issuer = Some(provider.issuer());
+                config.require_auth = true;
+            });
+
+            let web = env.web_app().await;
+            for _ in 0..2 {
+                let response = get(&web, "/", Some("Bearer valid-token")).await?;
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+            // the second request used the cached result
+            assert_eq!(provider.userinfo_requests(), 1);
+
+            let response = get(&web, "/", Some("Bearer invalid-token")).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+            Ok(())
+        })
+    }
+}
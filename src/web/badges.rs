@@ -0,0 +1,411 @@
+//! SVG badges for READMEs, rendered from our own data.
+//!
+//! All badges of a crate live under `/{name}/`, so the CDN invalidation after each build
+//! purges them, and we can cache them in the CDN until then.
+
+use crate::{
+    db::types::BuildStatus,
+    impl_axum_webpage,
+    web::{
+        MatchedRelease, ReqVersion,
+        cache::CachePolicy,
+        error::{AxumNope, AxumResult},
+        extractors::{DbConnection, Path},
+        match_version, select_release,
+    },
+};
+use askama::Template;
+use axum::{extract::Query, http::StatusCode, response::IntoResponse};
+use serde::Deserialize;
+use tracing::instrument;
+
+const GREEN: &str = "#4c1";
+const YELLOW_GREEN: &str = "#97ca00";
+const YELLOW: &str = "#dfb317";
+const RED: &str = "#e05d44";
+const BLUE: &str = "#007ec6";
+const GREY: &str = "#9f9f9f";
+
+/// Horizontal padding around the texts of a badge, on each side.
+const PADDING: u32 = 5;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BadgeKind {
+    /// whether the release has documentation
+    #[default]
+    Docs,
+    /// how many of the public items are documented
+    Coverage,
+    /// the newest version with documentation
+    Version,
+    /// the status of the latest build
+    Build,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct BadgeQueryParams {
+    version: Option<ReqVersion>,
+    #[serde(default)]
+    kind: BadgeKind,
+}
+
+#[derive(Template, Debug, Clone, PartialEq, Eq)]
+#[template(path = "crate/badge.svg", escape = "html")]
+struct Badge {
+    label: String,
+    message: String,
+    color: &'static str,
+    found: bool,
+}
+
+impl_axum_webpage! {
+    Badge,
+    status = |badge| if badge.found { StatusCode::OK } else { StatusCode::NOT_FOUND },
+    content_type = "image/svg+xml",
+    cache_policy = |badge| if badge.found {
+        CachePolicy::ForeverInCdn
+    } else {
+        CachePolicy::NoCaching
+    },
+}
+
+impl Badge {
+    fn new(label: &str, message: impl Into<String>, color: &'static str) -> Self {
+        Self {
+            label: label.to_owned(),
+            message: message.into(),
+            color,
+            found: true,
+        }
+    }
+
+    fn label_width(&self) -> u32 {
+        text_width(&self.label) + 2 * PADDING
+    }
+
+    fn message_width(&self) -> u32 {
+        text_width(&self.message) + 2 * PADDING
+    }
+
+    fn width(&self) -> u32 {
+        self.label_width() + self.message_width()
+    }
+
+    /// the horizontal center of the label
+    fn label_x(&self) -> f32 {
+        self.label_width() as f32 / 2.0
+    }
+
+    /// the horizontal center of the message
+    fn message_x(&self) -> f32 {
+        self.label_width() as f32 + self.message_width() as f32 / 2.0
+    }
+
+    fn label_text_width(&self) -> u32 {
+        text_width(&self.label)
+    }
+
+    fn message_text_width(&self) -> u32 {
+        text_width(&self.message)
+    }
+}
+
+/// Approximates the width of a text in 11px Verdana, the font shields.io uses.
+///
+/// The SVG sets the text length to this width, so when the reader doesn't have Verdana the
+/// text is stretched to fit instead of overflowing the badge.
+fn text_width(text: &str) -> u32 {
+    let width: f32 = text
+        .chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '\'' | '|' => 3.1,
+            '.' | ',' | ':' | ';' | '!' | ' ' => 3.9,
+            'f' | 't' | 'r' | 'I' | '-' | '(' | ')' | '[' | ']' => 4.6,
+            'm' => 10.7,
+            'w' => 8.9,
+            'M' | 'W' => 10.0,
+            '%' => 11.8,
+            '0'..='9' => 7.0,
+            'a'..='z' => 6.6,
+            'A'..='Z' => 7.6,
+            _ => 7.0,
+        })
+        .sum();
+    width.ceil() as u32
+}
+
+fn coverage_color(percent: f32) -> &'static str {
+    if percent >= 90.0 {
+        GREEN
+    } else if percent >= 75.0 {
+        YELLOW_GREEN
+    } else if percent >= 50.0 {
+        YELLOW
+    } else {
+        RED
+    }
+}
+
+async fn badge(
+    conn: &mut sqlx::PgConnection,
+    kind: BadgeKind,
+    req_version: &ReqVersion,
+    matched: MatchedRelease,
+) -> AxumResult<Badge> {
+    let release = &matched.release;
+    Ok(match kind {
+        BadgeKind::Docs => match (release.rustdoc_status, release.build_status) {
+            (Some(true), _) => Badge::new("docs", "passing", GREEN),
+            (_, BuildStatus::InProgress) => Badge::new("docs", "building", YELLOW),
+            _ => Badge::new("docs", "failing", RED),
+        },
+        BadgeKind::Coverage => {
+            let coverage = sqlx::query!(
+                "SELECT documented_items, total_items
+                 FROM doc_coverage
+                 WHERE release_id = $1",
+                release.id.0,
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            match coverage.and_then(|row| Some((row.documented_items?, row.total_items?))) {
+                Some((documented, total)) if total > 0 => {
+                    let percent = documented as f32 * 100.0 / total as f32;
+                    Badge::new(
+                        "doc coverage",
+                        format!("{:.0}%", percent.floor()),
+                        coverage_color(percent),
+                    )
+                }
+                _ => Badge::new("doc coverage", "unknown", GREY),
+            }
+        }
+        BadgeKind::Version => {
+            let documented: Vec<_> = matched
+                .all_releases
+                .iter()
+                .filter(|release| release.rustdoc_status == Some(true))
+                .cloned()
+                .collect();
+            match select_release(&documented, req_version) {
+                Ok(release) => Badge::new("docs.rs", release.version.to_string(), BLUE),
+                Err(_) => Badge::new("docs.rs", "none", GREY),
+            }
+        }
+        BadgeKind::Build => {
+            let latest_build = sqlx::query_scalar!(
+                r#"SELECT build_status as "build_status: BuildStatus"
+                 FROM builds
+                 WHERE rid = $1
+                 ORDER BY id DESC
+                 LIMIT 1"#,
+                release.id.0,
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            match latest_build {
+                Some(BuildStatus::Success) => Badge::new("docs build", "passing", GREEN),
+                Some(BuildStatus::Failure) => Badge::new("docs build", "failing", RED),
+                Some(BuildStatus::InProgress) => Badge::new("docs build", "building", YELLOW),
+                None => Badge::new("docs build", "queued", GREY),
+            }
+        }
+    })
+}
+
+#[instrument(skip(conn))]
+pub(crate) async fn badge_handler(
+    Path(name): Path<String>,
+    Query(query): Query<BadgeQueryParams>,
+    mut conn: DbConnection,
+) -> AxumResult<impl IntoResponse> {
+    let req_version = query.version.unwrap_or_default();
+
+    match match_version(&mut conn, &name, &req_version).await {
+        Ok(matched) => badge(&mut conn, query.kind, &req_version, matched).await,
+        Err(AxumNope::CrateNotFound | AxumNope::VersionNotFound) => {
+            let label = match query.kind {
+                BadgeKind::Docs => "docs",
+                BadgeKind::Coverage => "doc coverage",
+                BadgeKind::Version => "docs.rs",
+                BadgeKind::Build => "docs build",
+            };
+            Ok(Badge {
+                found: false,
+                ..Badge::new(label, "not found", GREY)
+            })
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docbuilder::DocCoverage;
+    use crate::test::{AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper};
+    use test_case::test_case;
+
+    #[test_case("docs", "docs", "passing")]
+    #[test_case("coverage", "doc coverage", "66%")]
+    #[test_case("version", "docs.rs", "0.5.1+zstd.1.4.4")]
+    #[test_case("build", "docs build", "passing")]
+    fn badge(kind: &str, label: &str, message: &str) {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("zstd")
+                .version("0.5.1+zstd.1.4.4")
+                .doc_coverage(DocCoverage {
+                    total_items: 3,
+                    documented_items: 2,
+                    total_items_needing_examples: 0,
+                    items_with_examples: 0,
+                })
+                .create()
+                .await?;
+
+            let response = env
+                .web_app()
+                .await
+                .get(&format!("/zstd/badge.svg?kind={kind}"))
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "image/svg+xml");
+            response.assert_cache_control(CachePolicy::ForeverInCdn, &env.config());
+
+            let svg = response.text().await?;
+            assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+            assert!(svg.contains(&format!("<title>{label}: {message}</title>")));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn docs_badge_for_version() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.2.0")
+                .build_result_failed()
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let svg = web.get("/foo/badge.svg").await?.text().await?;
+            assert!(svg.contains("<title>docs: failing</title>"));
+            assert!(svg.contains(RED));
+
+            let svg = web.get("/foo/badge.svg?version=0.1").await?.text().await?;
+            assert!(svg.contains("<title>docs: passing</title>"));
+            assert!(svg.contains(GREEN));
+
+            // the latest documented version, not the latest version
+            let svg = web.get("/foo/badge.svg?kind=version").await?.text().await?;
+            assert!(svg.contains("<title>docs.rs: 0.1.0</title>"));
+
+            let svg = web.get("/foo/badge.svg?kind=build").await?.text().await?;
+            assert!(svg.contains("<title>docs build: failing</title>"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn build_badge_shows_latest_build() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .builds(vec![
+                    FakeBuild::default(),
+                    FakeBuild::default().build_status(BuildStatus::InProgress),
+                ])
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let svg = web.get("/foo/badge.svg").await?.text().await?;
+            assert!(svg.contains("<title>docs: passing</title>"));
+            let svg = web.get("/foo/badge.svg?kind=build").await?.text().await?;
+            assert!(svg.contains("<title>docs build: building</title>"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn coverage_badge_without_coverage() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let svg = env
+                .web_app()
+                .await
+                .get("/foo/badge.svg?kind=coverage")
+                .await?
+                .text()
+                .await?;
+            assert!(svg.contains("<title>doc coverage: unknown</title>"));
+
+            Ok(())
+        })
+    }
+
+    #[test_case("/unknown/badge.svg")]
+    #[test_case("/foo/badge.svg?version=0.2")]
+    fn not_found(path: &str) {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let response = env.web_app().await.get(path).await?;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert_eq!(response.headers()["content-type"], "image/svg+xml");
+            response.assert_cache_control(CachePolicy::NoCaching, &env.config());
+            assert!(
+                response
+                    .text()
+                    .await?
+                    .contains("<title>docs: not found</title>")
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let svg = Badge::new("docs", "<b>", GREY).render().unwrap();
+        assert!(svg.contains("<title>docs: &#60;b&#62;</title>"));
+        assert!(!svg.contains("<b>"));
+    }
+
+    #[test]
+    fn text_widths() {
+        assert_eq!(text_width(""), 0);
+        assert!(text_width("docs") < text_width("docs build"));
+        assert!(text_width("illi") < text_width("mmmm"));
+    }
+}
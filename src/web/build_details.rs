@@ -1,9 +1,14 @@
 use crate::{
     AsyncStorage, Config,
-    db::{BuildId, types::BuildStatus},
+    db::{
+        BuildId,
+        types::{BuildStatus, LockfileSource},
+    },
     impl_axum_webpage,
+    storage::{BUILD_ARTIFACTS, CARGO_METADATA_ARTIFACT, build_artifact_path},
     web::{
         MetaData,
+        cache::CachePolicy,
         error::{AxumNope, AxumResult},
         extractors::{DbConnection, Path},
         file::File,
@@ -13,7 +18,11 @@ use crate::{
 };
 use anyhow::Context as _;
 use askama::Template;
-use axum::{extract::Extension, response::IntoResponse};
+use axum::{
+    extract::Extension,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::IntoResponse,
+};
 use chrono::{DateTime, Utc};
 use futures_util::TryStreamExt;
 use semver::Version;
@@ -29,6 +38,9 @@ pub(crate) struct BuildDetails {
     build_time: Option<DateTime<Utc>>,
     output: String,
     errors: Option<String>,
+    pinned_toolchain: Option<String>,
+    /// Only set for builds that stored their `Cargo.lock` and `cargo metadata` output.
+    lockfile_source: Option<LockfileSource>,
 }
 
 #[derive(Template)]
@@ -78,7 +90,9 @@ pub(crate) async fn build_details_handler(
              COALESCE(builds.build_finished, builds.build_started) as build_time,
              builds.output,
              builds.errors,
-             releases.default_target
+             builds.pinned_toolchain,
+             builds.lockfile_source as "lockfile_source: LockfileSource",
+             COALESCE(builds.default_target, releases.default_target) as default_target
          FROM builds
          INNER JOIN releases ON releases.id = builds.rid
          INNER JOIN crates ON releases.crate_id = crates.id
@@ -124,9 +138,8 @@ pub(crate) async fn build_details_handler(
                 None
             }
         } else {
-            // this can only happen when `releases.default_target` is NULL,
-            // which is the case for in-progress builds or builds which errored
-            // before we could determine the target.
+            // this can only happen when we don't know the default target,
+            // which is the case for builds which errored before we could determine it.
             // For the "error" case we show `row.errors`, which should contain what we need to see.
             None
         };
@@ -151,6 +164,8 @@ pub(crate) async fn build_details_handler(
             build_time: row.build_time,
             output,
             errors: row.errors,
+            pinned_toolchain: row.pinned_toolchain,
+            lockfile_source: row.lockfile_source,
         },
         all_log_filenames,
         current_filename,
@@ -158,8 +173,77 @@ pub(crate) async fn build_details_handler(
     .into_response())
 }
 
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct BuildArtifactParams {
+    pub(crate) name: String,
+    pub(crate) version: Version,
+    pub(crate) id: String,
+    pub(crate) filename: String,
+}
+
+/// Downloads a file we stored for a build, like the `Cargo.lock` it used.
+pub(crate) async fn build_artifact_handler(
+    Path(params): Path<BuildArtifactParams>,
+    mut conn: DbConnection,
+    Extension(config): Extension<Arc<Config>>,
+    Extension(storage): Extension<Arc<AsyncStorage>>,
+) -> AxumResult<impl IntoResponse> {
+    let id = params
+        .id
+        .parse()
+        .map(BuildId)
+        .map_err(|_| AxumNope::BuildNotFound)?;
+    if !BUILD_ARTIFACTS.contains(&params.filename.as_str()) {
+        return Err(AxumNope::ResourceNotFound);
+    }
+
+    sqlx::query_scalar!(
+        "SELECT builds.id
+         FROM builds
+         INNER JOIN releases ON releases.id = builds.rid
+         INNER JOIN crates ON releases.crate_id = crates.id
+         WHERE builds.id = $1 AND crates.name = $2 AND releases.version = $3",
+        id.0,
+        params.name,
+        params.version.to_string(),
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AxumNope::BuildNotFound)?;
+
+    let file = File::from_path(
+        &storage,
+        &build_artifact_path(id, &params.filename),
+        &config,
+    )
+    .await?;
+
+    let content_type = if params.filename == CARGO_METADATA_ARTIFACT {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+    Ok((
+        Extension(CachePolicy::NoCaching),
+        [
+            (CONTENT_TYPE, content_type.to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", params.filename),
+            ),
+        ],
+        file.0.content,
+    )
+        .into_response())
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::db::{
+        initialize_build, initialize_crate, initialize_release, set_build_default_target,
+        set_build_lockfile_source, set_build_pinned_toolchain, types::LockfileSource,
+    };
+    use crate::storage::{CARGO_LOCK_ARTIFACT, build_artifact_path};
     use crate::test::{
         AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper,
         fake_release_that_failed_before_build,
@@ -249,6 +333,147 @@ mod tests {
         });
     }
 
+    #[test]
+    fn live_build_log() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let crate_id = initialize_crate(&mut conn, "foo").await?;
+            let release_id = initialize_release(&mut conn, crate_id, "0.1.0").await?;
+            let build_id = initialize_build(&mut conn, release_id).await?;
+
+            // the live logs uploaded by the builder, while the release doesn't know its
+            // default target yet.
+            set_build_default_target(&mut conn, build_id, "x86_64-unknown-linux-gnu").await?;
+            let storage = env.async_storage().await;
+            storage
+                .store_one(
+                    format!("build-logs/{build_id}/aarch64-unknown-linux-gnu.txt"),
+                    "[INFO] other target",
+                )
+                .await?;
+            storage
+                .store_one(
+                    format!("build-logs/{build_id}/x86_64-unknown-linux-gnu.txt"),
+                    "[INFO] still building",
+                )
+                .await?;
+
+            let page = kuchikiki::parse_html().one(
+                env.web_app()
+                    .await
+                    .get(&format!("/crate/foo/0.1.0/builds/{build_id}"))
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?,
+            );
+
+            let log = page.select("pre").unwrap().next().unwrap().text_contents();
+            assert!(log.contains("[INFO] still building"), "{}", log);
+
+            let details = page.select_first(".build-details").unwrap();
+            assert!(details.attributes.borrow().contains("data-in-progress"));
+            assert!(page.select("script").unwrap().any(|script| {
+                script
+                    .attributes
+                    .borrow()
+                    .get("src")
+                    .is_some_and(|src| src.starts_with("/-/static/build-log.js"))
+            }));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn pinned_toolchain() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let crate_id = initialize_crate(&mut conn, "foo").await?;
+            let release_id = initialize_release(&mut conn, crate_id, "0.1.0").await?;
+            let build_id = initialize_build(&mut conn, release_id).await?;
+
+            let web = env.web_app().await;
+            let url = format!("/crate/foo/0.1.0/builds/{build_id}");
+            let page = kuchikiki::parse_html().one(web.get(&url).await?.text().await?);
+            assert!(page.select_first("[data-pinned-toolchain]").is_err());
+
+            set_build_pinned_toolchain(&mut conn, build_id, "nightly-2025-10-01").await?;
+
+            let page = kuchikiki::parse_html().one(web.get(&url).await?.text().await?);
+            let info = page.select_first("[data-pinned-toolchain]").unwrap();
+            assert!(
+                info.text_contents()
+                    .contains("pinned to the nightly-2025-10-01 toolchain"),
+                "{}",
+                info.text_contents()
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn build_artifacts() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let crate_id = initialize_crate(&mut conn, "foo").await?;
+            let release_id = initialize_release(&mut conn, crate_id, "0.1.0").await?;
+            let build_id = initialize_build(&mut conn, release_id).await?;
+
+            let web = env.web_app().await;
+            let url = format!("/crate/foo/0.1.0/builds/{build_id}");
+            let page = kuchikiki::parse_html().one(web.get(&url).await?.text().await?);
+            assert!(page.select_first("[data-lockfile]").is_err());
+
+            env.async_storage()
+                .await
+                .store_one(
+                    build_artifact_path(build_id, CARGO_LOCK_ARTIFACT),
+                    "# This file is automatically @generated by Cargo.",
+                )
+                .await?;
+            set_build_lockfile_source(&mut conn, build_id, LockfileSource::Regenerated).await?;
+
+            let page = kuchikiki::parse_html().one(web.get(&url).await?.text().await?);
+            let info = page.select_first("[data-lockfile]").unwrap();
+            assert!(info.text_contents().contains("it was regenerated"));
+            let links: Vec<_> = info
+                .as_node()
+                .select("a")
+                .unwrap()
+                .map(|link| link.attributes.borrow().get("href").unwrap().to_owned())
+                .collect();
+            assert_eq!(
+                links,
+                [
+                    format!("{url}/artifacts/Cargo.lock"),
+                    format!("{url}/artifacts/cargo-metadata.json"),
+                ]
+            );
+
+            let res = web.assert_success(&links[0]).await?;
+            assert_eq!(
+                res.headers()["content-disposition"],
+                "attachment; filename=\"Cargo.lock\""
+            );
+            assert_eq!(
+                res.text().await?,
+                "# This file is automatically @generated by Cargo."
+            );
+
+            web.assert_not_found(&links[1]).await?;
+            web.assert_not_found(&format!("{url}/artifacts/Cargo.toml"))
+                .await?;
+            web.assert_not_found(&format!(
+                "/crate/bar/0.1.0/builds/{build_id}/artifacts/Cargo.lock"
+            ))
+            .await?;
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn db_build_logs() {
         async_wrapper(|env| async move {
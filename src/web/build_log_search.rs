@@ -0,0 +1,188 @@
+//! Admin endpoint to search the logs of failed builds, see [`crate::build_logs`].
+
+use crate::{
+    Config,
+    build_logs::{self, BuildLogMatch, BuildLogQuery},
+    web::{
+        auth::{Principal, unauthorized},
+        cache::CachePolicy,
+        error::{AxumNope, JsonAxumNope, JsonAxumResult},
+        extractors::DbConnection,
+    },
+};
+use axum::{
+    Json,
+    extract::{Extension, Query},
+    response::{IntoResponse, Response as AxumResponse},
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const DEFAULT_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct BuildLogSearchParams {
+    q: String,
+    #[serde(rename = "crate")]
+    crate_name: Option<String>,
+    toolchain: Option<String>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct BuildLogSearchResult {
+    #[serde(flatten)]
+    log: BuildLogMatch,
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BuildLogSearchResults {
+    results: Vec<BuildLogSearchResult>,
+}
+
+/// Searches the logs of failed builds, only for the principals in `admin_principals`.
+pub(crate) async fn build_log_search_handler(
+    principal: Option<Extension<Principal>>,
+    Extension(config): Extension<Arc<Config>>,
+    Query(params): Query<BuildLogSearchParams>,
+    mut conn: DbConnection,
+) -> JsonAxumResult<AxumResponse> {
+    let Some(Extension(principal)) = principal else {
+        return Ok(unauthorized());
+    };
+    if !principal.is_admin(&config) {
+        return Err(JsonAxumNope(AxumNope::Unauthorized(
+            "admin access required",
+        )));
+    }
+
+    let query = BuildLogQuery {
+        text: params.q,
+        crate_name: params.crate_name.filter(|name| !name.is_empty()),
+        toolchain: params.toolchain.filter(|toolchain| !toolchain.is_empty()),
+        since: params.since,
+        until: params.until,
+        limit: params.limit.unwrap_or(DEFAULT_LIMIT),
+    };
+    if let Err(err) = build_logs::validate_query(&query) {
+        return Err(JsonAxumNope(AxumNope::BadRequest(err)));
+    }
+
+    let results = build_logs::search_build_logs(&mut conn, &query)
+        .await
+        .map_err(|err| JsonAxumNope(AxumNope::InternalError(err)))?
+        .into_iter()
+        .map(|log| BuildLogSearchResult {
+            url: log.build_details_path(),
+            log,
+        })
+        .collect();
+
+    Ok((
+        Extension(CachePolicy::NoStoreMustRevalidate),
+        Json(BuildLogSearchResults { results }),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{BuildId, types::BuildStatus};
+    use crate::test::{AxumResponseTestExt, FakeBuild, async_wrapper};
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use tower::ServiceExt as _;
+
+    async fn search(
+        web: &axum::Router,
+        query: &str,
+        token: Option<&str>,
+    ) -> anyhow::Result<AxumResponse> {
+        let mut request = Request::builder().uri(format!("/_/build-logs/search?{query}"));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(web.clone().oneshot(request.body(Body::empty())?).await?)
+    }
+
+    #[test]
+    fn search_build_logs() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token,bob:bob-token".parse().unwrap();
+                config.admin_principals = vec!["alice".into()];
+            });
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .builds(vec![
+                    FakeBuild::default()
+                        .build_status(BuildStatus::Failure)
+                        .s3_build_log("[INFO] error[E0658]: unstable"),
+                ])
+                .create()
+                .await?;
+            let mut conn = env.async_db().await.async_conn().await;
+            let build_id: BuildId =
+                sqlx::query_scalar!(r#"SELECT id as "id: BuildId" FROM builds"#)
+                    .fetch_one(&mut *conn)
+                    .await?;
+            build_logs::index_build_log(
+                &mut conn,
+                build_id,
+                "x86_64-unknown-linux-gnu",
+                "[INFO] error[E0658]: unstable",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+
+            let response = search(&web, "q=E0658", None).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert!(response.headers().contains_key("www-authenticate"));
+
+            let response = search(&web, "q=E0658", Some("bob-token")).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+            let response = search(&web, "q=%5B%5D", Some("alice-token")).await?;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let response =
+                search(&web, "q=error%5BE0658%5D&crate=foo", Some("alice-token")).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+            let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+            assert_eq!(
+                value,
+                serde_json::json!({
+                    "results": [{
+                        "name": "foo",
+                        "version": "0.1.0",
+                        "build_id": build_id.0,
+                        "target": "x86_64-unknown-linux-gnu",
+                        "rustc_version": "rustc 2.0.0-nightly (000000000 1970-01-01)",
+                        "build_finished": value["results"][0]["build_finished"],
+                        "line": "[INFO] error[E0658]: unstable",
+                        "url": format!(
+                            "/crate/foo/0.1.0/builds/{build_id}/x86_64-unknown-linux-gnu.txt"
+                        ),
+                    }],
+                })
+            );
+
+            let response = search(&web, "q=E0658&crate=bar", Some("alice-token")).await?;
+            let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+            assert_eq!(value["results"], serde_json::json!([]));
+
+            Ok(())
+        })
+    }
+}
@@ -1,6 +1,6 @@
 use super::{
     cache::CachePolicy,
-    error::{AxumNope, JsonAxumNope, JsonAxumResult},
+    error::{AxumNope, JsonAxumNope, JsonAxumResult, JsonError},
     headers::CanonicalUrl,
 };
 use crate::{
@@ -31,12 +31,12 @@ use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct Build {
-    id: BuildId,
-    rustc_version: Option<String>,
-    docsrs_version: Option<String>,
-    build_status: BuildStatus,
-    build_time: Option<DateTime<Utc>>,
-    errors: Option<String>,
+    pub(super) id: BuildId,
+    pub(super) rustc_version: Option<String>,
+    pub(super) docsrs_version: Option<String>,
+    pub(super) build_status: BuildStatus,
+    pub(super) build_time: Option<DateTime<Utc>>,
+    pub(super) errors: Option<String>,
 }
 
 #[derive(Template)]
@@ -129,6 +129,23 @@ async fn build_trigger_check(
 // FUTURE: move to a crate-global enum with all special priorities?
 const TRIGGERED_REBUILD_PRIORITY: i32 = 5;
 
+/// Queues a rebuild of a release, used by crates.io.
+#[utoipa::path(
+    post,
+    path = "/crate/{name}/{version}/rebuild",
+    tag = "builds",
+    params(
+        ("name" = String, Path, description = "The name of the crate."),
+        ("version" = String, Path, description = "The exact version of the release."),
+    ),
+    security(("crates_io_token" = [])),
+    responses(
+        (status = CREATED, description = "the release was queued for a rebuild"),
+        (status = BAD_REQUEST, description = "the release is already queued", body = JsonError),
+        (status = UNAUTHORIZED, body = JsonError),
+        (status = NOT_FOUND, body = JsonError),
+    )
+)]
 pub(crate) async fn build_trigger_rebuild_handler(
     Path((name, version)): Path<(String, Version)>,
     mut conn: DbConnection,
@@ -171,7 +188,7 @@ pub(crate) async fn build_trigger_rebuild_handler(
     Ok((StatusCode::CREATED, Json(serde_json::json!({}))))
 }
 
-async fn get_builds(
+pub(super) async fn get_builds(
     conn: &mut sqlx::PgConnection,
     name: &str,
     version: &Version,
@@ -493,6 +510,7 @@ mod tests {
                 memory: Some(6 * 1024 * 1024 * 1024),
                 targets: Some(1),
                 timeout: Some(std::time::Duration::from_secs(2 * 60 * 60)),
+                toolchain: None,
             };
             Overrides::save(&mut conn, "foo", limits).await?;
 
@@ -3,7 +3,7 @@ use crate::db::{BuildId, ReleaseId};
 use crate::registry_api::OwnerKind;
 use crate::utils::{get_correct_docsrs_style_file, report_error};
 use crate::{
-    AsyncStorage,
+    AsyncBuildQueue, AsyncStorage,
     db::{CrateId, types::BuildStatus},
     impl_axum_webpage,
     storage::PathNotFoundError,
@@ -40,21 +40,21 @@ pub(crate) struct CrateDetails {
     pub(crate) dependencies: Option<Value>,
     readme: Option<String>,
     rustdoc: Option<String>, // this is description_long in database
-    release_time: Option<DateTime<Utc>>,
-    build_status: BuildStatus,
+    pub(crate) release_time: Option<DateTime<Utc>>,
+    pub(crate) build_status: BuildStatus,
     pub latest_build_id: Option<BuildId>,
     last_successful_build: Option<String>,
     pub rustdoc_status: Option<bool>,
     pub archive_storage: bool,
     pub repository_url: Option<String>,
     pub homepage_url: Option<String>,
-    keywords: Option<Value>,
+    pub(crate) keywords: Option<Value>,
     have_examples: Option<bool>, // need to check this manually
     pub target_name: Option<String>,
     releases: Vec<Release>,
     repository_metadata: Option<RepositoryMetadata>,
     pub(crate) metadata: MetaData,
-    is_library: Option<bool>,
+    pub(crate) is_library: Option<bool>,
     pub(crate) license: Option<String>,
     pub(crate) parsed_license: Option<Vec<super::licenses::LicenseSegment>>,
     pub(crate) documentation_url: Option<String>,
@@ -450,6 +450,8 @@ struct CrateDetailsPage {
     rustdoc: Option<String>, // this is description_long in database
     source_size: Option<i64>,
     documentation_size: Option<i64>,
+    /// when we expect the build to be finished, for releases that are being built
+    build_finish_estimate: Option<DateTime<Utc>>,
 }
 
 impl CrateDetailsPage {
@@ -474,6 +476,7 @@ pub(crate) struct CrateDetailHandlerParams {
 pub(crate) async fn crate_details_handler(
     Path(params): Path<CrateDetailHandlerParams>,
     Extension(storage): Extension<Arc<AsyncStorage>>,
+    Extension(build_queue): Extension<Arc<AsyncBuildQueue>>,
     mut conn: DbConnection,
 ) -> AxumResult<AxumResponse> {
     let req_version = params.version.ok_or_else(|| {
@@ -503,6 +506,14 @@ pub(crate) async fn crate_details_handler(
         Err(e) => warn!("error fetching readme: {:?}", &e),
     }
 
+    let build_finish_estimate = if details.build_status == BuildStatus::InProgress {
+        build_queue
+            .estimate_finish_time(&details.name, &details.version.to_string())
+            .await?
+    } else {
+        None
+    };
+
     let CrateDetails {
         version,
         name,
@@ -552,6 +563,7 @@ pub(crate) async fn crate_details_handler(
         rustdoc,
         source_size,
         documentation_size,
+        build_finish_estimate,
     }
     .into_response();
     res.extensions_mut()
@@ -1496,6 +1508,46 @@ mod tests {
         });
     }
 
+    #[test]
+    fn documented_binary() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("bat")
+                .version("0.2.0")
+                .documented_binary()
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let page =
+                kuchikiki::parse_html().one(web.get("/crate/bat/0.2.0").await?.text().await?);
+            assert!(page.select_first("#main .warning").is_err());
+            assert!(
+                page.select_first("#main .info")
+                    .unwrap()
+                    .text_contents()
+                    .contains("is not a library, its documentation is of its binaries")
+            );
+            let link = page
+                .select_first("a.pure-menu-link[href='/crate/bat/0.2.0']")
+                .unwrap();
+            assert!(
+                !link
+                    .attributes
+                    .borrow()
+                    .get("class")
+                    .unwrap()
+                    .contains("warn")
+            );
+
+            web.assert_redirect("/bat/0.2.0", "/bat/0.2.0/bat/").await?;
+            web.assert_success("/bat/0.2.0/bat/").await?;
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn releases_dropdowns_show_in_progress() {
         async_wrapper(|env| async move {
@@ -2386,6 +2438,34 @@ path = "src/lib.rs"
         })
     }
 
+    #[test]
+    fn test_build_finish_estimate_in_progress() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("dummy")
+                .version("0.1.0")
+                .builds(vec![
+                    FakeBuild::default().build_status(BuildStatus::InProgress),
+                ])
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            let page =
+                kuchikiki::parse_html().one(web.get("/crate/dummy/0.1.0").await?.text().await?);
+            let info = page
+                .select(".info")
+                .unwrap()
+                .map(|el| el.text_contents())
+                .find(|text| text.contains("Build is in progress"))
+                .expect("missing in-progress info");
+            assert!(info.contains("expected around"));
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_sizes_display() {
         async_wrapper(|env| async move {
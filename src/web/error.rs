@@ -9,8 +9,10 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response as AxumResponse},
 };
+use serde::Serialize;
 use std::borrow::Cow;
 use tracing::error;
+use utoipa::ToSchema;
 
 use super::AxumErrorPage;
 
@@ -186,6 +188,21 @@ impl IntoResponse for AxumNope {
 /// `AxumNope` but generating error responses in JSON (for API).
 pub(crate) struct JsonAxumNope(pub AxumNope);
 
+/// The body of error responses in JSON.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct JsonError {
+    title: &'static str,
+    #[schema(value_type = String)]
+    message: Cow<'static, str>,
+}
+
+impl From<AxumNope> for JsonError {
+    fn from(err: AxumNope) -> Self {
+        let ErrorInfo { title, message, .. } = err.into_error_info();
+        JsonError { title, message }
+    }
+}
+
 impl IntoResponse for JsonAxumNope {
     fn into_response(self) -> AxumResponse {
         match self.0 {
@@ -201,14 +218,7 @@ impl IntoResponse for JsonAxumNope {
                     message,
                     status,
                 } = self.0.into_error_info();
-                (
-                    status,
-                    Json(serde_json::json!({
-                        "title": title,
-                        "message": message,
-                    })),
-                )
-                    .into_response()
+                (status, Json(JsonError { title, message })).into_response()
             }
         }
     }
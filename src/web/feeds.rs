@@ -0,0 +1,411 @@
+//! Atom feeds of what happened to a crate, or to all crates of an owner: new releases,
+//! finished builds and yanks.
+
+use crate::{
+    db::{access_rules, types::BuildStatus},
+    impl_axum_webpage,
+    web::{
+        auth::Principal,
+        cache::CachePolicy,
+        error::{AxumNope, AxumResult},
+        extractors::{DbConnection, Path},
+    },
+};
+use askama::Template;
+use axum::{extract::Extension, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use futures_util::stream::TryStreamExt;
+
+/// How many entries a feed contains, newest first.
+const ENTRIES_IN_FEED: i64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FeedEntry {
+    id: String,
+    title: String,
+    link: String,
+    updated: DateTime<Utc>,
+    summary: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "releases/events_feed.xml")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EventFeed {
+    title: String,
+    /// the path of the feed itself
+    self_link: String,
+    /// the path of the page the feed belongs to
+    link: String,
+    entries: Vec<FeedEntry>,
+    /// whether the entries depend on who is asking, because some crates are private
+    private: bool,
+}
+
+impl_axum_webpage! {
+    EventFeed,
+    content_type = "application/atom+xml",
+    cache_policy = |feed| if feed.private {
+        CachePolicy::NoStoreMustRevalidate
+    } else {
+        CachePolicy::ShortInCdnAndBrowser
+    },
+}
+
+impl EventFeed {
+    fn updated(&self) -> Option<DateTime<Utc>> {
+        self.entries.first().map(|entry| entry.updated)
+    }
+}
+
+/// Loads the newest events of the given crates.
+async fn feed_entries(
+    conn: &mut sqlx::PgConnection,
+    crate_ids: &[i32],
+) -> AxumResult<Vec<FeedEntry>> {
+    Ok(sqlx::query!(
+        r#"SELECT
+            events.name as "name!",
+            events.version as "version!",
+            events.kind as "kind!",
+            events.time as "time!",
+            events.build_id,
+            events.build_status as "build_status: BuildStatus",
+            events.description,
+            events.rustdoc_status,
+            events.target_name
+         FROM (
+            SELECT
+                crates.name, releases.version, 'release' AS kind,
+                releases.release_time AS time,
+                NULL::INT AS build_id, NULL::build_status AS build_status,
+                releases.description, releases.rustdoc_status, releases.target_name
+            FROM releases
+            INNER JOIN crates ON crates.id = releases.crate_id
+            WHERE crates.id = ANY($1) AND releases.release_time IS NOT NULL
+
+            UNION ALL
+
+            SELECT
+                crates.name, releases.version, 'build' AS kind,
+                builds.build_finished AS time,
+                builds.id AS build_id, builds.build_status,
+                NULL, releases.rustdoc_status, releases.target_name
+            FROM builds
+            INNER JOIN releases ON releases.id = builds.rid
+            INNER JOIN crates ON crates.id = releases.crate_id
+            WHERE
+                crates.id = ANY($1) AND
+                builds.build_status != 'in_progress' AND
+                builds.build_finished IS NOT NULL
+
+            UNION ALL
+
+            SELECT
+                crates.name, releases.version, 'yank' AS kind,
+                releases.yanked_at AS time,
+                NULL, NULL,
+                NULL, releases.rustdoc_status, releases.target_name
+            FROM releases
+            INNER JOIN crates ON crates.id = releases.crate_id
+            WHERE crates.id = ANY($1) AND releases.yanked AND releases.yanked_at IS NOT NULL
+         ) AS events
+         ORDER BY events.time DESC, events.build_id DESC NULLS LAST
+         LIMIT $2"#,
+        crate_ids,
+        ENTRIES_IN_FEED,
+    )
+    .fetch(&mut *conn)
+    .map_ok(|row| {
+        let name = row.name;
+        let version = row.version;
+        let release_link = match (row.rustdoc_status, row.target_name) {
+            (Some(true), Some(target_name)) => format!("/{name}/{version}/{target_name}/"),
+            _ => format!("/crate/{name}/{version}"),
+        };
+
+        match (row.kind.as_str(), row.build_id) {
+            ("build", Some(build_id)) => FeedEntry {
+                id: format!("urn:docs-rs:{name}:{version}:build:{build_id}"),
+                title: if row.build_status == Some(BuildStatus::Success) {
+                    format!("{name}-{version}: documentation built")
+                } else {
+                    format!("{name}-{version}: build failed")
+                },
+                link: format!("/crate/{name}/{version}/builds/{build_id}"),
+                updated: row.time,
+                summary: None,
+            },
+            ("yank", _) => FeedEntry {
+                id: format!("urn:docs-rs:{name}:{version}:yank:{}", row.time.timestamp()),
+                title: format!("{name}-{version} yanked"),
+                link: format!("/crate/{name}/{version}"),
+                updated: row.time,
+                summary: None,
+            },
+            _ => FeedEntry {
+                // the same id as in the global feed
+                id: format!("urn:docs-rs:{name}:{version}"),
+                title: format!("{name}-{version} released"),
+                link: release_link,
+                updated: row.time,
+                summary: row.description,
+            },
+        }
+    })
+    .try_collect()
+    .await?)
+}
+
+pub(crate) async fn crate_feed_handler(
+    Path(name): Path<String>,
+    mut conn: DbConnection,
+) -> AxumResult<impl IntoResponse> {
+    let krate = sqlx::query!(
+        "SELECT id, name
+         FROM crates
+         WHERE normalize_crate_name(name) = normalize_crate_name($1)",
+        name,
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AxumNope::CrateNotFound)?;
+
+    Ok(EventFeed {
+        title: format!("{} on docs.rs", krate.name),
+        self_link: format!("/crate/{}/feed", krate.name),
+        link: format!("/crate/{}", krate.name),
+        entries: feed_entries(&mut conn, &[krate.id]).await?,
+        // the auth middleware takes care of private crates, they have a name in the path.
+        private: false,
+    })
+}
+
+pub(crate) async fn owner_feed_handler(
+    Path(owner): Path<String>,
+    principal: Option<Extension<Principal>>,
+    mut conn: DbConnection,
+) -> AxumResult<impl IntoResponse> {
+    let login = owner.strip_prefix('@').unwrap_or(&owner);
+
+    let crates: Vec<_> = sqlx::query!(
+        "SELECT crates.id, crates.name
+         FROM owners
+         INNER JOIN owner_rels ON owner_rels.oid = owners.id
+         INNER JOIN crates ON crates.id = owner_rels.cid
+         WHERE owners.login = $1",
+        login,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    if crates.is_empty() {
+        return Err(AxumNope::OwnerNotFound);
+    }
+
+    // only private crates the principal may see end up in the feed.
+    let names: Vec<&str> = crates.iter().map(|krate| krate.name.as_str()).collect();
+    let allowed_principals = access_rules::allowed_principals_for_crates(&mut conn, &names).await?;
+    let crate_ids: Vec<i32> = crates
+        .iter()
+        .filter(|krate| match allowed_principals.get(&krate.name) {
+            None => true,
+            Some(allowed) => principal
+                .as_ref()
+                .is_some_and(|Extension(principal)| principal.is_allowed(allowed)),
+        })
+        .map(|krate| krate.id)
+        .collect();
+
+    Ok(EventFeed {
+        title: format!("Crates of {login} on docs.rs"),
+        self_link: format!("/releases/{owner}/feed"),
+        link: format!("/releases/{owner}"),
+        entries: feed_entries(&mut conn, &crate_ids).await?,
+        private: !allowed_principals.is_empty(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::access_rules::{AccessTarget, add_access_rule},
+        registry_api::{CrateOwner, OwnerKind},
+        test::{AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper},
+        web::cache::CachePolicy,
+    };
+    use axum::{body::Body, http::Request};
+    use reqwest::StatusCode;
+    use tower::ServiceExt as _;
+
+    /// The titles of the entries in a feed, newest first.
+    fn entry_titles(feed: &str) -> Vec<&str> {
+        feed.split("<entry>")
+            .skip(1)
+            .map(|entry| {
+                let start = entry.find("<title>").unwrap() + "<title>".len();
+                let end = entry.find("</title>").unwrap();
+                &entry[start..end]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crate_feed() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .description("a crate & more")
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.2.0")
+                .builds(vec![
+                    FakeBuild::default().successful(false),
+                    FakeBuild::default(),
+                ])
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("bar")
+                .version("1.0.0")
+                .create()
+                .await?;
+            env.async_build_queue()
+                .await
+                .set_yanked("foo", "0.1.0", true)
+                .await?;
+
+            let response = env.web_app().await.get("/crate/foo/feed").await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "application/atom+xml");
+            response.assert_cache_control(CachePolicy::ShortInCdnAndBrowser, &env.config());
+
+            let feed = response.text().await?;
+            assert!(feed.contains("<title>foo on docs.rs</title>"));
+            assert!(feed.contains("a crate &#38; more"));
+            assert!(!feed.contains("bar-1.0.0"));
+
+            let titles = entry_titles(&feed);
+            assert_eq!(titles.len(), 6);
+            // yanking happened last
+            assert_eq!(titles[0], "foo-0.1.0 yanked");
+            for title in [
+                "foo-0.1.0 released",
+                "foo-0.1.0: documentation built",
+                "foo-0.2.0 released",
+                "foo-0.2.0: documentation built",
+                "foo-0.2.0: build failed",
+            ] {
+                assert!(titles.contains(&title), "{title} missing in {titles:?}");
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn crate_feed_not_found() {
+        async_wrapper(|env| async move {
+            env.web_app()
+                .await
+                .assert_not_found("/crate/unknown/feed")
+                .await?;
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unyanked_release_has_no_yank_entry() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+            let queue = env.async_build_queue().await;
+            queue.set_yanked("foo", "0.1.0", true).await?;
+            queue.set_yanked("foo", "0.1.0", false).await?;
+
+            let feed = env
+                .web_app()
+                .await
+                .get("/crate/foo/feed")
+                .await?
+                .text()
+                .await?;
+            assert!(!entry_titles(&feed).contains(&"foo-0.1.0 yanked"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn owner_feed() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            let owner = CrateOwner {
+                login: "someone".into(),
+                avatar: "".into(),
+                kind: OwnerKind::User,
+            };
+            for name in ["foo", "private"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .add_owner(owner.clone())
+                    .create()
+                    .await?;
+            }
+            env.fake_release()
+                .await
+                .name("bar")
+                .version("1.0.0")
+                .create()
+                .await?;
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private"),
+                "alice",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/releases/@someone/feed").await?;
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+            let feed = response.text().await?;
+            assert!(feed.contains("<title>Crates of someone on docs.rs</title>"));
+            let titles = entry_titles(&feed);
+            assert!(titles.contains(&"foo-0.1.0 released"));
+            assert!(!titles.iter().any(|title| title.starts_with("private")));
+            assert!(!titles.iter().any(|title| title.starts_with("bar")));
+
+            let response = web
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/releases/@someone/feed")
+                        .header("Authorization", "Bearer alice-token")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            let feed = response.text().await?;
+            assert!(entry_titles(&feed).contains(&"private-0.1.0 released"));
+
+            web.assert_not_found("/releases/@unknown/feed").await?;
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,183 @@
+//! URLs of crates from the alternative registries we host.
+//!
+//! Their crates are stored as `{registry}/{name}`, and served at `/{registry}/{name}/...`
+//! and `/crate/{registry}/{name}/...`. Before routing, we join the registry and the name
+//! into a single path segment, so the handlers see the registry-qualified crate name.
+
+use crate::db::{Pool, registries};
+use axum::{
+    extract::{Request, State},
+    http::Uri,
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tracing::warn;
+use url::form_urlencoded;
+
+/// How long we cache the registry names.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The registry names, and when we loaded them.
+type CachedNames = Option<(Instant, Arc<HashSet<String>>)>;
+
+#[derive(Clone)]
+pub(crate) struct HostedRegistries {
+    pool: Pool,
+    names: Arc<RwLock<CachedNames>>,
+}
+
+impl HostedRegistries {
+    pub(crate) fn new(pool: Pool) -> Self {
+        Self {
+            pool,
+            names: Arc::default(),
+        }
+    }
+
+    async fn names(&self) -> anyhow::Result<Arc<HashSet<String>>> {
+        if let Some((loaded_at, names)) = &*self.names.read().unwrap()
+            && loaded_at.elapsed() < REFRESH_INTERVAL
+        {
+            return Ok(names.clone());
+        }
+
+        let mut conn = self.pool.get_async().await?;
+        let names: Arc<HashSet<String>> = Arc::new(
+            registries::list_registries(&mut conn)
+                .await?
+                .into_iter()
+                .map(|registry| registry.name)
+                .collect(),
+        );
+        *self.names.write().unwrap() = Some((Instant::now(), names.clone()));
+        Ok(names)
+    }
+}
+
+pub(crate) async fn rewrite_hosted_registry_urls(
+    State(registries): State<HostedRegistries>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match registries.names().await {
+        Ok(names) => {
+            if let Some(uri) = rewrite_uri(request.uri(), |name| names.contains(name)) {
+                *request.uri_mut() = uri;
+            }
+        }
+        // without the names, we can still serve all other crates.
+        Err(err) => warn!(?err, "failed to load hosted registries"),
+    }
+
+    next.run(request).await
+}
+
+/// Returns the internal URI for URLs of hosted registries.
+///
+/// * `/{registry}/{name}/...` => `/{registry}%2F{name}/...`
+/// * `/crate/{registry}/{name}/...` => `/crate/{registry}%2F{name}/...`
+/// * `/{registry}` and `/{registry}/` => the crate search of the registry
+fn rewrite_uri(uri: &Uri, is_registry: impl Fn(&str) -> bool) -> Option<Uri> {
+    let path = uri.path().strip_prefix('/')?;
+    let (prefix, path) = match path.strip_prefix("crate/") {
+        Some(path) => ("/crate/", path),
+        None => ("/", path),
+    };
+
+    let (registry, rest) = path.split_once('/').unwrap_or((path, ""));
+    if !is_registry(registry) {
+        return None;
+    }
+
+    let new_uri = if rest.is_empty() {
+        if prefix != "/" {
+            return None;
+        }
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.append_pair("registry", registry);
+        for (key, value) in form_urlencoded::parse(uri.query().unwrap_or("").as_bytes()) {
+            query.append_pair(&key, &value);
+        }
+        format!("/releases/search?{}", query.finish())
+    } else {
+        let mut new_uri = format!("{prefix}{registry}%2F{rest}");
+        if let Some(query) = uri.query() {
+            new_uri.push('?');
+            new_uri.push_str(query);
+        }
+        new_uri
+    };
+
+    new_uri.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::registries::add_registry;
+    use crate::test::{AxumResponseTestExt, AxumRouterTestExt, async_wrapper};
+    use test_case::test_case;
+
+    #[test_case("/acme/foo/1.0.0/foo/", Some("/acme%2Ffoo/1.0.0/foo/"))]
+    #[test_case("/acme/foo", Some("/acme%2Ffoo"))]
+    #[test_case(
+        "/acme/foo/latest/foo/?search=x",
+        Some("/acme%2Ffoo/latest/foo/?search=x")
+    )]
+    #[test_case("/crate/acme/foo/1.0.0", Some("/crate/acme%2Ffoo/1.0.0"))]
+    #[test_case("/acme", Some("/releases/search?registry=acme"))]
+    #[test_case("/acme/?query=foo", Some("/releases/search?registry=acme&query=foo"))]
+    #[test_case("/crate/acme", None)]
+    #[test_case("/foo/1.0.0/foo/", None)]
+    #[test_case("/crate/foo/1.0.0", None)]
+    #[test_case("/", None)]
+    fn test_rewrite_uri(uri: &str, expected: Option<&str>) {
+        assert_eq!(
+            rewrite_uri(&uri.parse().unwrap(), |name| name == "acme")
+                .as_ref()
+                .map(Uri::to_string)
+                .as_deref(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_hosted_registry_crate() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+
+            // the same crate name in both registries
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("1.0.0")
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("acme/foo")
+                .version("2.0.0")
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            web.assert_success("/foo/1.0.0/foo/").await?;
+            web.assert_success("/acme/foo/2.0.0/foo/").await?;
+            web.assert_redirect("/acme/foo", "/acme/foo/latest/foo/")
+                .await?;
+            web.assert_not_found("/acme/foo/1.0.0/foo/").await?;
+
+            let page = web.get("/crate/acme/foo/latest").await?;
+            assert!(page.status().is_success());
+            assert!(page.text().await?.contains("2.0.0"));
+
+            Ok(())
+        })
+    }
+}
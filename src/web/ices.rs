@@ -0,0 +1,194 @@
+use crate::{
+    ices::{self, IceReport},
+    impl_axum_webpage,
+    web::{
+        error::AxumResult,
+        extractors::DbConnection,
+        page::templates::{RenderBrands, RenderRegular, RenderSolid},
+    },
+};
+use askama::Template;
+use axum::response::IntoResponse;
+use std::fmt::Write as _;
+use url::form_urlencoded;
+
+/// How many ICEs we show.
+pub(super) const MAX_ICES: i64 = 100;
+
+/// The URL of a new rust-lang/rust issue for an ICE, with everything we know about it.
+pub(super) fn issue_url(ice: &IceReport) -> String {
+    let mut title = format!("ICE: {}", ice.message);
+    if title.len() > 100 {
+        title = title.chars().take(100).collect();
+        title.push('…');
+    }
+
+    let mut body = format!(
+        "docs.rs found this internal compiler error while building the documentation of {} crates",
+        ice.crate_count
+    );
+    match (ice.first_toolchain, ice.last_toolchain) {
+        (Some(first), Some(last)) if first != last => {
+            let _ = write!(body, ", with the nightlies from {first} to {last}");
+        }
+        (Some(first), _) => {
+            let _ = write!(body, ", with the nightly from {first}");
+        }
+        _ => {}
+    }
+    body.push_str(".\n\n");
+    if let Some(location) = &ice.location {
+        let _ = writeln!(body, "Location: `{location}`\n");
+    }
+    let _ = writeln!(body, "```\n{}\n```\n", ice.message);
+    if !ice.query_stack.is_empty() {
+        let _ = writeln!(body, "Query stack: `{}`\n", ice.query_stack.join("` → `"));
+    }
+    body.push_str("Affected crates, with their build logs:\n\n");
+    for krate in ice.crates.iter().take(10) {
+        let _ = writeln!(
+            body,
+            "- [{} {}](https://docs.rs{}) ({})",
+            krate.name,
+            krate.version,
+            krate.build_details_path(),
+            krate.rustc_version,
+        );
+    }
+
+    let query = form_urlencoded::Serializer::new(String::new())
+        .append_pair("labels", "C-bug,I-ICE,T-compiler")
+        .append_pair("title", &title)
+        .append_pair("body", &body)
+        .finish();
+    format!("https://github.com/rust-lang/rust/issues/new?{query}")
+}
+
+#[derive(Template)]
+#[template(path = "releases/ices.html")]
+#[derive(Debug, Clone)]
+struct IcesPage {
+    description: &'static str,
+    ices: Vec<IceReport>,
+}
+
+impl_axum_webpage! { IcesPage }
+
+impl IcesPage {
+    fn issue_url(&self, ice: &IceReport) -> String {
+        issue_url(ice)
+    }
+}
+
+pub(crate) async fn ices_handler(mut conn: DbConnection) -> AxumResult<impl IntoResponse> {
+    Ok(IcesPage {
+        description: "Internal compiler errors found in builds",
+        ices: ices::list_ices(&mut conn, MAX_ICES).await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{BuildId, types::BuildStatus},
+        test::{AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper},
+    };
+    use kuchikiki::traits::TendrilSink;
+
+    #[test]
+    fn shows_ices() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .builds(vec![
+                    FakeBuild::default()
+                        .build_status(BuildStatus::Failure)
+                        .rustc_version("rustc 1.92.0-nightly (abcdef012 2025-10-01)"),
+                ])
+                .create()
+                .await?;
+            let mut conn = env.async_db().await.async_conn().await;
+            let build_id: BuildId =
+                sqlx::query_scalar!(r#"SELECT id as "id: BuildId" FROM builds"#)
+                    .fetch_one(&mut *conn)
+                    .await?;
+            ices::record_ices(
+                &mut conn,
+                build_id,
+                "x86_64-unknown-linux-gnu",
+                "rustc 1.92.0-nightly (abcdef012 2025-10-01)",
+                "error: internal compiler error: compiler/rustc_x/src/lib.rs:1:2: no type for `bar`",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/releases/ices").await?;
+            assert!(response.status().is_success());
+            let page = kuchikiki::parse_html().one(response.text().await?);
+
+            let ice = page.select_first("#main .ice").unwrap();
+            assert!(ice.text_contents().contains("no type for `bar`"));
+            assert!(
+                ice.text_contents()
+                    .contains("compiler/rustc_x/src/lib.rs:1:2")
+            );
+            let crate_link = ice.as_node().select_first("li a").unwrap();
+            assert_eq!(
+                crate_link.attributes.borrow().get("href").unwrap(),
+                format!("/crate/foo/0.1.0/builds/{build_id}/x86_64-unknown-linux-gnu.txt")
+            );
+            let issue_link = ice.as_node().select_first("a.issue").unwrap();
+            assert!(
+                issue_link
+                    .attributes
+                    .borrow()
+                    .get("href")
+                    .unwrap()
+                    .starts_with("https://github.com/rust-lang/rust/issues/new?labels=C-bug")
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn issue_body() {
+        let ice = IceReport {
+            signature: "signature".into(),
+            message: "no type for `bar`".into(),
+            location: Some("compiler/rustc_x/src/lib.rs:1:2".into()),
+            query_stack: vec!["typeck".into(), "analysis".into()],
+            first_seen: Default::default(),
+            last_seen: Default::default(),
+            first_toolchain: chrono::NaiveDate::from_ymd_opt(2025, 10, 1),
+            last_toolchain: chrono::NaiveDate::from_ymd_opt(2025, 10, 5),
+            crate_count: 1,
+            crates: vec![ices::IceCrate {
+                name: "foo".into(),
+                version: "0.1.0".into(),
+                build_id: 1,
+                target: "x86_64-unknown-linux-gnu".into(),
+                rustc_version: "rustc 1.92.0-nightly (abcdef012 2025-10-05)".into(),
+                toolchain_date: chrono::NaiveDate::from_ymd_opt(2025, 10, 5),
+            }],
+        };
+
+        let url = url::Url::parse(&issue_url(&ice)).unwrap();
+        let query: std::collections::HashMap<_, _> = url.query_pairs().collect();
+        assert_eq!(query["title"], "ICE: no type for `bar`");
+        assert_eq!(
+            query["body"],
+            "docs.rs found this internal compiler error while building the documentation of 1 crates, \
+             with the nightlies from 2025-10-01 to 2025-10-05.\n\n\
+             Location: `compiler/rustc_x/src/lib.rs:1:2`\n\n\
+             ```\nno type for `bar`\n```\n\n\
+             Query stack: `typeck` → `analysis`\n\n\
+             Affected crates, with their build logs:\n\n\
+             - [foo 0.1.0](https://docs.rs/crate/foo/0.1.0/builds/1/x86_64-unknown-linux-gnu.txt) \
+             (rustc 1.92.0-nightly (abcdef012 2025-10-05))\n"
+        );
+    }
+}
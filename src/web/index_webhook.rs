@@ -0,0 +1,151 @@
+//! Webhook the registry index sends when it changes.
+
+use super::error::{AxumNope, JsonAxumNope, JsonAxumResult};
+use crate::{AsyncBuildQueue, Config};
+use axum::{Json, body::Bytes, extract::Extension, http::HeaderMap, response::IntoResponse};
+use hmac::{Hmac, Mac};
+use http::StatusCode;
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// The header GitHub sends the HMAC-SHA256 signature of the payload in.
+const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+/// Asks the registry watcher to check the index for new crates.
+///
+/// The payload is ignored, but when a secret is configured, the signature has to be valid.
+pub(crate) async fn index_webhook_handler(
+    Extension(build_queue): Extension<Arc<AsyncBuildQueue>>,
+    Extension(config): Extension<Arc<Config>>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> JsonAxumResult<impl IntoResponse> {
+    if let Some(secret) = &config.index_webhook_secret {
+        let signature =
+            headers
+                .get(SIGNATURE_HEADER)
+                .ok_or(JsonAxumNope(AxumNope::Unauthorized(
+                    "Missing webhook signature",
+                )))?;
+
+        if !verify_signature(secret, &payload, signature.as_bytes()) {
+            return Err(JsonAxumNope(AxumNope::Unauthorized(
+                "The webhook signature is not valid",
+            )));
+        }
+    }
+
+    build_queue
+        .request_index_sync()
+        .await
+        .map_err(|e| JsonAxumNope(e.into()))?;
+
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({}))))
+}
+
+/// Checks a signature in the `sha256=<hex digest>` format GitHub uses.
+fn verify_signature(secret: &str, payload: &[u8], signature: &[u8]) -> bool {
+    let Some(signature) = signature
+        .strip_prefix(b"sha256=")
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+    else {
+        return false;
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload);
+    // `verify_slice` compares in constant time.
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_queue::INDEX_SYNC_CHANNEL;
+    use crate::test::{AxumResponseTestExt, AxumRouterTestExt, async_wrapper};
+    use axum::{body::Body, http::Request};
+    use sqlx::postgres::PgListener;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn sign(secret: &str, payload: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(payload);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let payload = br#"{"ref": "refs/heads/master"}"#;
+        let signature = sign("secret", payload);
+
+        assert!(verify_signature("secret", payload, signature.as_bytes()));
+        assert!(!verify_signature("other", payload, signature.as_bytes()));
+        assert!(!verify_signature("secret", b"{}", signature.as_bytes()));
+        assert!(!verify_signature(
+            "secret",
+            payload,
+            signature.trim_start_matches("sha256=").as_bytes()
+        ));
+        assert!(!verify_signature("secret", payload, b"sha256=invalid"));
+    }
+
+    #[test]
+    fn webhook_requests_index_sync() {
+        async_wrapper(|env| async move {
+            let mut listener = PgListener::connect(&env.config().database_url).await?;
+            listener.listen(INDEX_SYNC_CHANNEL).await?;
+
+            let response = env.web_app().await.post("/_/index-webhook").await?;
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+            tokio::time::timeout(Duration::from_secs(5), listener.recv()).await??;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn webhook_with_secret() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| config.index_webhook_secret = Some("secret".into()));
+            let payload = r#"{"ref": "refs/heads/master"}"#;
+
+            let response = env.web_app().await.post("/_/index-webhook").await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let json: serde_json::Value = response.json().await?;
+            assert_eq!(
+                json,
+                serde_json::json!({
+                    "title": "Unauthorized",
+                    "message": "Missing webhook signature"
+                })
+            );
+
+            for (signature, expected_status) in [
+                (
+                    sign("invalid", payload.as_bytes()),
+                    StatusCode::UNAUTHORIZED,
+                ),
+                (sign("secret", payload.as_bytes()), StatusCode::ACCEPTED),
+            ] {
+                let response = env
+                    .web_app()
+                    .await
+                    .oneshot(
+                        Request::builder()
+                            .uri("/_/index-webhook")
+                            .method("POST")
+                            .header(SIGNATURE_HEADER, signature)
+                            .body(Body::from(payload))
+                            .unwrap(),
+                    )
+                    .await?;
+                assert_eq!(response.status(), expected_status);
+            }
+
+            Ok(())
+        })
+    }
+}
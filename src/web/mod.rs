@@ -6,6 +6,7 @@ pub mod page;
 use crate::db::CrateId;
 use crate::db::ReleaseId;
 use crate::db::types::BuildStatus;
+use crate::events::EventListener;
 use crate::utils::get_correct_docsrs_style_file;
 use crate::utils::report_error;
 use crate::web::page::templates::{RenderBrands, RenderSolid, filters};
@@ -15,7 +16,11 @@ use axum_extra::middleware::option_layer;
 use serde_json::Value;
 use tracing::{info, instrument};
 
+mod api;
+pub(crate) mod auth;
+mod badges;
 mod build_details;
+mod build_log_search;
 mod builds;
 pub(crate) mod cache;
 pub(crate) mod crate_details;
@@ -23,15 +28,21 @@ mod csp;
 pub(crate) mod error;
 mod extractors;
 mod features;
+mod feeds;
 mod file;
 mod headers;
 mod highlight;
+mod hosted_registries;
+mod ices;
+mod index_webhook;
 mod licenses;
 mod markdown;
 pub(crate) mod metrics;
+mod openapi;
 mod releases;
 mod routes;
 pub(crate) mod rustdoc;
+mod rustdoc_warnings;
 mod sitemap;
 mod source;
 mod statics;
@@ -66,6 +77,7 @@ use tower_http::{catch_panic::CatchPanicLayer, timeout::TimeoutLayer, trace::Tra
 use url::form_urlencoded;
 
 use self::crate_details::Release;
+pub(crate) use self::routes::RESERVED_PREFIXES;
 
 // from https://github.com/servo/rust-url/blob/master/url/src/parser.rs
 // and https://github.com/tokio-rs/axum/blob/main/axum-extra/src/lib.rs
@@ -309,19 +321,31 @@ async fn match_version(
         return Err(AxumNope::CrateNotFound);
     }
 
+    let release = select_release(&releases, input_version)?.clone();
+
+    Ok(MatchedRelease {
+        name: name.to_owned(),
+        corrected_name,
+        req_version: input_version.clone(),
+        release,
+        all_releases: releases,
+    })
+}
+
+/// Picks the release matching the requested version from all releases of a crate.
+///
+/// `releases` have to be sorted newest version first, like `releases_for_crate` returns them.
+fn select_release<'a>(
+    releases: &'a [Release],
+    input_version: &ReqVersion,
+) -> Result<&'a Release, AxumNope> {
     let req_semver: VersionReq = match input_version {
         ReqVersion::Exact(parsed_req_version) => {
             if let Some(release) = releases
                 .iter()
                 .find(|release| &release.version == parsed_req_version)
             {
-                return Ok(MatchedRelease {
-                    name: name.to_owned(),
-                    corrected_name,
-                    req_version: input_version.clone(),
-                    release: release.clone(),
-                    all_releases: releases,
-                });
+                return Ok(release);
             }
 
             if let Ok(version_req) = VersionReq::parse(&parsed_req_version.to_string()) {
@@ -340,33 +364,21 @@ async fn match_version(
     // when matching semver requirements,
     // we generally only want to look at non-yanked releases,
     // excluding releases which just contain in-progress builds
-    if let Some(release) = semver_match(&releases, &req_semver, |r: &Release| {
+    if let Some(release) = semver_match(releases, &req_semver, |r: &Release| {
         r.build_status != BuildStatus::InProgress && (r.yanked.is_none() || r.yanked == Some(false))
     }) {
-        return Ok(MatchedRelease {
-            name: name.to_owned(),
-            corrected_name,
-            req_version: input_version.clone(),
-            release: release.clone(),
-            all_releases: releases,
-        });
+        return Ok(release);
     }
 
     // when we don't find any match with "normal" releases, we also look into in-progress releases
-    if let Some(release) = semver_match(&releases, &req_semver, |r: &Release| {
+    if let Some(release) = semver_match(releases, &req_semver, |r: &Release| {
         r.yanked.is_none() || r.yanked == Some(false)
     }) {
-        return Ok(MatchedRelease {
-            name: name.to_owned(),
-            corrected_name,
-            req_version: input_version.clone(),
-            release: release.clone(),
-            all_releases: releases,
-        });
+        return Ok(release);
     }
 
-    // Since we return with a CrateNotFound earlier if the db reply is empty,
-    // we know that versions were returned but none satisfied the version requirement.
+    // Callers return a CrateNotFound for crates without releases,
+    // so we know that there are releases but none satisfied the version requirement.
     // This can only happen when all versions are yanked.
     Err(AxumNope::VersionNotFound)
 }
@@ -428,6 +440,9 @@ async fn apply_middleware<C: Context>(
             .layer(option_layer(config.request_timeout.map(TimeoutLayer::new)))
             .layer(Extension(context.async_pool().await?))
             .layer(Extension(build_queue))
+            .layer(Extension(Arc::new(EventListener::new(
+                &config.database_url,
+            ))))
             .layer(Extension(context.service_metrics()?))
             .layer(Extension(context.instance_metrics()?))
             .layer(Extension(context.config()?))
@@ -438,7 +453,14 @@ async fn apply_middleware<C: Context>(
             .layer(option_layer(has_templates.then_some(middleware::from_fn(
                 page::web_page::render_templates_middleware,
             ))))
-            .layer(middleware::from_fn(cache::cache_middleware)),
+            .layer(middleware::from_fn(cache::cache_middleware))
+            // the metrics app has no pages that need authentication.
+            .layer(option_layer(has_templates.then_some(Extension(Arc::new(
+                auth::Authenticator::new(&config)?,
+            )))))
+            .layer(option_layer(
+                has_templates.then_some(middleware::from_fn(auth::auth_middleware)),
+            )),
     ))
 }
 
@@ -446,7 +468,15 @@ pub(crate) async fn build_axum_app<C: Context>(
     context: &C,
     template_data: Arc<TemplateData>,
 ) -> Result<AxumRouter, Error> {
-    apply_middleware(routes::build_axum_routes(), context, Some(template_data)).await
+    let app = apply_middleware(routes::build_axum_routes(), context, Some(template_data)).await?;
+
+    // URLs of hosted registries have to be rewritten before the routing.
+    Ok(AxumRouter::new()
+        .fallback_service(app)
+        .layer(middleware::from_fn_with_state(
+            hosted_registries::HostedRegistries::new(context.async_pool().await?),
+            hosted_registries::rewrite_hosted_registry_urls,
+        )))
 }
 
 pub(crate) async fn build_metrics_axum_app<C: Context>(context: &C) -> Result<AxumRouter, Error> {
@@ -0,0 +1,251 @@
+//! The OpenAPI description of our JSON endpoints, served at `/openapi.json`.
+//!
+//! The operations and schemas come from the `#[utoipa::path]` attributes on the handlers and
+//! the response types they return. The tests here request every documented endpoint and
+//! validate the responses against the schema, so they fail when the two drift apart.
+
+use super::{api, builds, rustdoc, status};
+use axum::{Json, http::header::ACCESS_CONTROL_ALLOW_ORIGIN, response::IntoResponse};
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{Http, HttpAuthScheme, SecurityScheme},
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "docs.rs",
+        description = "The JSON endpoints of docs.rs, see https://docs.rs/about/api",
+    ),
+    paths(
+        api::crate_details_handler,
+        api::crate_releases_handler,
+        api::builds_handler,
+        api::build_log_handler,
+        api::features_handler,
+        api::platforms_handler,
+        api::release_list_handler,
+        api::bulk_status_handler,
+        api::ices_handler,
+        status::status_handler,
+        builds::build_trigger_rebuild_handler,
+        rustdoc::json_download_handler,
+    ),
+    modifiers(&CratesIoToken)
+)]
+struct ApiDoc;
+
+/// The token crates.io uses to trigger rebuilds.
+struct CratesIoToken;
+
+impl Modify for CratesIoToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "crates_io_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+    }
+}
+
+pub(crate) async fn openapi_handler() -> impl IntoResponse {
+    (
+        [(ACCESS_CONTROL_ALLOW_ORIGIN, "*")],
+        Json(ApiDoc::openapi()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiDoc;
+    use crate::test::{AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper};
+    use axum::{body::Body, http::Request};
+    use reqwest::StatusCode;
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use tower::ServiceExt as _;
+    use utoipa::OpenApi as _;
+
+    /// Builds a validator for a schema in the OpenAPI document.
+    ///
+    /// Our schemas allow new fields, so clients keep working when we add some. For the tests
+    /// we don't, so a field we forgot to document fails them too.
+    fn validator(schema: &Value) -> jsonschema::Validator {
+        fn deny_additional_properties(value: &mut Value) {
+            match value {
+                Value::Object(object) => {
+                    if object.contains_key("properties") {
+                        object
+                            .entry("additionalProperties")
+                            .or_insert(Value::Bool(false));
+                    }
+                    object.values_mut().for_each(deny_additional_properties);
+                }
+                Value::Array(array) => array.iter_mut().for_each(deny_additional_properties),
+                _ => {}
+            }
+        }
+
+        let mut schema = schema.clone();
+        schema["components"] = serde_json::to_value(ApiDoc::openapi().components).unwrap();
+        deny_additional_properties(&mut schema);
+
+        jsonschema::options()
+            .with_draft(jsonschema::Draft::Draft202012)
+            .should_validate_formats(true)
+            .build(&schema)
+            .expect("invalid schema")
+    }
+
+    #[test]
+    fn openapi_json() {
+        async_wrapper(|env| async move {
+            let response = env.web_app().await.get("/openapi.json").await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+
+            let doc: Value = response.json().await?;
+            assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+            assert!(doc["paths"]["/api/v1/crates/{name}/{version}"]["get"].is_object());
+            assert!(doc["paths"]["/crate/{name}/{version}/rebuild"]["post"].is_object());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn validator_catches_drift() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let validator = validator(
+            &doc["paths"]["/api/v1/crates/{name}/releases"]["get"]["responses"]["200"]["content"]["application/json"]
+                ["schema"],
+        );
+
+        let release = json!({
+            "version": "0.1.0",
+            "build_status": "success",
+            "yanked": false,
+            "rustdoc_status": true,
+            "is_library": true,
+            "release_time": "2020-01-01T00:00:00Z",
+        });
+        assert!(validator.is_valid(&json!({ "releases": [release] })));
+
+        let mut missing = release.clone();
+        missing.as_object_mut().unwrap().remove("yanked");
+        assert!(!validator.is_valid(&json!({ "releases": [missing] })));
+
+        let mut undocumented = release.clone();
+        undocumented["new_field"] = true.into();
+        assert!(!validator.is_valid(&json!({ "releases": [undocumented] })));
+
+        let mut wrong_type = release;
+        wrong_type["build_status"] = "unknown".into();
+        assert!(!validator.is_valid(&json!({ "releases": [wrong_type] })));
+    }
+
+    #[test]
+    fn responses_match_schema() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| config.cratesio_token = Some("secret".into()));
+
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .default_target("x86_64-unknown-linux-gnu")
+                .features(HashMap::from([("default".into(), vec![])]))
+                .builds(vec![
+                    FakeBuild::default().build_log_for_other_target("other_target", "log"),
+                ])
+                .create()
+                .await?;
+
+            let build_id: i32 = sqlx::query_scalar!("SELECT id FROM builds")
+                .fetch_one(&mut *env.async_db().await.async_conn().await)
+                .await?;
+
+            let params = HashMap::from([
+                ("name", "foo".to_string()),
+                ("version", "0.1.0".to_string()),
+                ("id", build_id.to_string()),
+                ("filename", "x86_64-unknown-linux-gnu.txt".to_string()),
+                ("target", "x86_64-unknown-linux-gnu".to_string()),
+                ("format_version", "latest".to_string()),
+                ("kind", "recent".to_string()),
+            ]);
+            let bodies = HashMap::from([(
+                "/api/v1/crates/status",
+                json!({ "crates": [{ "name": "foo" }, { "name": "unknown" }] }),
+            )]);
+
+            let doc = serde_json::to_value(ApiDoc::openapi())?;
+            let web = env.web_app().await;
+
+            for (path, item) in doc["paths"].as_object().unwrap() {
+                for (method, operation) in item.as_object().unwrap() {
+                    let mut uri = path.clone();
+                    for param in operation["parameters"].as_array().into_iter().flatten() {
+                        if param["in"] == "path" {
+                            let name = param["name"].as_str().unwrap();
+                            let value = params
+                                .get(name)
+                                .unwrap_or_else(|| panic!("no test value for parameter {name}"));
+                            uri = uri.replace(&format!("{{{name}}}"), value);
+                        }
+                    }
+
+                    let mut request = Request::builder()
+                        .method(method.to_uppercase().as_str())
+                        .uri(&uri)
+                        .header("Authorization", "Bearer secret");
+                    let body = if operation.get("requestBody").is_some() {
+                        let body = bodies
+                            .get(path.as_str())
+                            .unwrap_or_else(|| panic!("no test body for {method} {path}"));
+                        request = request.header("Content-Type", "application/json");
+                        Body::from(body.to_string())
+                    } else {
+                        Body::empty()
+                    };
+                    let response = web.clone().oneshot(request.body(body)?).await?;
+
+                    let status = response.status();
+                    assert!(
+                        status.is_success() || status.is_redirection(),
+                        "{method} {uri} failed: {status}",
+                    );
+                    let Some(expected_response) = operation["responses"].get(status.as_str())
+                    else {
+                        panic!("{method} {uri} returned an undocumented {status}");
+                    };
+
+                    let content_type = response
+                        .headers()
+                        .get("content-type")
+                        .map(|value| value.to_str().unwrap().to_owned());
+                    let body = response.bytes().await?;
+
+                    if let Some(schema) =
+                        expected_response["content"]["application/json"].get("schema")
+                    {
+                        assert_eq!(content_type.as_deref(), Some("application/json"));
+                        let body: Value = serde_json::from_slice(&body)?;
+                        let errors: Vec<_> = validator(schema)
+                            .iter_errors(&body)
+                            .map(|error| error.to_string())
+                            .collect();
+                        assert!(
+                            errors.is_empty(),
+                            "{method} {uri} doesn't match its schema: {errors:?}\n{body:#}"
+                        );
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
@@ -3,10 +3,19 @@
 use crate::{
     AsyncBuildQueue, Config, InstanceMetrics, RegistryApi,
     build_queue::{QueuedCrate, REBUILD_PRIORITY},
-    cdn, impl_axum_webpage,
+    cdn,
+    db::{
+        Pool, access_rules,
+        builders::{self, Builder},
+        registries::{self, Registry, crate_name},
+    },
+    events::EventListener,
+    impl_axum_webpage,
     utils::report_error,
     web::{
-        ReqVersion, axum_parse_uri_with_params, axum_redirect, encode_url_path,
+        ReqVersion,
+        auth::{Principal, retain_visible},
+        axum_parse_uri_with_params, axum_redirect, encode_url_path,
         error::{AxumNope, AxumResult},
         extractors::{DbConnection, Path},
         match_version,
@@ -17,7 +26,11 @@ use anyhow::{Context as _, Result, anyhow};
 use askama::Template;
 use axum::{
     extract::{Extension, Query},
-    response::{IntoResponse, Response as AxumResponse},
+    http::header::CACHE_CONTROL,
+    response::{
+        IntoResponse, Response as AxumResponse,
+        sse::{Event as SseEvent, KeepAlive, Sse},
+    },
 };
 use base64::{Engine, engine::general_purpose::STANDARD as b64};
 use chrono::{DateTime, Utc};
@@ -28,6 +41,7 @@ use sqlx::Row;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tracing::warn;
 use url::form_urlencoded;
 
@@ -39,6 +53,8 @@ const RELEASES_IN_HOME: i64 = 15;
 const RELEASES_IN_RELEASES: i64 = 30;
 /// Releases in recent releases feed
 const RELEASES_IN_FEED: i64 = 150;
+/// Owners in the backlog section of /releases/queue
+const OWNER_BACKLOG_SIZE: usize = 10;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Release {
@@ -138,6 +154,15 @@ pub(crate) enum ReleaseStatus {
     NotAvailable(String),
 }
 
+impl ReleaseStatus {
+    fn name(&self) -> &str {
+        match self {
+            Self::Available(release) => &release.name,
+            Self::NotAvailable(name) => name,
+        }
+    }
+}
+
 struct SearchResult {
     pub results: Vec<ReleaseStatus>,
     pub prev_page: Option<String>,
@@ -260,23 +285,106 @@ async fn get_search_results(
     })
 }
 
+/// Searches the crates of a hosted registry.
+///
+/// crates.io doesn't know these crates, so we search our database, sorted by name.
+async fn get_registry_search_results(
+    conn: &mut sqlx::PgConnection,
+    registry: &Registry,
+    query: &str,
+    page: i64,
+) -> Result<(Vec<ReleaseStatus>, bool), anyhow::Error> {
+    let mut releases: Vec<ReleaseStatus> = sqlx::query!(
+        r#"SELECT
+               crates.name,
+               releases.version,
+               releases.description,
+               release_build_status.last_build_time,
+               releases.target_name,
+               releases.rustdoc_status,
+               repositories.stars as "stars?",
+               EXISTS (
+                   SELECT 1
+                   FROM releases AS all_releases
+                   WHERE
+                       all_releases.crate_id = crates.id AND
+                       all_releases.yanked = false
+               ) AS has_unyanked_releases
+
+           FROM crates
+           INNER JOIN releases ON crates.latest_version_id = releases.id
+           INNER JOIN release_build_status ON releases.id = release_build_status.rid
+           LEFT JOIN repositories ON releases.repository_id = repositories.id
+
+           WHERE
+               crates.registry_id = $1 AND
+               strpos(lower(split_part(crates.name, '/', 2)), lower($2)) > 0
+
+           ORDER BY crates.name
+           LIMIT $3 OFFSET $4"#,
+        registry.id,
+        query,
+        RELEASES_IN_RELEASES + 1,
+        (page - 1) * RELEASES_IN_RELEASES,
+    )
+    .fetch(&mut *conn)
+    .map_ok(|row| {
+        ReleaseStatus::Available(Release {
+            name: row.name,
+            version: row.version,
+            description: row.description,
+            build_time: row.last_build_time,
+            target_name: row.target_name,
+            rustdoc_status: row.rustdoc_status.unwrap_or(false),
+            stars: row.stars.unwrap_or(0),
+            has_unyanked_releases: row.has_unyanked_releases,
+            href: None,
+        })
+    })
+    .try_collect()
+    .await?;
+
+    let has_next_page = releases.len() > RELEASES_IN_RELEASES as usize;
+    releases.truncate(RELEASES_IN_RELEASES as usize);
+    Ok((releases, has_next_page))
+}
+
 #[derive(Template)]
 #[template(path = "core/home.html")]
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct HomePage {
     recent_releases: Vec<Release>,
+    /// whether the releases depend on who is asking, because some crates are private
+    private: bool,
 }
 
 impl_axum_webpage! {
     HomePage,
-    cache_policy = |_| CachePolicy::ShortInCdnAndBrowser,
+    cache_policy = |page| if page.private {
+        CachePolicy::NoStoreMustRevalidate
+    } else {
+        CachePolicy::ShortInCdnAndBrowser
+    },
 }
 
-pub(crate) async fn home_page(mut conn: DbConnection) -> AxumResult<impl IntoResponse> {
-    let recent_releases =
+pub(crate) async fn home_page(
+    principal: Option<Extension<Principal>>,
+    mut conn: DbConnection,
+) -> AxumResult<impl IntoResponse> {
+    let mut recent_releases =
         get_releases(&mut conn, 1, RELEASES_IN_HOME, Order::ReleaseTime, true).await?;
+    let private = retain_visible(
+        &mut conn,
+        principal.as_deref(),
+        &mut recent_releases,
+        |release| &release.name,
+    )
+    .await?;
 
-    Ok(HomePage { recent_releases })
+    Ok(HomePage {
+        recent_releases,
+        private,
+    })
 }
 
 #[derive(Template)]
@@ -284,17 +392,38 @@ pub(crate) async fn home_page(mut conn: DbConnection) -> AxumResult<impl IntoRes
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ReleaseFeed {
     recent_releases: Vec<Release>,
+    /// whether the releases depend on who is asking, because some crates are private
+    private: bool,
 }
 
 impl_axum_webpage! {
     ReleaseFeed,
     content_type = "application/xml",
+    cache_policy = |feed| if feed.private {
+        CachePolicy::NoStoreMustRevalidate
+    } else {
+        CachePolicy::NoCaching
+    },
 }
 
-pub(crate) async fn releases_feed_handler(mut conn: DbConnection) -> AxumResult<impl IntoResponse> {
-    let recent_releases =
+pub(crate) async fn releases_feed_handler(
+    principal: Option<Extension<Principal>>,
+    mut conn: DbConnection,
+) -> AxumResult<impl IntoResponse> {
+    let mut recent_releases =
         get_releases(&mut conn, 1, RELEASES_IN_FEED, Order::ReleaseTime, true).await?;
-    Ok(ReleaseFeed { recent_releases })
+    let private = retain_visible(
+        &mut conn,
+        principal.as_deref(),
+        &mut recent_releases,
+        |release| &release.name,
+    )
+    .await?;
+
+    Ok(ReleaseFeed {
+        recent_releases,
+        private,
+    })
 }
 
 #[derive(Template)]
@@ -308,9 +437,18 @@ struct ViewReleases {
     show_previous_page: bool,
     page_number: i64,
     owner: Option<String>,
+    /// whether the releases depend on who is asking, because some crates are private
+    private: bool,
 }
 
-impl_axum_webpage! { ViewReleases }
+impl_axum_webpage! {
+    ViewReleases,
+    cache_policy = |page| if page.private {
+        CachePolicy::NoStoreMustRevalidate
+    } else {
+        CachePolicy::NoCaching
+    },
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub(crate) enum ReleaseType {
@@ -346,6 +484,7 @@ impl ReleaseType {
 
 pub(crate) async fn releases_handler(
     conn: &mut sqlx::PgConnection,
+    principal: Option<&Principal>,
     page: Option<i64>,
     release_type: ReleaseType,
 ) -> AxumResult<impl IntoResponse + use<>> {
@@ -370,7 +509,7 @@ pub(crate) async fn releases_handler(
         }
     };
 
-    let releases = get_releases(
+    let mut releases = get_releases(
         &mut *conn,
         page_number,
         RELEASES_IN_RELEASES,
@@ -385,6 +524,11 @@ pub(crate) async fn releases_handler(
         page_number != 1,
     );
 
+    let private = retain_visible(&mut *conn, principal, &mut releases, |release| {
+        &release.name
+    })
+    .await?;
+
     Ok(ViewReleases {
         releases: releases
             .into_iter()
@@ -396,35 +540,64 @@ pub(crate) async fn releases_handler(
         show_previous_page,
         page_number,
         owner: None,
+        private,
     })
 }
 
 pub(crate) async fn recent_releases_handler(
     page: Option<Path<i64>>,
+    principal: Option<Extension<Principal>>,
     mut conn: DbConnection,
 ) -> AxumResult<impl IntoResponse> {
-    releases_handler(&mut conn, page.map(|p| p.0), ReleaseType::Recent).await
+    releases_handler(
+        &mut conn,
+        principal.as_deref(),
+        page.map(|p| p.0),
+        ReleaseType::Recent,
+    )
+    .await
 }
 
 pub(crate) async fn releases_by_stars_handler(
     page: Option<Path<i64>>,
+    principal: Option<Extension<Principal>>,
     mut conn: DbConnection,
 ) -> AxumResult<impl IntoResponse> {
-    releases_handler(&mut conn, page.map(|p| p.0), ReleaseType::Stars).await
+    releases_handler(
+        &mut conn,
+        principal.as_deref(),
+        page.map(|p| p.0),
+        ReleaseType::Stars,
+    )
+    .await
 }
 
 pub(crate) async fn releases_recent_failures_handler(
     page: Option<Path<i64>>,
+    principal: Option<Extension<Principal>>,
     mut conn: DbConnection,
 ) -> AxumResult<impl IntoResponse> {
-    releases_handler(&mut conn, page.map(|p| p.0), ReleaseType::RecentFailures).await
+    releases_handler(
+        &mut conn,
+        principal.as_deref(),
+        page.map(|p| p.0),
+        ReleaseType::RecentFailures,
+    )
+    .await
 }
 
 pub(crate) async fn releases_failures_by_stars_handler(
     page: Option<Path<i64>>,
+    principal: Option<Extension<Principal>>,
     mut conn: DbConnection,
 ) -> AxumResult<impl IntoResponse> {
-    releases_handler(&mut conn, page.map(|p| p.0), ReleaseType::Failures).await
+    releases_handler(
+        &mut conn,
+        principal.as_deref(),
+        page.map(|p| p.0),
+        ReleaseType::Failures,
+    )
+    .await
 }
 
 pub(crate) async fn owner_handler(Path(owner): Path<String>) -> AxumResult<impl IntoResponse> {
@@ -443,11 +616,15 @@ pub(super) struct Search {
     pub(super) releases: Vec<ReleaseStatus>,
     pub(super) search_query: Option<String>,
     pub(super) search_sort_by: Option<String>,
+    /// the hosted registry the search is limited to
+    pub(super) search_registry: Option<String>,
     pub(super) previous_page_link: Option<String>,
     pub(super) next_page_link: Option<String>,
     /// This should always be `ReleaseType::Search`
     pub(super) release_type: ReleaseType,
     pub(super) status: http::StatusCode,
+    /// whether the results depend on who is asking, because some crates are private
+    pub(super) private: bool,
 }
 
 impl Default for Search {
@@ -456,11 +633,13 @@ impl Default for Search {
             title: String::default(),
             releases: Vec::default(),
             search_query: None,
+            search_registry: None,
             previous_page_link: None,
             next_page_link: None,
             search_sort_by: None,
             release_type: ReleaseType::Search,
             status: http::StatusCode::OK,
+            private: false,
         }
     }
 }
@@ -522,9 +701,15 @@ async fn redirect_to_random_crate(
 impl_axum_webpage! {
     Search,
     status = |search| search.status,
+    cache_policy = |search| if search.private {
+        CachePolicy::NoStoreMustRevalidate
+    } else {
+        CachePolicy::NoCaching
+    },
 }
 
 pub(crate) async fn search_handler(
+    principal: Option<Extension<Principal>>,
     mut conn: DbConnection,
     Extension(config): Extension<Arc<Config>>,
     Extension(registry): Extension<Arc<RegistryApi>>,
@@ -539,6 +724,14 @@ pub(crate) async fn search_handler(
         .get("sort")
         .map(|q| q.to_string())
         .unwrap_or_else(|| "relevance".to_string());
+    let hosted_registry = match params.remove("registry") {
+        Some(name) => Some(
+            registries::registry_by_name(&mut conn, &name)
+                .await?
+                .ok_or(AxumNope::NoResults)?,
+        ),
+        None => None,
+    };
     // check if I am feeling lucky button pressed and redirect user to crate page
     // if there is a match. Also check for paths to items within crates.
     if params.remove("i-am-feeling-lucky").is_some() || query.contains("::") {
@@ -558,6 +751,7 @@ pub(crate) async fn search_handler(
             }
             None => &query,
         };
+        let krate = &crate_name(hosted_registry.as_ref(), krate);
 
         // since we never pass a version into `match_version` here, we'll never get
         // `MatchVersion::Exact`, so the distinction between `Exact` and `Semver` doesn't
@@ -591,7 +785,19 @@ pub(crate) async fn search_handler(
         }
     }
 
-    let search_result = if let Some(paginate) = params.get("paginate") {
+    if let Some(hosted_registry) = hosted_registry {
+        let mut search = registry_search(&mut conn, &hosted_registry, query, &params).await?;
+        search.private = retain_visible(
+            &mut conn,
+            principal.as_deref(),
+            &mut search.releases,
+            ReleaseStatus::name,
+        )
+        .await?;
+        return Ok(search.into_response());
+    }
+
+    let mut search_result = if let Some(paginate) = params.get("paginate") {
         let decoded = b64.decode(paginate.as_bytes()).map_err(|e| {
             warn!("error when decoding pagination base64 string \"{paginate}\": {e:?}");
             AxumNope::NoResults
@@ -628,6 +834,13 @@ pub(crate) async fn search_handler(
     } else {
         return Err(AxumNope::NoResults);
     };
+    let private = retain_visible(
+        &mut conn,
+        principal.as_deref(),
+        &mut search_result.results,
+        ReleaseStatus::name,
+    )
+    .await?;
 
     let title = if search_result.results.is_empty() {
         format!("No results found for '{query}'")
@@ -646,11 +859,58 @@ pub(crate) async fn search_handler(
         previous_page_link: search_result
             .prev_page
             .map(|params| format!("/releases/search?paginate={}", b64.encode(params))),
+        private,
         ..Default::default()
     }
     .into_response())
 }
 
+/// The search in a hosted registry, without a query it lists all its crates.
+async fn registry_search(
+    conn: &mut sqlx::PgConnection,
+    registry: &Registry,
+    query: String,
+    params: &HashMap<String, String>,
+) -> AxumResult<Search> {
+    let page = params
+        .get("page")
+        .and_then(|page| page.parse::<i64>().ok())
+        .unwrap_or(1)
+        .max(1);
+
+    let (releases, has_next_page) =
+        get_registry_search_results(conn, registry, &query, page).await?;
+
+    let page_link = |page: i64| {
+        format!(
+            "/releases/search?{}",
+            form_urlencoded::Serializer::new(String::new())
+                .append_pair("registry", &registry.name)
+                .append_pair("query", &query)
+                .append_pair("page", &page.to_string())
+                .finish()
+        )
+    };
+
+    let title = if query.is_empty() {
+        format!("Crates in {}", registry.name)
+    } else if releases.is_empty() {
+        format!("No results found for '{query}' in {}", registry.name)
+    } else {
+        format!("Search results for '{query}' in {}", registry.name)
+    };
+
+    Ok(Search {
+        title,
+        releases,
+        next_page_link: has_next_page.then(|| page_link(page + 1)),
+        previous_page_link: (page > 1).then(|| page_link(page - 1)),
+        search_query: Some(query),
+        search_registry: Some(registry.name.clone()),
+        ..Default::default()
+    })
+}
+
 #[derive(Template)]
 #[template(path = "releases/activity.html")]
 #[derive(Debug, Clone, PartialEq)]
@@ -722,10 +982,13 @@ pub(crate) async fn activity_handler(mut conn: DbConnection) -> AxumResult<impl
 #[derive(Debug, Clone, PartialEq, Serialize)]
 struct BuildQueuePage {
     description: &'static str,
-    queue: Vec<QueuedCrate>,
+    /// queued crates with their estimated finish time
+    queue: Vec<(QueuedCrate, Option<DateTime<Utc>>)>,
     rebuild_queue: Vec<QueuedCrate>,
     active_cdn_deployments: Vec<String>,
-    in_progress_builds: Vec<(String, String)>,
+    in_progress_builds: Vec<(String, String, Option<DateTime<Utc>>)>,
+    owner_backlog: Vec<(String, usize)>,
+    builders: Vec<Builder>,
     expand_rebuild_queue: bool,
 }
 
@@ -738,6 +1001,8 @@ pub(crate) struct BuildQueueParams {
 
 pub(crate) async fn build_queue_handler(
     Extension(build_queue): Extension<Arc<AsyncBuildQueue>>,
+    Extension(config): Extension<Arc<Config>>,
+    principal: Option<Extension<Principal>>,
     mut conn: DbConnection,
     Query(params): Query<BuildQueueParams>,
 ) -> AxumResult<impl IntoResponse> {
@@ -754,7 +1019,14 @@ pub(crate) async fn build_queue_handler(
     // reverse the list, so the oldest comes first
     active_cdn_deployments.reverse();
 
-    let in_progress_builds: Vec<(String, String)> = sqlx::query!(
+    let mut estimates: HashMap<(String, String), DateTime<Utc>> = build_queue
+        .estimate_finish_times(None)
+        .await?
+        .into_iter()
+        .map(|estimate| ((estimate.name, estimate.version), estimate.finished_at))
+        .collect();
+
+    let in_progress_builds: Vec<(String, String, Option<DateTime<Utc>>)> = sqlx::query!(
         r#"SELECT
             crates.name,
             releases.version
@@ -768,7 +1040,10 @@ pub(crate) async fn build_queue_handler(
     .fetch_all(&mut *conn)
     .await?
     .into_iter()
-    .map(|rec| (rec.name, rec.version))
+    .map(|rec| {
+        let estimate = estimates.remove(&(rec.name.clone(), rec.version.clone()));
+        (rec.name, rec.version, estimate)
+    })
     .collect();
 
     let mut rebuild_queue = Vec::new();
@@ -777,7 +1052,7 @@ pub(crate) async fn build_queue_handler(
         .await?
         .into_iter()
         .filter(|krate| {
-            !in_progress_builds.iter().any(|(name, version)| {
+            !in_progress_builds.iter().any(|(name, version, _)| {
                 // use `.any` instead of `.contains` to avoid cloning name& version for the match
                 *name == krate.name && *version == krate.version
             })
@@ -797,33 +1072,122 @@ pub(crate) async fn build_queue_handler(
         }
     });
 
+    let queue = queue
+        .into_iter()
+        .map(|krate| {
+            let estimate = estimates.remove(&(krate.name.clone(), krate.version.clone()));
+            (krate, estimate)
+        })
+        .collect();
+
+    // only show owners with more than one queued release, these are the ones
+    // where the fair scheduling between owners makes a difference.
+    let owner_backlog = build_queue
+        .pending_count_by_owner()
+        .await?
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .take(OWNER_BACKLOG_SIZE)
+        .collect();
+
+    // the build servers are infrastructure details, only admins can see them.
+    let builders = if principal.is_some_and(|Extension(principal)| principal.is_admin(&config)) {
+        builders::active_builders(&mut conn, config.stale_build_timeout).await?
+    } else {
+        Vec::new()
+    };
+
     Ok(BuildQueuePage {
         description: "crate documentation scheduled to build & deploy",
         queue,
         rebuild_queue,
         active_cdn_deployments,
         in_progress_builds,
+        owner_backlog,
+        builders,
         expand_rebuild_queue: params.expand.is_some(),
     })
 }
 
+/// Streams the build activity shown on the queue page as server-sent events.
+///
+/// Events for crates with access rules are only sent to the principals allowed to see them.
+///
+/// Subscribers that fall behind get a `lagged` event, after which they should reload
+/// the queue.
+pub(crate) async fn build_queue_events_handler(
+    Extension(listener): Extension<Arc<EventListener>>,
+    Extension(pool): Extension<Pool>,
+    principal: Option<Extension<Principal>>,
+) -> AxumResult<impl IntoResponse> {
+    let mut receiver = listener.subscribe().await?;
+
+    let stream = async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let allowed_principals = match pool.get_async().await {
+                        Ok(mut conn) => {
+                            access_rules::allowed_principals(&mut conn, event.crate_name()).await
+                        }
+                        Err(err) => Err(err.into()),
+                    };
+                    let visible = match allowed_principals {
+                        Ok(allowed) => {
+                            allowed.is_empty()
+                                || principal
+                                    .as_ref()
+                                    .is_some_and(|Extension(principal)| principal.is_allowed(&allowed))
+                        }
+                        Err(err) => {
+                            report_error(&err.context("error checking the access rules of an event"));
+                            false
+                        }
+                    };
+                    if visible {
+                        yield SseEvent::default().event(event.name()).json_data(&event);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    yield Ok(SseEvent::default().event("lagged").data(missed.to_string()))
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+
+    let mut response = Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response();
+    // axum sets its own `Cache-Control` header, we control caching with `CachePolicy`.
+    response.headers_mut().remove(CACHE_CONTROL);
+
+    Ok((Extension(CachePolicy::NoCaching), response))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::access_rules::{AccessTarget, add_access_rule};
     use crate::db::types::BuildStatus;
-    use crate::db::{finish_build, initialize_build, initialize_crate, initialize_release};
+    use crate::db::{
+        BuildId, finish_build, initialize_build, initialize_crate, initialize_release,
+    };
     use crate::registry_api::{CrateOwner, OwnerKind};
     use crate::test::{
         AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper,
         fake_release_that_failed_before_build,
     };
     use anyhow::Error;
+    use axum::{body::Body, http::Request};
     use chrono::{Duration, TimeZone};
+    use http_body_util::BodyExt as _;
     use kuchikiki::traits::TendrilSink;
     use mockito::Matcher;
     use reqwest::StatusCode;
     use serde_json::json;
     use test_case::test_case;
+    use tower::ServiceExt as _;
 
     #[test]
     fn test_release_list_with_incomplete_release_and_successful_build() {
@@ -1714,6 +2078,61 @@ mod tests {
         })
     }
 
+    #[test_case("/")]
+    #[test_case("/releases")]
+    #[test_case("/releases/stars")]
+    #[test_case("/releases/recent-failures")]
+    #[test_case("/releases/failures")]
+    #[test_case("/releases/feed")]
+    fn release_lists_hide_private_crates(path: &str) {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            // failed builds with stars, so both crates are in every list.
+            for name in ["public-crate", "private-crate"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .github_stats("some/repo", 10, 10, 10)
+                    .build_result_failed()
+                    .create()
+                    .await?;
+            }
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private-crate"),
+                "alice",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            let response = web.get(path).await?;
+            assert!(response.status().is_success());
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+            let page = response.text().await?;
+            assert!(page.contains("public-crate"));
+            assert!(!page.contains("private-crate"));
+
+            let response = web
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(path)
+                        .header("Authorization", "Bearer alice-token")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            assert!(response.text().await?.contains("private-crate"));
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn release_activity() {
         async_wrapper(|env| async move {
@@ -1883,6 +2302,147 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_releases_queue_not_before() {
+        async_wrapper(|env| async move {
+            let web = env.web_app().await;
+
+            let queue = env.async_build_queue().await;
+            queue
+                .schedule_crate(
+                    "foo",
+                    "1.0.0",
+                    0,
+                    None,
+                    Some(Utc.with_ymd_and_hms(2100, 1, 2, 3, 4, 5).unwrap()),
+                )
+                .await?;
+
+            let page = kuchikiki::parse_html().one(web.get("/releases/queue").await?.text().await?);
+            let item = page
+                .select_first(".queue-list > li")
+                .expect("missing list item");
+            assert!(
+                item.text_contents()
+                    .contains("not before: 2100-01-02 03:04 UTC")
+            );
+            // the build can't start before the scheduled time
+            assert!(
+                item.text_contents()
+                    .contains("expected around 2100-01-02 03:06 UTC")
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_releases_queue_builders() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+                config.admin_principals = vec!["alice".into()];
+            });
+            let web = env.web_app().await;
+
+            let release_id = env
+                .fake_release()
+                .await
+                .name("foo")
+                .version("1.0.0")
+                .builds(vec![
+                    FakeBuild::default().build_status(BuildStatus::InProgress),
+                ])
+                .create()
+                .await?;
+
+            let mut conn = env.async_db().await.async_conn().await;
+            let build_id = sqlx::query_scalar!(
+                r#"SELECT id as "id: BuildId" FROM builds WHERE rid = $1"#,
+                release_id.0
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            builders::register_builder(&mut conn, "builder-1", Some("nightly")).await?;
+            builders::set_current_build(&mut conn, "builder-1", Some(build_id)).await?;
+            builders::register_builder(&mut conn, "builder-2", None).await?;
+
+            // only admins see the build servers
+            let page = kuchikiki::parse_html().one(web.get("/releases/queue").await?.text().await?);
+            assert!(page.select_first(".builders-list").is_err());
+
+            let response = web
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/releases/queue")
+                        .header("Authorization", "Bearer alice-token")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            let page = kuchikiki::parse_html().one(response.text().await?);
+            let items: Vec<_> = page
+                .select(".builders-list > li")
+                .expect("missing builders list")
+                .map(|li| li.text_contents())
+                .collect();
+            assert_eq!(items.len(), 2);
+            assert!(items[0].contains("builder-1"));
+            assert!(items[0].contains("(nightly)"));
+            assert!(items[0].contains("building"));
+            assert!(items[0].contains("foo 1.0.0"));
+            assert!(items[1].contains("builder-2"));
+            assert!(items[1].contains("idle"));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_releases_queue_owner_backlog() {
+        async_wrapper(|env| async move {
+            let web = env.web_app().await;
+
+            for name in ["aws-a", "aws-b", "other"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .add_owner(CrateOwner {
+                        login: if name == "other" { "someone" } else { "aws" }.into(),
+                        avatar: "https://example.org/avatar".into(),
+                        kind: OwnerKind::User,
+                    })
+                    .create()
+                    .await?;
+            }
+
+            let empty =
+                kuchikiki::parse_html().one(web.get("/releases/queue").await?.text().await?);
+            assert!(empty.select_first(".owner-backlog-list").is_err());
+
+            let queue = env.async_build_queue().await;
+            queue.add_crate("aws-a", "0.2.0", 0, None).await?;
+            queue.add_crate("aws-b", "0.2.0", 0, None).await?;
+            queue.add_crate("other", "0.2.0", 0, None).await?;
+
+            let full = kuchikiki::parse_html().one(web.get("/releases/queue").await?.text().await?);
+            let items = full
+                .select(".owner-backlog-list > li")
+                .expect("missing list items")
+                .collect::<Vec<_>>();
+
+            // owners with a single queued crate are not shown
+            assert_eq!(items.len(), 1);
+            let a = items[0].as_node().select_first("a").expect("missing link");
+            assert_eq!(a.text_contents(), "aws");
+            assert_eq!(a.attributes.borrow().get("href").unwrap(), "/releases/aws");
+            assert!(items[0].text_contents().contains("2 queued crates"));
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_releases_queue_in_progress() {
         async_wrapper(|env| async move {
@@ -1934,6 +2494,125 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_releases_queue_events() {
+        async_wrapper(|env| async move {
+            let response = env.web_app().await.get("/releases/queue/events").await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "text/event-stream");
+            response.assert_cache_control(CachePolicy::NoCaching, &env.config());
+
+            env.async_build_queue()
+                .await
+                .add_crate("queue-events", "1.0.0", 0, None)
+                .await?;
+            env.fake_release()
+                .await
+                .name("queue-events")
+                .version("1.0.0")
+                .create()
+                .await?;
+
+            // other tests send events on the same channel, we only look at ours.
+            let mut body = response.into_body();
+            let mut events = Vec::new();
+            tokio::time::timeout(std::time::Duration::from_secs(10), async {
+                while events.len() < 3 {
+                    let frame = body.frame().await.expect("the event stream ended")?;
+                    let Ok(data) = frame.into_data() else {
+                        continue;
+                    };
+                    let event = String::from_utf8(data.to_vec())?;
+                    if event.contains(r#""name":"queue-events""#) {
+                        events.push(event.lines().next().unwrap_or_default().to_owned());
+                    }
+                }
+                Ok::<_, Error>(())
+            })
+            .await??;
+
+            assert_eq!(
+                events,
+                [
+                    "event: queued",
+                    "event: build_started",
+                    "event: build_finished"
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_releases_queue_events_hide_protected_crates() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("queue-events-private"),
+                "alice",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            let anonymous = web.get("/releases/queue/events").await?;
+            let alice = web
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/releases/queue/events")
+                        .header("Authorization", "Bearer alice-token")
+                        .body(Body::empty())?,
+                )
+                .await?;
+
+            let build_queue = env.async_build_queue().await;
+            build_queue
+                .add_crate("queue-events-private", "1.0.0", 0, None)
+                .await?;
+            build_queue
+                .add_crate("queue-events-public", "1.0.0", 0, None)
+                .await?;
+
+            // collects the crates of our events, until we saw the one for the public crate.
+            async fn queued_crates(response: AxumResponse) -> Result<Vec<String>> {
+                let mut body = response.into_body();
+                let mut crates = Vec::new();
+                tokio::time::timeout(std::time::Duration::from_secs(10), async {
+                    while !crates.iter().any(|name| name == "queue-events-public") {
+                        let frame = body.frame().await.expect("the event stream ended")?;
+                        let Ok(data) = frame.into_data() else {
+                            continue;
+                        };
+                        let event = String::from_utf8(data.to_vec())?;
+                        for name in ["queue-events-private", "queue-events-public"] {
+                            if event.contains(&format!(r#""name":"{name}""#)) {
+                                crates.push(name.to_owned());
+                            }
+                        }
+                    }
+                    Ok::<_, Error>(())
+                })
+                .await??;
+                Ok(crates)
+            }
+
+            assert_eq!(queued_crates(anonymous).await?, ["queue-events-public"]);
+            assert_eq!(
+                queued_crates(alice).await?,
+                ["queue-events-private", "queue-events-public"]
+            );
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_releases_rebuild_queue_empty() {
         async_wrapper(|env| async move {
@@ -2309,4 +2988,159 @@ mod tests {
             Ok(())
         });
     }
+
+    #[test]
+    fn search_hides_private_crates() {
+        async_wrapper(|env| async move {
+            let mut crates_io = mockito::Server::new_async().await;
+            env.override_config(|config| {
+                config.registry_api_host = crates_io.url().parse().unwrap();
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            for name in ["public-crate", "private-crate"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .create()
+                    .await?;
+            }
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private-crate"),
+                "alice",
+            )
+            .await?;
+
+            crates_io
+                .mock("GET", "/api/v1/crates")
+                .match_query(Matcher::Any)
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(
+                    json!({
+                        "crates": [
+                            { "name": "public-crate" },
+                            { "name": "private-crate" },
+                        ],
+                        "meta": {
+                            "next_page": null,
+                            "prev_page": null,
+                        }
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let web = env.web_app().await;
+            let response = web.get("/releases/search?query=crate").await?;
+            response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
+            let page = response.text().await?;
+            assert!(page.contains("public-crate"));
+            assert!(!page.contains("private-crate"));
+
+            let response = web
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/releases/search?query=crate")
+                        .header("Authorization", "Bearer alice-token")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            assert!(response.text().await?.contains("private-crate"));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn search_hosted_registry_hides_private_crates() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.auth_tokens = "alice:alice-token".parse().unwrap();
+            });
+            let mut conn = env.async_db().await.async_conn().await;
+            registries::add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+            for name in ["acme/public-crate", "acme/private-crate"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .create()
+                    .await?;
+            }
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("acme/private-crate"),
+                "alice",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            assert_eq!(
+                get_release_links("/releases/search?registry=acme", &web).await?,
+                vec!["/acme/public-crate/latest/public_crate/"]
+            );
+
+            let response = web
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/releases/search?registry=acme")
+                        .header("Authorization", "Bearer alice-token")
+                        .body(Body::empty())?,
+                )
+                .await?;
+            assert!(response.text().await?.contains("private-crate"));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn search_hosted_registry() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            registries::add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+
+            for name in ["foo", "acme/foo", "acme/foobar", "acme/other"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .create()
+                    .await?;
+            }
+
+            let web = env.web_app().await;
+            // crates.io isn't asked, so there is no mock for it.
+            assert_eq!(
+                get_release_links("/acme/?query=foo", &web).await?,
+                vec!["/acme/foo/latest/foo/", "/acme/foobar/latest/foobar/"]
+            );
+            assert_eq!(
+                get_release_links("/releases/search?registry=acme&query=other", &web).await?,
+                vec!["/acme/other/latest/other/"]
+            );
+            // without a query we list all crates of the registry
+            assert_eq!(get_release_links("/acme", &web).await?.len(), 3);
+
+            web.assert_redirect(
+                "/releases/search?registry=acme&query=foobar&i-am-feeling-lucky=1",
+                "/acme/foobar/0.1.0/foobar/",
+            )
+            .await?;
+            web.assert_not_found("/releases/search?registry=unknown&query=foo")
+                .await?;
+
+            Ok(())
+        });
+    }
 }
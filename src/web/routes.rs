@@ -16,6 +16,23 @@ use tracing::{debug, instrument};
 
 const INTERNAL_PREFIXES: &[&str] = &["-", "about", "crate", "releases", "sitemap.xml"];
 
+/// The first path segments of our own routes, a registry can't use them as its name.
+///
+/// Keep this in sync when adding a route with a new first segment.
+pub(crate) const RESERVED_PREFIXES: &[&str] = &[
+    "-",
+    "_",
+    "about",
+    "api",
+    "crate",
+    "favicon.ico",
+    "openapi.json",
+    "opensearch.xml",
+    "releases",
+    "robots.txt",
+    "sitemap.xml",
+];
+
 #[instrument(skip_all)]
 pub(crate) fn get_static<H, T, S>(handler: H) -> MethodRouter<S, Infallible>
 where
@@ -101,6 +118,46 @@ pub(super) fn build_metric_routes() -> AxumRouter {
         )
 }
 
+fn build_api_routes() -> AxumRouter {
+    use super::api;
+
+    AxumRouter::new()
+        .route(
+            "/api/v1/crates/status",
+            post_internal(api::bulk_status_handler),
+        )
+        .route_with_tsr(
+            "/api/v1/crates/{name}/releases",
+            get_internal(api::crate_releases_handler),
+        )
+        .route_with_tsr(
+            "/api/v1/crates/{name}/{version}",
+            get_internal(api::crate_details_handler),
+        )
+        .route_with_tsr(
+            "/api/v1/crates/{name}/{version}/builds",
+            get_internal(api::builds_handler),
+        )
+        .route_with_tsr(
+            "/api/v1/crates/{name}/{version}/builds/{id}/logs/{filename}",
+            get_internal(api::build_log_handler),
+        )
+        .route_with_tsr(
+            "/api/v1/crates/{name}/{version}/features",
+            get_internal(api::features_handler),
+        )
+        .route_with_tsr(
+            "/api/v1/crates/{name}/{version}/platforms",
+            get_internal(api::platforms_handler),
+        )
+        .route_with_tsr("/api/v1/ices", get_internal(api::ices_handler))
+        .route_with_tsr(
+            "/api/v1/releases/{kind}",
+            get_internal(api::release_list_handler),
+        )
+        .layer(middleware::from_fn(api::cors_middleware))
+}
+
 fn cached_permanent_redirect(uri: &str) -> impl IntoResponse {
     (
         Extension(CachePolicy::ForeverInCdnAndBrowser),
@@ -142,6 +199,10 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/opensearch.xml",
             get_static(|| async { cached_permanent_redirect("/-/static/opensearch.xml") }),
         )
+        .route(
+            "/openapi.json",
+            get_internal(super::openapi::openapi_handler),
+        )
         .route_with_tsr(
             "/sitemap.xml",
             get_internal(super::sitemap::sitemapindex_handler),
@@ -155,6 +216,7 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             get_internal(super::sitemap::about_builds_handler),
         )
         .merge(build_metric_routes())
+        .merge(build_api_routes())
         .route_with_tsr("/about", get_internal(super::sitemap::about_handler))
         .route_with_tsr(
             "/about/{subpage}",
@@ -201,6 +263,10 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/crate/{name}/{version}",
             get_internal(super::crate_details::crate_details_handler),
         )
+        .route_with_tsr(
+            "/crate/{name}/feed",
+            get_internal(super::feeds::crate_feed_handler),
+        )
         .route_with_tsr(
             "/releases/feed",
             get_internal(super::releases::releases_feed_handler),
@@ -213,6 +279,10 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/releases/{owner}/{page}",
             get_internal(super::releases::owner_handler),
         )
+        .route_with_tsr(
+            "/releases/{owner}/feed",
+            get_internal(super::feeds::owner_feed_handler),
+        )
         .route_with_tsr(
             "/releases/activity",
             get_internal(super::releases::activity_handler),
@@ -225,6 +295,11 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/releases/queue",
             get_internal(super::releases::build_queue_handler),
         )
+        .route("/releases/ices", get_internal(super::ices::ices_handler))
+        .route(
+            "/releases/queue/events",
+            get_internal(super::releases::build_queue_events_handler),
+        )
         .route_with_tsr(
             "/crate/{name}/{version}/builds",
             get_internal(super::builds::build_list_handler),
@@ -233,6 +308,14 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/crate/{name}/{version}/rebuild",
             post_internal(super::builds::build_trigger_rebuild_handler),
         )
+        .route(
+            "/_/index-webhook",
+            post_internal(super::index_webhook::index_webhook_handler),
+        )
+        .route(
+            "/_/build-logs/search",
+            get_internal(super::build_log_search::build_log_search_handler),
+        )
         .route(
             "/crate/{name}/{version}/status.json",
             get_internal(super::status::status_handler),
@@ -245,10 +328,18 @@ pub(super) fn build_axum_routes() -> AxumRouter {
             "/crate/{name}/{version}/builds/{id}/{filename}",
             get_internal(super::build_details::build_details_handler),
         )
+        .route_with_tsr(
+            "/crate/{name}/{version}/builds/{id}/artifacts/{filename}",
+            get_internal(super::build_details::build_artifact_handler),
+        )
         .route_with_tsr(
             "/crate/{name}/{version}/features",
             get_internal(super::features::build_features_handler),
         )
+        .route(
+            "/crate/{name}/{version}/warnings",
+            get_internal(super::rustdoc_warnings::rustdoc_warnings_handler),
+        )
         .route_with_tsr(
             "/crate/{name}/{version}/source/",
             get_internal(super::source::source_browser_handler),
@@ -345,7 +436,7 @@ pub(super) fn build_axum_routes() -> AxumRouter {
         )
         .route(
             "/{name}/badge.svg",
-            get_internal(super::rustdoc::badge_handler),
+            get_internal(super::badges::badge_handler),
         )
         .route(
             "/{name}",
@@ -441,6 +532,37 @@ mod tests {
         });
     }
 
+    #[test]
+    fn reserved_prefixes_are_routed() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+            let web = env.web_app().await;
+
+            for prefix in super::RESERVED_PREFIXES {
+                let path = match *prefix {
+                    "-" => "/-/sitemap/f/sitemap.xml",
+                    "_" => "/_/build-logs/search",
+                    "api" => "/api/v1/crates/status",
+                    "crate" => "/crate/foo",
+                    prefix => &format!("/{prefix}"),
+                };
+                // without a route of ours, the path would go to the rustdoc handlers and
+                // end up as "crate not found".
+                assert_ne!(
+                    web.get(path).await?.status(),
+                    StatusCode::NOT_FOUND,
+                    "{path}"
+                );
+            }
+            Ok(())
+        })
+    }
+
     #[test]
     fn serve_rustdoc_content_not_found() {
         async_wrapper(|env| async move {
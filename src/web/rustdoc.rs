@@ -9,7 +9,9 @@ use crate::{
     },
     utils,
     web::{
-        MetaData, ReqVersion, axum_cached_redirect, axum_parse_uri_with_params,
+        MetaData, ReqVersion,
+        auth::Protected,
+        axum_cached_redirect, axum_parse_uri_with_params,
         cache::CachePolicy,
         crate_details::CrateDetails,
         csp::Csp,
@@ -459,13 +461,19 @@ pub(crate) async fn rustdoc_html_server_handler(
 ) -> AxumResult<AxumResponse> {
     // since we directly use the Uri-path and not the extracted params from the router,
     // we have to percent-decode the string here.
-    let original_path = percent_encoding::percent_decode(uri.path().as_bytes())
-        .decode_utf8()
-        .map_err(|err| AxumNope::BadRequest(err.into()))?;
+    let decode = |path: &str| -> AxumResult<String> {
+        Ok(percent_encoding::percent_decode(path.as_bytes())
+            .decode_utf8()
+            .map_err(|err| AxumNope::BadRequest(err.into()))?
+            .into_owned())
+    };
+    let original_path = decode(uri.path())?;
 
-    let mut req_path: Vec<&str> = original_path.split('/').collect();
-    // Remove the empty start, the name and the version from the path
-    req_path.drain(..3).for_each(drop);
+    // Remove the empty start, the name and the version from the path.
+    // We split before decoding, the names of crates from hosted registries contain an
+    // encoded `/`.
+    let rest = uri.path().splitn(4, '/').nth(3).map(decode).transpose()?;
+    let mut req_path: Vec<&str> = rest.iter().flat_map(|rest| rest.split('/')).collect();
 
     // Pages generated by Rustdoc are not ready to be served with a CSP yet.
     csp.suppress(true);
@@ -634,7 +642,7 @@ pub(crate) async fn rustdoc_html_server_handler(
                 error!(
                     krate = params.name,
                     version = krate.version.to_string(),
-                    original_path = original_path.as_ref(),
+                    original_path,
                     storage_path,
                     "Couldn't find crate documentation root on storage.
                         Something is wrong with the build."
@@ -884,29 +892,6 @@ pub(crate) async fn target_redirect_handler(
     )?)
 }
 
-#[derive(Deserialize, Debug)]
-pub(crate) struct BadgeQueryParams {
-    version: Option<ReqVersion>,
-}
-
-#[instrument(skip_all)]
-pub(crate) async fn badge_handler(
-    Path(name): Path<String>,
-    Query(query): Query<BadgeQueryParams>,
-) -> AxumResult<impl IntoResponse> {
-    let url = url::Url::parse(&format!(
-        "https://img.shields.io/docsrs/{name}/{}",
-        query.version.unwrap_or_default(),
-    ))
-    .context("could not parse URL")?;
-
-    Ok((
-        StatusCode::MOVED_PERMANENTLY,
-        [(http::header::LOCATION, url.to_string())],
-        Extension(CachePolicy::ForeverInCdnAndBrowser),
-    ))
-}
-
 #[derive(Clone, Deserialize, Debug)]
 pub(crate) struct JsonDownloadParams {
     pub(crate) name: String,
@@ -915,6 +900,39 @@ pub(crate) struct JsonDownloadParams {
     pub(crate) format_version: Option<String>,
 }
 
+/// Downloads the rustdoc JSON of a release.
+///
+/// The target and the format version can be left out, as in `/crate/{name}/{version}/json`,
+/// for the default target and the latest format version. Adding `.gz` or `.zst` to the URL
+/// selects the compression, the default is zstd.
+#[utoipa::path(
+    get,
+    path = "/crate/{name}/{version}/{target}/json/{format_version}",
+    tag = "rustdoc json",
+    params(
+        ("name" = String, Path, description = "The name of the crate."),
+        (
+            "version" = String,
+            Path,
+            description = "An exact version, `latest`, or a semver requirement.",
+        ),
+        ("target" = String, Path, description = "One of the doc targets of the release."),
+        (
+            "format_version" = String,
+            Path,
+            description = "A rustdoc JSON format version, or `latest`.",
+        ),
+    ),
+    responses(
+        (status = FOUND, description = "redirect to the compressed JSON file"),
+        (
+            status = OK,
+            description = "the compressed JSON file, for private crates",
+            content_type = "application/octet-stream",
+        ),
+        (status = NOT_FOUND, description = "the release, target or JSON file doesn't exist"),
+    )
+)]
 #[instrument(skip_all)]
 pub(crate) async fn json_download_handler(
     Path(params): Path<JsonDownloadParams>,
@@ -922,7 +940,8 @@ pub(crate) async fn json_download_handler(
     Extension(config): Extension<Arc<Config>>,
     Extension(storage): Extension<Arc<AsyncStorage>>,
     file_extension: Option<PathFileExtension>,
-) -> AxumResult<impl IntoResponse> {
+    protected: Option<Extension<Protected>>,
+) -> AxumResult<AxumResponse> {
     // TODO: we could also additionally read the accept-encoding header here. But especially
     // in combination with priorities it's complex to parse correctly. So for now only
     // file extensions in the URL.
@@ -1003,15 +1022,22 @@ pub(crate) async fn json_download_handler(
         Some(wanted_compression),
     );
 
-    let redirect = |storage_path: &str| {
-        super::axum_cached_redirect(
+    let redirect = async |storage_path: &str| -> AxumResult<AxumResponse> {
+        if protected.is_some() {
+            // the public bucket URL would bypass the access rules.
+            return Ok(StreamingFile::from_path(&storage, storage_path)
+                .await?
+                .into_response());
+        }
+        Ok(super::axum_cached_redirect(
             format!("{}/{}", config.s3_static_root_path, storage_path),
             CachePolicy::ForeverInCdn,
-        )
+        )?
+        .into_response())
     };
 
     if storage.exists(&storage_path).await? {
-        Ok(redirect(&storage_path)?)
+        redirect(&storage_path).await
     } else {
         // we have old files on the bucket where we stored zstd compressed files,
         // with content-encoding=zstd & just a `.json` file extension.
@@ -1028,7 +1054,7 @@ pub(crate) async fn json_download_handler(
             if storage.exists(&storage_path).await? {
                 // we have an old file with a `.json` extension,
                 // redirect to that as fallback
-                return Ok(redirect(&storage_path)?);
+                return redirect(&storage_path).await;
             }
         }
 
@@ -1042,7 +1068,8 @@ pub(crate) async fn download_handler(
     mut conn: DbConnection,
     Extension(storage): Extension<Arc<AsyncStorage>>,
     Extension(config): Extension<Arc<Config>>,
-) -> AxumResult<impl IntoResponse> {
+    protected: Option<Extension<Protected>>,
+) -> AxumResult<AxumResponse> {
     let version = match_version(&mut conn, &name, &req_version)
         .await?
         .assume_exact_name()?
@@ -1050,6 +1077,13 @@ pub(crate) async fn download_handler(
 
     let archive_path = rustdoc_archive_path(&name, &version.to_string());
 
+    if protected.is_some() {
+        // private archives stay private, and we serve them ourselves.
+        return Ok(StreamingFile::from_path(&storage, &archive_path)
+            .await?
+            .into_response());
+    }
+
     // not all archives are set for public access yet, so we check if
     // the access is set and fix it if needed.
     let archive_is_public = match storage
@@ -1074,7 +1108,8 @@ pub(crate) async fn download_handler(
     Ok(super::axum_cached_redirect(
         format!("{}/{}", config.s3_static_root_path, archive_path),
         CachePolicy::ForeverInCdn,
-    )?)
+    )?
+    .into_response())
 }
 
 /// Serves shared resources used by rustdoc-generated documentation.
@@ -1643,31 +1678,6 @@ mod test {
         })
     }
 
-    #[test]
-    fn badges_are_urlencoded() {
-        async_wrapper(|env| async move {
-            env.fake_release()
-                .await
-                .name("zstd")
-                .version("0.5.1+zstd.1.4.4")
-                .create()
-                .await?;
-
-            let frontend = env.web_app().await;
-            let response = frontend
-                .assert_redirect_cached_unchecked(
-                    "/zstd/badge.svg",
-                    "https://img.shields.io/docsrs/zstd/latest",
-                    CachePolicy::ForeverInCdnAndBrowser,
-                    &env.config(),
-                )
-                .await?;
-            assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
-
-            Ok(())
-        })
-    }
-
     #[test_case(true)]
     #[test_case(false)]
     fn crate_name_percent_decoded_redirect(archive_storage: bool) {
@@ -0,0 +1,308 @@
+use crate::{
+    docbuilder::MAX_WARNINGS_PER_TARGET,
+    impl_axum_webpage,
+    web::{
+        MetaData, ReqVersion,
+        cache::CachePolicy,
+        error::{AxumNope, AxumResult, EscapedURI},
+        extractors::{DbConnection, Path},
+        filters,
+        headers::CanonicalUrl,
+        match_version,
+        page::templates::{RenderBrands, RenderRegular, RenderSolid},
+    },
+};
+use askama::Template;
+use axum::response::IntoResponse;
+use futures_util::TryStreamExt as _;
+
+#[derive(Debug, Clone)]
+struct Warning {
+    code: Option<String>,
+    message: String,
+    file: Option<String>,
+    line: Option<i32>,
+    rendered: String,
+}
+
+impl Warning {
+    /// The `file:line` the warning is about, if it's a file of the crate.
+    fn location(&self) -> Option<String> {
+        let file = self.file.as_deref()?;
+        // files outside of the crate, like in the standard library.
+        if file.starts_with('/') || file.split('/').any(|part| part == "..") {
+            return None;
+        }
+        Some(match self.line {
+            Some(line) => format!("{file}:{line}"),
+            None => file.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TargetWarnings {
+    target: String,
+    warnings: Vec<Warning>,
+}
+
+#[derive(Template)]
+#[template(path = "crate/rustdoc_warnings.html")]
+#[derive(Debug, Clone)]
+struct RustdocWarningsPage {
+    metadata: MetaData,
+    targets: Vec<TargetWarnings>,
+    canonical_url: CanonicalUrl,
+    is_latest_url: bool,
+}
+
+impl_axum_webpage! {
+    RustdocWarningsPage,
+    cache_policy = |page| if page.is_latest_url {
+        CachePolicy::ForeverInCdn
+    } else {
+        CachePolicy::ForeverInCdnAndStaleInBrowser
+    },
+}
+
+impl RustdocWarningsPage {
+    pub(crate) fn use_direct_platform_links(&self) -> bool {
+        true
+    }
+
+    fn is_truncated(&self, target: &TargetWarnings) -> bool {
+        target.warnings.len() >= MAX_WARNINGS_PER_TARGET
+    }
+
+    /// The link into the source browser for a warning.
+    fn source_url(&self, warning: &Warning) -> Option<String> {
+        warning.location()?;
+        let file = warning.file.as_deref()?;
+        Some(format!(
+            "/crate/{}/{}/source/{}#{}",
+            self.metadata.name,
+            self.metadata.req_version,
+            file,
+            warning.line.unwrap_or(1)
+        ))
+    }
+}
+
+pub(crate) async fn rustdoc_warnings_handler(
+    Path((name, req_version)): Path<(String, ReqVersion)>,
+    mut conn: DbConnection,
+) -> AxumResult<impl IntoResponse> {
+    let version = match_version(&mut conn, &name, &req_version)
+        .await?
+        .assume_exact_name()?
+        .into_canonical_req_version_or_else(|version| {
+            AxumNope::Redirect(
+                EscapedURI::new(&format!("/crate/{}/{}/warnings", &name, version), None),
+                CachePolicy::ForeverInCdn,
+            )
+        })?
+        .into_version();
+
+    let metadata =
+        MetaData::from_crate(&mut conn, &name, &version, Some(req_version.clone())).await?;
+
+    let mut targets: Vec<TargetWarnings> = Vec::new();
+    let mut rows = sqlx::query!(
+        r#"SELECT
+            rustdoc_warnings.target,
+            rustdoc_warnings.code,
+            rustdoc_warnings.message,
+            rustdoc_warnings.file,
+            rustdoc_warnings.line,
+            rustdoc_warnings.rendered
+         FROM rustdoc_warnings
+         INNER JOIN releases ON releases.id = rustdoc_warnings.release_id
+         INNER JOIN crates ON crates.id = releases.crate_id
+         WHERE crates.name = $1 AND releases.version = $2
+         ORDER BY
+            rustdoc_warnings.target = releases.default_target DESC,
+            rustdoc_warnings.target,
+            rustdoc_warnings.id"#,
+        name,
+        version.to_string(),
+    )
+    .fetch(&mut *conn);
+
+    while let Some(row) = rows.try_next().await? {
+        let warning = Warning {
+            code: row.code,
+            message: row.message,
+            file: row.file,
+            line: row.line,
+            rendered: row.rendered,
+        };
+        match targets.last_mut() {
+            Some(last) if last.target == row.target => last.warnings.push(warning),
+            _ => targets.push(TargetWarnings {
+                target: row.target,
+                warnings: vec![warning],
+            }),
+        }
+    }
+    drop(rows);
+
+    Ok(RustdocWarningsPage {
+        metadata,
+        targets,
+        is_latest_url: req_version.is_latest(),
+        canonical_url: CanonicalUrl::from_path(format!("/crate/{}/latest/warnings", &name)),
+    }
+    .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::replace_rustdoc_warnings;
+    use crate::docbuilder::RustdocWarning;
+    use crate::test::{AxumResponseTestExt, AxumRouterTestExt, async_wrapper};
+    use kuchikiki::traits::TendrilSink;
+
+    fn warning(code: &str, file: &str, line: i32) -> RustdocWarning {
+        RustdocWarning {
+            code: Some(code.into()),
+            message: "unresolved link to `Bar`".into(),
+            file: Some(file.into()),
+            line: Some(line),
+            rendered: format!("warning: unresolved link to `Bar`\n --> {file}:{line}:5"),
+        }
+    }
+
+    #[test]
+    fn location() {
+        let warning = |file: &str, line| Warning {
+            code: None,
+            message: "message".into(),
+            file: Some(file.into()),
+            line,
+            rendered: "rendered".into(),
+        };
+        assert_eq!(
+            warning("src/lib.rs", Some(3)).location().as_deref(),
+            Some("src/lib.rs:3")
+        );
+        assert_eq!(
+            warning("src/lib.rs", None).location().as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(
+            warning("/rustc/library/core/lib.rs", Some(3)).location(),
+            None
+        );
+        assert_eq!(warning("../other/src/lib.rs", Some(3)).location(), None);
+    }
+
+    #[test]
+    fn shows_warnings_per_target() {
+        async_wrapper(|env| async move {
+            let release_id = env
+                .fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .default_target("x86_64-unknown-linux-gnu")
+                .create()
+                .await?;
+            let mut conn = env.async_db().await.async_conn().await;
+            replace_rustdoc_warnings(
+                &mut conn,
+                release_id,
+                &[
+                    (
+                        "i686-pc-windows-msvc".into(),
+                        vec![warning("rustdoc::invalid_html_tags", "src/lib.rs", 7)],
+                    ),
+                    (
+                        "x86_64-unknown-linux-gnu".into(),
+                        vec![
+                            warning("rustdoc::broken_intra_doc_links", "src/lib.rs", 3),
+                            warning(
+                                "rustdoc::broken_intra_doc_links",
+                                "/rustc/library/core/src/lib.rs",
+                                1,
+                            ),
+                        ],
+                    ),
+                ],
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/crate/foo/0.1.0/warnings").await?;
+            assert!(response.status().is_success());
+            response
+                .assert_cache_control(CachePolicy::ForeverInCdnAndStaleInBrowser, &env.config());
+            let page = kuchikiki::parse_html().one(response.text().await?);
+
+            // the default target comes first.
+            let targets: Vec<_> = page
+                .select("#main h3")
+                .unwrap()
+                .map(|el| el.text_contents())
+                .collect();
+            assert_eq!(
+                targets,
+                ["x86_64-unknown-linux-gnu (2)", "i686-pc-windows-msvc (1)"]
+            );
+
+            let links: Vec<_> = page
+                .select("#main .rustdoc-warning a.location")
+                .unwrap()
+                .map(|el| el.attributes.borrow().get("href").unwrap().to_owned())
+                .collect();
+            assert_eq!(
+                links,
+                [
+                    "/crate/foo/0.1.0/source/src/lib.rs#3",
+                    "/crate/foo/0.1.0/source/src/lib.rs#7",
+                ]
+            );
+            assert!(
+                page.select_first("#main .rustdoc-warning pre")
+                    .unwrap()
+                    .text_contents()
+                    .contains("--> src/lib.rs:3:5")
+            );
+
+            // a rebuild replaces them.
+            replace_rustdoc_warnings(
+                &mut conn,
+                release_id,
+                &[("x86_64-unknown-linux-gnu".into(), Vec::new())],
+            )
+            .await?;
+            let page = kuchikiki::parse_html()
+                .one(web.get("/crate/foo/0.1.0/warnings").await?.text().await?);
+            assert!(page.select_first("[data-id='no-warnings']").is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn semver_redirect() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.2.1")
+                .create()
+                .await?;
+
+            let web = env.web_app().await;
+            web.assert_redirect_cached(
+                "/crate/foo/~0.2/warnings",
+                "/crate/foo/0.2.1/warnings",
+                CachePolicy::ForeverInCdn,
+                &env.config(),
+            )
+            .await?;
+            Ok(())
+        });
+    }
+}
@@ -5,6 +5,7 @@ use crate::{
     utils::{ConfigName, get_config},
     web::{
         AxumErrorPage,
+        auth::retain_visible,
         error::{AxumNope, AxumResult},
         extractors::{DbConnection, Path},
         page::templates::{RenderBrands, RenderSolid, filters},
@@ -67,7 +68,7 @@ pub(crate) async fn sitemap_handler(
         return Err(AxumNope::ResourceNotFound);
     }
 
-    let releases: Vec<_> = sqlx::query!(
+    let mut releases: Vec<_> = sqlx::query!(
         r#"SELECT crates.name,
                 releases.target_name,
                 MAX(releases.release_time) as "release_time!"
@@ -97,6 +98,9 @@ pub(crate) async fn sitemap_handler(
     .try_collect()
     .await?;
 
+    // the sitemap is for search engines, so only public crates end up in it.
+    retain_visible(&mut conn, None, &mut releases, |row| &row.crate_name).await?;
+
     Ok(SitemapXml { releases })
 }
 
@@ -141,6 +145,7 @@ about_page!(AboutPageMetadata, "core/about/metadata.html");
 about_page!(AboutPageRedirection, "core/about/redirections.html");
 about_page!(AboutPageDownload, "core/about/download.html");
 about_page!(AboutPageRustdocJson, "core/about/rustdoc-json.html");
+about_page!(AboutPageApi, "core/about/api.html");
 
 pub(crate) async fn about_handler(subpage: Option<Path<String>>) -> AxumResult<impl IntoResponse> {
     let subpage = match subpage {
@@ -155,6 +160,7 @@ pub(crate) async fn about_handler(subpage: Option<Path<String>>) -> AxumResult<i
         "redirections" => AboutPageRedirection.into_response(),
         "download" => AboutPageDownload.into_response(),
         "rustdoc-json" => AboutPageRustdocJson.into_response(),
+        "api" => AboutPageApi.into_response(),
         _ => {
             let msg = "This /about page does not exist. \
                 Perhaps you are interested in <a href=\"https://github.com/rust-lang/docs.rs/tree/master/templates/core/about\">creating</a> it?";
@@ -171,6 +177,7 @@ pub(crate) async fn about_handler(subpage: Option<Path<String>>) -> AxumResult<i
 
 #[cfg(test)]
 mod tests {
+    use crate::db::access_rules::{AccessTarget, add_access_rule};
     use crate::test::{AxumResponseTestExt, AxumRouterTestExt, async_wrapper};
     use axum::http::StatusCode;
 
@@ -183,6 +190,38 @@ mod tests {
         })
     }
 
+    #[test]
+    fn sitemap_hides_private_crates() {
+        async_wrapper(|env| async move {
+            for name in ["public-crate", "private-crate"] {
+                env.fake_release()
+                    .await
+                    .name(name)
+                    .version("0.1.0")
+                    .create()
+                    .await?;
+            }
+            let mut conn = env.async_db().await.async_conn().await;
+            add_access_rule(
+                &mut conn,
+                &env.config(),
+                &*env.async_storage().await,
+                AccessTarget::Crate("private-crate"),
+                "alice",
+            )
+            .await?;
+
+            let web = env.web_app().await;
+            let response = web.get("/-/sitemap/p/sitemap.xml").await?;
+            assert!(response.status().is_success());
+            let content = response.text().await?;
+            assert!(content.contains("public-crate"));
+            assert!(!content.contains("private-crate"));
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn sitemap_invalid_letters() {
         async_wrapper(|env| async move {
@@ -1,16 +1,111 @@
-use super::{cache::CachePolicy, error::AxumNope};
-use crate::web::{
-    ReqVersion,
-    error::{AxumResult, EscapedURI},
-    extractors::{DbConnection, Path},
-    match_version,
+use super::{MetaData, cache::CachePolicy, error::AxumNope};
+use crate::{
+    AsyncBuildQueue,
+    db::{BuildId, types::BuildStatus},
+    web::{
+        ReqVersion,
+        builds::get_builds,
+        error::{AxumResult, EscapedURI},
+        extractors::{DbConnection, Path},
+        match_version,
+    },
 };
 use axum::{
     Json, extract::Extension, http::header::ACCESS_CONTROL_ALLOW_ORIGIN, response::IntoResponse,
 };
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt as _;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use utoipa::ToSchema;
+
+/// The build status of a release, including builds that didn't start yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum ReleaseBuildStatus {
+    /// waiting in the build queue
+    Queued,
+    InProgress,
+    Success,
+    Failure,
+}
+
+impl From<BuildStatus> for ReleaseBuildStatus {
+    fn from(status: BuildStatus) -> Self {
+        match status {
+            BuildStatus::Success => Self::Success,
+            BuildStatus::Failure => Self::Failure,
+            BuildStatus::InProgress => Self::InProgress,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct LatestBuild {
+    id: BuildId,
+    build_status: BuildStatus,
+    /// when the build finished, or started when it's still in progress
+    build_time: Option<DateTime<Utc>>,
+    /// empty while the build is in progress
+    rustc_version: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct TargetStatus {
+    target: String,
+    /// whether the documentation for this target was built
+    doc_status: bool,
+    docs_url: Option<String>,
+    /// the rustdoc JSON format versions we have for this target, including `latest`
+    rustdoc_json_format_versions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ReleaseStatus {
+    /// the exact version the request matched
+    version: String,
+    /// whether the release has documentation
+    doc_status: bool,
+    build_status: ReleaseBuildStatus,
+    /// when the release is queued or being built, an estimate when the build will be finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(format = DateTime)]
+    build_finish_estimate: Option<String>,
+    /// `null` for releases that were never built
+    latest_build: Option<LatestBuild>,
+    /// the documentation of the default target
+    docs_url: Option<String>,
+    default_target: Option<String>,
+    /// the targets of the latest build, and the targets with documentation
+    targets: Vec<TargetStatus>,
+}
 
+/// The documentation status of a release, for crates.io and other tools.
+#[utoipa::path(
+    get,
+    path = "/crate/{name}/{version}/status.json",
+    tag = "crates",
+    params(
+        ("name" = String, Path, description = "The name of the crate."),
+        (
+            "version" = String,
+            Path,
+            description = "An exact version, `latest`, or a semver requirement which redirects to \
+                the matching exact version.",
+        ),
+    ),
+    responses(
+        (status = OK, body = ReleaseStatus),
+        (status = FOUND, description = "redirect to the matching exact version"),
+        (status = NOT_FOUND, description = "the crate or version doesn't exist"),
+    )
+)]
 pub(crate) async fn status_handler(
     Path((name, req_version)): Path<(String, ReqVersion)>,
+    Extension(build_queue): Extension<Arc<AsyncBuildQueue>>,
     mut conn: DbConnection,
 ) -> impl IntoResponse {
     (
@@ -24,6 +119,8 @@ pub(crate) async fn status_handler(
                 .assume_exact_name()?;
 
             let rustdoc_status = matched_release.rustdoc_status();
+            let release_id = matched_release.id();
+            let target_name = matched_release.target_name().map(ToOwned::to_owned);
 
             let version = matched_release
                 .into_canonical_req_version_or_else(|version| {
@@ -34,12 +131,118 @@ pub(crate) async fn status_handler(
                 })?
                 .into_version();
 
-            let json = Json(serde_json::json!({
-                "version": version.to_string(),
-                "doc_status": rustdoc_status,
-            }));
+            let latest_build = get_builds(&mut conn, &name, &version)
+                .await?
+                .into_iter()
+                .next();
+
+            let build_status = match &latest_build {
+                Some(build) if build.build_status == BuildStatus::InProgress => {
+                    ReleaseBuildStatus::InProgress
+                }
+                _ if build_queue
+                    .has_build_queued(&name, &version.to_string())
+                    .await? =>
+                {
+                    ReleaseBuildStatus::Queued
+                }
+                Some(build) => build.build_status.into(),
+                None => ReleaseBuildStatus::Queued,
+            };
 
-            AxumResult::Ok(json.into_response())
+            let release = sqlx::query!(
+                "SELECT default_target, doc_targets FROM releases WHERE id = $1",
+                release_id.0,
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            let doc_targets = release
+                .doc_targets
+                .map(MetaData::parse_doc_targets)
+                .unwrap_or_default();
+
+            // all targets of the latest finished build have a build log, older builds didn't
+            // record their targets.
+            let mut targets: BTreeSet<String> = doc_targets.iter().cloned().collect();
+            if let Some(build) = &latest_build
+                && build.build_status != BuildStatus::InProgress
+            {
+                targets.extend(
+                    sqlx::query_scalar!("SELECT targets FROM builds WHERE id = $1", build.id.0)
+                        .fetch_one(&mut *conn)
+                        .await?
+                        .unwrap_or_default(),
+                );
+            }
+
+            let mut rustdoc_json_format_versions: BTreeMap<String, BTreeSet<String>> =
+                BTreeMap::new();
+            let mut formats = sqlx::query!(
+                "SELECT target, format_version
+                 FROM rustdoc_json_formats
+                 WHERE release_id = $1",
+                release_id.0,
+            )
+            .fetch(&mut *conn);
+            while let Some(row) = formats.try_next().await? {
+                rustdoc_json_format_versions
+                    .entry(row.target)
+                    .or_default()
+                    .insert(row.format_version);
+            }
+            drop(formats);
+
+            let docs_url = |target: &str| {
+                let target_name = target_name.as_deref()?;
+                let doc_status = rustdoc_status && doc_targets.iter().any(|t| t == target);
+                doc_status.then(|| {
+                    if release.default_target.as_deref() == Some(target) {
+                        format!("/{name}/{version}/{target_name}/")
+                    } else {
+                        format!("/{name}/{version}/{target}/{target_name}/")
+                    }
+                })
+            };
+
+            let targets = targets
+                .into_iter()
+                .map(|target| TargetStatus {
+                    doc_status: doc_targets.contains(&target),
+                    docs_url: docs_url(&target),
+                    rustdoc_json_format_versions: rustdoc_json_format_versions
+                        .remove(&target)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                    target,
+                })
+                .collect();
+
+            // only releases that are queued or being built have an estimate. We compute it last, so
+            // it is as fresh as possible.
+            let build_finish_estimate = build_queue
+                .estimate_finish_time(&name, &version.to_string())
+                .await?
+                .map(|estimate| estimate.to_rfc3339());
+
+            AxumResult::Ok(
+                Json(ReleaseStatus {
+                    version: version.to_string(),
+                    doc_status: rustdoc_status,
+                    build_status,
+                    build_finish_estimate,
+                    latest_build: latest_build.map(|build| LatestBuild {
+                        id: build.id,
+                        build_status: build.build_status,
+                        build_time: build.build_time,
+                        rustc_version: build.rustc_version,
+                    }),
+                    docs_url: release.default_target.as_deref().and_then(docs_url),
+                    default_target: release.default_target,
+                    targets,
+                })
+                .into_response(),
+            )
         }
         .await,
     )
@@ -47,7 +250,8 @@ pub(crate) async fn status_handler(
 
 #[cfg(test)]
 mod tests {
-    use crate::test::{AxumResponseTestExt, AxumRouterTestExt, async_wrapper};
+    use crate::db::types::BuildStatus;
+    use crate::test::{AxumResponseTestExt, AxumRouterTestExt, FakeBuild, async_wrapper};
     use crate::web::cache::CachePolicy;
     use reqwest::StatusCode;
     use test_case::test_case;
@@ -62,6 +266,7 @@ mod tests {
                 .await
                 .name("foo")
                 .version("0.1.0")
+                .default_target("x86_64-unknown-linux-gnu")
                 .create()
                 .await?;
 
@@ -73,13 +278,33 @@ mod tests {
             response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
             assert_eq!(response.headers()["access-control-allow-origin"], "*");
             assert_eq!(response.status(), StatusCode::OK);
-            let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+            let mut value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+            assert!(value["latest_build"]["id"].is_number());
+            assert!(value["latest_build"]["build_time"].is_string());
+            value["latest_build"]["id"].take();
+            value["latest_build"]["build_time"].take();
 
             assert_eq!(
                 value,
                 serde_json::json!({
                     "version": "0.1.0",
                     "doc_status": true,
+                    "build_status": "success",
+                    "latest_build": {
+                        "id": null,
+                        "build_status": "success",
+                        "build_time": null,
+                        "rustc_version": "rustc 2.0.0-nightly (000000000 1970-01-01)",
+                    },
+                    "docs_url": "/foo/0.1.0/foo/",
+                    "default_target": "x86_64-unknown-linux-gnu",
+                    "targets": [{
+                        "target": "x86_64-unknown-linux-gnu",
+                        "doc_status": true,
+                        "docs_url": "/foo/0.1.0/foo/",
+                        "rustdoc_json_format_versions": ["42", "latest"],
+                    }],
                 })
             );
 
@@ -87,6 +312,157 @@ mod tests {
         });
     }
 
+    #[test]
+    fn build_finish_estimate_for_queued_release() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            env.async_build_queue()
+                .await
+                .add_crate("foo", "0.1.0", 0, None)
+                .await?;
+
+            // the fake build took no time, so the estimate is about when we request it.
+            let requested_at = chrono::Utc::now();
+            let response = env
+                .web_app()
+                .await
+                .get("/crate/foo/0.1.0/status.json")
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+            let estimate = value["build_finish_estimate"]
+                .as_str()
+                .expect("missing estimate");
+            assert!(chrono::DateTime::parse_from_rfc3339(estimate)? >= requested_at);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn targets() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .default_target("x86_64-unknown-linux-gnu")
+                .add_target("x86_64-pc-windows-msvc")
+                .builds(vec![
+                    FakeBuild::default()
+                        .build_log_for_other_target("x86_64-pc-windows-msvc", "ok")
+                        .build_log_for_other_target("i686-unknown-linux-gnu", "failed"),
+                ])
+                .create()
+                .await?;
+
+            let response = env
+                .web_app()
+                .await
+                .get("/crate/foo/0.1.0/status.json")
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+            assert_eq!(value["docs_url"], "/foo/0.1.0/foo/");
+            assert_eq!(
+                value["targets"],
+                serde_json::json!([
+                    {
+                        "target": "i686-unknown-linux-gnu",
+                        "doc_status": false,
+                        "docs_url": null,
+                        "rustdoc_json_format_versions": [],
+                    },
+                    {
+                        "target": "x86_64-pc-windows-msvc",
+                        "doc_status": true,
+                        "docs_url": "/foo/0.1.0/x86_64-pc-windows-msvc/foo/",
+                        "rustdoc_json_format_versions": ["42", "latest"],
+                    },
+                    {
+                        "target": "x86_64-unknown-linux-gnu",
+                        "doc_status": true,
+                        "docs_url": "/foo/0.1.0/foo/",
+                        "rustdoc_json_format_versions": ["42", "latest"],
+                    },
+                ])
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn build_status_queued() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+            env.async_build_queue()
+                .await
+                .add_crate("foo", "0.1.0", 0, None)
+                .await?;
+
+            let response = env
+                .web_app()
+                .await
+                .get("/crate/foo/0.1.0/status.json")
+                .await?;
+            let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+            // a rebuild of a release that already has documentation
+            assert_eq!(value["build_status"], "queued");
+            assert_eq!(value["doc_status"], true);
+            assert_eq!(value["latest_build"]["build_status"], "success");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn build_status_in_progress() {
+        async_wrapper(|env| async move {
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .builds(vec![
+                    FakeBuild::default(),
+                    FakeBuild::default().build_status(BuildStatus::InProgress),
+                ])
+                .create()
+                .await?;
+            env.async_build_queue()
+                .await
+                .add_crate("foo", "0.1.0", 0, None)
+                .await?;
+
+            let response = env
+                .web_app()
+                .await
+                .get("/crate/foo/0.1.0/status.json")
+                .await?;
+            let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+            assert_eq!(value["build_status"], "in_progress");
+            assert_eq!(value["latest_build"]["build_status"], "in_progress");
+            assert!(value["build_finish_estimate"].is_string());
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn redirect_latest() {
         async_wrapper(|env| async move {
@@ -154,17 +530,18 @@ mod tests {
                 .await?;
             response.assert_cache_control(CachePolicy::NoStoreMustRevalidate, &env.config());
             assert_eq!(response.headers()["access-control-allow-origin"], "*");
-            dbg!(&response);
             assert_eq!(response.status(), StatusCode::OK);
             let value: serde_json::Value = serde_json::from_str(&response.text().await?)?;
 
-            assert_eq!(
-                value,
-                serde_json::json!({
-                    "version": "0.1.0",
-                    "doc_status": false,
-                })
-            );
+            assert_eq!(value["version"], "0.1.0");
+            assert_eq!(value["doc_status"], false);
+            assert_eq!(value["build_status"], "failure");
+            assert_eq!(value["latest_build"]["build_status"], "failure");
+            assert_eq!(value["docs_url"], serde_json::Value::Null);
+            assert_eq!(value["targets"].as_array().unwrap().len(), 1);
+            assert_eq!(value["targets"][0]["target"], "x86_64-unknown-linux-gnu");
+            assert_eq!(value["targets"][0]["doc_status"], false);
+            assert_eq!(value["targets"][0]["docs_url"], serde_json::Value::Null);
 
             Ok(())
         });
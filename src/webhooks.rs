@@ -0,0 +1,783 @@
+//! Webhooks we send when a build finishes.
+//!
+//! A webhook subscribes to a single crate, all crates of an owner, or all crates of a hosted
+//! registry. `finish_build` queues a delivery for every matching webhook, and a background
+//! task sends them, retrying failed deliveries with an increasing delay. Every delivery is
+//! kept with the result of its last attempt, as a log of what we sent.
+
+use crate::{
+    Config,
+    db::{BuildId, registries::split_crate_name, types::BuildStatus},
+    repositories::APP_USER_AGENT,
+};
+use anyhow::{Context as _, Result, bail};
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt as _, stream::TryStreamExt};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use sha2::Sha256;
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+/// The header with the HMAC-SHA256 signature of the payload, in the `sha256=<hex digest>`
+/// format GitHub uses.
+pub const SIGNATURE_HEADER: &str = "x-docsrs-signature-256";
+/// The header with the ID of the delivery, the same for all attempts.
+pub const DELIVERY_HEADER: &str = "x-docsrs-delivery";
+/// The header with the kind of event.
+pub const EVENT_HEADER: &str = "x-docsrs-event";
+
+const BUILD_FINISHED_EVENT: &str = "build_finished";
+
+/// How many deliveries we send per run of the background task.
+const DELIVERIES_PER_RUN: i64 = 100;
+/// How many deliveries we send at the same time.
+const DELIVERY_CONCURRENCY: usize = 8;
+
+/// What a webhook subscribes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookTarget<'a> {
+    Crate(&'a str),
+    Owner(&'a str),
+    Registry(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Webhook {
+    pub id: i32,
+    pub crate_name: Option<String>,
+    pub owner: Option<String>,
+    pub registry: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub build_id: i32,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub last_status: Option<i32>,
+    pub last_error: Option<String>,
+}
+
+/// The payload we send when a build finished.
+#[derive(Debug, Serialize)]
+struct BuildFinished {
+    event: &'static str,
+    #[serde(rename = "crate")]
+    krate: String,
+    version: String,
+    build_id: BuildId,
+    build_status: BuildStatus,
+    rustc_version: Option<String>,
+    docsrs_version: Option<String>,
+    finished_at: Option<DateTime<Utc>>,
+    /// whether the release has documentation, also when an earlier build produced it
+    doc_status: bool,
+    build_url: String,
+    docs_url: Option<String>,
+}
+
+/// Returns all webhooks, sorted by what they subscribe to.
+pub async fn list_webhooks(conn: &mut sqlx::PgConnection) -> Result<Vec<Webhook>> {
+    Ok(sqlx::query_as!(
+        Webhook,
+        r#"SELECT
+            webhooks.id,
+            webhooks.crate_name,
+            webhooks.owner,
+            registries.name as "registry?",
+            webhooks.url
+         FROM webhooks
+         LEFT JOIN registries ON registries.id = webhooks.registry_id
+         ORDER BY registries.name, webhooks.owner, webhooks.crate_name, webhooks.id"#
+    )
+    .fetch(conn)
+    .try_collect()
+    .await?)
+}
+
+/// Subscribes a URL to the builds of a crate, an owner, or a registry.
+pub async fn add_webhook(
+    conn: &mut sqlx::PgConnection,
+    target: WebhookTarget<'_>,
+    url: &Url,
+    secret: &str,
+) -> Result<i32> {
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("webhook URLs have to use http or https");
+    }
+    if secret.is_empty() {
+        bail!("webhooks need a secret to sign the payloads with");
+    }
+
+    let (crate_name, owner, registry_id) = match target {
+        WebhookTarget::Crate(name) => (Some(name), None, None),
+        WebhookTarget::Owner(login) => (None, Some(login.strip_prefix('@').unwrap_or(login)), None),
+        WebhookTarget::Registry(name) => {
+            let registry = crate::db::registries::registry_by_name(&mut *conn, name)
+                .await?
+                .with_context(|| format!("registry {name} does not exist"))?;
+            (None, None, Some(registry.id))
+        }
+    };
+
+    Ok(sqlx::query_scalar!(
+        "INSERT INTO webhooks (crate_name, owner, registry_id, url, secret)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id",
+        crate_name,
+        owner,
+        registry_id,
+        url.as_str(),
+        secret,
+    )
+    .fetch_one(conn)
+    .await?)
+}
+
+/// Removes a webhook, together with its deliveries.
+pub async fn remove_webhook(conn: &mut sqlx::PgConnection, id: i32) -> Result<()> {
+    if sqlx::query!("DELETE FROM webhooks WHERE id = $1", id)
+        .execute(conn)
+        .await?
+        .rows_affected()
+        == 0
+    {
+        bail!("webhook {id} does not exist");
+    }
+    Ok(())
+}
+
+/// Returns the newest deliveries, of all webhooks or of a single one.
+pub async fn list_deliveries(
+    conn: &mut sqlx::PgConnection,
+    webhook_id: Option<i32>,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>> {
+    Ok(sqlx::query_as!(
+        WebhookDelivery,
+        "SELECT
+            id,
+            webhook_id,
+            build_id,
+            attempts,
+            created_at,
+            delivered_at,
+            last_status,
+            last_error
+         FROM webhook_deliveries
+         WHERE $1::INT IS NULL OR webhook_id = $1
+         ORDER BY id DESC
+         LIMIT $2",
+        webhook_id,
+        limit,
+    )
+    .fetch(conn)
+    .try_collect()
+    .await?)
+}
+
+/// Queues a delivery of the build result for every webhook subscribed to the crate.
+///
+/// Returns how many deliveries were queued.
+#[instrument(skip(conn))]
+pub(crate) async fn queue_build_webhooks(
+    conn: &mut sqlx::PgConnection,
+    build_id: BuildId,
+) -> Result<u64> {
+    let build = sqlx::query!(
+        r#"SELECT
+            crates.id as crate_id,
+            crates.name,
+            releases.id as release_id,
+            releases.version,
+            releases.rustdoc_status,
+            releases.target_name,
+            builds.build_status as "build_status: BuildStatus",
+            builds.rustc_version,
+            builds.docsrs_version,
+            builds.build_finished
+         FROM builds
+         INNER JOIN releases ON releases.id = builds.rid
+         INNER JOIN crates ON crates.id = releases.crate_id
+         WHERE builds.id = $1"#,
+        build_id.0,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let name = build.name;
+    let version = build.version;
+    let doc_status = build.rustdoc_status.unwrap_or(false);
+    let payload = BuildFinished {
+        event: BUILD_FINISHED_EVENT,
+        build_url: format!("https://docs.rs/crate/{name}/{version}/builds/{build_id}"),
+        docs_url: build
+            .target_name
+            .filter(|_| doc_status)
+            .map(|target_name| format!("https://docs.rs/{name}/{version}/{target_name}/")),
+        build_id,
+        build_status: build.build_status,
+        rustc_version: build.rustc_version,
+        docsrs_version: build.docsrs_version,
+        finished_at: build.build_finished,
+        doc_status,
+        version: version.clone(),
+        krate: name.clone(),
+    };
+
+    let (registry, _) = split_crate_name(&name);
+    let queued = sqlx::query!(
+        "INSERT INTO webhook_deliveries (webhook_id, release_id, build_id, payload)
+         SELECT webhooks.id, $1, $2, $3
+         FROM webhooks
+         WHERE
+            normalize_crate_name(webhooks.crate_name) = normalize_crate_name($4) OR
+            webhooks.owner IN (
+                SELECT owners.login
+                FROM owners
+                INNER JOIN owner_rels ON owner_rels.oid = owners.id
+                WHERE owner_rels.cid = $5
+            ) OR
+            webhooks.registry_id = (SELECT registries.id FROM registries WHERE registries.name = $6)",
+        build.release_id,
+        build_id.0,
+        serde_json::to_value(&payload)?,
+        name,
+        build.crate_id,
+        registry,
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    if queued > 0 {
+        debug!(name, version, queued, "queued build webhooks");
+    }
+    Ok(queued)
+}
+
+/// The HTTP client we send webhooks with.
+pub(crate) fn webhook_client(config: &Config) -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .timeout(config.webhook_timeout)
+        // a redirect is a failed delivery, like for GitHub webhooks.
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?)
+}
+
+/// Signs a payload in the `sha256=<hex digest>` format.
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// A delivery we claimed and are about to send.
+struct ClaimedDelivery {
+    id: i32,
+    payload: serde_json::Value,
+    attempts: i32,
+    url: String,
+    secret: String,
+}
+
+/// Claims the due deliveries, so concurrent runs on other servers don't send them too.
+///
+/// Claiming moves `next_attempt_at` past the end of our run, like a lease. When we don't record
+/// a result, for example because the server crashed, the deliveries are due again after that.
+async fn claim_deliveries(
+    conn: &mut sqlx::PgConnection,
+    config: &Config,
+) -> Result<Vec<ClaimedDelivery>> {
+    // every delivery can take up to `webhook_timeout`, and we send a few at the same time.
+    // We double that, so there is time left to record the results.
+    let lease = config.webhook_timeout
+        * (DELIVERIES_PER_RUN as u32).div_ceil(DELIVERY_CONCURRENCY as u32)
+        * 2;
+
+    Ok(sqlx::query_as!(
+        ClaimedDelivery,
+        "UPDATE webhook_deliveries
+         SET next_attempt_at = NOW() + make_interval(secs => $3)
+         FROM webhooks
+         WHERE
+            webhooks.id = webhook_deliveries.webhook_id AND
+            webhook_deliveries.id IN (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE
+                    delivered_at IS NULL AND
+                    attempts < $1 AND
+                    next_attempt_at <= NOW()
+                ORDER BY id
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+         RETURNING
+            webhook_deliveries.id,
+            webhook_deliveries.payload,
+            webhook_deliveries.attempts,
+            webhooks.url,
+            webhooks.secret",
+        config.webhook_max_attempts,
+        DELIVERIES_PER_RUN,
+        lease.as_secs_f64(),
+    )
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Sends the queued deliveries that are due.
+///
+/// Failed deliveries are retried after `webhook_retry_delay`, doubling the delay for every
+/// further attempt, until we tried `webhook_max_attempts` times.
+///
+/// We claim the deliveries before sending them, see [`claim_deliveries`], and record the result
+/// of each one as soon as we have it.
+///
+/// Returns how many deliveries succeeded.
+#[instrument(skip_all)]
+pub(crate) async fn deliver_queued_webhooks(
+    conn: &mut sqlx::PgConnection,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<usize> {
+    let deliveries = claim_deliveries(&mut *conn, config).await?;
+
+    let mut results = futures_util::stream::iter(deliveries)
+        .map(|delivery| async move {
+            let payload = delivery.payload.to_string();
+            let signature = sign(&delivery.secret, payload.as_bytes());
+
+            let result = match client
+                .post(&delivery.url)
+                .header(CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, signature)
+                .header(DELIVERY_HEADER, delivery.id.to_string())
+                .header(EVENT_HEADER, BUILD_FINISHED_EVENT)
+                .body(payload)
+                .send()
+                .await
+            {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16()), None)
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    Some(format!("unexpected status {}", response.status())),
+                ),
+                Err(err) => (None, Some(format!("{err:#}"))),
+            };
+            (delivery, result)
+        })
+        .buffer_unordered(DELIVERY_CONCURRENCY);
+
+    let mut delivered = 0;
+    while let Some((delivery, (status, error))) = results.next().await {
+        if let Some(error) = error {
+            warn!(
+                id = delivery.id,
+                url = delivery.url,
+                attempts = delivery.attempts + 1,
+                error,
+                "webhook delivery failed"
+            );
+            let delay =
+                config.webhook_retry_delay * 2u32.pow(delivery.attempts.clamp(0, 16) as u32);
+            sqlx::query!(
+                "UPDATE webhook_deliveries
+                 SET
+                    attempts = attempts + 1,
+                    next_attempt_at = $2,
+                    last_status = $3,
+                    last_error = $4
+                 WHERE id = $1",
+                delivery.id,
+                Utc::now() + delay,
+                status.map(i32::from),
+                error,
+            )
+            .execute(&mut *conn)
+            .await?;
+        } else {
+            sqlx::query!(
+                "UPDATE webhook_deliveries
+                 SET
+                    attempts = attempts + 1,
+                    delivered_at = NOW(),
+                    last_status = $2,
+                    last_error = NULL
+                 WHERE id = $1",
+                delivery.id,
+                status.map(i32::from),
+            )
+            .execute(&mut *conn)
+            .await?;
+            delivered += 1;
+        }
+    }
+
+    Ok(delivered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::registries::add_registry;
+    use crate::registry_api::{CrateOwner, OwnerKind};
+    use crate::test::{TestWebhookReceiver, async_wrapper};
+    use axum::http::StatusCode;
+
+    async fn deliveries(conn: &mut sqlx::PgConnection) -> Result<Vec<WebhookDelivery>> {
+        let mut deliveries = list_deliveries(conn, None, 100).await?;
+        deliveries.reverse();
+        Ok(deliveries)
+    }
+
+    #[test]
+    fn test_webhooks() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+            let url: Url = "https://example.com/hook".parse()?;
+
+            let id = add_webhook(&mut conn, WebhookTarget::Crate("foo"), &url, "secret").await?;
+            add_webhook(&mut conn, WebhookTarget::Owner("@alice"), &url, "secret").await?;
+            add_webhook(&mut conn, WebhookTarget::Registry("acme"), &url, "secret").await?;
+            assert!(
+                add_webhook(
+                    &mut conn,
+                    WebhookTarget::Registry("unknown"),
+                    &url,
+                    "secret"
+                )
+                .await
+                .is_err()
+            );
+            assert!(
+                add_webhook(&mut conn, WebhookTarget::Crate("foo"), &url, "")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                add_webhook(
+                    &mut conn,
+                    WebhookTarget::Crate("foo"),
+                    &"ftp://example.com".parse()?,
+                    "secret"
+                )
+                .await
+                .is_err()
+            );
+
+            let webhooks = list_webhooks(&mut conn).await?;
+            assert_eq!(webhooks.len(), 3);
+            // the `@` of the owner is optional
+            assert!(
+                webhooks
+                    .iter()
+                    .any(|webhook| webhook.owner.as_deref() == Some("alice"))
+            );
+
+            remove_webhook(&mut conn, id).await?;
+            assert!(remove_webhook(&mut conn, id).await.is_err());
+            assert_eq!(list_webhooks(&mut conn).await?.len(), 2);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_queue_build_webhooks() {
+        async_wrapper(|env| async move {
+            let mut conn = env.async_db().await.async_conn().await;
+            let url: Url = "https://example.com/hook".parse()?;
+            let crate_hook =
+                add_webhook(&mut conn, WebhookTarget::Crate("foo-bar"), &url, "s").await?;
+            let owner_hook =
+                add_webhook(&mut conn, WebhookTarget::Owner("alice"), &url, "s").await?;
+            add_webhook(&mut conn, WebhookTarget::Crate("other"), &url, "s").await?;
+            add_registry(&mut conn, "acme", "https://acme.example/index", None).await?;
+            let registry_hook =
+                add_webhook(&mut conn, WebhookTarget::Registry("acme"), &url, "s").await?;
+
+            // finishing the build of a release queues the deliveries
+            env.fake_release()
+                .await
+                .name("foo_bar")
+                .version("0.1.0")
+                .add_owner(CrateOwner {
+                    login: "alice".into(),
+                    avatar: "".into(),
+                    kind: OwnerKind::User,
+                })
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("unrelated")
+                .version("1.0.0")
+                .create()
+                .await?;
+            env.fake_release()
+                .await
+                .name("acme/foo_bar")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let deliveries = deliveries(&mut conn).await?;
+            assert_eq!(deliveries.len(), 3);
+            let mut webhook_ids: Vec<_> = deliveries.iter().map(|d| d.webhook_id).collect();
+            webhook_ids.sort();
+            assert_eq!(webhook_ids, [crate_hook, owner_hook, registry_hook]);
+            assert!(deliveries.iter().all(|d| d.attempts == 0));
+
+            let payload: serde_json::Value = sqlx::query_scalar!(
+                "SELECT payload FROM webhook_deliveries WHERE webhook_id = $1",
+                crate_hook
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            let build_id = deliveries[0].build_id;
+            assert_eq!(
+                payload,
+                serde_json::json!({
+                    "event": "build_finished",
+                    "crate": "foo_bar",
+                    "version": "0.1.0",
+                    "build_id": build_id,
+                    "build_status": "success",
+                    "rustc_version": "rustc 2.0.0-nightly (000000000 1970-01-01)",
+                    "docsrs_version": "docs.rs 1.0.0 (000000000 1970-01-01)",
+                    "finished_at": payload["finished_at"],
+                    "doc_status": true,
+                    "build_url": format!(
+                        "https://docs.rs/crate/foo_bar/0.1.0/builds/{build_id}"
+                    ),
+                    "docs_url": "https://docs.rs/foo_bar/0.1.0/foo_bar/",
+                })
+            );
+            assert!(payload["finished_at"].is_string());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_deliver_with_retries() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.webhook_retry_delay = std::time::Duration::ZERO;
+                config.webhook_max_attempts = 3;
+            });
+            let receiver = TestWebhookReceiver::new(&[
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+            ])
+            .await?;
+
+            let mut conn = env.async_db().await.async_conn().await;
+            add_webhook(
+                &mut conn,
+                WebhookTarget::Crate("foo"),
+                &receiver.url(),
+                "secret",
+            )
+            .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let config = env.config();
+            let client = webhook_client(&config)?;
+
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                0
+            );
+            let delivery = deliveries(&mut conn).await?.remove(0);
+            assert_eq!(delivery.attempts, 1);
+            assert_eq!(delivery.last_status, Some(500));
+            assert!(delivery.last_error.is_some());
+            assert!(delivery.delivered_at.is_none());
+
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                0
+            );
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                1
+            );
+            let delivery = deliveries(&mut conn).await?.remove(0);
+            assert_eq!(delivery.attempts, 3);
+            assert_eq!(delivery.last_status, Some(200));
+            assert_eq!(delivery.last_error, None);
+            assert!(delivery.delivered_at.is_some());
+
+            // nothing left to deliver
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                0
+            );
+
+            let requests = receiver.requests();
+            assert_eq!(requests.len(), 3);
+            for request in &requests {
+                assert_eq!(request.headers[EVENT_HEADER], "build_finished");
+                assert_eq!(
+                    request.headers[DELIVERY_HEADER],
+                    delivery.id.to_string().as_str()
+                );
+                assert_eq!(
+                    request.headers[SIGNATURE_HEADER],
+                    sign("secret", &request.body).as_str()
+                );
+                let payload: serde_json::Value = serde_json::from_slice(&request.body)?;
+                assert_eq!(payload["crate"], "foo");
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_give_up_after_max_attempts() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.webhook_retry_delay = std::time::Duration::ZERO;
+                config.webhook_max_attempts = 2;
+            });
+            let receiver = TestWebhookReceiver::new(&[StatusCode::NOT_FOUND; 5]).await?;
+
+            let mut conn = env.async_db().await.async_conn().await;
+            add_webhook(
+                &mut conn,
+                WebhookTarget::Crate("foo"),
+                &receiver.url(),
+                "secret",
+            )
+            .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let config = env.config();
+            let client = webhook_client(&config)?;
+            for _ in 0..4 {
+                assert_eq!(
+                    deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                    0
+                );
+            }
+            assert_eq!(receiver.requests().len(), 2);
+            assert_eq!(deliveries(&mut conn).await?[0].attempts, 2);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_claimed_deliveries() {
+        async_wrapper(|env| async move {
+            let receiver = TestWebhookReceiver::new(&[]).await?;
+
+            let mut conn = env.async_db().await.async_conn().await;
+            add_webhook(
+                &mut conn,
+                WebhookTarget::Crate("foo"),
+                &receiver.url(),
+                "secret",
+            )
+            .await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let config = env.config();
+            let client = webhook_client(&config)?;
+
+            // another server is delivering this right now
+            let mut other_conn = env.async_db().await.async_conn().await;
+            assert_eq!(claim_deliveries(&mut other_conn, &config).await?.len(), 1);
+
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                0
+            );
+            assert!(receiver.requests().is_empty());
+            assert_eq!(deliveries(&mut conn).await?[0].attempts, 0);
+
+            // the other server crashed, and the lease ran out.
+            sqlx::query!("UPDATE webhook_deliveries SET next_attempt_at = NOW()")
+                .execute(&mut *conn)
+                .await?;
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                1
+            );
+            assert_eq!(receiver.requests().len(), 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        async_wrapper(|env| async move {
+            env.override_config(|config| {
+                config.webhook_retry_delay = std::time::Duration::from_secs(60 * 60);
+            });
+            let mut conn = env.async_db().await.async_conn().await;
+            // nothing listens on this port.
+            let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+            let url: Url = format!("http://{}/", listener.local_addr()?).parse()?;
+            drop(listener);
+            add_webhook(&mut conn, WebhookTarget::Crate("foo"), &url, "secret").await?;
+            env.fake_release()
+                .await
+                .name("foo")
+                .version("0.1.0")
+                .create()
+                .await?;
+
+            let config = env.config();
+            let client = webhook_client(&config)?;
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                0
+            );
+            let delivery = deliveries(&mut conn).await?.remove(0);
+            assert_eq!(delivery.attempts, 1);
+            assert_eq!(delivery.last_status, None);
+            assert!(delivery.last_error.is_some());
+
+            // the next attempt is only due in an hour
+            assert_eq!(
+                deliver_queued_webhooks(&mut conn, &config, &client).await?,
+                0
+            );
+            assert_eq!(deliveries(&mut conn).await?[0].attempts, 1);
+
+            Ok(())
+        })
+    }
+}
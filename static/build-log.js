@@ -0,0 +1,40 @@
+// Follows the log of a build that is still in progress, until it's finished.
+(function() {
+    const UPDATE_INTERVAL = 5000;
+
+    function isScrolledToBottom() {
+        return window.innerHeight + window.scrollY >= document.body.scrollHeight - 20;
+    }
+
+    async function update() {
+        try {
+            const response = await fetch(window.location.href, {cache: "no-store"});
+            if (!response.ok) {
+                return;
+            }
+            const page = new DOMParser().parseFromString(await response.text(), "text/html");
+            const updated = page.querySelector(".build-details");
+            const current = document.querySelector(".build-details");
+            if (!updated || !current) {
+                return;
+            }
+
+            // keep following the end of the log, if that's where the reader is.
+            const follow = isScrolledToBottom();
+            current.replaceWith(updated);
+            if (follow) {
+                window.scrollTo(0, document.body.scrollHeight);
+            }
+            if (!updated.hasAttribute("data-in-progress")) {
+                return;
+            }
+        } catch (error) {
+            console.error("failed to update the build log", error);
+        }
+        setTimeout(update, UPDATE_INTERVAL);
+    }
+
+    if (document.querySelector(".build-details[data-in-progress]")) {
+        setTimeout(update, UPDATE_INTERVAL);
+    }
+})();
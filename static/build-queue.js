@@ -0,0 +1,43 @@
+// Updates the build queue page when builds are queued, started or finished.
+(function() {
+    if (!window.EventSource) {
+        return;
+    }
+
+    let pendingUpdate = null;
+
+    // Events often come in bursts, so we wait a bit and then update the page once.
+    function scheduleUpdate() {
+        if (pendingUpdate !== null) {
+            return;
+        }
+        pendingUpdate = setTimeout(async() => {
+            pendingUpdate = null;
+            try {
+                const response = await fetch(window.location.href, {cache: "no-store"});
+                if (!response.ok) {
+                    return;
+                }
+                const page = new DOMParser().parseFromString(await response.text(), "text/html");
+                const updated = page.querySelector(".recent-releases-container");
+                const current = document.querySelector(".recent-releases-container");
+                if (updated && current) {
+                    current.replaceWith(updated);
+                }
+            } catch (error) {
+                console.error("failed to update the build queue", error);
+            }
+        }, 1000);
+    }
+
+    const events = new EventSource("/releases/queue/events");
+    for (const name of [
+        "queued",
+        "build_started",
+        "build_finished",
+        "cdn_invalidation_queued",
+        "lagged",
+    ]) {
+        events.addEventListener(name, scheduleUpdate);
+    }
+})();
@@ -0,0 +1,10 @@
+//! A command line tool with internal documentation.
+
+/// Parses the arguments of the tool.
+fn parse_args() -> Vec<String> {
+    std::env::args().skip(1).collect()
+}
+
+fn main() {
+    println!("{:?}", parse_args());
+}